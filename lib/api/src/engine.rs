@@ -28,6 +28,74 @@ impl Engine {
     pub fn deterministic_id(&self) -> &str {
         self.0.deterministic_id()
     }
+
+    #[cfg(feature = "sys")]
+    /// Returns the fingerprint of the middlewares of the compiler of this
+    /// engine, or `None` for a headless engine or if one of the middlewares
+    /// has no fingerprint.
+    ///
+    /// Modules record the fingerprint of the middlewares they were compiled
+    /// with, see [`Module::middleware_fingerprint`](crate::Module::middleware_fingerprint),
+    /// and an engine with a compiler refuses to deserialize modules compiled
+    /// with other middlewares, or with middlewares without a fingerprint.
+    pub fn middleware_fingerprint(&self) -> Option<[u8; 32]> {
+        self.0.middleware_fingerprint()
+    }
+
+    #[cfg(feature = "sys")]
+    /// Returns whether this engine has no compiler, see
+    /// [`EngineBuilder::headless`](crate::EngineBuilder::headless).
+    pub fn is_headless(&self) -> bool {
+        self.0.is_headless()
+    }
+
+    #[cfg(feature = "sys")]
+    /// Makes the modules compiled by this engine from now on check the
+    /// interrupt flag of their store, so that they can be stopped with
+    /// [`Store::interrupt_handle`](crate::Store::interrupt_handle).
+    /// Interrupt checks are off by default.
+    ///
+    /// An engine with a compiler refuses to deserialize modules compiled
+    /// with another setting.
+    pub fn set_interrupt_checks(&mut self, enable: bool) {
+        self.0.set_interrupt_checks(enable)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Returns whether the modules compiled by this engine check the
+    /// interrupt flag of their store.
+    pub fn interrupt_checks(&self) -> bool {
+        self.0.interrupt_checks()
+    }
+
+    #[cfg(feature = "sys")]
+    /// Makes the modules compiled by this engine from now on check the
+    /// epoch deadline of their store, see
+    /// [`Store::set_epoch_deadline`](crate::Store::set_epoch_deadline).
+    /// Epoch interruption is off by default.
+    ///
+    /// An engine with a compiler refuses to deserialize modules compiled
+    /// with another setting.
+    pub fn set_epoch_interruption(&mut self, enable: bool) {
+        self.0.set_epoch_interruption(enable)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Returns whether the modules compiled by this engine check the epoch
+    /// deadline of their store.
+    pub fn epoch_interruption(&self) -> bool {
+        self.0.epoch_interruption()
+    }
+
+    #[cfg(feature = "sys")]
+    /// Increments the epoch of this engine, which is shared by all its
+    /// clones and the stores using them, see
+    /// [`Store::set_epoch_deadline`](crate::Store::set_epoch_deadline).
+    ///
+    /// This is typically called periodically by a timer thread.
+    pub fn increment_epoch(&self) {
+        self.0.increment_epoch()
+    }
 }
 
 impl AsEngineRef for Engine {
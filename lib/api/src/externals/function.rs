@@ -12,6 +12,8 @@ use crate::{
 use wasmer_types::RawValue;
 
 use crate::native_type::WasmTypeList;
+#[cfg(feature = "sys")]
+use std::future::Future;
 
 /// The `HostFunction` trait represents the set of functions that
 /// can be used as host function. To uphold this statement, it is
@@ -154,6 +156,68 @@ impl Function {
         Self(function_impl::Function::new_with_env(store, env, ty, func))
     }
 
+    #[cfg(feature = "sys")]
+    /// Creates a new asynchronous host `Function` (dynamic) with the provided
+    /// signature.
+    ///
+    /// The future returned by `func` is awaited while the WebAssembly code
+    /// calling the function is suspended, so the function can only be called
+    /// from an asynchronous call, like [`Function::call_async`] or
+    /// [`TypedFunction::call_async`]. Calling it otherwise traps.
+    pub fn new_async<FT, F, Fut>(store: &mut impl AsStoreMut, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&[Value]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Value>, RuntimeError>> + 'static + Send,
+    {
+        let env = FunctionEnv::new(&mut store.as_store_mut(), ());
+        let wrapped_func = move |_env: FunctionEnvMut<()>, args: &[Value]| func(args);
+        Self::new_async_with_env(store, &env, ty, wrapped_func)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Creates a new asynchronous host `Function` (dynamic) with the provided
+    /// signature, see [`Function::new_async`].
+    ///
+    /// Takes a [`FunctionEnv`] that is passed into func. The future can't
+    /// borrow the environment: what it needs from it must be taken out before
+    /// it is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use wasmer::{Function, FunctionEnv, FunctionEnvMut, Store, Type, Value};
+    /// # let mut store = Store::default();
+    /// let env = FunctionEnv::new(&mut store, Arc::new(vec![1, 2, 3]));
+    ///
+    /// let f = Function::new_async_with_env(
+    ///     &mut store,
+    ///     &env,
+    ///     ([Type::I32], [Type::I32]),
+    ///     |env: FunctionEnvMut<Arc<Vec<i32>>>, args| {
+    ///         let storage = env.data().clone();
+    ///         let index = args[0].unwrap_i32() as usize;
+    ///         async move { Ok(vec![Value::I32(storage[index])]) }
+    ///     },
+    /// );
+    /// ```
+    pub fn new_async_with_env<FT, F, Fut, T: Send + 'static>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        ty: FT,
+        func: F,
+    ) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(FunctionEnvMut<T>, &[Value]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Value>, RuntimeError>> + 'static + Send,
+    {
+        Self(function_impl::Function::new_async_with_env(
+            store, env, ty, func,
+        ))
+    }
+
     #[deprecated(
         since = "3.0.0",
         note = "new_native() has been renamed to new_typed()."
@@ -332,6 +396,23 @@ impl Function {
         self.0.call(store, params)
     }
 
+    #[cfg(feature = "sys")]
+    /// Calls the `Function` asynchronously.
+    ///
+    /// Unlike [`Function::call`], the asynchronous host functions created
+    /// with [`Function::new_async`] can be called: while the future of one of
+    /// them is pending, the WebAssembly code is suspended and the future
+    /// returned here is pending too.
+    ///
+    /// Dropping the future before it completes cancels the call.
+    pub async fn call_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+    ) -> Result<Box<[Value]>, RuntimeError> {
+        self.0.call_async(store, params).await
+    }
+
     #[doc(hidden)]
     #[allow(missing_docs)]
     pub fn call_raw(
@@ -6,12 +6,16 @@ use crate::sys::externals::memory as memory_impl;
 use super::memory_view::MemoryView;
 use crate::exports::{ExportError, Exportable};
 use crate::store::{AsStoreMut, AsStoreRef};
+#[cfg(feature = "merkle")]
+use crate::sys::MemoryMerkleTree;
 use crate::vm::{VMExtern, VMExternMemory, VMMemory};
 use crate::Extern;
 use crate::MemoryAccessError;
 use crate::MemoryType;
 use std::mem::MaybeUninit;
 use wasmer_types::{MemoryError, Pages};
+#[cfg(feature = "sys")]
+use wasmer_vm::DirtyPages;
 
 /// A WebAssembly `memory` instance.
 ///
@@ -119,6 +123,74 @@ impl Memory {
         self.0.grow(store, delta)
     }
 
+    #[cfg(feature = "sys")]
+    /// Starts recording the pages written to this memory, by WebAssembly
+    /// code or by the host, so that they can be listed with
+    /// [`Memory::dirty_pages`].
+    ///
+    /// Writes are detected by write-protecting the memory, so the first
+    /// write to each page after a reset is slower.
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let mut store = Store::default();
+    /// let m = Memory::new(&mut store, MemoryType::new(2, None, false)).unwrap();
+    /// m.enable_dirty_tracking(&mut store).unwrap();
+    /// m.view(&store).write_u8(70000, 1).unwrap();
+    ///
+    /// assert_eq!(m.dirty_pages(&store).unwrap().wasm_pages(), vec![1]);
+    /// ```
+    pub fn enable_dirty_tracking(&self, store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
+        self.0.enable_dirty_tracking(store)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Stops recording the pages written to this memory.
+    pub fn disable_dirty_tracking(&self, store: &mut impl AsStoreMut) {
+        self.0.disable_dirty_tracking(store)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Returns the pages written since dirty page tracking was enabled or
+    /// last reset with [`Memory::reset_dirty_pages`].
+    ///
+    /// Pages beyond the current size of the memory are not reported.
+    pub fn dirty_pages(&self, store: &impl AsStoreRef) -> Result<DirtyPages, MemoryError> {
+        self.0.dirty_pages(store)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Forgets the pages written so far.
+    pub fn reset_dirty_pages(&self, store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
+        self.0.reset_dirty_pages(store)
+    }
+
+    #[cfg(feature = "merkle")]
+    /// Builds a Merkle tree over the pages of this memory, which can then
+    /// be kept up to date with [`MemoryMerkleTree::update`].
+    ///
+    /// This enables dirty page tracking on the memory, which the tree uses
+    /// to only rehash the pages written between two updates. Resetting the
+    /// dirty pages by other means makes the tree miss these writes.
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let mut store = Store::default();
+    /// let m = Memory::new(&mut store, MemoryType::new(2, None, false)).unwrap();
+    /// let mut tree = m.merkle_tree(&mut store).unwrap();
+    /// m.view(&store).write_u8(70000, 1).unwrap();
+    ///
+    /// let root = tree.update(&mut store, &m).unwrap();
+    /// assert_eq!(root, m.view(&store).merkle_root());
+    /// ```
+    pub fn merkle_tree(
+        &self,
+        store: &mut impl AsStoreMut,
+    ) -> Result<MemoryMerkleTree, MemoryError> {
+        self.enable_dirty_tracking(store)?;
+        Ok(MemoryMerkleTree::new(&self.view(store)))
+    }
+
     /// Copies the memory to a new store and returns a memory reference to it
     pub fn copy_to_store(
         &self,
@@ -1,5 +1,7 @@
 use super::memory::{Memory, MemoryBuffer};
 use crate::store::AsStoreRef;
+#[cfg(feature = "merkle")]
+use crate::sys::MemoryMerkleTree;
 use crate::MemoryAccessError;
 use std::mem::MaybeUninit;
 use wasmer_types::Pages;
@@ -156,6 +158,14 @@ impl<'a> MemoryView<'a> {
         Ok(new_memory)
     }
 
+    #[cfg(feature = "merkle")]
+    /// Computes the Merkle root of the memory, see [`MemoryMerkleTree`].
+    ///
+    /// The root only depends on the size and the contents of the memory.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        MemoryMerkleTree::new(self).root()
+    }
+
     /// Copies the memory to another new memory object
     pub fn copy_to_memory(&self, amount: u64, new_memory: &Self) -> Result<(), MemoryAccessError> {
         let mut offset = 0;
@@ -1,10 +1,18 @@
 use crate::exports::Exports;
 use crate::module::Module;
+#[cfg(feature = "sys")]
+use crate::RuntimeError;
 use crate::{Extern, InstantiationError};
 use std::fmt;
 
 use crate::imports::Imports;
 use crate::store::AsStoreMut;
+#[cfg(feature = "sys")]
+use crate::store::AsStoreRef;
+#[cfg(feature = "sys")]
+use crate::vm::VMImports;
+#[cfg(feature = "sys")]
+use wasmer_types::{SnapshotError, StoreId, StoreSnapshot};
 
 #[cfg(feature = "js")]
 use crate::js::instance as instance_imp;
@@ -98,6 +106,69 @@ impl Instance {
     pub fn module(&self) -> &Module {
         &self.module
     }
+
+    #[cfg(feature = "sys")]
+    /// Captures the state of the memories, tables and globals of this
+    /// instance, including the imported ones.
+    ///
+    /// The snapshot can be restored into this instance or into another
+    /// instance of the same [`Module`] with [`Instance::restore_snapshot`].
+    pub fn snapshot(&self, store: &impl AsStoreRef) -> Result<StoreSnapshot, SnapshotError> {
+        self._inner.snapshot(store)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Restores a snapshot taken with [`Instance::snapshot`].
+    ///
+    /// Memories and tables are grown to the size recorded in the snapshot.
+    /// If the snapshot can't be restored, nothing is written.
+    pub fn restore_snapshot(
+        &self,
+        store: &mut impl AsStoreMut,
+        snapshot: &StoreSnapshot,
+    ) -> Result<(), SnapshotError> {
+        self._inner.restore_snapshot(store, snapshot)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Brings this instance back to its state right after instantiation,
+    /// which is much cheaper than creating a new instance.
+    ///
+    /// The memories and tables defined by the instance keep their
+    /// allocations: they are cleared, shrunk back to their minimum size and
+    /// filled again from the data and element segments of the module. The
+    /// globals are set to their initial values, and the start function of the
+    /// module is run again if `run_start` is set.
+    ///
+    /// Imported memories, tables and globals are not reset, but the
+    /// segments of the module targeting them are applied again, as on
+    /// instantiation.
+    ///
+    /// Memories with a checkpoint or tracking their dirty pages can't be
+    /// reset.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store, TypedFunction};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut store = Store::default();
+    /// let module = Module::new(&store, r#"(module
+    ///   (global $counter (mut i32) (i32.const 0))
+    ///   (func (export "increment") (result i32)
+    ///     (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+    ///     (global.get $counter)))"#)?;
+    /// let instance = Instance::new(&mut store, &module, &imports! {})?;
+    /// let increment: TypedFunction<(), i32> =
+    ///     instance.exports.get_typed_function(&store, "increment")?;
+    /// assert_eq!(increment.call(&mut store)?, 1);
+    ///
+    /// instance.reset(&mut store, true)?;
+    /// assert_eq!(increment.call(&mut store)?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reset(&self, store: &mut impl AsStoreMut, run_start: bool) -> Result<(), RuntimeError> {
+        self._inner.reset(store, &self.module, run_start)
+    }
 }
 
 impl fmt::Debug for Instance {
@@ -107,3 +178,122 @@ impl fmt::Debug for Instance {
             .finish()
     }
 }
+
+#[cfg(feature = "sys")]
+/// A [`Module`] whose imports have been resolved and type-checked once,
+/// ready to be instantiated many times.
+///
+/// Creating an [`Instance`] from an `InstancePre` skips the lookup of the
+/// imports by name and their type checking, leaving only the allocation and
+/// the initialization of the instance.
+///
+/// The imports belong to the [`Store`](crate::Store) they were created in,
+/// so an `InstancePre` can only create instances in that store, and only
+/// while none of them has been removed from it.
+///
+/// ```
+/// # use wasmer::{imports, Store, Module, Global, Value, InstancePre};
+/// # fn main() -> anyhow::Result<()> {
+/// let mut store = Store::default();
+/// let module = Module::new(&store, r#"(module (global (import "host" "var") i32))"#)?;
+/// let imports = imports!{
+///   "host" => {
+///     "var" => Global::new(&mut store, Value::I32(2))
+///   }
+/// };
+/// let instance_pre = InstancePre::new(&store, &module, &imports)?;
+/// for _ in 0..10 {
+///     let _instance = instance_pre.instantiate(&mut store)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InstancePre {
+    module: Module,
+    store_id: StoreId,
+    externs: Vec<Extern>,
+    imports: VMImports,
+}
+
+// The resolved imports hold pointers into the store owning them, which
+// are only used when instantiating in that store, after checking that the
+// externs they point to are still in it.
+#[cfg(feature = "sys")]
+unsafe impl Send for InstancePre {}
+#[cfg(feature = "sys")]
+unsafe impl Sync for InstancePre {}
+
+#[cfg(feature = "sys")]
+impl InstancePre {
+    /// Resolves and type-checks the imports of `module` in the given
+    /// [`Imports`].
+    ///
+    /// ## Errors
+    ///
+    /// The function returns the link errors that [`Instance::new`] would.
+    pub fn new(
+        store: &impl AsStoreRef,
+        module: &Module,
+        imports: &Imports,
+    ) -> Result<Self, InstantiationError> {
+        let externs = imports
+            .imports_for_module(module)
+            .map_err(InstantiationError::Link)?;
+        Self::new_by_index(store, module, &externs)
+    }
+
+    /// Resolves and type-checks the imports of `module`, given in the
+    /// order of the imports of the module.
+    pub fn new_by_index(
+        store: &impl AsStoreRef,
+        module: &Module,
+        externs: &[Extern],
+    ) -> Result<Self, InstantiationError> {
+        let imports = module.0.resolve_imports(store, externs)?;
+        Ok(Self {
+            module: module.clone(),
+            store_id: store.as_store_ref().objects().id(),
+            externs: externs.to_vec(),
+            imports,
+        })
+    }
+
+    /// Gets the [`Module`] to instantiate.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Creates a new [`Instance`] of the module.
+    ///
+    /// ## Errors
+    ///
+    /// The function returns [`InstantiationError::DifferentStores`] if
+    /// `store` is not the store of the imports or if one of them has been
+    /// removed from it, like [`Instance::new`] does, and otherwise the
+    /// runtime errors of the `start` function of the module.
+    pub fn instantiate(&self, store: &mut impl AsStoreMut) -> Result<Instance, InstantiationError> {
+        if store.as_store_ref().objects().id() != self.store_id
+            || !self.externs.iter().all(|ext| ext.is_from_store(store))
+        {
+            return Err(InstantiationError::DifferentStores);
+        }
+        let (_inner, exports) = unsafe {
+            instance_imp::Instance::new_resolved(store, &self.module, self.imports.clone())?
+        };
+        Ok(Instance {
+            _inner,
+            module: self.module.clone(),
+            exports,
+        })
+    }
+}
+
+#[cfg(feature = "sys")]
+impl fmt::Debug for InstancePre {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstancePre")
+            .field("module", &self.module)
+            .finish()
+    }
+}
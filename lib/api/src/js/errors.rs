@@ -1,8 +1,9 @@
 use crate::js::lib::std::string::String;
+use crate::js::lib::std::vec::Vec;
 pub use crate::js::trap::RuntimeError;
 #[cfg(feature = "std")]
 use thiserror::Error;
-use wasmer_types::ImportError;
+use wasmer_types::{ExternType, ImportError};
 
 /// The WebAssembly.LinkError object indicates an error during
 /// module instantiation (besides traps from the start function).
@@ -24,4 +25,21 @@ pub enum LinkError {
     /// Insufficient resources available for linking.
     #[cfg_attr(feature = "std", error("Insufficient resources: {0}"))]
     Resource(String),
+
+    /// An import was defined twice while shadowing is not allowed.
+    #[cfg_attr(feature = "std", error("Import {0:?}.{1:?} is already defined"))]
+    Shadowed(String, String),
+
+    /// Imports of the module, with their expected type, were not found.
+    #[cfg_attr(feature = "std", error("Unknown imports: {}", import_names(.0)))]
+    UnknownImports(Vec<(String, String, ExternType)>),
+}
+
+#[cfg(feature = "std")]
+fn import_names(imports: &[(String, String, ExternType)]) -> String {
+    imports
+        .iter()
+        .map(|(module, name, _)| format!("{:?}.{:?}", module, name))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
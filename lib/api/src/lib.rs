@@ -309,7 +309,11 @@
 //! - `compilation`
 #![cfg_attr(feature = "compiler", doc = "(enabled),")]
 #![cfg_attr(not(feature = "compiler"), doc = "(disabled),")]
-//!   enables compilation with the wasmer engine.
+//!   enables compilation with the wasmer engine,
+//! - `merkle`
+#![cfg_attr(feature = "merkle", doc = "(enabled),")]
+#![cfg_attr(not(feature = "merkle"), doc = "(disabled),")]
+//!   enables Merkle trees over the pages of memories.
 //!
 //! The features that set defaults come in sets that are mutually exclusive.
 //!
@@ -438,6 +442,7 @@ mod function_env;
 mod imports;
 mod instance;
 mod into_bytes;
+mod linker;
 mod mem_access;
 mod module;
 mod native_type;
@@ -468,14 +473,20 @@ pub use extern_ref::ExternRef;
 pub use function_env::{FunctionEnv, FunctionEnvMut};
 pub use imports::Imports;
 pub use instance::Instance;
+#[cfg(feature = "sys")]
+pub use instance::InstancePre;
 pub use into_bytes::IntoBytes;
+pub use linker::{Linker, LinkerFallbackFn};
 pub use mem_access::{MemoryAccessError, WasmRef, WasmSlice, WasmSliceIter};
 pub use module::{IoCompileError, Module};
 pub use native_type::{FromToNativeWasmType, NativeWasmTypeInto, WasmTypeList};
 pub use ptr::{Memory32, Memory64, MemorySize, WasmPtr, WasmPtr64};
 pub use store::{AsStoreMut, AsStoreRef, OnCalledHandler, Store, StoreId, StoreMut, StoreRef};
 #[cfg(feature = "sys")]
-pub use store::{TrapHandlerFn, Tunables};
+pub use store::{
+    CallHook, CallHookFn, CalledFunction, EpochDeadlineAction, InterruptHandle,
+    ResourceLimitAction, ResourceLimiter, StoreCheckpoint, TrapHandlerFn, Tunables,
+};
 pub use typed_function::TypedFunction;
 pub use value::Value;
 
@@ -485,10 +496,10 @@ pub use wasmer_derive::ValueType;
 // TODO: OnCalledAction is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
 pub use wasmer_types::{
     is_wasm, Bytes, CompileError, CpuFeature, DeserializeError, ExportIndex, ExportType,
-    ExternType, FunctionType, GlobalInit, GlobalType, ImportType, LocalFunctionIndex, MemoryError,
-    MemoryType, MiddlewareError, Mutability, OnCalledAction, Pages, ParseCpuFeatureError,
-    SerializeError, TableType, Target, Type, ValueType, WasmError, WasmResult, WASM_MAX_PAGES,
-    WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    ExternType, FunctionIndex, FunctionType, GlobalInit, GlobalType, ImportType, IntegrityError,
+    LocalFunctionIndex, MemoryError, MemoryType, MiddlewareError, Mutability, OnCalledAction,
+    Pages, ParseCpuFeatureError, SerializeError, TableType, Target, TrapCode, Type, ValueType,
+    WasmError, WasmResult, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 #[cfg(feature = "wat")]
 pub use wat::parse_bytes as wat2wasm;
@@ -0,0 +1,297 @@
+//! The linker module resolves the imports of modules against host
+//! definitions and the exports of instances registered under a module name,
+//! so that modules can be linked together.
+use crate::externals::function::{WithEnv, WithoutEnv};
+use crate::imports::Imports;
+use crate::store::{AsStoreMut, StoreMut};
+use crate::{
+    Extern, Function, FunctionEnv, HostFunction, Instance, InstantiationError, LinkError, Module,
+    WasmTypeList,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use wasmer_types::ImportType;
+
+#[cfg(feature = "sys")]
+use crate::InstancePre;
+
+/// A closure resolving the imports a [`Linker`] has no definition for.
+pub type LinkerFallbackFn = dyn Fn(&mut StoreMut<'_>, &ImportType) -> Option<Extern> + Send + Sync;
+
+/// Resolves the imports of modules by `(module, name)`, against host
+/// definitions and the exports of instances registered under a module name.
+///
+/// Unlike [`Imports`], a `Linker` refuses by default to define an import a
+/// second time, reports every import it can't resolve at once, and can
+/// resolve the imports it has no definition for with a fallback closure.
+///
+/// ```
+/// # use wasmer::{FunctionEnv, FunctionEnvMut, Linker, Module, Store, TypedFunction};
+/// # fn main() -> anyhow::Result<()> {
+/// let mut store = Store::default();
+/// let env = FunctionEnv::new(&mut store, 0u32);
+/// let mut linker = Linker::new();
+/// linker.define_typed_with_env(&mut store, &env, "host", "count", |mut env: FunctionEnvMut<u32>| {
+///     *env.data_mut() += 1;
+/// })?;
+///
+/// // The library is instantiated once, and its exports are imported by
+/// // the module linked against it.
+/// let library = Module::new(&store, r#"(module
+///   (import "host" "count" (func $count))
+///   (func (export "double") (param i32) (result i32)
+///     (call $count)
+///     (i32.mul (local.get 0) (i32.const 2))))"#)?;
+/// linker.define_module(&mut store, "lib", &library)?;
+///
+/// let module = Module::new(&store, r#"(module
+///   (import "lib" "double" (func $double (param i32) (result i32)))
+///   (func (export "run") (result i32) (call $double (i32.const 21))))"#)?;
+/// let instance = linker.instantiate(&mut store, &module)?;
+/// let run: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "run")?;
+/// assert_eq!(run.call(&mut store)?, 42);
+/// assert_eq!(*env.as_ref(&store), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct Linker {
+    map: HashMap<(String, String), Extern>,
+    allow_shadowing: bool,
+    fallback: Option<Arc<LinkerFallbackFn>>,
+}
+
+impl Linker {
+    /// Creates an empty `Linker`, which doesn't allow shadowing.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets whether a definition can replace an earlier one with the same
+    /// module and name. When it can't, defining it again returns
+    /// [`LinkError::Shadowed`].
+    pub fn allow_shadowing(&mut self, allow: bool) -> &mut Self {
+        self.allow_shadowing = allow;
+        self
+    }
+
+    /// Sets the closure called for the imports the linker has no definition
+    /// for, which returns the extern to use or `None`.
+    ///
+    /// The extern returned isn't added to the linker.
+    pub fn set_fallback(
+        &mut self,
+        fallback: impl Fn(&mut StoreMut<'_>, &ImportType) -> Option<Extern> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.fallback = Some(Arc::new(fallback));
+        self
+    }
+
+    /// Removes the fallback closure set with [`Linker::set_fallback`].
+    pub fn clear_fallback(&mut self) -> &mut Self {
+        self.fallback = None;
+        self
+    }
+
+    /// Defines the import `name` of `module`.
+    pub fn define(
+        &mut self,
+        module: &str,
+        name: &str,
+        item: impl Into<Extern>,
+    ) -> Result<&mut Self, LinkError> {
+        let key = (module.to_string(), name.to_string());
+        if !self.allow_shadowing && self.map.contains_key(&key) {
+            return Err(LinkError::Shadowed(key.0, key.1));
+        }
+        self.map.insert(key, item.into());
+        Ok(self)
+    }
+
+    /// Defines the import `name` of `module` as a host function.
+    pub fn define_typed<F, Args, Rets>(
+        &mut self,
+        store: &mut impl AsStoreMut,
+        module: &str,
+        name: &str,
+        func: F,
+    ) -> Result<&mut Self, LinkError>
+    where
+        F: HostFunction<(), Args, Rets, WithoutEnv> + 'static + Send + Sync,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        self.check_shadowing(module, name)?;
+        let function = Function::new_typed(store, func);
+        self.define(module, name, function)
+    }
+
+    /// Defines the import `name` of `module` as a host function with the
+    /// environment `env`.
+    pub fn define_typed_with_env<T: Send + 'static, F, Args, Rets>(
+        &mut self,
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        module: &str,
+        name: &str,
+        func: F,
+    ) -> Result<&mut Self, LinkError>
+    where
+        F: HostFunction<T, Args, Rets, WithEnv> + 'static + Send + Sync,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        self.check_shadowing(module, name)?;
+        let function = Function::new_typed_with_env(store, env, func);
+        self.define(module, name, function)
+    }
+
+    /// Defines every export of `instance` as an import of `module`.
+    ///
+    /// When shadowing isn't allowed, nothing is defined if one of the
+    /// exports is already defined.
+    pub fn define_instance(
+        &mut self,
+        module: &str,
+        instance: &Instance,
+    ) -> Result<&mut Self, LinkError> {
+        for (name, _) in instance.exports.iter() {
+            self.check_shadowing(module, name)?;
+        }
+        for (name, ext) in instance.exports.iter() {
+            self.map
+                .insert((module.to_string(), name.clone()), ext.clone());
+        }
+        Ok(self)
+    }
+
+    /// Defines every import of `imports`.
+    ///
+    /// When shadowing isn't allowed, nothing is defined if one of the
+    /// imports is already defined.
+    pub fn define_imports(&mut self, imports: &Imports) -> Result<&mut Self, LinkError> {
+        for (module, name) in imports.map.keys() {
+            self.check_shadowing(module, name)?;
+        }
+        self.map.extend(
+            imports
+                .map
+                .iter()
+                .map(|(key, ext)| (key.clone(), ext.clone())),
+        );
+        Ok(self)
+    }
+
+    /// Instantiates `module` with the imports of this linker, and defines
+    /// its exports as imports of the module `name`, so that the modules
+    /// instantiated after it can import them.
+    pub fn define_module(
+        &mut self,
+        store: &mut impl AsStoreMut,
+        name: &str,
+        module: &Module,
+    ) -> Result<Instance, InstantiationError> {
+        for export in module.exports() {
+            self.check_shadowing(name, export.name())
+                .map_err(InstantiationError::Link)?;
+        }
+        let instance = self.instantiate(store, module)?;
+        self.define_instance(name, &instance)
+            .map_err(InstantiationError::Link)?;
+        Ok(instance)
+    }
+
+    /// Gets the definition of the import `name` of `module`.
+    pub fn get(&self, module: &str, name: &str) -> Option<&Extern> {
+        self.map.get(&(module.to_string(), name.to_string()))
+    }
+
+    /// Iterates over the definitions of this linker, as
+    /// `(module, name, extern)`.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, &Extern)> {
+        self.map
+            .iter()
+            .map(|((module, name), ext)| (module.as_str(), name.as_str(), ext))
+    }
+
+    /// Resolves the imports of `module`, in the order in which the module
+    /// declares them.
+    ///
+    /// The imports without a definition are resolved with the fallback
+    /// closure, if any. The ones left are all reported in a single
+    /// [`LinkError::UnknownImports`].
+    pub fn imports_for_module(
+        &self,
+        store: &mut impl AsStoreMut,
+        module: &Module,
+    ) -> Result<Vec<Extern>, LinkError> {
+        let mut store = store.as_store_mut();
+        let mut externs = Vec::new();
+        let mut unknown = Vec::new();
+        for import in module.imports() {
+            let ext = match self.get(import.module(), import.name()) {
+                Some(ext) => Some(ext.clone()),
+                None => self
+                    .fallback
+                    .as_ref()
+                    .and_then(|fallback| fallback(&mut store, &import)),
+            };
+            match ext {
+                Some(ext) => externs.push(ext),
+                None => unknown.push((
+                    import.module().to_string(),
+                    import.name().to_string(),
+                    import.ty().clone(),
+                )),
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(LinkError::UnknownImports(unknown));
+        }
+        Ok(externs)
+    }
+
+    /// Instantiates `module` with the imports of this linker.
+    pub fn instantiate(
+        &self,
+        store: &mut impl AsStoreMut,
+        module: &Module,
+    ) -> Result<Instance, InstantiationError> {
+        let externs = self
+            .imports_for_module(store, module)
+            .map_err(InstantiationError::Link)?;
+        Instance::new_by_index(store, module, &externs)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Resolves the imports of `module` once as an [`InstancePre`], to
+    /// instantiate it many times.
+    pub fn instantiate_pre(
+        &self,
+        store: &mut impl AsStoreMut,
+        module: &Module,
+    ) -> Result<InstancePre, InstantiationError> {
+        let externs = self
+            .imports_for_module(store, module)
+            .map_err(InstantiationError::Link)?;
+        InstancePre::new_by_index(store, module, &externs)
+    }
+
+    fn check_shadowing(&self, module: &str, name: &str) -> Result<(), LinkError> {
+        if !self.allow_shadowing && self.get(module, name).is_some() {
+            return Err(LinkError::Shadowed(module.to_string(), name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Linker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Linker")
+            .field("definitions", &self.map.len())
+            .field("allow_shadowing", &self.allow_shadowing)
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
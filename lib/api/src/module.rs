@@ -267,6 +267,157 @@ impl Module {
         )?))
     }
 
+    #[cfg(feature = "sys")]
+    /// Deserializes a serialized Module located in a `Path` into a `Module`,
+    /// mapping its code into memory instead of copying it.
+    ///
+    /// The code of the module is mapped executable directly from the file,
+    /// so loading it is cheaper than with [`Module::deserialize_from_file`],
+    /// and the pages holding it are shared with the page cache: only the
+    /// pages patched at load time use private memory. The metadata of the
+    /// module is validated before being deserialized.
+    ///
+    /// If the code can't be mapped, e.g. because the module was serialized
+    /// on a host with a different page size, it is copied instead.
+    ///
+    /// # Safety
+    ///
+    /// Please check [`Module::deserialize`]. Besides, the file must not be
+    /// modified or truncated while the engine is alive: replace it with a
+    /// new file instead.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # let mut store = Store::default();
+    /// # fn main() -> anyhow::Result<()> {
+    /// let module = Module::deserialize_from_file_mapped(&store, path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn deserialize_from_file_mapped(
+        engine: &impl AsEngineRef,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self(module_imp::Module::deserialize_from_file_mapped(
+            engine, path,
+        )?))
+    }
+
+    #[cfg(feature = "sys")]
+    /// Serializes a module into a checked binary representation, which
+    /// can later be safely processed via [`Module::deserialize_checked`].
+    ///
+    /// The serialized module records the engine and target it was
+    /// compiled for, and is signed with `key` if one is given, or
+    /// protected by a checksum otherwise. A checksum only detects
+    /// corruption: sign the module to detect tampering. A module with a
+    /// checksum is deserialized with the unsafe
+    /// [`Module::deserialize_checksummed`].
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut store = Store::default();
+    /// # let module = Module::from_file(&store, "path/to/foo.wasm")?;
+    /// let serialized = module.serialize_checked(&store, Some(b"secret key"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize_checked(
+        &self,
+        engine: &impl AsEngineRef,
+        key: Option<&[u8]>,
+    ) -> Result<Bytes, SerializeError> {
+        self.0.serialize_checked(engine, key)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Deserializes a module serialized with [`Module::serialize_checked`]
+    /// and signed with `key`.
+    ///
+    /// Unlike [`Module::deserialize`], this function is safe: the
+    /// signature is verified before anything is deserialized, as well as
+    /// the engine, the target and the CPU features the module was compiled
+    /// for. A failed check is reported as a [`DeserializeError::Integrity`]
+    /// error.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut store = Store::default();
+    /// let module = Module::deserialize_checked(&store, serialized_data, b"secret key")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deserialize_checked(
+        engine: &impl AsEngineRef,
+        bytes: impl IntoBytes,
+        key: &[u8],
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self(module_imp::Module::deserialize_checked(
+            engine, bytes, key,
+        )?))
+    }
+
+    #[cfg(feature = "sys")]
+    /// Deserializes a module serialized with [`Module::serialize_checked`]
+    /// without a key, and protected by a checksum.
+    ///
+    /// The checks of [`Module::deserialize_checked`] are run with the
+    /// checksum instead of a signature.
+    ///
+    /// # Safety
+    ///
+    /// A checksum only detects corruption: anyone able to alter the bytes
+    /// can compute the checksum of the altered bytes. As with
+    /// [`Module::deserialize`], the bytes must come from a trusted source.
+    pub unsafe fn deserialize_checksummed(
+        engine: &impl AsEngineRef,
+        bytes: impl IntoBytes,
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self(module_imp::Module::deserialize_checksummed(
+            engine, bytes,
+        )?))
+    }
+
+    #[cfg(feature = "sys")]
+    /// Deserializes a module serialized with [`Module::serialize_checked`]
+    /// and located in a `Path`.
+    ///
+    /// The file is read in memory before it is checked, so that it can't
+    /// change in between. Please check [`Module::deserialize_checked`].
+    pub fn deserialize_checked_from_file(
+        engine: &impl AsEngineRef,
+        path: impl AsRef<Path>,
+        key: &[u8],
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self(module_imp::Module::deserialize_checked_from_file(
+            engine, path, key,
+        )?))
+    }
+
+    #[cfg(feature = "sys")]
+    /// Deserializes a module serialized with [`Module::serialize_checked`]
+    /// without a key, and located in a `Path`.
+    ///
+    /// # Safety
+    ///
+    /// Please check [`Module::deserialize_checksummed`].
+    pub unsafe fn deserialize_checksummed_from_file(
+        engine: &impl AsEngineRef,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self(module_imp::Module::deserialize_checksummed_from_file(
+            engine, path,
+        )?))
+    }
+
     /// Returns the name of the current module.
     ///
     /// This name is normally set in the WebAssembly bytecode by some
@@ -379,6 +530,17 @@ impl Module {
         self.0.custom_sections(name)
     }
 
+    #[cfg(feature = "sys")]
+    /// Returns the fingerprint of the middlewares the module was compiled
+    /// with, such as the metering middleware and its cost table.
+    ///
+    /// The fingerprint is all zeros for a module compiled without
+    /// middlewares, and `None` for a module compiled with a middleware
+    /// without a fingerprint, which can't be cached.
+    pub fn middleware_fingerprint(&self) -> Option<[u8; 32]> {
+        self.0.middleware_fingerprint()
+    }
+
     /// The ABI of the [`ModuleInfo`] is very unstable, we refactor it very often.
     /// This function is public because in some cases it can be useful to get some
     /// extra information from the module.
@@ -1,20 +1,33 @@
 use crate::engine::{AsEngineRef, Engine, EngineRef};
+#[cfg(feature = "sys")]
+use crate::{Extern, Instance, RuntimeError};
 use derivative::Derivative;
+#[cfg(feature = "sys")]
+use std::ptr::NonNull;
 use std::{
     fmt,
     ops::{Deref, DerefMut},
 };
 #[cfg(feature = "sys")]
 pub use wasmer_compiler::Tunables;
+#[cfg(feature = "sys")]
+use wasmer_types::{
+    ExportIndex, FunctionIndex, MemoryError, RemoveError, SnapshotError, StoreSnapshot, TrapCode,
+};
 pub use wasmer_types::{OnCalledAction, StoreId};
 #[cfg(feature = "sys")]
 use wasmer_vm::init_traps;
 #[cfg(feature = "sys")]
-pub use wasmer_vm::TrapHandlerFn;
+pub use wasmer_vm::{
+    EpochDeadlineAction, InterruptHandle, ResourceLimitAction, ResourceLimiter, StoreCheckpoint,
+    TrapHandlerFn,
+};
 
 #[cfg(feature = "sys")]
 use crate::sys::NativeEngineExt;
 #[cfg(feature = "sys")]
+use wasmer_vm::VMCallerCheckedAnyfunc;
+#[cfg(feature = "sys")]
 pub use wasmer_vm::{StoreHandle, StoreObjects};
 
 #[cfg(feature = "js")]
@@ -26,6 +39,74 @@ pub type OnCalledHandler = Box<
     dyn FnOnce(StoreMut<'_>) -> Result<OnCalledAction, Box<dyn std::error::Error + Send + Sync>>,
 >;
 
+#[cfg(feature = "sys")]
+/// A transition between the host and WebAssembly code, reported to the
+/// call hook of a store, see [`Store::set_call_hook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallHook {
+    /// The host is calling a WebAssembly function.
+    CallingWasm,
+    /// A WebAssembly function called by the host returned or trapped.
+    ReturningFromWasm,
+    /// WebAssembly code is calling a host function.
+    CallingHost,
+    /// A host function called by WebAssembly code returned or failed.
+    ReturningFromHost,
+}
+
+#[cfg(feature = "sys")]
+/// The function called in a transition reported to the call hook of a
+/// store.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CalledFunction {
+    /// The index of a WebAssembly function in its module, `None` for a
+    /// host function.
+    pub index: Option<FunctionIndex>,
+    /// The name of a WebAssembly function, from the name section of its
+    /// module or else from its exports, or the Rust type name of a host
+    /// function.
+    pub name: Option<String>,
+}
+
+#[cfg(feature = "sys")]
+impl CalledFunction {
+    /// Describes the WebAssembly function of `objects` whose
+    /// `VMCallerCheckedAnyfunc` is `anyfunc`.
+    pub(crate) fn wasm(objects: &StoreObjects, anyfunc: NonNull<VMCallerCheckedAnyfunc>) -> Self {
+        let (instance, index) = match objects.function_instance(anyfunc) {
+            Some(found) => found,
+            None => return Self::default(),
+        };
+        let module = instance.module_ref();
+        let name = module.function_names.get(&index).cloned().or_else(|| {
+            module
+                .exports
+                .iter()
+                .find_map(|(name, export)| match export {
+                    ExportIndex::Function(i) if *i == index => Some(name.clone()),
+                    _ => None,
+                })
+        });
+        Self {
+            index: Some(index),
+            name,
+        }
+    }
+
+    /// Describes the host function `F`.
+    pub(crate) fn host<F>() -> Self {
+        Self {
+            index: None,
+            name: Some(std::any::type_name::<F>().to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "sys")]
+/// The call hook of a store, see [`Store::set_call_hook`].
+pub type CallHookFn =
+    dyn FnMut(StoreMut<'_>, CallHook, &CalledFunction) -> Result<(), RuntimeError> + Send;
+
 /// We require the context to have a fixed memory address for its lifetime since
 /// various bits of the VM have raw pointers that point back to it. Hence we
 /// wrap the actual context in a box.
@@ -40,6 +121,9 @@ pub(crate) struct StoreInner {
     pub(crate) trap_handler: Option<Box<TrapHandlerFn<'static>>>,
     #[derivative(Debug = "ignore")]
     pub(crate) on_called: Option<OnCalledHandler>,
+    #[cfg(feature = "sys")]
+    #[derivative(Debug = "ignore")]
+    pub(crate) call_hook: Option<Box<CallHookFn>>,
 }
 
 /// The store represents all global state that can be manipulated by
@@ -63,13 +147,21 @@ impl Store {
         #[cfg(feature = "sys")]
         init_traps();
 
+        let engine = engine.into();
+        #[allow(unused_mut)]
+        let mut objects = StoreObjects::default();
+        #[cfg(feature = "sys")]
+        objects.set_epoch_counter(engine.0.epoch_counter());
+
         Self {
             inner: Box::new(StoreInner {
-                objects: Default::default(),
-                engine: engine.into(),
+                objects,
+                engine,
                 #[cfg(feature = "sys")]
                 trap_handler: None,
                 on_called: None,
+                #[cfg(feature = "sys")]
+                call_hook: None,
             }),
         }
     }
@@ -130,6 +222,309 @@ impl Store {
     pub fn id(&self) -> StoreId {
         self.inner.objects.id()
     }
+
+    #[cfg(feature = "sys")]
+    /// Captures the state of every memory, table and global in this store.
+    ///
+    /// Function references are recorded relative to the instances and
+    /// host functions of the store, so the snapshot can be restored into
+    /// any store in which the same objects were created in the same order.
+    pub fn snapshot(&self) -> Result<StoreSnapshot, SnapshotError> {
+        self.inner.objects.snapshot()
+    }
+
+    #[cfg(feature = "sys")]
+    /// Restores a snapshot taken with [`Store::snapshot`].
+    ///
+    /// Memories and tables are grown to the size recorded in the snapshot.
+    /// If the snapshot can't be restored, nothing is written.
+    pub fn restore_snapshot(&mut self, snapshot: &StoreSnapshot) -> Result<(), SnapshotError> {
+        self.inner.objects.restore_snapshot(snapshot)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Removes `instance` from this store, freeing it together with the
+    /// memories, tables, globals and functions it defines, instead of
+    /// keeping them until the store is dropped.
+    ///
+    /// The instance can't be removed while another instance imports one of
+    /// its exports, while a table or global outside of the instance holds a
+    /// reference to one of its functions, or while WebAssembly code of the
+    /// store is running. Using the instance or its exports once removed
+    /// panics, and [`Extern::is_from_store`] returns `false` for them.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut store = Store::default();
+    /// let module = Module::new(&store, "(module (memory (export \"memory\") 1))")?;
+    /// for _ in 0..10 {
+    ///     let instance = Instance::new(&mut store, &module, &imports! {})?;
+    ///     // ... use the instance
+    ///     store.remove_instance(&instance)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_instance(&mut self, instance: &Instance) -> Result<(), RemoveError> {
+        instance._inner.remove(self)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Removes a function, memory, table or global created by the host
+    /// from this store.
+    ///
+    /// The object can't be removed while an instance imports it, while a
+    /// table or global holds a reference to it if it is a function, or while
+    /// WebAssembly code of the store is running. The objects exported by an
+    /// instance are removed with [`Store::remove_instance`]. An
+    /// [`InstancePre`](crate::InstancePre) importing a removed object fails
+    /// to instantiate.
+    pub fn remove_extern(&mut self, ext: &Extern) -> Result<(), RemoveError> {
+        if !ext.is_from_store(self) {
+            return Err(RemoveError::NotFound);
+        }
+        self.inner.objects.remove_extern(ext.to_vm_extern())
+    }
+
+    #[cfg(feature = "sys")]
+    /// Starts a checkpoint of every memory, table and global in this store.
+    ///
+    /// Memories only record the pages written after the checkpoint, so
+    /// rolling back is cheap even for large memories. Only one checkpoint
+    /// can be active at a time.
+    pub fn checkpoint(&mut self) -> Result<StoreCheckpoint, MemoryError> {
+        self.inner.objects.checkpoint()
+    }
+
+    #[cfg(feature = "sys")]
+    /// Undoes every change made to the memories, tables and globals of
+    /// this store since `checkpoint`.
+    ///
+    /// Objects created after the checkpoint are left untouched.
+    pub fn rollback(&mut self, checkpoint: StoreCheckpoint) -> Result<(), MemoryError> {
+        self.inner.objects.rollback(checkpoint)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Ends `checkpoint`, keeping the changes made since.
+    pub fn commit(&mut self, checkpoint: StoreCheckpoint) -> Result<(), MemoryError> {
+        self.inner.objects.commit(checkpoint)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Runs `f` as a transaction: if it returns an error, for example the
+    /// [`RuntimeError`](crate::RuntimeError) of a call that trapped, every
+    /// change it made to the memories, tables and globals of this store is
+    /// undone.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut store = Store::default();
+    /// let module = Module::new(&store, r#"(module
+    ///   (memory (export "memory") 1)
+    ///   (func (export "run")
+    ///     (i32.store (i32.const 0) (i32.const 42))
+    ///     unreachable))"#)?;
+    /// let instance = Instance::new(&mut store, &module, &imports! {})?;
+    /// let run = instance.exports.get_typed_function::<(), ()>(&store, "run")?;
+    ///
+    /// assert!(store.transaction(|store| run.call(store)).is_err());
+    /// let memory = instance.exports.get_memory("memory")?;
+    /// assert_eq!(memory.view(&store).read_u8(0)?, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transaction<T, E>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<MemoryError>,
+    {
+        let checkpoint = self.checkpoint()?;
+        match f(self) {
+            Ok(value) => {
+                self.commit(checkpoint)?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.rollback(checkpoint)?;
+                Err(e)
+            }
+        }
+    }
+
+    #[cfg(feature = "sys")]
+    /// Returns a handle to interrupt the WebAssembly code running in this
+    /// store, which can be sent to other threads.
+    ///
+    /// Only the modules compiled by an engine with interrupt checks, see
+    /// [`Engine::set_interrupt_checks`](crate::Engine::set_interrupt_checks),
+    /// can be interrupted. The interrupted call fails with a
+    /// [`RuntimeError`](crate::RuntimeError) whose trap code is
+    /// [`TrapCode::Interrupted`](crate::TrapCode::Interrupted).
+    ///
+    /// The interrupt stays pending until it stops a call: an interrupt sent
+    /// while no code is running stops the next call into the store, as
+    /// soon as it starts.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Engine, Instance, Module, Store, TrapCode};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut engine = Engine::default();
+    /// engine.set_interrupt_checks(true);
+    /// let mut store = Store::new(engine);
+    /// let module = Module::new(&store, r#"(module
+    ///   (func (export "spin") (loop (br 0))))"#)?;
+    /// let instance = Instance::new(&mut store, &module, &imports! {})?;
+    /// let spin = instance.exports.get_typed_function::<(), ()>(&store, "spin")?;
+    ///
+    /// let handle = store.interrupt_handle();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_millis(10));
+    ///     handle.interrupt();
+    /// });
+    /// let error = spin.call(&mut store).unwrap_err();
+    /// assert_eq!(error.to_trap(), Some(TrapCode::Interrupted));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.inner.objects.interrupt_handle()
+    }
+
+    #[cfg(feature = "sys")]
+    /// Sets the epoch deadline of this store to `ticks_beyond_current`
+    /// epochs after the current epoch of its engine, see
+    /// [`Engine::increment_epoch`].
+    ///
+    /// Once the deadline is reached, the running call fails with a
+    /// [`RuntimeError`](crate::RuntimeError) whose trap code is
+    /// [`TrapCode::EpochDeadlineReached`](crate::TrapCode::EpochDeadlineReached),
+    /// unless the callback set with [`Self::set_epoch_deadline_callback`]
+    /// extends the deadline. Calls keep failing until a new deadline is
+    /// set. There is no deadline until one is set.
+    ///
+    /// Only the modules compiled by an engine with epoch interruption, see
+    /// [`Engine::set_epoch_interruption`], check the deadline.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Engine, Instance, Module, Store, TrapCode};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut engine = Engine::default();
+    /// engine.set_epoch_interruption(true);
+    /// let mut store = Store::new(engine);
+    /// let module = Module::new(&store, r#"(module
+    ///   (func (export "spin") (loop (br 0))))"#)?;
+    /// let instance = Instance::new(&mut store, &module, &imports! {})?;
+    /// let spin = instance.exports.get_typed_function::<(), ()>(&store, "spin")?;
+    ///
+    /// let engine = store.engine().clone();
+    /// std::thread::spawn(move || loop {
+    ///     std::thread::sleep(std::time::Duration::from_millis(1));
+    ///     engine.increment_epoch();
+    /// });
+    /// store.set_epoch_deadline(10);
+    /// let error = spin.call(&mut store).unwrap_err();
+    /// assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineReached));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_epoch_deadline(&mut self, ticks_beyond_current: u64) {
+        self.inner.objects.set_epoch_deadline(ticks_beyond_current)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Sets the callback called with the current epoch when this store
+    /// reaches its epoch deadline. It either stops the execution, or lets
+    /// it continue with a new deadline, for example to count the time
+    /// slices used by a call.
+    pub fn set_epoch_deadline_callback(
+        &mut self,
+        callback: impl FnMut(u64) -> EpochDeadlineAction + Send + 'static,
+    ) {
+        self.inner
+            .objects
+            .set_epoch_deadline_callback(Some(Box::new(callback)))
+    }
+
+    #[cfg(feature = "sys")]
+    /// Sets the resource limiter of this store, consulted before any
+    /// instance, memory or table is created in it, and before any of its
+    /// memories or tables grows.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// /// Limits the memories of a store to 10 pages.
+    /// struct TenPages;
+    ///
+    /// impl ResourceLimiter for TenPages {
+    ///     fn memory_growing(&mut self, _: Pages, desired: Pages, _: Option<Pages>) -> ResourceLimitAction {
+    ///         if desired <= Pages(10) {
+    ///             ResourceLimitAction::Allow
+    ///         } else {
+    ///             ResourceLimitAction::Deny
+    ///         }
+    ///     }
+    ///
+    ///     fn table_growing(&mut self, _: u32, _: u32, _: Option<u32>) -> ResourceLimitAction {
+    ///         ResourceLimitAction::Allow
+    ///     }
+    /// }
+    ///
+    /// let mut store = Store::default();
+    /// store.set_resource_limiter(TenPages);
+    /// assert!(Memory::new(&mut store, MemoryType::new(11, None, false)).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_resource_limiter(&mut self, limiter: impl ResourceLimiter + 'static) {
+        self.inner
+            .objects
+            .set_resource_limiter(Some(Box::new(limiter)))
+    }
+
+    #[cfg(feature = "sys")]
+    /// Sets a hook called on every transition between the host and the
+    /// WebAssembly code of this store: when the host calls a WebAssembly
+    /// function and when it returns, and when WebAssembly code calls a
+    /// host function and when it returns.
+    ///
+    /// An error returned by the hook aborts the call with that error. The
+    /// hook is not called for the calls it makes itself.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut store = Store::default();
+    /// let module = Module::new(&store, r#"(module (func (export "run")))"#)?;
+    /// let instance = Instance::new(&mut store, &module, &imports! {})?;
+    /// let run = instance.exports.get_function("run")?;
+    ///
+    /// store.set_call_hook(|_, hook, function| {
+    ///     if hook == CallHook::CallingWasm && function.name.as_deref() == Some("run") {
+    ///         return Err(RuntimeError::new("`run` is not allowed"));
+    ///     }
+    ///     Ok(())
+    /// });
+    /// assert!(run.call(&mut store, &[]).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_call_hook(
+        &mut self,
+        hook: impl FnMut(StoreMut<'_>, CallHook, &CalledFunction) -> Result<(), RuntimeError>
+            + Send
+            + 'static,
+    ) {
+        self.inner.call_hook = Some(Box::new(hook));
+    }
+
+    #[cfg(feature = "sys")]
+    /// Removes the call hook of this store.
+    pub fn clear_call_hook(&mut self) {
+        self.inner.call_hook = None;
+    }
 }
 
 impl PartialEq for Store {
@@ -229,6 +624,15 @@ impl<'a> StoreRef<'a> {
             .as_ref()
             .map(|handler| handler.as_ref() as *const _)
     }
+
+    /// Clears the interrupt of this store if it is what stopped the call
+    /// that failed with `error`.
+    #[cfg(feature = "sys")]
+    pub(crate) fn acknowledge_interrupt(&self, error: &RuntimeError) {
+        if error.clone().to_trap() == Some(TrapCode::Interrupted) {
+            self.inner.objects.clear_interrupt();
+        }
+    }
 }
 
 /// A temporary handle to a [`Store`].
@@ -282,6 +686,26 @@ impl<'a> StoreMut<'a> {
     {
         self.inner.on_called.replace(Box::new(callback));
     }
+
+    /// Calls the call hook of this store, if any, for a transition into or
+    /// out of the function described by `function`.
+    #[cfg(feature = "sys")]
+    pub(crate) fn call_hook(
+        &mut self,
+        hook: CallHook,
+        function: impl FnOnce(&StoreObjects) -> CalledFunction,
+    ) -> Result<(), RuntimeError> {
+        // The hook is taken out while it runs, so that the calls it makes
+        // are not hooked.
+        let mut call_hook = match self.inner.call_hook.take() {
+            Some(call_hook) => call_hook,
+            None => return Ok(()),
+        };
+        let function = function(&self.inner.objects);
+        let result = call_hook(self.as_store_mut(), hook, &function);
+        self.inner.call_hook = Some(call_hook);
+        result
+    }
 }
 
 /// Helper trait for a value that is convertible to a [`StoreRef`].
@@ -1,15 +1,19 @@
 use crate::externals::function::{HostFunction, WithEnv, WithoutEnv};
 use crate::native_type::{FromToNativeWasmType, IntoResult, NativeWasmTypeInto, WasmTypeList};
-use crate::store::{AsStoreMut, AsStoreRef, StoreInner, StoreMut};
+use crate::store::{AsStoreMut, AsStoreRef, CallHook, CalledFunction, StoreInner, StoreMut};
 use crate::vm::VMExternFunction;
 use crate::{FunctionEnv, FunctionEnvMut, FunctionType, RuntimeError, Value};
+use std::error::Error;
+use std::future::Future;
 use std::panic::{self, AssertUnwindSafe};
-use std::{cell::UnsafeCell, cmp::max, ffi::c_void};
+use std::pin::Pin;
+use std::{cell::UnsafeCell, cmp::max, ffi::c_void, ptr::NonNull};
 use wasmer_types::{NativeWasmType, RawValue};
 use wasmer_vm::{
-    on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, MaybeInstanceOwned,
-    StoreHandle, VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext, VMExtern, VMFuncRef,
-    VMFunction, VMFunctionBody, VMFunctionContext, VMFunctionKind, VMTrampoline,
+    await_on_wasm_stack, on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline,
+    AsyncWasmCall, AwaitError, MaybeInstanceOwned, StoreHandle, VMCallerCheckedAnyfunc, VMContext,
+    VMDynamicFunctionContext, VMExtern, VMFuncRef, VMFunction, VMFunctionBody, VMFunctionContext,
+    VMFunctionKind, VMTrampoline,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -53,7 +57,12 @@ impl Function {
                     store_mut,
                     func_env: func_env.clone(),
                 };
-                let returns = func(env, &args)?;
+                store.call_hook(CallHook::CallingHost, |_| CalledFunction::host::<F>())?;
+                let returns = func(env, &args);
+                let returned =
+                    store.call_hook(CallHook::ReturningFromHost, |_| CalledFunction::host::<F>());
+                let returns = returns?;
+                returned?;
 
                 // We need to dynamically check that the returns
                 // match the expected types, as well as expected length.
@@ -108,6 +117,95 @@ impl Function {
         }
     }
 
+    pub fn new_async_with_env<FT, F, Fut, T: Send + 'static>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        ty: FT,
+        func: F,
+    ) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(FunctionEnvMut<T>, &[Value]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Value>, RuntimeError>> + 'static + Send,
+    {
+        let function_type = ty.into();
+        let func_ty = function_type.clone();
+        let func_env = env.clone();
+        let raw_store = store.as_store_mut().as_raw() as *mut u8;
+        let wrapper = move |values_vec: *mut RawValue| -> Result<HostFuture, RuntimeError> {
+            unsafe {
+                let mut store = StoreMut::from_raw(raw_store as *mut StoreInner);
+                let mut args = Vec::with_capacity(func_ty.params().len());
+                for (i, ty) in func_ty.params().iter().enumerate() {
+                    args.push(Value::from_raw(&mut store, *ty, *values_vec.add(i)));
+                }
+                let store_mut = StoreMut::from_raw(raw_store as *mut StoreInner);
+                let env = FunctionEnvMut {
+                    store_mut,
+                    func_env: func_env.clone(),
+                };
+                store.call_hook(CallHook::CallingHost, |_| CalledFunction::host::<F>())?;
+                let future = func(env, &args);
+                let func_ty = func_ty.clone();
+                Ok(Box::pin(async move {
+                    let returns = future.await;
+                    let mut store = StoreMut::from_raw(raw_store as *mut StoreInner);
+                    let returned = store
+                        .call_hook(CallHook::ReturningFromHost, |_| CalledFunction::host::<F>());
+                    let returns = returns?;
+                    returned?;
+
+                    let return_types = returns.iter().map(|ret| ret.ty());
+                    if return_types.ne(func_ty.results().iter().copied()) {
+                        return Err(RuntimeError::new(format!(
+                            "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
+                            func_ty.results(),
+                            returns.iter().map(|ret| ret.ty())
+                        )));
+                    }
+                    for (i, ret) in returns.iter().enumerate() {
+                        *values_vec.add(i) = ret.as_raw(&store);
+                    }
+                    Ok(())
+                }) as HostFuture)
+            }
+        };
+        let mut host_data = Box::new(VMDynamicFunctionContext {
+            address: std::ptr::null(),
+            ctx: AsyncDynamicFunction { func: wrapper },
+        });
+        host_data.address = host_data.ctx.func_body_ptr();
+
+        // As for `new_with_env`, the engine linker replaces the address
+        // with one pointing to a generated dynamic trampoline.
+        let func_ptr = std::ptr::null() as *const VMFunctionBody;
+        let type_index = store
+            .as_store_mut()
+            .engine()
+            .0
+            .register_signature(&function_type);
+        let vmctx = VMFunctionContext {
+            host_env: host_data.as_ref() as *const _ as *mut c_void,
+        };
+        let call_trampoline = host_data.ctx.call_trampoline_address();
+        let anyfunc = VMCallerCheckedAnyfunc {
+            func_ptr,
+            type_index,
+            vmctx,
+            call_trampoline,
+        };
+
+        let vm_function = VMFunction {
+            anyfunc: MaybeInstanceOwned::Host(Box::new(UnsafeCell::new(anyfunc))),
+            kind: VMFunctionKind::Dynamic,
+            signature: function_type,
+            host_data,
+        };
+        Self {
+            handle: StoreHandle::new(store.as_store_mut().objects_mut(), vm_function),
+        }
+    }
+
     /// Creates a new host `Function` from a native function.
     pub fn new_typed<F, Args, Rets>(store: &mut impl AsStoreMut, func: F) -> Self
     where
@@ -236,16 +334,36 @@ impl Function {
             )));
         }
 
-        let mut values_vec = vec![RawValue { i32: 0 }; max(params.len(), results.len())];
+        let values_vec = self.raw_params(store, &signature, params, results.len())?;
+
+        // Invoke the call
+        self.call_wasm_raw(store, trampoline, values_vec, results)?;
+        Ok(())
+    }
+
+    /// Converts `params` into the raw values passed to the function, in a
+    /// buffer large enough to also hold its results.
+    fn raw_params(
+        &self,
+        store: &mut impl AsStoreMut,
+        signature: &FunctionType,
+        params: &[Value],
+        results_len: usize,
+    ) -> Result<Vec<RawValue>, RuntimeError> {
+        let mut values_vec = vec![RawValue { i32: 0 }; max(params.len(), results_len)];
 
         // Store the argument values into `values_vec`.
         let param_tys = signature.params().iter();
         for ((arg, slot), ty) in params.iter().zip(&mut values_vec).zip(param_tys) {
             if arg.ty() != *ty {
-                let param_types = format_types_for_error_message(params);
+                let param_types = params
+                    .iter()
+                    .map(|param| param.ty().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
                 return Err(RuntimeError::new(format!(
                     "Parameters of type [{}] did not match signature {}",
-                    param_types, &signature,
+                    param_types, signature,
                 )));
             }
             if !arg.is_from_store(store) {
@@ -253,10 +371,7 @@ impl Function {
             }
             *slot = arg.as_raw(store);
         }
-
-        // Invoke the call
-        self.call_wasm_raw(store, trampoline, values_vec, results)?;
-        Ok(())
+        Ok(values_vec)
     }
 
     fn call_wasm_raw(
@@ -266,43 +381,12 @@ impl Function {
         mut params: Vec<RawValue>,
         results: &mut [Value],
     ) -> Result<(), RuntimeError> {
-        // Call the trampoline.
-        let result = {
-            let mut r;
-            // TODO: This loop is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
-            loop {
-                let vm_function = self.handle.get(store.as_store_ref().objects());
-                r = unsafe {
-                    wasmer_call_trampoline(
-                        store.as_store_ref().signal_handler(),
-                        vm_function.anyfunc.as_ptr().as_ref().vmctx,
-                        trampoline,
-                        vm_function.anyfunc.as_ptr().as_ref().func_ptr,
-                        params.as_mut_ptr() as *mut u8,
-                    )
-                };
-                let store_mut = store.as_store_mut();
-                if let Some(callback) = store_mut.inner.on_called.take() {
-                    match callback(store_mut) {
-                        Ok(wasmer_types::OnCalledAction::InvokeAgain) => {
-                            continue;
-                        }
-                        Ok(wasmer_types::OnCalledAction::Finish) => {
-                            break;
-                        }
-                        Ok(wasmer_types::OnCalledAction::Trap(trap)) => {
-                            return Err(RuntimeError::user(trap))
-                        }
-                        Err(trap) => return Err(RuntimeError::user(trap)),
-                    }
-                }
-                break;
-            }
-            r
-        };
-        if let Err(error) = result {
-            return Err(RuntimeError::from_trap(error));
-        }
+        let anyfunc = self
+            .handle
+            .get(store.as_store_ref().objects())
+            .anyfunc
+            .as_ptr();
+        call_wasm_trampoline(store, anyfunc, trampoline, params.as_mut_ptr())?;
 
         // Load the return values out of `values_vec`.
         let signature = self.ty(store);
@@ -357,6 +441,121 @@ impl Function {
         Ok(results.into_boxed_slice())
     }
 
+    pub async fn call_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+    ) -> Result<Box<[Value]>, RuntimeError> {
+        let signature = self.ty(store);
+        if signature.params().len() != params.len() {
+            return Err(RuntimeError::new(format!(
+                "Parameters of type [{}] did not match signature {}",
+                params
+                    .iter()
+                    .map(|param| param.ty().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                &signature
+            )));
+        }
+        let mut values_vec =
+            self.raw_params(store, &signature, params, signature.results().len())?;
+
+        self.call_raw_async(store, &mut values_vec).await?;
+
+        let results = signature
+            .results()
+            .iter()
+            .zip(values_vec)
+            .map(|(&value_type, raw)| unsafe { Value::from_raw(store, value_type, raw) })
+            .collect();
+        Ok(results)
+    }
+
+    /// Calls the function asynchronously, with `values` holding its
+    /// arguments and then its results.
+    pub(crate) async fn call_raw_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        values: &mut [RawValue],
+    ) -> Result<(), RuntimeError> {
+        self.call_hook(store, CallHook::CallingWasm)?;
+        let result = self.call_wasm_async_unhooked(store, values).await;
+        let returned = self.call_hook(store, CallHook::ReturningFromWasm);
+        result?;
+        returned
+    }
+
+    fn call_hook(&self, store: &mut impl AsStoreMut, hook: CallHook) -> Result<(), RuntimeError> {
+        let anyfunc = self
+            .handle
+            .get(store.as_store_ref().objects())
+            .anyfunc
+            .as_ptr();
+        store
+            .as_store_mut()
+            .call_hook(hook, |objects| CalledFunction::wasm(objects, anyfunc))
+    }
+
+    async fn call_wasm_async_unhooked(
+        &self,
+        store: &mut impl AsStoreMut,
+        values: &mut [RawValue],
+    ) -> Result<(), RuntimeError> {
+        let mut r;
+        loop {
+            let call = self.async_wasm_call(store, values);
+            // No object can be removed from the store during the call, even
+            // while it is suspended.
+            let guard = WasmCallGuard::begin(store);
+            r = call.await;
+            drop(guard);
+            let store_mut = store.as_store_mut();
+            if let Some(callback) = store_mut.inner.on_called.take() {
+                match callback(store_mut) {
+                    Ok(wasmer_types::OnCalledAction::InvokeAgain) => {
+                        continue;
+                    }
+                    Ok(wasmer_types::OnCalledAction::Finish) => {
+                        break;
+                    }
+                    Ok(wasmer_types::OnCalledAction::Trap(trap)) => {
+                        return Err(RuntimeError::user(trap))
+                    }
+                    Err(trap) => return Err(RuntimeError::user(trap)),
+                }
+            }
+            break;
+        }
+        if let Err(error) = r {
+            let error = RuntimeError::from_trap(error);
+            store.as_store_ref().acknowledge_interrupt(&error);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    fn async_wasm_call(
+        &self,
+        store: &mut impl AsStoreMut,
+        values: &mut [RawValue],
+    ) -> AsyncWasmCall {
+        let anyfunc = self
+            .handle
+            .get(store.as_store_ref().objects())
+            .anyfunc
+            .as_ptr();
+        unsafe {
+            AsyncWasmCall::new(
+                store.as_store_ref().signal_handler(),
+                anyfunc.as_ref().vmctx,
+                anyfunc.as_ref().call_trampoline,
+                anyfunc.as_ref().func_ptr,
+                values.as_mut_ptr() as *mut u8,
+            )
+        }
+    }
+
     pub(crate) fn vm_funcref(&self, store: &impl AsStoreRef) -> VMFuncRef {
         let vm_function = self.handle.get(store.as_store_ref().objects());
         if vm_function.kind == VMFunctionKind::Dynamic {
@@ -395,7 +594,7 @@ impl Function {
 
     /// Checks whether this `Function` can be used with the given store.
     pub fn is_from_store(&self, store: &impl AsStoreRef) -> bool {
-        self.handle.store_id() == store.as_store_ref().objects().id()
+        self.handle.is_alive(store.as_store_ref().objects())
     }
 
     pub(crate) fn to_vm_extern(&self) -> VMExtern {
@@ -403,6 +602,118 @@ impl Function {
     }
 }
 
+/// Calls the WebAssembly function `anyfunc` of the store through
+/// `trampoline`, with `values` holding its arguments and then its results.
+///
+/// The call hook of the store is called before and after the call. An
+/// error of the function takes precedence over an error of the hook.
+pub(crate) fn call_wasm_trampoline(
+    store: &mut impl AsStoreMut,
+    anyfunc: NonNull<VMCallerCheckedAnyfunc>,
+    trampoline: VMTrampoline,
+    values: *mut RawValue,
+) -> Result<(), RuntimeError> {
+    store
+        .as_store_mut()
+        .call_hook(CallHook::CallingWasm, |objects| {
+            CalledFunction::wasm(objects, anyfunc)
+        })?;
+    let result = call_wasm_trampoline_unhooked(store, anyfunc, trampoline, values);
+    let returned = store
+        .as_store_mut()
+        .call_hook(CallHook::ReturningFromWasm, |objects| {
+            CalledFunction::wasm(objects, anyfunc)
+        });
+    result?;
+    returned
+}
+
+fn call_wasm_trampoline_unhooked(
+    store: &mut impl AsStoreMut,
+    anyfunc: NonNull<VMCallerCheckedAnyfunc>,
+    trampoline: VMTrampoline,
+    values: *mut RawValue,
+) -> Result<(), RuntimeError> {
+    let mut r;
+    // TODO: This loop is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
+    loop {
+        let signal_handler = store.as_store_ref().signal_handler();
+        // No object can be removed from the store during the call, and the
+        // guard ends it even if a host function panics.
+        let guard = WasmCallGuard::begin(store);
+        r = unsafe {
+            wasmer_call_trampoline(
+                signal_handler,
+                anyfunc.as_ref().vmctx,
+                trampoline,
+                anyfunc.as_ref().func_ptr,
+                values as *mut u8,
+            )
+        };
+        drop(guard);
+        let store_mut = store.as_store_mut();
+        if let Some(callback) = store_mut.inner.on_called.take() {
+            match callback(store_mut) {
+                Ok(wasmer_types::OnCalledAction::InvokeAgain) => {
+                    continue;
+                }
+                Ok(wasmer_types::OnCalledAction::Finish) => {
+                    break;
+                }
+                Ok(wasmer_types::OnCalledAction::Trap(trap)) => {
+                    return Err(RuntimeError::user(trap))
+                }
+                Err(trap) => return Err(RuntimeError::user(trap)),
+            }
+        }
+        break;
+    }
+    if let Err(error) = r {
+        let error = RuntimeError::from_trap(error);
+        store.as_store_ref().acknowledge_interrupt(&error);
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Counts a call in the wasm calls of a store, until it returns, unwinds,
+/// or, for an asynchronous call, is cancelled.
+struct WasmCallGuard<'a, S: AsStoreMut> {
+    store: &'a mut S,
+}
+
+impl<'a, S: AsStoreMut> WasmCallGuard<'a, S> {
+    fn begin(store: &'a mut S) -> Self {
+        store.objects_mut().begin_wasm_call();
+        Self { store }
+    }
+}
+
+impl<'a, S: AsStoreMut> Drop for WasmCallGuard<'a, S> {
+    fn drop(&mut self) {
+        self.store.objects_mut().end_wasm_call();
+    }
+}
+
+/// Calls the host function `func`, of type `F`, from WebAssembly code.
+///
+/// The call hook of the store is called before and after the call. An
+/// error of the function takes precedence over an error of the hook.
+fn call_host<F, Rets, E>(
+    store: &mut StoreMut,
+    func: impl FnOnce() -> Result<Rets, E>,
+) -> Result<Rets, Box<dyn Error + Send + Sync>>
+where
+    E: Error + Send + Sync + 'static,
+{
+    store.call_hook(CallHook::CallingHost, |_| CalledFunction::host::<F>())?;
+    let result = func();
+    let returned = store.call_hook(CallHook::ReturningFromHost, |_| CalledFunction::host::<F>());
+    let result = result?;
+    returned?;
+    Ok(result)
+}
+
 /// Host state for a dynamic function.
 pub(crate) struct DynamicFunction<F> {
     func: F,
@@ -448,6 +759,67 @@ where
     }
 }
 
+/// The future of an asynchronous host function, writing its results.
+type HostFuture = Pin<Box<dyn Future<Output = Result<(), RuntimeError>>>>;
+
+/// Host state for an asynchronous dynamic function.
+pub(crate) struct AsyncDynamicFunction<F> {
+    func: F,
+}
+
+impl<F> AsyncDynamicFunction<F>
+where
+    F: Fn(*mut RawValue) -> Result<HostFuture, RuntimeError> + 'static,
+{
+    // This function wraps our func, to make it compatible with the
+    // reverse trampoline signature. It runs on the Wasm stack, which is
+    // suspended while the future of the function is pending.
+    unsafe extern "C" fn func_wrapper(
+        this: &mut VMDynamicFunctionContext<Self>,
+        values_vec: *mut RawValue,
+    ) {
+        let future =
+            on_host_stack(|| panic::catch_unwind(AssertUnwindSafe(|| (this.ctx.func)(values_vec))));
+        let mut future = match future {
+            Ok(Ok(future)) => future,
+            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+            Err(panic) => resume_panic(panic),
+        };
+
+        let result = await_on_wasm_stack(future.as_mut());
+        // Nothing may be left to drop on the Wasm stack when it is unwound.
+        drop(future);
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+            Err(AwaitError::NotAsync) => raise_user_trap(Box::new(RuntimeError::new(
+                "asynchronous host functions can only be called from an asynchronous call",
+            ))),
+            Err(AwaitError::Cancelled) => raise_user_trap(Box::new(RuntimeError::new(
+                "the asynchronous call was cancelled",
+            ))),
+            Err(AwaitError::Panic(panic)) => resume_panic(panic),
+        }
+    }
+
+    fn func_body_ptr(&self) -> *const VMFunctionBody {
+        Self::func_wrapper as *const VMFunctionBody
+    }
+
+    fn call_trampoline_address(&self) -> VMTrampoline {
+        Self::call_trampoline
+    }
+
+    unsafe extern "C" fn call_trampoline(
+        vmctx: *mut VMContext,
+        _body: *const VMFunctionBody,
+        args: *mut RawValue,
+    ) {
+        let dynamic_function = &mut *(vmctx as *mut VMDynamicFunctionContext<Self>);
+        Self::func_wrapper(dynamic_function, args);
+    }
+}
+
 /// Represents a low-level Wasm static host function. See
 /// [`crate::Function::new_typed`] and
 /// [`crate::Function::new_typed_with_env`] to learn more.
@@ -496,19 +868,21 @@ macro_rules! impl_host_function {
                                     let $x = FromToNativeWasmType::from_native(NativeWasmTypeInto::from_abi(&mut store, $x));
                                 )*
                                 // println!("func wrapper2 {:p}", *env.raw_env);
-                                let store_mut = StoreMut::from_raw(env.raw_store as *mut _);
-                                let f_env = FunctionEnvMut {
-                                    store_mut,
-                                    func_env: env.env.clone(),
-                                };
-                                // println!("func wrapper3");
-                                (env.func)(f_env, $($x),* ).into_result()
+                                call_host::<Func, _, _>(&mut store, || {
+                                    let store_mut = StoreMut::from_raw(env.raw_store as *mut _);
+                                    let f_env = FunctionEnvMut {
+                                        store_mut,
+                                        func_env: env.env.clone(),
+                                    };
+                                    // println!("func wrapper3");
+                                    (env.func)(f_env, $($x),* ).into_result()
+                                })
                             }))
                         });
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(&mut store),
-                            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+                            Ok(Err(trap)) => raise_user_trap(trap),
                             Err(panic) => resume_panic(panic) ,
                         }
                     }
@@ -579,13 +953,13 @@ macro_rules! impl_host_function {
                                 $(
                                     let $x = FromToNativeWasmType::from_native(NativeWasmTypeInto::from_abi(&mut store, $x));
                                 )*
-                                (env.func)($($x),* ).into_result()
+                                call_host::<Func, _, _>(&mut store, || (env.func)($($x),* ).into_result())
                             }))
                         });
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(&mut store),
-                            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+                            Ok(Err(trap)) => raise_user_trap(trap),
                             Err(panic) => resume_panic(panic) ,
                         }
                     }
@@ -84,7 +84,7 @@ impl Global {
     }
 
     pub fn is_from_store(&self, store: &impl AsStoreRef) -> bool {
-        self.handle.store_id() == store.as_store_ref().objects().id()
+        self.handle.is_alive(store.as_store_ref().objects())
     }
 
     pub(crate) fn to_vm_extern(&self) -> VMExtern {
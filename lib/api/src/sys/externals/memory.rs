@@ -12,7 +12,7 @@ use std::slice;
 #[cfg(feature = "tracing")]
 use tracing::warn;
 use wasmer_types::Pages;
-use wasmer_vm::{LinearMemory, MemoryError, StoreHandle, VMExtern, VMMemory};
+use wasmer_vm::{DirtyPages, LinearMemory, MemoryError, StoreHandle, Trap, VMExtern, VMMemory};
 
 #[derive(Debug, Clone)]
 pub struct Memory {
@@ -22,6 +22,11 @@ pub struct Memory {
 impl Memory {
     pub fn new(store: &mut impl AsStoreMut, ty: MemoryType) -> Result<Self, MemoryError> {
         let mut store = store.as_store_mut();
+        if !memory_growing(&mut store, Pages(0), ty.minimum, ty.maximum)? {
+            return Err(MemoryError::Generic(
+                "the resource limiter denied the creation of the memory".to_string(),
+            ));
+        }
         let tunables = store.engine().tunables();
         let style = tunables.memory_style(&ty);
         let memory = tunables.create_host_memory(&ty, &style)?;
@@ -52,7 +57,40 @@ impl Memory {
     where
         IntoPages: Into<Pages>,
     {
-        self.handle.get_mut(store.objects_mut()).grow(delta.into())
+        let delta = delta.into();
+        let memory = self.handle.get(store.as_store_ref().objects());
+        let current = memory.size();
+        let maximum = memory.ty().maximum;
+        let desired = Pages(current.0.saturating_add(delta.0));
+        if !memory_growing(store, current, desired, maximum)? {
+            return Err(MemoryError::CouldNotGrow {
+                current,
+                attempted_delta: delta,
+            });
+        }
+        self.handle.get_mut(store.objects_mut()).grow(delta)
+    }
+
+    pub fn enable_dirty_tracking(&self, store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
+        self.handle
+            .get_mut(store.objects_mut())
+            .enable_dirty_tracking()
+    }
+
+    pub fn disable_dirty_tracking(&self, store: &mut impl AsStoreMut) {
+        self.handle
+            .get_mut(store.objects_mut())
+            .disable_dirty_tracking()
+    }
+
+    pub fn dirty_pages(&self, store: &impl AsStoreRef) -> Result<DirtyPages, MemoryError> {
+        self.handle
+            .get(store.as_store_ref().objects())
+            .dirty_pages()
+    }
+
+    pub fn reset_dirty_pages(&self, store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
+        self.handle.get_mut(store.objects_mut()).reset_dirty_pages()
     }
 
     pub fn copy_to_store(
@@ -93,7 +131,7 @@ impl Memory {
 
     /// Checks whether this `Memory` can be used with the given context.
     pub fn is_from_store(&self, store: &impl AsStoreRef) -> bool {
-        self.handle.store_id() == store.as_store_ref().objects().id()
+        self.handle.is_alive(store.as_store_ref().objects())
     }
 
     pub fn try_clone(&self, store: &impl AsStoreRef) -> Option<VMMemory> {
@@ -115,6 +153,23 @@ impl std::cmp::PartialEq for Memory {
 
 impl std::cmp::Eq for Memory {}
 
+/// Asks the resource limiter of the store whether a memory may grow from
+/// `current` to `desired` pages, turning its traps into errors.
+fn memory_growing(
+    store: &mut impl AsStoreMut,
+    current: Pages,
+    desired: Pages,
+    maximum: Option<Pages>,
+) -> Result<bool, MemoryError> {
+    store
+        .objects_mut()
+        .memory_growing(current, desired, maximum)
+        .map_err(|trap| match trap {
+            Trap::Lib { trap_code, .. } => MemoryError::Trap(trap_code),
+            trap => MemoryError::Generic(format!("{:?}", trap)),
+        })
+}
+
 /// Underlying buffer for a memory.
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct MemoryBuffer<'a> {
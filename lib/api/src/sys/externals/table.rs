@@ -55,6 +55,15 @@ impl Table {
     ) -> Result<Self, RuntimeError> {
         let item = value_to_table_element(&mut store, init)?;
         let mut store = store.as_store_mut();
+        if !store
+            .objects_mut()
+            .table_growing(0, ty.minimum, ty.maximum)
+            .map_err(RuntimeError::from_trap)?
+        {
+            return Err(RuntimeError::new(
+                "the resource limiter denied the creation of the table",
+            ));
+        }
         let tunables = store.engine().tunables();
         let style = tunables.table_style(&ty);
         let mut table = tunables
@@ -101,9 +110,16 @@ impl Table {
         init: Value,
     ) -> Result<u32, RuntimeError> {
         let item = value_to_table_element(store, init)?;
-        self.handle
-            .get_mut(store.objects_mut())
-            .grow(delta, item)
+        let table = self.handle.get(store.as_store_ref().objects());
+        let current = table.size();
+        let maximum = table.ty().maximum;
+        let allowed = store
+            .objects_mut()
+            .table_growing(current, current.saturating_add(delta), maximum)
+            .map_err(RuntimeError::from_trap)?;
+        allowed
+            .then(|| self.handle.get_mut(store.objects_mut()).grow(delta, item))
+            .flatten()
             .ok_or_else(|| RuntimeError::new(format!("failed to grow table by `{}`", delta)))
     }
 
@@ -145,7 +161,7 @@ impl Table {
 
     /// Checks whether this `Table` can be used with the given context.
     pub fn is_from_store(&self, store: &impl AsStoreRef) -> bool {
-        self.handle.store_id() == store.as_store_ref().objects().id()
+        self.handle.is_alive(store.as_store_ref().objects())
     }
 
     pub(crate) fn to_vm_extern(&self) -> VMExtern {
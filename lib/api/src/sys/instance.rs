@@ -1,11 +1,13 @@
 use crate::errors::InstantiationError;
 use crate::exports::Exports;
 use crate::module::Module;
+use wasmer_types::{RemoveError, SnapshotError, StoreSnapshot};
 use wasmer_vm::{StoreHandle, VMInstance};
 
 use crate::imports::Imports;
-use crate::store::AsStoreMut;
-use crate::Extern;
+use crate::store::{AsStoreMut, AsStoreRef};
+use crate::vm::VMImports;
+use crate::{Extern, RuntimeError};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Instance {
@@ -70,6 +72,55 @@ impl Instance {
         Ok((instance, exports))
     }
 
+    /// # Safety
+    ///
+    /// The imports must have been resolved for `module` in `store`.
+    pub(crate) unsafe fn new_resolved(
+        store: &mut impl AsStoreMut,
+        module: &Module,
+        imports: VMImports,
+    ) -> Result<(Self, Exports), InstantiationError> {
+        let mut handle = module.0.instantiate_resolved(store, imports)?;
+        let exports = Self::get_exports(store, module, &mut handle);
+        let instance = Self {
+            _handle: StoreHandle::new(store.objects_mut(), handle),
+        };
+
+        Ok((instance, exports))
+    }
+
+    pub(crate) fn snapshot(&self, store: &impl AsStoreRef) -> Result<StoreSnapshot, SnapshotError> {
+        self._handle.get(store.as_store_ref().objects()).snapshot()
+    }
+
+    pub(crate) fn restore_snapshot(
+        &self,
+        store: &mut impl AsStoreMut,
+        snapshot: &StoreSnapshot,
+    ) -> Result<(), SnapshotError> {
+        self._handle
+            .get_mut(store.objects_mut())
+            .restore_snapshot(snapshot)
+    }
+
+    pub(crate) fn remove(&self, store: &mut impl AsStoreMut) -> Result<(), RemoveError> {
+        if self._handle.store_id() != store.as_store_ref().objects().id() {
+            return Err(RemoveError::NotFound);
+        }
+        store
+            .objects_mut()
+            .remove_instance(self._handle.internal_handle())
+    }
+
+    pub(crate) fn reset(
+        &self,
+        store: &mut impl AsStoreMut,
+        module: &Module,
+        run_start: bool,
+    ) -> Result<(), RuntimeError> {
+        unsafe { module.0.reset_instance(store, &self._handle, run_start) }
+    }
+
     fn get_exports(
         store: &mut impl AsStoreMut,
         module: &Module,
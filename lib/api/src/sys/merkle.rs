@@ -0,0 +1,179 @@
+//! Merkle commitments to the contents of linear memories.
+
+use crate::store::AsStoreMut;
+use crate::{Memory, MemoryView};
+use sha2::{Digest, Sha256};
+use wasmer_types::{MemoryError, WASM_PAGE_SIZE};
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const ROOT_PREFIX: u8 = 2;
+
+/// The node used to pad the leaves up to a power of two.
+const EMPTY_NODE: [u8; 32] = [0; 32];
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// A Merkle tree over the pages of a linear memory.
+///
+/// The tree is built over the WebAssembly pages of the memory, so its root
+/// only depends on the contents and the size of the memory, not on the
+/// host page size or on the compiler. With `H` being SHA-256:
+///
+/// - every page is a leaf `H(0x00 || data)`, over its 65536 bytes;
+/// - the leaves are padded with `[0; 32]` up to the next power of two, and
+///   every inner node is `H(0x01 || left || right)`;
+/// - the root is `H(0x02 || pages || top)`, where `pages` is the number of
+///   pages of the memory as a little-endian `u32` and `top` is the top
+///   node of the tree.
+///
+/// A tree built with [`Memory::merkle_tree`] can be brought up to date
+/// with [`MemoryMerkleTree::update`], which only rehashes the pages
+/// written since the previous update.
+#[derive(Debug, Clone)]
+pub struct MemoryMerkleTree {
+    /// Number of pages of the memory.
+    pages: u32,
+    /// The nodes of the tree, level by level from the leaves up to the
+    /// top node.
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MemoryMerkleTree {
+    /// Builds the tree over every page of a memory.
+    pub fn new(view: &MemoryView) -> Self {
+        let mut tree = Self {
+            pages: 0,
+            levels: Vec::new(),
+        };
+        tree.rehash(view, Vec::new());
+        tree
+    }
+
+    /// Returns the root of the tree, which commits to the contents and the
+    /// size of the memory.
+    pub fn root(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([ROOT_PREFIX]);
+        hasher.update(self.pages.to_le_bytes());
+        hasher.update(self.levels.last().unwrap()[0]);
+        hasher.finalize().into()
+    }
+
+    /// Rehashes the pages of `memory` written since the tree was built or
+    /// last updated, and returns the new root.
+    ///
+    /// The tree relies on the dirty page tracking of the memory, enabled
+    /// by [`Memory::merkle_tree`], and resets it.
+    pub fn update(
+        &mut self,
+        store: &mut impl AsStoreMut,
+        memory: &Memory,
+    ) -> Result<[u8; 32], MemoryError> {
+        let dirty = memory.dirty_pages(store)?;
+        self.rehash(&memory.view(store), dirty.wasm_pages());
+        memory.reset_dirty_pages(store)?;
+        Ok(self.root())
+    }
+
+    /// Rehashes the pages in `written`, as well as the pages added or
+    /// removed since the last time, and the nodes above them.
+    fn rehash(&mut self, view: &MemoryView, written: Vec<u32>) {
+        let pages = view.size().0;
+        let capacity = (pages as usize).next_power_of_two();
+        let resized = self
+            .levels
+            .first()
+            .map_or(true, |leaves| leaves.len() != capacity);
+
+        let mut changed: Vec<usize> = written
+            .into_iter()
+            .filter(|&page| page < pages)
+            .chain(self.pages.min(pages)..self.pages.max(pages))
+            .map(|page| page as usize)
+            .collect();
+        changed.sort_unstable();
+        changed.dedup();
+
+        if resized {
+            let mut leaves = vec![EMPTY_NODE; capacity];
+            if let Some(old) = self.levels.first() {
+                let kept = self.pages.min(pages) as usize;
+                leaves[..kept].copy_from_slice(&old[..kept]);
+            }
+            self.levels = vec![leaves];
+        }
+        let mut data = vec![0; WASM_PAGE_SIZE];
+        for &page in &changed {
+            self.levels[0][page] = if page < pages as usize {
+                view.read((page * WASM_PAGE_SIZE) as u64, &mut data)
+                    .expect("the page is within the memory");
+                let mut hasher = Sha256::new();
+                hasher.update([LEAF_PREFIX]);
+                hasher.update(&data);
+                hasher.finalize().into()
+            } else {
+                EMPTY_NODE
+            };
+        }
+        self.pages = pages;
+
+        if resized {
+            // Rebuild every level above the leaves.
+            while self.levels.last().unwrap().len() > 1 {
+                let level = self
+                    .levels
+                    .last()
+                    .unwrap()
+                    .chunks(2)
+                    .map(|pair| hash_node(&pair[0], &pair[1]))
+                    .collect();
+                self.levels.push(level);
+            }
+        } else {
+            // Only rehash the paths from the changed leaves to the top.
+            for depth in 1..self.levels.len() {
+                for index in &mut changed {
+                    *index /= 2;
+                }
+                changed.dedup();
+                let (lower, upper) = self.levels.split_at_mut(depth);
+                let lower = &lower[depth - 1];
+                for &index in &changed {
+                    upper[0][index] = hash_node(&lower[2 * index], &lower[2 * index + 1]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryType, Store};
+
+    #[test]
+    fn merkle_root_format() {
+        let mut store = Store::default();
+        let memory = Memory::new(&mut store, MemoryType::new(1, None, false)).unwrap();
+
+        let leaf: [u8; 32] = Sha256::new()
+            .chain_update([LEAF_PREFIX])
+            .chain_update(vec![0; WASM_PAGE_SIZE])
+            .finalize()
+            .into();
+        let root: [u8; 32] = Sha256::new()
+            .chain_update([ROOT_PREFIX])
+            .chain_update(1u32.to_le_bytes())
+            .chain_update(leaf)
+            .finalize()
+            .into();
+        assert_eq!(memory.view(&store).merkle_root(), root);
+    }
+}
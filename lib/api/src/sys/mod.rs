@@ -3,27 +3,40 @@ pub(crate) mod extern_ref;
 pub(crate) mod externals;
 pub(crate) mod instance;
 pub(crate) mod mem_access;
+#[cfg(feature = "merkle")]
+pub(crate) mod merkle;
 pub(crate) mod module;
 mod tunables;
 pub(crate) mod typed_function;
 
 pub use crate::sys::engine::NativeEngineExt;
+#[cfg(feature = "merkle")]
+pub use crate::sys::merkle::MemoryMerkleTree;
 pub use crate::sys::tunables::BaseTunables;
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
     wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareReaderState, ModuleMiddleware,
 };
-pub use wasmer_compiler::{Artifact, EngineBuilder, Features, FrameInfo, Tunables};
+pub use wasmer_compiler::{
+    Artifact, EngineBuilder, Features, FrameInfo, PoolingTunables, Tunables,
+};
 #[cfg(feature = "cranelift")]
 pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
 #[cfg(feature = "llvm")]
 pub use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
 #[cfg(feature = "singlepass")]
 pub use wasmer_compiler_singlepass::Singlepass;
+pub use wasmer_types::RemoveError;
+pub use wasmer_types::{
+    GlobalSnapshot, MemorySnapshot, ReferenceSnapshot, SnapshotError, StoreSnapshot, TableSnapshot,
+};
+pub use wasmer_vm::DirtyPages;
+pub use wasmer_vm::{PoolingAllocator, PoolingConfig};
 
 pub(crate) mod vm {
     //! The `vm` module re-exports wasmer-vm types.
+    pub(crate) use wasmer_vm::Imports as VMImports;
     use wasmer_vm::InternalStoreHandle;
     pub(crate) use wasmer_vm::{
         VMExtern, VMExternRef, VMFuncRef, VMFunction, VMFunctionBody, VMFunctionEnvironment,
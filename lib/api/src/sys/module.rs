@@ -9,9 +9,10 @@ use wasmer_types::{
     CompileError, DeserializeError, ExportsIterator, ImportsIterator, ModuleInfo, SerializeError,
 };
 use wasmer_types::{ExportType, ImportType};
+use wasmer_vm::StoreHandle;
 
-use crate::vm::VMInstance;
-use crate::{AsStoreMut, AsStoreRef, InstantiationError, IntoBytes};
+use crate::vm::{VMImports, VMInstance};
+use crate::{AsStoreMut, AsStoreRef, InstantiationError, IntoBytes, RuntimeError};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Module {
@@ -91,6 +92,83 @@ impl Module {
         Ok(Self::from_artifact(artifact))
     }
 
+    pub unsafe fn deserialize_from_file_mapped(
+        engine: &impl AsEngineRef,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, DeserializeError> {
+        let artifact = engine
+            .as_engine_ref()
+            .engine()
+            .0
+            .deserialize_from_file_mapped(path.as_ref())?;
+        Ok(Self::from_artifact(artifact))
+    }
+
+    pub(crate) fn serialize_checked(
+        &self,
+        engine: &impl AsEngineRef,
+        key: Option<&[u8]>,
+    ) -> Result<Bytes, SerializeError> {
+        engine
+            .as_engine_ref()
+            .engine()
+            .0
+            .serialize_checked(&self.artifact, key)
+            .map(|bytes| bytes.into())
+    }
+
+    pub fn deserialize_checked(
+        engine: &impl AsEngineRef,
+        bytes: impl IntoBytes,
+        key: &[u8],
+    ) -> Result<Self, DeserializeError> {
+        let bytes = bytes.into_bytes();
+        let artifact = engine
+            .as_engine_ref()
+            .engine()
+            .0
+            .deserialize_checked(&bytes, key)?;
+        Ok(Self::from_artifact(artifact))
+    }
+
+    pub unsafe fn deserialize_checksummed(
+        engine: &impl AsEngineRef,
+        bytes: impl IntoBytes,
+    ) -> Result<Self, DeserializeError> {
+        let bytes = bytes.into_bytes();
+        let artifact = engine
+            .as_engine_ref()
+            .engine()
+            .0
+            .deserialize_checksummed(&bytes)?;
+        Ok(Self::from_artifact(artifact))
+    }
+
+    pub fn deserialize_checked_from_file(
+        engine: &impl AsEngineRef,
+        path: impl AsRef<Path>,
+        key: &[u8],
+    ) -> Result<Self, DeserializeError> {
+        let artifact = engine
+            .as_engine_ref()
+            .engine()
+            .0
+            .deserialize_checked_from_file(path.as_ref(), key)?;
+        Ok(Self::from_artifact(artifact))
+    }
+
+    pub unsafe fn deserialize_checksummed_from_file(
+        engine: &impl AsEngineRef,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, DeserializeError> {
+        let artifact = engine
+            .as_engine_ref()
+            .engine()
+            .0
+            .deserialize_checksummed_from_file(path.as_ref())?;
+        Ok(Self::from_artifact(artifact))
+    }
+
     fn from_artifact(artifact: Arc<Artifact>) -> Self {
         Self { artifact }
     }
@@ -100,6 +178,15 @@ impl Module {
         store: &mut impl AsStoreMut,
         imports: &[crate::Extern],
     ) -> Result<VMInstance, InstantiationError> {
+        let imports = self.resolve_imports(store, imports)?;
+        unsafe { self.instantiate_resolved(store, imports) }
+    }
+
+    pub(crate) fn resolve_imports(
+        &self,
+        store: &impl AsStoreRef,
+        imports: &[crate::Extern],
+    ) -> Result<VMImports, InstantiationError> {
         if !self.artifact.allocated() {
             // Return an error mentioning that the artifact is compiled for a different
             // platform.
@@ -111,30 +198,64 @@ impl Module {
                 return Err(InstantiationError::DifferentStores);
             }
         }
+        Ok(self.artifact.resolve_imports(
+            &imports
+                .iter()
+                .map(crate::Extern::to_vm_extern)
+                .collect::<Vec<_>>(),
+            store.as_store_ref().objects(),
+        )?)
+    }
+
+    /// # Safety
+    ///
+    /// The imports must have been resolved for this module in `store`.
+    pub(crate) unsafe fn instantiate_resolved(
+        &self,
+        store: &mut impl AsStoreMut,
+        imports: VMImports,
+    ) -> Result<VMInstance, InstantiationError> {
         let mut store_mut = store.as_store_mut();
         let (engine, objects) = store_mut.engine_and_objects_mut();
-        unsafe {
-            let mut instance_handle = self.artifact.instantiate(
-                engine.tunables(),
-                &imports
-                    .iter()
-                    .map(crate::Extern::to_vm_extern)
-                    .collect::<Vec<_>>(),
-                objects,
-            )?;
-
-            // After the instance handle is created, we need to initialize
-            // the data, call the start function and so. However, if any
-            // of this steps traps, we still need to keep the instance alive
-            // as some of the Instance elements may have placed in other
-            // instance tables.
-            self.artifact.finish_instantiation(
-                store.as_store_ref().signal_handler(),
-                &mut instance_handle,
-            )?;
-
-            Ok(instance_handle)
+        let mut instance_handle =
+            self.artifact
+                .instantiate_resolved(engine.tunables(), imports, objects)?;
+
+        // After the instance handle is created, we need to initialize
+        // the data, call the start function and so. However, if any
+        // of this steps traps, we still need to keep the instance alive
+        // as some of the Instance elements may have placed in other
+        // instance tables.
+        let result = self
+            .artifact
+            .finish_instantiation(store.as_store_ref().signal_handler(), &mut instance_handle);
+        if let Err(wasmer_compiler::InstantiationError::Start(error)) = &result {
+            store.as_store_ref().acknowledge_interrupt(error);
         }
+        result?;
+
+        Ok(instance_handle)
+    }
+
+    /// # Safety
+    ///
+    /// `handle` must have been instantiated from this module.
+    pub(crate) unsafe fn reset_instance(
+        &self,
+        store: &mut impl AsStoreMut,
+        handle: &StoreHandle<VMInstance>,
+        run_start: bool,
+    ) -> Result<(), RuntimeError> {
+        let trap_handler = store.as_store_ref().signal_handler();
+        let result = self.artifact.reset_instance(
+            trap_handler,
+            handle.get_mut(store.objects_mut()),
+            run_start,
+        );
+        if let Err(error) = &result {
+            store.as_store_ref().acknowledge_interrupt(error);
+        }
+        result
     }
 
     pub(crate) fn name(&self) -> Option<&str> {
@@ -165,4 +286,8 @@ impl Module {
     pub(crate) fn info(&self) -> &ModuleInfo {
         self.artifact.module_info()
     }
+
+    pub(crate) fn middleware_fingerprint(&self) -> Option<[u8; 32]> {
+        self.artifact.middleware_fingerprint()
+    }
 }
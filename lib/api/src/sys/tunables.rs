@@ -225,6 +225,10 @@ mod tests {
                 attempted_delta: Pages::from(0u32)
             }
         );
+        assert_eq!(
+            vmmemory.checkpoint().err().unwrap(),
+            MemoryError::Unsupported("checkpoints".to_string())
+        );
     }
 
     #[test]
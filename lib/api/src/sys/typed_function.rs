@@ -1,8 +1,10 @@
 use crate::{FromToNativeWasmType, RuntimeError, TypedFunction, WasmTypeList};
+use std::cmp::max;
 use wasmer_types::RawValue;
 
 use crate::native_type::NativeWasmTypeInto;
 use crate::store::{AsStoreMut, AsStoreRef};
+use crate::sys::externals::function::call_wasm_trampoline;
 
 macro_rules! impl_native_traits {
     (  $( $x:ident ),* ) => {
@@ -16,14 +18,12 @@ macro_rules! impl_native_traits {
             #[allow(unused_mut)]
             #[allow(clippy::too_many_arguments)]
             pub fn call(&self, store: &mut impl AsStoreMut, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
-                let anyfunc = unsafe {
-                    *self.func.0
-                        .handle
-                        .get(store.as_store_ref().objects())
-                        .anyfunc
-                        .as_ptr()
-                        .as_ref()
-                };
+                let anyfunc = self.func.0
+                    .handle
+                    .get(store.as_store_ref().objects())
+                    .anyfunc
+                    .as_ptr();
+                let trampoline = unsafe { anyfunc.as_ref().call_trampoline };
                 // Ensure all parameters come from the same context.
                 if $(!FromToNativeWasmType::is_from_store(&$x, store) ||)* false {
                     return Err(RuntimeError::new(
@@ -47,29 +47,7 @@ macro_rules! impl_native_traits {
                     rets_list.as_mut()
                 };
 
-                let mut r;
-                loop {
-                    r = unsafe {
-                        wasmer_vm::wasmer_call_trampoline(
-                            store.as_store_ref().signal_handler(),
-                            anyfunc.vmctx,
-                            anyfunc.call_trampoline,
-                            anyfunc.func_ptr,
-                            args_rets.as_mut_ptr() as *mut u8,
-                        )
-                    };
-                    let store_mut = store.as_store_mut();
-                    if let Some(callback) = store_mut.inner.on_called.take() {
-                        match callback(store_mut) {
-                            Ok(wasmer_types::OnCalledAction::InvokeAgain) => { continue; }
-                            Ok(wasmer_types::OnCalledAction::Finish) => { break; }
-                            Ok(wasmer_types::OnCalledAction::Trap(trap)) => { return Err(RuntimeError::user(trap)) },
-                            Err(trap) => { return Err(RuntimeError::user(trap)) },
-                        }
-                    }
-                    break;
-                }
-                r?;
+                call_wasm_trampoline(store, anyfunc, trampoline, args_rets.as_mut_ptr())?;
 
                 let num_rets = rets_list.len();
                 if !using_rets_array && num_rets > 0 {
@@ -97,19 +75,32 @@ macro_rules! impl_native_traits {
                 // Ok(Rets::from_c_struct(results))
             }
 
+            /// Call the typed func asynchronously and return results.
+            ///
+            /// See [`Function::call_async`](crate::Function::call_async).
+            #[allow(clippy::too_many_arguments)]
+            pub async fn call_async(&self, store: &mut impl AsStoreMut, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
+                // Ensure all parameters come from the same context.
+                if $(!FromToNativeWasmType::is_from_store(&$x, store) ||)* false {
+                    return Err(RuntimeError::new(
+                        "cross-`Store` values are not supported",
+                    ));
+                }
+                let params_list = vec![ $( $x.to_native().into_raw(store) ),* ];
+                self.call_raw_async(store, params_list).await
+            }
+
             #[doc(hidden)]
             #[allow(missing_docs)]
             #[allow(unused_mut)]
             #[allow(clippy::too_many_arguments)]
             pub fn call_raw(&self, store: &mut impl AsStoreMut, mut params_list: Vec<RawValue> ) -> Result<Rets, RuntimeError> {
-                let anyfunc = unsafe {
-                    *self.func.0
-                        .handle
-                        .get(store.as_store_ref().objects())
-                        .anyfunc
-                        .as_ptr()
-                        .as_ref()
-                };
+                let anyfunc = self.func.0
+                    .handle
+                    .get(store.as_store_ref().objects())
+                    .anyfunc
+                    .as_ptr();
+                let trampoline = unsafe { anyfunc.as_ref().call_trampoline };
                 // TODO: when `const fn` related features mature more, we can declare a single array
                 // of the correct size here.
                 let mut rets_list_array = Rets::empty_array();
@@ -126,30 +117,7 @@ macro_rules! impl_native_traits {
                     rets_list.as_mut()
                 };
 
-                let mut r;
-                loop {
-                    r = unsafe {
-                        wasmer_vm::wasmer_call_trampoline(
-                            store.as_store_ref().signal_handler(),
-                            anyfunc.vmctx,
-                            anyfunc.call_trampoline,
-                            anyfunc.func_ptr,
-                            args_rets.as_mut_ptr() as *mut u8,
-                        )
-                    };
-                    let store_mut = store.as_store_mut();
-                    if let Some(callback) = store_mut.inner.on_called.take() {
-                        // TODO: OnCalledAction is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
-                        match callback(store_mut) {
-                            Ok(wasmer_types::OnCalledAction::InvokeAgain) => { continue; }
-                            Ok(wasmer_types::OnCalledAction::Finish) => { break; }
-                            Ok(wasmer_types::OnCalledAction::Trap(trap)) => { return Err(RuntimeError::user(trap)) },
-                            Err(trap) => { return Err(RuntimeError::user(trap)) },
-                        }
-                    }
-                    break;
-                }
-                r?;
+                call_wasm_trampoline(store, anyfunc, trampoline, args_rets.as_mut_ptr())?;
 
                 let num_rets = rets_list.len();
                 if !using_rets_array && num_rets > 0 {
@@ -180,6 +148,30 @@ macro_rules! impl_native_traits {
     };
 }
 
+impl<Args, Rets> TypedFunction<Args, Rets>
+where
+    Args: WasmTypeList,
+    Rets: WasmTypeList,
+{
+    async fn call_raw_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        params_list: Vec<RawValue>,
+    ) -> Result<Rets, RuntimeError> {
+        let mut rets_list_array = Rets::empty_array();
+        let num_rets = rets_list_array.as_mut().len();
+        let mut values = params_list;
+        values.resize(max(values.len(), num_rets), RawValue { i32: 0 });
+
+        self.func.0.call_raw_async(store, &mut values).await?;
+
+        rets_list_array
+            .as_mut()
+            .copy_from_slice(&values[..num_rets]);
+        Ok(unsafe { Rets::from_array(store, rets_list_array) })
+    }
+}
+
 impl_native_traits!();
 impl_native_traits!(A1);
 impl_native_traits!(A1, A2);
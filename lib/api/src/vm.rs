@@ -9,7 +9,7 @@ pub(crate) use crate::js::vm::{
 #[cfg(feature = "sys")]
 pub(crate) use crate::sys::vm::{
     VMExtern, VMExternFunction, VMExternGlobal, VMExternMemory, VMExternRef, VMExternTable,
-    VMFuncRef, VMFunctionBody, VMFunctionEnvironment, VMInstance, VMTrampoline,
+    VMFuncRef, VMFunctionBody, VMFunctionEnvironment, VMImports, VMInstance, VMTrampoline,
 };
 
 #[cfg(feature = "js")]
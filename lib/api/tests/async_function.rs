@@ -0,0 +1,245 @@
+#[cfg(feature = "sys")]
+pub mod async_function {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "host" "read" (func $read (param i32) (result i32)))
+(func (export "sum") (param i32) (result i32)
+      (i32.add (call $read (local.get 0))
+               (call $read (i32.add (local.get 0) (i32.const 1)))))
+)"#;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Polls `future` to completion, and returns how many times it was
+    /// pending.
+    fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        let mut pending = 0;
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return (output, pending),
+                Poll::Pending => pending += 1,
+            }
+        }
+    }
+
+    /// A future which is pending until `ready` is set.
+    struct WaitFor {
+        ready: Arc<AtomicBool>,
+    }
+
+    impl Future for WaitFor {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            if self.ready.load(Ordering::SeqCst) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    /// A future which is pending the first `n` times it is polled.
+    struct YieldTimes(usize);
+
+    impl Future for YieldTimes {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 == 0 {
+                return Poll::Ready(());
+            }
+            self.0 -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    fn read_function(store: &mut Store, ready: Arc<AtomicBool>) -> Function {
+        Function::new_async(store, ([Type::I32], [Type::I32]), move |args| {
+            let value = args[0].unwrap_i32() * 10;
+            let ready = ready.clone();
+            async move {
+                WaitFor { ready }.await;
+                Ok(vec![Value::I32(value)])
+            }
+        })
+    }
+
+    #[universal_test]
+    fn host_futures_suspend_the_call() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let read = Function::new_async(&mut store, ([Type::I32], [Type::I32]), |args| {
+            let value = args[0].unwrap_i32() * 10;
+            async move {
+                YieldTimes(3).await;
+                Ok(vec![Value::I32(value)])
+            }
+        });
+        let instance = Instance::new(
+            &mut store,
+            &module,
+            &imports! { "host" => { "read" => read } },
+        )?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let (result, pending) = block_on(sum.call_async(&mut store, 1));
+        assert_eq!(result?, 30);
+        assert_eq!(pending, 6);
+
+        let sum = instance.exports.get_function("sum")?;
+        let (result, _) = block_on(sum.call_async(&mut store, &[Value::I32(2)]));
+        assert_eq!(&*result?, &[Value::I32(50)]);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn calls_are_resumed_on_another_thread() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let ready = Arc::new(AtomicBool::new(false));
+        let read = read_function(&mut store, ready.clone());
+        let instance = Instance::new(
+            &mut store,
+            &module,
+            &imports! { "host" => { "read" => read } },
+        )?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut call = Box::pin(sum.call_async(&mut store, 4));
+        assert!(call
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+        ready.store(true, Ordering::SeqCst);
+        let result =
+            std::thread::scope(|scope| scope.spawn(move || block_on(call).0).join().unwrap());
+        assert_eq!(result?, 90);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn dropped_calls_are_cancelled() -> Result<()> {
+        struct DropFlag(Arc<AtomicBool>);
+
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let dropped = Arc::new(AtomicBool::new(false));
+        let flag = dropped.clone();
+        let read = Function::new_async(&mut store, ([Type::I32], [Type::I32]), move |args| {
+            let value = args[0].unwrap_i32();
+            let flag = DropFlag(flag.clone());
+            async move {
+                YieldTimes(usize::MAX).await;
+                drop(flag);
+                Ok(vec![Value::I32(value)])
+            }
+        });
+        let instance = Instance::new(
+            &mut store,
+            &module,
+            &imports! { "host" => { "read" => read } },
+        )?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut call = Box::pin(sum.call_async(&mut store, 1));
+        for _ in 0..3 {
+            assert!(call
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending());
+        }
+        assert!(!dropped.load(Ordering::SeqCst));
+        drop(call);
+        assert!(dropped.load(Ordering::SeqCst));
+
+        // The store is left as if the call had trapped.
+        store.remove_instance(&instance)?;
+        Ok(())
+    }
+
+    #[universal_test]
+    fn async_functions_need_an_async_call() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let read = read_function(&mut store, Arc::new(AtomicBool::new(true)));
+        let instance = Instance::new(
+            &mut store,
+            &module,
+            &imports! { "host" => { "read" => read } },
+        )?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        let error = sum.call(&mut store, 1).unwrap_err();
+        assert!(error.message().contains("asynchronous call"));
+        // The synchronous functions can still be called asynchronously.
+        let double = Function::new_typed(&mut store, |x: i32| x * 2);
+        let double: TypedFunction<i32, i32> = double.typed(&store)?;
+        assert_eq!(block_on(double.call_async(&mut store, 21)).0?, 42);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn async_functions_with_env() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let env = FunctionEnv::new(&mut store, log.clone());
+        let read = Function::new_async_with_env(
+            &mut store,
+            &env,
+            ([Type::I32], [Type::I32]),
+            |env: FunctionEnvMut<Arc<Mutex<Vec<i32>>>>, args| {
+                let log = env.data().clone();
+                let value = args[0].unwrap_i32();
+                async move {
+                    YieldTimes(1).await;
+                    log.lock().unwrap().push(value);
+                    if value > 5 {
+                        return Err(RuntimeError::new("out of bounds"));
+                    }
+                    Ok(vec![Value::I32(value)])
+                }
+            },
+        );
+        let instance = Instance::new(
+            &mut store,
+            &module,
+            &imports! { "host" => { "read" => read } },
+        )?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+
+        assert_eq!(block_on(sum.call_async(&mut store, 2)).0?, 5);
+        let error = block_on(sum.call_async(&mut store, 5)).0.unwrap_err();
+        assert_eq!(error.message(), "out of bounds");
+        assert_eq!(*log.lock().unwrap(), [2, 3, 5, 6]);
+        Ok(())
+    }
+}
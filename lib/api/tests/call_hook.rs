@@ -0,0 +1,138 @@
+#[cfg(feature = "sys")]
+pub mod call_hook {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    use std::sync::{Arc, Mutex};
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "host" "tick" (func $tick))
+(func $run (export "run") (param $n i32) (result i32)
+      (local $i i32)
+      (loop $continue
+            (call $tick)
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $continue (i32.lt_u (local.get $i) (local.get $n))))
+      (local.get $i))
+)"#;
+
+    type Transitions = Arc<Mutex<Vec<(CallHook, Option<FunctionIndex>, bool)>>>;
+
+    /// Instantiates `WAT` with a `tick` function counting its calls.
+    fn instantiate(store: &mut Store) -> Result<(Instance, FunctionEnv<u32>)> {
+        let module = Module::new(store, WAT)?;
+        let env = FunctionEnv::new(store, 0);
+        let tick = Function::new_typed_with_env(store, &env, |mut env: FunctionEnvMut<u32>| {
+            *env.data_mut() += 1;
+        });
+        let imports = imports! { "host" => { "tick" => tick } };
+        let instance = Instance::new(store, &module, &imports)?;
+        Ok((instance, env))
+    }
+
+    /// Sets a call hook recording the transitions of `store`, with the
+    /// index of the function and whether it has a name.
+    fn record_transitions(store: &mut Store) -> Transitions {
+        let transitions = Transitions::default();
+        let recorded = transitions.clone();
+        store.set_call_hook(move |_, hook, function| {
+            let mut recorded = recorded.lock().unwrap();
+            recorded.push((hook, function.index, function.name.is_some()));
+            Ok(())
+        });
+        transitions
+    }
+
+    #[universal_test]
+    fn call_hook_transitions() -> Result<()> {
+        let mut store = Store::default();
+        let (instance, _) = instantiate(&mut store)?;
+        let run = instance.exports.get_function("run")?;
+        let transitions = record_transitions(&mut store);
+
+        let result = run.call(&mut store, &[Value::I32(2)])?;
+        assert_eq!(result[0], Value::I32(2));
+        let run_index = Some(FunctionIndex::from_u32(1));
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![
+                (CallHook::CallingWasm, run_index, true),
+                (CallHook::CallingHost, None, true),
+                (CallHook::ReturningFromHost, None, true),
+                (CallHook::CallingHost, None, true),
+                (CallHook::ReturningFromHost, None, true),
+                (CallHook::ReturningFromWasm, run_index, true),
+            ]
+        );
+
+        // Typed calls are hooked too.
+        transitions.lock().unwrap().clear();
+        let run: TypedFunction<i32, i32> = run.typed(&store)?;
+        assert_eq!(run.call(&mut store, 1)?, 1);
+        assert_eq!(transitions.lock().unwrap().len(), 4);
+
+        store.clear_call_hook();
+        transitions.lock().unwrap().clear();
+        assert_eq!(run.call(&mut store, 1)?, 1);
+        assert!(transitions.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[universal_test]
+    fn call_hook_names() -> Result<()> {
+        let mut store = Store::default();
+        let (instance, _) = instantiate(&mut store)?;
+        let run = instance.exports.get_function("run")?;
+        let names = Arc::new(Mutex::new(vec![]));
+        let recorded = names.clone();
+        store.set_call_hook(move |_, _, function| {
+            recorded
+                .lock()
+                .unwrap()
+                .push(function.name.clone().unwrap());
+            Ok(())
+        });
+
+        run.call(&mut store, &[Value::I32(1)])?;
+        let names = names.lock().unwrap();
+        assert_eq!(names[0], "run");
+        assert!(names[1].contains("instantiate"));
+        assert_eq!(names[3], "run");
+        Ok(())
+    }
+
+    #[universal_test]
+    fn call_hook_aborts_host_call() -> Result<()> {
+        let mut store = Store::default();
+        let (instance, env) = instantiate(&mut store)?;
+        let run = instance.exports.get_function("run")?;
+        store.set_call_hook(|_, hook, _| match hook {
+            CallHook::CallingHost => Err(RuntimeError::new("host calls are not allowed")),
+            _ => Ok(()),
+        });
+
+        let error = run.call(&mut store, &[Value::I32(10)]).unwrap_err();
+        assert_eq!(error.message(), "host calls are not allowed");
+        assert_eq!(*env.as_ref(&store), 0);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn call_hook_aborts_return() -> Result<()> {
+        let mut store = Store::default();
+        let (instance, env) = instantiate(&mut store)?;
+        let run = instance.exports.get_function("run")?;
+        store.set_call_hook(|_, hook, _| match hook {
+            CallHook::ReturningFromWasm => Err(RuntimeError::new("results are not allowed")),
+            _ => Ok(()),
+        });
+
+        let error = run.call(&mut store, &[Value::I32(10)]).unwrap_err();
+        assert_eq!(error.message(), "results are not allowed");
+        assert_eq!(*env.as_ref(&store), 10);
+        Ok(())
+    }
+}
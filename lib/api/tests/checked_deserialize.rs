@@ -0,0 +1,145 @@
+#[cfg(feature = "sys")]
+pub mod checked_deserialize {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(func (export "add") (param i32 i32) (result i32)
+      (i32.add (local.get 0) (local.get 1)))
+)"#;
+
+    fn integrity_error<T>(result: Result<T, DeserializeError>) -> IntegrityError {
+        match result {
+            Err(DeserializeError::Integrity(error)) => error,
+            Err(error) => panic!("expected an integrity error, got {}", error),
+            Ok(_) => panic!("expected an integrity error"),
+        }
+    }
+
+    fn call_add(store: &mut Store, module: &Module) -> Result<i32> {
+        let instance = Instance::new(store, module, &imports! {})?;
+        let add: TypedFunction<(i32, i32), i32> =
+            instance.exports.get_typed_function(store, "add")?;
+        Ok(add.call(store, 20, 22)?)
+    }
+
+    #[universal_test]
+    fn checked_roundtrip() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+
+        let serialized = module.serialize_checked(&store, None)?;
+        let module = unsafe { Module::deserialize_checksummed(&store, serialized)? };
+        assert_eq!(call_add(&mut store, &module)?, 42);
+
+        let signed = module.serialize_checked(&store, Some(b"key"))?;
+        let module = Module::deserialize_checked(&store, signed, b"key")?;
+        assert_eq!(call_add(&mut store, &module)?, 42);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn checked_roundtrip_from_file() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let file = tempfile::NamedTempFile::new()?;
+        std::fs::write(file.path(), module.serialize_checked(&store, Some(b"key"))?)?;
+
+        let module = Module::deserialize_checked_from_file(&store, file.path(), b"key")?;
+        assert_eq!(call_add(&mut store, &module)?, 42);
+
+        std::fs::write(file.path(), module.serialize_checked(&store, None)?)?;
+        let module = unsafe { Module::deserialize_checksummed_from_file(&store, file.path())? };
+        assert_eq!(call_add(&mut store, &module)?, 42);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn altered_bytes_are_detected() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+
+        let mut serialized = module.serialize_checked(&store, None)?.to_vec();
+        let last = serialized.len() - 1;
+        serialized[last] ^= 1;
+        assert_eq!(
+            integrity_error(unsafe { Module::deserialize_checksummed(&store, serialized) }),
+            IntegrityError::ChecksumMismatch
+        );
+
+        let mut signed = module.serialize_checked(&store, Some(b"key"))?.to_vec();
+        signed[100] ^= 1;
+        assert_eq!(
+            integrity_error(Module::deserialize_checked(&store, signed, b"key")),
+            IntegrityError::InvalidSignature
+        );
+        Ok(())
+    }
+
+    #[universal_test]
+    fn keys_must_match() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let serialized = module.serialize_checked(&store, None)?;
+        let signed = module.serialize_checked(&store, Some(b"key"))?;
+
+        assert_eq!(
+            integrity_error(Module::deserialize_checked(
+                &store,
+                signed.clone(),
+                b"other key"
+            )),
+            IntegrityError::InvalidSignature
+        );
+        assert_eq!(
+            integrity_error(unsafe { Module::deserialize_checksummed(&store, signed) }),
+            IntegrityError::KeyRequired
+        );
+        assert_eq!(
+            integrity_error(Module::deserialize_checked(&store, serialized, b"key")),
+            IntegrityError::Unsigned
+        );
+        Ok(())
+    }
+
+    #[universal_test]
+    fn malformed_bytes_are_rejected() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let serialized = module.serialize_checked(&store, None)?;
+
+        for len in (0..128).chain(serialized.len() - 16..serialized.len()) {
+            assert!(
+                unsafe { Module::deserialize_checksummed(&store, &serialized[..len]) }.is_err()
+            );
+        }
+        // Unchecked artifacts are not accepted either.
+        assert!(matches!(
+            Module::deserialize_checked(&store, module.serialize()?, b"key"),
+            Err(DeserializeError::Incompatible(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(all(feature = "cranelift", feature = "singlepass"))]
+    #[universal_test]
+    fn engines_must_match() -> Result<()> {
+        let cranelift = Store::new(Cranelift::default());
+        let singlepass = Store::new(Singlepass::default());
+        let module = Module::new(&cranelift, WAT)?;
+        let serialized = module.serialize_checked(&cranelift, Some(b"key"))?;
+
+        match integrity_error(Module::deserialize_checked(&singlepass, serialized, b"key")) {
+            IntegrityError::EngineMismatch { expected, found } => {
+                assert_eq!(expected, singlepass.engine().deterministic_id());
+                assert_eq!(found, cranelift.engine().deterministic_id());
+            }
+            error => panic!("expected an engine mismatch, got {}", error),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(feature = "sys")]
+pub mod checkpoint {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "env" "host_write" (func $host_write (param i32)))
+(type $ret_i32_ty (func (result i32)))
+(memory (export "memory") 2)
+(table $table (export "table") 2 funcref)
+(global $counter (export "counter") (mut i32) (i32.const 0))
+(elem (i32.const 0) $one)
+(elem declare func $two)
+
+(func $one (result i32) (i32.const 1))
+(func $two (result i32) (i32.const 2))
+
+(func (export "run") (param $fail i32)
+      (i32.store (i32.const 16) (i32.const 0xdeadbeef))
+      (i32.store8 (i32.const 70000) (i32.const 7))
+      (call $host_write (i32.const 100))
+      (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+      (drop (memory.grow (i32.const 3)))
+      (i32.store (i32.const 200000) (i32.const 9))
+      (table.set $table (i32.const 0) (ref.func $two))
+      (drop (table.grow $table (ref.null func) (i32.const 2)))
+      (if (local.get $fail) (then unreachable)))
+(func (export "call") (param i32) (result i32)
+      (call_indirect $table (type $ret_i32_ty) (local.get 0)))
+)"#;
+
+    fn instantiate(store: &mut Store) -> Result<Instance> {
+        let module = Module::new(&*store, WAT)?;
+        #[derive(Clone)]
+        struct Env {
+            memory: Option<Memory>,
+        }
+        let env = FunctionEnv::new(store, Env { memory: None });
+        let host_write =
+            Function::new_typed_with_env(store, &env, |env: FunctionEnvMut<Env>, offset: i32| {
+                let memory = env.data().memory.as_ref().unwrap();
+                memory.view(&env).write(offset as u64, b"host").unwrap();
+            });
+        let imports = imports! {
+            "env" => {
+                "host_write" => host_write,
+            },
+        };
+        let instance = Instance::new(store, &module, &imports)?;
+        env.as_mut(store).memory = Some(instance.exports.get_memory("memory")?.clone());
+        Ok(instance)
+    }
+
+    #[universal_test]
+    fn rollback_on_trap() -> Result<()> {
+        let mut store = Store::default();
+        let instance = instantiate(&mut store)?;
+        let run = instance
+            .exports
+            .get_typed_function::<i32, ()>(&store, "run")?;
+
+        let result = store.transaction(|store| run.call(store, 1));
+        assert!(result.is_err());
+
+        let memory = instance.exports.get_memory("memory")?;
+        let view = memory.view(&store);
+        assert_eq!(view.size(), Pages(2));
+        assert_eq!(view.read_u8(16)?, 0);
+        assert_eq!(view.read_u8(70000)?, 0);
+        assert_eq!(view.read_u8(100)?, 0);
+        let counter = instance.exports.get_global("counter")?;
+        assert_eq!(counter.get(&mut store), Value::I32(0));
+        let table = instance.exports.get_table("table")?;
+        assert_eq!(table.size(&store), 2);
+        let call = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "call")?;
+        assert_eq!(call.call(&mut store, 0)?, 1);
+
+        // The memory is writable again and can grow over the discarded pages.
+        store.transaction(|store| run.call(store, 0))?;
+        let view = memory.view(&store);
+        assert_eq!(view.size(), Pages(5));
+        assert_eq!(view.read_u8(70000)?, 7);
+        assert_eq!(view.read_u8(200001)?, 0);
+        assert_eq!(counter.get(&mut store), Value::I32(1));
+        assert_eq!(call.call(&mut store, 0)?, 2);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn checkpoint_commit_and_rollback() -> Result<()> {
+        let mut store = Store::default();
+        let instance = instantiate(&mut store)?;
+        let run = instance
+            .exports
+            .get_typed_function::<i32, ()>(&store, "run")?;
+        let memory = instance.exports.get_memory("memory")?;
+
+        let checkpoint = store.checkpoint()?;
+        // Only one checkpoint can be active at a time.
+        assert!(store.checkpoint().is_err());
+        run.call(&mut store, 0)?;
+        store.commit(checkpoint)?;
+        assert_eq!(memory.view(&store).read_u8(100)?, b'h');
+
+        let checkpoint = store.checkpoint()?;
+        memory.view(&store).write_u8(100, 0)?;
+        run.call(&mut store, 0)?;
+        assert_eq!(memory.view(&store).size(), Pages(8));
+        store.rollback(checkpoint)?;
+        let view = memory.view(&store);
+        assert_eq!(view.size(), Pages(5));
+        assert_eq!(view.read_u8(100)?, b'h');
+        assert_eq!(view.read_u8(200000)?, 9);
+        Ok(())
+    }
+}
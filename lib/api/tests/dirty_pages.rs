@@ -0,0 +1,87 @@
+#[cfg(feature = "sys")]
+pub mod dirty_pages {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(memory (export "memory") 4)
+(func (export "store") (param $offset i32) (param $value i32)
+      (i32.store8 (local.get $offset) (local.get $value)))
+(func (export "grow") (param $delta i32) (result i32)
+      (memory.grow (local.get $delta)))
+(func (export "fail") (param $offset i32)
+      (i32.store8 (local.get $offset) (i32.const 1))
+      unreachable)
+)"#;
+
+    #[universal_test]
+    fn dirty_pages_of_wasm_and_host_writes() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let write = instance
+            .exports
+            .get_typed_function::<(i32, i32), ()>(&store, "store")?;
+        let grow = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "grow")?;
+
+        assert!(memory.dirty_pages(&store).is_err());
+        memory.enable_dirty_tracking(&mut store)?;
+        assert!(memory.dirty_pages(&store)?.is_empty());
+
+        write.call(&mut store, 65536 * 2 + 10, 1)?;
+        memory.view(&store).write(65536 * 3 - 1, &[1, 2])?;
+        let dirty = memory.dirty_pages(&store)?;
+        assert_eq!(dirty.wasm_pages(), vec![2, 3]);
+        assert_eq!(memory.view(&store).read_u8(65536 * 3)?, 2);
+
+        memory.reset_dirty_pages(&mut store)?;
+        assert!(memory.dirty_pages(&store)?.is_empty());
+
+        // Pages added by growing the memory are tracked as well.
+        assert_eq!(grow.call(&mut store, 2)?, 4);
+        write.call(&mut store, 65536 * 5, 1)?;
+        write.call(&mut store, 3, 1)?;
+        assert_eq!(memory.dirty_pages(&store)?.wasm_pages(), vec![0, 5]);
+
+        memory.disable_dirty_tracking(&mut store);
+        assert!(memory.dirty_pages(&store).is_err());
+        write.call(&mut store, 65536 * 4, 1)?;
+        Ok(())
+    }
+
+    #[universal_test]
+    fn dirty_pages_with_checkpoints() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let write = instance
+            .exports
+            .get_typed_function::<(i32, i32), ()>(&store, "store")?;
+        let fail = instance
+            .exports
+            .get_typed_function::<i32, ()>(&store, "fail")?;
+
+        memory.enable_dirty_tracking(&mut store)?;
+        write.call(&mut store, 0, 7)?;
+        store.transaction(|store| write.call(store, 65536, 7))?;
+        assert!(store.transaction(|store| fail.call(store, 0)).is_err());
+        assert!(store
+            .transaction(|store| fail.call(store, 65536 * 2))
+            .is_err());
+
+        let view = memory.view(&store);
+        assert_eq!(view.read_u8(0)?, 7);
+        assert_eq!(view.read_u8(65536 * 2)?, 0);
+        // Pages restored by a rollback are reported as written.
+        assert_eq!(memory.dirty_pages(&store)?.wasm_pages(), vec![0, 1, 2]);
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(feature = "sys")]
+pub mod epoch {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    use std::sync::{Arc, Mutex};
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "host" "tick" (func $tick))
+(func (export "run") (param $n i32) (result i32)
+      (local $i i32)
+      (loop $continue
+            (call $tick)
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $continue (i32.lt_u (local.get $i) (local.get $n))))
+      (local.get $i))
+)"#;
+
+    fn epoch_interruptible_store() -> Store {
+        let mut engine = Engine::default();
+        engine.set_epoch_interruption(true);
+        Store::new(engine)
+    }
+
+    /// Instantiates `WAT` with a `tick` function incrementing the epoch of
+    /// the engine, and counting its calls.
+    fn instantiate(store: &mut Store) -> Result<(Function, FunctionEnv<(Engine, u32)>)> {
+        let module = Module::new(store, WAT)?;
+        let engine = store.engine().clone();
+        let env = FunctionEnv::new(store, (engine, 0));
+        let tick =
+            Function::new_typed_with_env(store, &env, |mut env: FunctionEnvMut<(Engine, u32)>| {
+                let (engine, ticks) = env.data_mut();
+                engine.increment_epoch();
+                *ticks += 1;
+            });
+        let imports = imports! { "host" => { "tick" => tick } };
+        let instance = Instance::new(store, &module, &imports)?;
+        let run = instance.exports.get_function("run")?.clone();
+        Ok((run, env))
+    }
+
+    #[universal_test]
+    fn epoch_interruption_is_off_by_default() -> Result<()> {
+        let mut store = Store::default();
+        let (run, _) = instantiate(&mut store)?;
+
+        store.set_epoch_deadline(3);
+        let result = run.call(&mut store, &[Value::I32(10)])?;
+        assert_eq!(result[0], Value::I32(10));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn no_epoch_deadline_by_default() -> Result<()> {
+        let mut store = epoch_interruptible_store();
+        let (run, _) = instantiate(&mut store)?;
+
+        let result = run.call(&mut store, &[Value::I32(10)])?;
+        assert_eq!(result[0], Value::I32(10));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn epoch_deadline_traps() -> Result<()> {
+        let mut store = epoch_interruptible_store();
+        let (run, env) = instantiate(&mut store)?;
+
+        store.set_epoch_deadline(3);
+        let error = run.call(&mut store, &[Value::I32(10)]).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineReached));
+        assert_eq!(env.as_ref(&store).1, 3);
+
+        // Calls keep failing until a new deadline is set.
+        let error = run.call(&mut store, &[Value::I32(10)]).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineReached));
+        assert_eq!(env.as_ref(&store).1, 3);
+
+        store.set_epoch_deadline(100);
+        let result = run.call(&mut store, &[Value::I32(10)])?;
+        assert_eq!(result[0], Value::I32(10));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn epoch_deadline_callback() -> Result<()> {
+        let mut store = epoch_interruptible_store();
+        let (run, env) = instantiate(&mut store)?;
+
+        // Give the call 3 more slices of 2 epochs, then stop it.
+        let reached = Arc::new(Mutex::new(vec![]));
+        let reached_in_callback = reached.clone();
+        store.set_epoch_deadline_callback(move |epoch| {
+            let mut reached = reached_in_callback.lock().unwrap();
+            reached.push(epoch);
+            if reached.len() <= 3 {
+                EpochDeadlineAction::Continue(2)
+            } else {
+                EpochDeadlineAction::Trap
+            }
+        });
+        store.set_epoch_deadline(2);
+
+        let error = run.call(&mut store, &[Value::I32(100)]).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineReached));
+        assert_eq!(*reached.lock().unwrap(), vec![2, 4, 6, 8]);
+        assert_eq!(env.as_ref(&store).1, 8);
+        Ok(())
+    }
+}
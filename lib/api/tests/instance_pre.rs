@@ -0,0 +1,98 @@
+#[cfg(feature = "sys")]
+pub mod instance_pre {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "host" "started" (func $started))
+(global $counter (export "counter") (mut i32) (i32.const 0))
+(func (export "increment") (result i32)
+      (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+      (global.get $counter))
+(start $started)
+)"#;
+
+    fn started(store: &mut Store) -> (Imports, FunctionEnv<u32>) {
+        let env = FunctionEnv::new(store, 0);
+        let started = Function::new_typed_with_env(store, &env, |mut env: FunctionEnvMut<u32>| {
+            *env.data_mut() += 1;
+        });
+        let imports = imports! { "host" => { "started" => started } };
+        (imports, env)
+    }
+
+    #[universal_test]
+    fn instance_pre_instantiates_many_times() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let (imports, env) = started(&mut store);
+        let instance_pre = InstancePre::new(&store, &module, &imports)?;
+
+        for _ in 0..5 {
+            let instance = instance_pre.instantiate(&mut store)?;
+            let increment: TypedFunction<(), i32> =
+                instance.exports.get_typed_function(&store, "increment")?;
+            assert_eq!(increment.call(&mut store)?, 1);
+            assert_eq!(increment.call(&mut store)?, 2);
+        }
+        assert_eq!(*env.as_ref(&store), 5);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn instance_pre_checks_imports() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+
+        let error = InstancePre::new(&store, &module, &imports! {}).unwrap_err();
+        assert!(matches!(
+            error,
+            InstantiationError::Link(LinkError::Import(_, _, _))
+        ));
+
+        let wrong = Function::new_typed(&mut store, |_: i32| {});
+        let imports = imports! { "host" => { "started" => wrong } };
+        let error = InstancePre::new(&store, &module, &imports).unwrap_err();
+        assert!(matches!(
+            error,
+            InstantiationError::Link(LinkError::Import(_, _, _))
+        ));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn instance_pre_rejects_other_stores() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let (imports, _) = started(&mut store);
+        let instance_pre = InstancePre::new(&store, &module, &imports)?;
+
+        let mut other = Store::default();
+        assert!(matches!(
+            instance_pre.instantiate(&mut other),
+            Err(InstantiationError::DifferentStores)
+        ));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn instance_pre_rejects_removed_imports() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let (imports, _) = started(&mut store);
+        let instance_pre = InstancePre::new(&store, &module, &imports)?;
+
+        // No instance imports the function yet, but the `InstancePre` does.
+        let started = imports.get_export("host", "started").unwrap();
+        store.remove_extern(&started)?;
+        assert!(matches!(
+            instance_pre.instantiate(&mut store),
+            Err(InstantiationError::DifferentStores)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(feature = "sys")]
+pub mod instance_reset {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "host" "started" (func $started))
+(memory (export "memory") 1)
+(table (export "table") 1 funcref)
+(global $counter (export "counter") (mut i32) (i32.const 7))
+(func $seven (result i32) (i32.const 7))
+(func (export "mutate")
+      (i32.store (i32.const 0) (i32.const 42))
+      (i32.store (i32.const 100) (i32.const 42))
+      (global.set $counter (i32.const 0))
+      (table.set (i32.const 0) (ref.null func))
+      (drop (memory.grow (i32.const 2)))
+      (drop (table.grow (ref.null func) (i32.const 3))))
+(func (export "init_passive")
+      (memory.init $passive (i32.const 200) (i32.const 0) (i32.const 3))
+      (data.drop $passive))
+(elem (i32.const 0) $seven)
+(data (i32.const 0) "wasm")
+(data $passive "abc")
+(start $started)
+)"#;
+
+    fn instantiate(store: &mut Store) -> Result<(Instance, FunctionEnv<u32>)> {
+        let module = Module::new(store, WAT)?;
+        let env = FunctionEnv::new(store, 0);
+        let started = Function::new_typed_with_env(store, &env, |mut env: FunctionEnvMut<u32>| {
+            *env.data_mut() += 1;
+        });
+        let imports = imports! { "host" => { "started" => started } };
+        let instance = Instance::new(store, &module, &imports)?;
+        Ok((instance, env))
+    }
+
+    fn read(store: &Store, memory: &Memory, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut bytes = vec![0; len];
+        memory.view(store).read(offset, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[universal_test]
+    fn reset_restores_initial_state() -> Result<()> {
+        let mut store = Store::default();
+        let (instance, env) = instantiate(&mut store)?;
+        let mutate: TypedFunction<(), ()> =
+            instance.exports.get_typed_function(&store, "mutate")?;
+        let memory = instance.exports.get_memory("memory")?.clone();
+        let table = instance.exports.get_table("table")?.clone();
+        let counter = instance.exports.get_global("counter")?.clone();
+
+        mutate.call(&mut store)?;
+        assert_eq!(memory.view(&store).size(), Pages(3));
+        assert_eq!(table.size(&store), 4);
+        assert_eq!(counter.get(&mut store), Value::I32(0));
+
+        instance.reset(&mut store, false)?;
+        assert_eq!(memory.view(&store).size(), Pages(1));
+        assert_eq!(read(&store, &memory, 0, 4)?, b"wasm");
+        assert_eq!(read(&store, &memory, 100, 4)?, [0; 4]);
+        assert_eq!(table.size(&store), 1);
+        assert!(table.get(&mut store, 0).unwrap().unwrap_funcref().is_some());
+        assert_eq!(counter.get(&mut store), Value::I32(7));
+        assert_eq!(*env.as_ref(&store), 1);
+
+        // The instance keeps working after a reset.
+        mutate.call(&mut store)?;
+        assert_eq!(read(&store, &memory, 100, 4)?, [42, 0, 0, 0]);
+        assert_eq!(memory.view(&store).size(), Pages(3));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn reset_reruns_start_function() -> Result<()> {
+        let mut store = Store::default();
+        let (instance, env) = instantiate(&mut store)?;
+
+        instance.reset(&mut store, true)?;
+        instance.reset(&mut store, true)?;
+        assert_eq!(*env.as_ref(&store), 3);
+        instance.reset(&mut store, false)?;
+        assert_eq!(*env.as_ref(&store), 3);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn reset_restores_passive_segments() -> Result<()> {
+        let mut store = Store::default();
+        let (instance, _) = instantiate(&mut store)?;
+        let init_passive: TypedFunction<(), ()> = instance
+            .exports
+            .get_typed_function(&store, "init_passive")?;
+        let memory = instance.exports.get_memory("memory")?.clone();
+
+        init_passive.call(&mut store)?;
+        assert_eq!(read(&store, &memory, 200, 3)?, b"abc");
+        // The segment was dropped.
+        assert!(init_passive.call(&mut store).is_err());
+
+        instance.reset(&mut store, false)?;
+        assert_eq!(read(&store, &memory, 200, 3)?, [0; 3]);
+        init_passive.call(&mut store)?;
+        assert_eq!(read(&store, &memory, 200, 3)?, b"abc");
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "sys")]
+pub mod interrupt {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "host" "tick" (func $tick))
+(func (export "run") (param $n i32) (result i32)
+      (local $i i32)
+      (loop $continue
+            (call $tick)
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $continue (i32.lt_u (local.get $i) (local.get $n))))
+      (local.get $i))
+(func (export "answer") (result i32)
+      (i32.const 42))
+)"#;
+
+    fn interruptible_store() -> Store {
+        let mut engine = Engine::default();
+        engine.set_interrupt_checks(true);
+        Store::new(engine)
+    }
+
+    fn tick(store: &mut Store) -> Function {
+        Function::new_typed(store, || {})
+    }
+
+    #[universal_test]
+    fn interrupt_checks_are_off_by_default() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let imports = imports! { "host" => { "tick" => tick(&mut store) } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let run = instance.exports.get_function("run")?;
+
+        store.interrupt_handle().interrupt();
+        let result = run.call(&mut store, &[Value::I32(10)])?;
+        assert_eq!(result[0], Value::I32(10));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn interrupt_between_calls() -> Result<()> {
+        let mut store = interruptible_store();
+        let module = Module::new(&store, WAT)?;
+        let imports = imports! { "host" => { "tick" => tick(&mut store) } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let run = instance.exports.get_function("run")?;
+        let answer = instance.exports.get_function("answer")?;
+
+        run.call(&mut store, &[Value::I32(10)])?;
+
+        // An interrupt sent while no code is running stays pending, and
+        // stops the next call, whichever function it calls.
+        store.interrupt_handle().interrupt();
+        let error = answer.call(&mut store, &[]).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::Interrupted));
+
+        // The interrupt is cleared once it has stopped a call.
+        assert!(!store.interrupt_handle().is_interrupted());
+        let result = answer.call(&mut store, &[])?;
+        assert_eq!(result[0], Value::I32(42));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn interrupt_from_host_function() -> Result<()> {
+        let mut store = interruptible_store();
+        let module = Module::new(&store, WAT)?;
+        let handle = store.interrupt_handle();
+        let env = FunctionEnv::new(&mut store, (handle, 0));
+        let tick = Function::new_typed_with_env(
+            &mut store,
+            &env,
+            |mut env: FunctionEnvMut<(InterruptHandle, u32)>| {
+                let (handle, ticks) = env.data_mut();
+                *ticks += 1;
+                if *ticks == 3 {
+                    handle.interrupt();
+                }
+            },
+        );
+        let imports = imports! { "host" => { "tick" => tick } };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let run = instance.exports.get_function("run")?;
+
+        let error = run.call(&mut store, &[Value::I32(10)]).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::Interrupted));
+        assert_eq!(env.as_ref(&store).1, 3);
+        assert!(!store.interrupt_handle().is_interrupted());
+
+        let result = run.call(&mut store, &[Value::I32(10)])?;
+        assert_eq!(result[0], Value::I32(10));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn interrupt_start_function() -> Result<()> {
+        let mut store = interruptible_store();
+        let module = Module::new(&store, "(module (func $start) (start $start))")?;
+
+        store.interrupt_handle().interrupt();
+        match Instance::new(&mut store, &module, &imports! {}) {
+            Err(InstantiationError::Start(error)) => {
+                assert_eq!(error.to_trap(), Some(TrapCode::Interrupted))
+            }
+            _ => panic!("the start function should have been interrupted"),
+        }
+        assert!(!store.interrupt_handle().is_interrupted());
+        Instance::new(&mut store, &module, &imports! {})?;
+        Ok(())
+    }
+}
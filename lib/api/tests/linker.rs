@@ -0,0 +1,179 @@
+pub mod linker {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const LIBRARY: &str = r#"(module
+(memory (export "memory") 1)
+(func (export "double") (param i32) (result i32)
+      (i32.mul (local.get 0) (i32.const 2)))
+)"#;
+
+    const USER: &str = r#"(module
+(import "lib" "double" (func $double (param i32) (result i32)))
+(import "lib" "memory" (memory 1))
+(func (export "run") (param i32) (result i32)
+      (i32.store (i32.const 0) (local.get 0))
+      (call $double (i32.load (i32.const 0))))
+)"#;
+
+    #[universal_test]
+    fn modules_are_linked_through_registered_instances() -> Result<()> {
+        let mut store = Store::default();
+        let mut linker = Linker::new();
+        let library = Module::new(&store, LIBRARY)?;
+        let library = linker.define_module(&mut store, "lib", &library)?;
+
+        let user = Module::new(&store, USER)?;
+        let instance = linker.instantiate(&mut store, &user)?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+        assert_eq!(run.call(&mut store, 21)?, 42);
+
+        // The memory is shared with the library.
+        let memory = library.exports.get_memory("memory")?;
+        let mut bytes = [0; 4];
+        memory.view(&store).read(0, &mut bytes)?;
+        assert_eq!(i32::from_le_bytes(bytes), 21);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn shadowing_is_explicit() -> Result<()> {
+        let mut store = Store::default();
+        let mut linker = Linker::new();
+        let library = Module::new(&store, LIBRARY)?;
+        let library = Instance::new(&mut store, &library, &imports! {})?;
+        linker.define_instance("lib", &library)?;
+        linker.define_typed(&mut store, "env", "one", || 1)?;
+
+        assert!(matches!(
+            linker.define_instance("lib", &library),
+            Err(LinkError::Shadowed(module, _)) if module == "lib"
+        ));
+        match linker.define_typed(&mut store, "env", "one", || 2) {
+            Err(LinkError::Shadowed(module, name)) => {
+                assert_eq!((module.as_str(), name.as_str()), ("env", "one"));
+            }
+            _ => panic!("expected the definition to be refused"),
+        }
+
+        linker.allow_shadowing(true);
+        linker.define_typed(&mut store, "env", "one", || 2)?;
+        let one = match linker.get("env", "one") {
+            Some(Extern::Function(one)) => one.clone(),
+            _ => panic!("expected a function"),
+        };
+        assert_eq!(one.call(&mut store, &[])?[0], Value::I32(2));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn every_unknown_import_is_reported() -> Result<()> {
+        let mut store = Store::default();
+        let mut linker = Linker::new();
+        linker.define_typed(&mut store, "lib", "double", |x: i32| x * 2)?;
+        let module = Module::new(
+            &store,
+            r#"(module
+            (import "env" "a" (func))
+            (import "lib" "double" (func (param i32) (result i32)))
+            (import "env" "b" (global i32)))"#,
+        )?;
+
+        match linker.instantiate(&mut store, &module) {
+            Err(InstantiationError::Link(LinkError::UnknownImports(unknown))) => {
+                let names: Vec<_> = unknown
+                    .iter()
+                    .map(|(module, name, _)| (module.as_str(), name.as_str()))
+                    .collect();
+                assert_eq!(names, [("env", "a"), ("env", "b")]);
+                assert_eq!(
+                    unknown[1].2,
+                    ExternType::Global(GlobalType::new(Type::I32, Mutability::Const))
+                );
+            }
+            _ => panic!("expected the unknown imports to be reported"),
+        }
+        Ok(())
+    }
+
+    #[universal_test]
+    fn fallback_resolves_unknown_imports() -> Result<()> {
+        let mut store = Store::default();
+        let mut linker = Linker::new();
+        linker.set_fallback(|store, import| match import.ty() {
+            ExternType::Function(ty) if import.module() == "env" => {
+                let results: Vec<_> = ty.results().iter().map(|_| Value::I32(7)).collect();
+                Some(Function::new(store, ty, move |_| Ok(results.clone())).into())
+            }
+            _ => None,
+        });
+        let module = Module::new(
+            &store,
+            r#"(module
+            (import "env" "seven" (func $seven (result i32)))
+            (func (export "run") (result i32) (call $seven)))"#,
+        )?;
+
+        let instance = linker.instantiate(&mut store, &module)?;
+        let run: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "run")?;
+        assert_eq!(run.call(&mut store)?, 7);
+        // The fallback doesn't define the import.
+        assert!(linker.get("env", "seven").is_none());
+
+        let other = Module::new(&store, r#"(module (import "other" "f" (func)))"#)?;
+        assert!(matches!(
+            linker.instantiate(&mut store, &other),
+            Err(InstantiationError::Link(LinkError::UnknownImports(_)))
+        ));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn functions_are_defined_with_an_env() -> Result<()> {
+        let mut store = Store::default();
+        let env = FunctionEnv::new(&mut store, Vec::<i32>::new());
+        let mut linker = Linker::new();
+        linker.define_typed_with_env(
+            &mut store,
+            &env,
+            "env",
+            "log",
+            |mut env: FunctionEnvMut<Vec<i32>>, value: i32| env.data_mut().push(value),
+        )?;
+        let module = Module::new(
+            &store,
+            r#"(module
+            (import "env" "log" (func $log (param i32)))
+            (func (export "run") (call $log (i32.const 1)) (call $log (i32.const 2))))"#,
+        )?;
+
+        let instance = linker.instantiate(&mut store, &module)?;
+        let run: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "run")?;
+        run.call(&mut store)?;
+        assert_eq!(env.as_ref(&store), &[1, 2]);
+        Ok(())
+    }
+
+    #[cfg(feature = "sys")]
+    #[universal_test]
+    fn instance_pre_from_linker() -> Result<()> {
+        let mut store = Store::default();
+        let mut linker = Linker::new();
+        let library = Module::new(&store, LIBRARY)?;
+        linker.define_module(&mut store, "lib", &library)?;
+        let user = Module::new(&store, USER)?;
+
+        let instance_pre = linker.instantiate_pre(&mut store, &user)?;
+        for i in 0..3 {
+            let instance = instance_pre.instantiate(&mut store)?;
+            let run: TypedFunction<i32, i32> =
+                instance.exports.get_typed_function(&store, "run")?;
+            assert_eq!(run.call(&mut store, i)?, i * 2);
+        }
+        Ok(())
+    }
+}
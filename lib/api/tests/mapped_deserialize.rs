@@ -0,0 +1,115 @@
+#[cfg(feature = "sys")]
+pub mod mapped_deserialize {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    use std::convert::TryInto;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(import "env" "offset" (func $offset (result i32)))
+(memory (export "memory") 1)
+(data (i32.const 16) "\2a")
+(func $load (param i32) (result i32)
+      (i32.load8_u (i32.add (local.get 0) (call $offset))))
+(func (export "run") (param i32) (result i32)
+      (call $load (local.get 0)))
+(func (export "fail")
+      unreachable)
+)"#;
+
+    fn call_run(store: &mut Store, module: &Module) -> Result<i32> {
+        let offset = Function::new_typed(store, || 6);
+        let instance = Instance::new(store, module, &imports! { "env" => { "offset" => offset } })?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(store, "run")?;
+        let fail: TypedFunction<(), ()> = instance.exports.get_typed_function(store, "fail")?;
+        assert!(fail.call(store).is_err());
+        Ok(run.call(store, 10)?)
+    }
+
+    #[universal_test]
+    fn mapped_roundtrip() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let file = tempfile::NamedTempFile::new()?;
+        module.serialize_to_file(file.path())?;
+
+        let mapped = unsafe { Module::deserialize_from_file_mapped(&store, file.path())? };
+        assert_eq!(call_run(&mut store, &mapped)?, 42);
+        // The code is executed from the file.
+        #[cfg(target_os = "linux")]
+        assert!(std::fs::read_to_string("/proc/self/maps")?
+            .lines()
+            .any(|line| line.contains(" r-xp ") && line.ends_with(file.path().to_str().unwrap())));
+        // The other ways to deserialize a module read the same format.
+        let copied = unsafe { Module::deserialize_from_file(&store, file.path())? };
+        assert_eq!(call_run(&mut store, &copied)?, 42);
+        let copied = unsafe { Module::deserialize(&store, std::fs::read(file.path())?)? };
+        assert_eq!(call_run(&mut store, &copied)?, 42);
+        Ok(())
+    }
+
+    #[cfg(feature = "singlepass")]
+    #[universal_test]
+    fn mapped_roundtrip_singlepass() -> Result<()> {
+        let mut store = Store::new(Singlepass::default());
+        let module = Module::new(&store, WAT)?;
+        let file = tempfile::NamedTempFile::new()?;
+        module.serialize_to_file(file.path())?;
+
+        let mapped = unsafe { Module::deserialize_from_file_mapped(&store, file.path())? };
+        assert_eq!(call_run(&mut store, &mapped)?, 42);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn serialization_is_deterministic() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let serialized = module.serialize()?;
+        assert_eq!(serialized, module.serialize()?);
+
+        let file = tempfile::NamedTempFile::new()?;
+        std::fs::write(file.path(), &serialized)?;
+        let mapped = unsafe { Module::deserialize_from_file_mapped(&store, file.path())? };
+        assert_eq!(serialized, mapped.serialize()?);
+        let copied = unsafe { Module::deserialize(&store, serialized.clone())? };
+        assert_eq!(serialized, copied.serialize()?);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn invalid_files_are_rejected() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let serialized = module.serialize()?.to_vec();
+        let file = tempfile::NamedTempFile::new()?;
+        let deserialize = |bytes: &[u8]| -> Result<DeserializeError> {
+            std::fs::write(file.path(), bytes)?;
+            match unsafe { Module::deserialize_from_file_mapped(&store, file.path()) } {
+                Err(error) => Ok(error),
+                Ok(_) => panic!("expected the file to be rejected"),
+            }
+        };
+
+        assert!(matches!(
+            deserialize(&serialized[..serialized.len() - 1])?,
+            DeserializeError::InvalidByteLength { .. }
+        ));
+        assert!(matches!(
+            deserialize(b"not a module")?,
+            DeserializeError::Incompatible(_)
+        ));
+        // Point the root of the metadata out of its bounds.
+        let metadata_len = u32::from_le_bytes(serialized[28..32].try_into()?) as usize;
+        let mut corrupted = serialized.clone();
+        corrupted[24 + metadata_len..32 + metadata_len].copy_from_slice(&[0xff; 8]);
+        assert!(matches!(
+            deserialize(&corrupted)?,
+            DeserializeError::CorruptedBinary(_)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(feature = "merkle")]
+pub mod merkle {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(memory (export "memory") 3)
+(func (export "store") (param $offset i32) (param $value i32)
+      (i32.store8 (local.get $offset) (local.get $value)))
+(func (export "grow") (param $delta i32) (result i32)
+      (memory.grow (local.get $delta)))
+)"#;
+
+    #[universal_test]
+    fn merkle_root_depends_on_contents_and_size() -> Result<()> {
+        let mut store = Store::default();
+        let a = Memory::new(&mut store, MemoryType::new(2, None, false))?;
+        let b = Memory::new(&mut store, MemoryType::new(2, None, false))?;
+        let c = Memory::new(&mut store, MemoryType::new(3, None, false))?;
+        assert_eq!(a.view(&store).merkle_root(), b.view(&store).merkle_root());
+        assert_ne!(a.view(&store).merkle_root(), c.view(&store).merkle_root());
+
+        b.view(&store).write_u8(65536 + 7, 1)?;
+        assert_ne!(a.view(&store).merkle_root(), b.view(&store).merkle_root());
+        b.view(&store).write_u8(65536 + 7, 0)?;
+        assert_eq!(a.view(&store).merkle_root(), b.view(&store).merkle_root());
+        Ok(())
+    }
+
+    #[universal_test]
+    fn merkle_tree_updates_incrementally() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let write = instance
+            .exports
+            .get_typed_function::<(i32, i32), ()>(&store, "store")?;
+        let grow = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "grow")?;
+
+        let mut tree = memory.merkle_tree(&mut store)?;
+        let initial = tree.root();
+        assert_eq!(initial, memory.view(&store).merkle_root());
+        assert_eq!(tree.update(&mut store, memory)?, initial);
+
+        write.call(&mut store, 65536 * 2 + 10, 1)?;
+        memory.view(&store).write(65536 - 1, &[1, 2])?;
+        let root = tree.update(&mut store, memory)?;
+        assert_ne!(root, initial);
+        assert_eq!(root, memory.view(&store).merkle_root());
+
+        // Growing past the next power of two rebuilds the tree.
+        assert_eq!(grow.call(&mut store, 2)?, 3);
+        write.call(&mut store, 65536 * 4, 1)?;
+        let root = tree.update(&mut store, memory)?;
+        assert_eq!(root, memory.view(&store).merkle_root());
+
+        // Growing within the same power of two only adds leaves.
+        assert_eq!(grow.call(&mut store, 1)?, 5);
+        write.call(&mut store, 3, 1)?;
+        let root = tree.update(&mut store, memory)?;
+        assert_eq!(root, memory.view(&store).merkle_root());
+
+        write.call(&mut store, 3, 0)?;
+        assert_eq!(
+            tree.update(&mut store, memory)?,
+            memory.view(&store).merkle_root()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(feature = "sys")]
+pub mod pooling {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(memory (export "memory") 1 4)
+(table (export "table") 1 funcref)
+(func (export "store") (param i32 i32)
+      (i32.store (local.get 0) (local.get 1)))
+(data (i32.const 0) "wasm")
+)"#;
+
+    fn pool(instances: usize) -> Result<PoolingAllocator> {
+        let config = PoolingConfig {
+            instances,
+            memories: instances,
+            memory_pages: Pages(2),
+            tables: instances,
+            table_elements: 4,
+            ..Default::default()
+        };
+        Ok(PoolingAllocator::new(config).map_err(anyhow::Error::msg)?)
+    }
+
+    fn pooled_store(pool: &PoolingAllocator) -> Store {
+        let mut engine = Engine::default();
+        let base = BaseTunables::for_target(engine.target());
+        engine.set_tunables(PoolingTunables::new(base, pool.clone()));
+        Store::new(engine)
+    }
+
+    #[universal_test]
+    fn pool_slots_are_reused() -> Result<()> {
+        let pool = pool(2)?;
+        {
+            let mut store = pooled_store(&pool);
+            let module = Module::new(&store, WAT)?;
+            let _first = Instance::new(&mut store, &module, &imports! {})?;
+            let _second = Instance::new(&mut store, &module, &imports! {})?;
+            assert_eq!(pool.instances_in_use(), 2);
+            assert_eq!(pool.memories_in_use(), 2);
+            assert_eq!(pool.tables_in_use(), 2);
+
+            // The pool is exhausted.
+            assert!(Instance::new(&mut store, &module, &imports! {}).is_err());
+        }
+        assert_eq!(pool.instances_in_use(), 0);
+        assert_eq!(pool.memories_in_use(), 0);
+        assert_eq!(pool.tables_in_use(), 0);
+
+        let mut store = pooled_store(&pool);
+        let module = Module::new(&store, WAT)?;
+        let _instance = Instance::new(&mut store, &module, &imports! {})?;
+        assert_eq!(pool.instances_in_use(), 1);
+        Ok(())
+    }
+
+    #[universal_test]
+    fn pooled_memories_start_zeroed() -> Result<()> {
+        let pool = pool(1)?;
+        for _ in 0..2 {
+            let mut store = pooled_store(&pool);
+            let module = Module::new(&store, WAT)?;
+            let instance = Instance::new(&mut store, &module, &imports! {})?;
+            let memory = instance.exports.get_memory("memory")?.clone();
+            let store_fn: TypedFunction<(i32, i32), ()> =
+                instance.exports.get_typed_function(&store, "store")?;
+
+            let mut bytes = [0; 8];
+            memory.view(&store).read(0, &mut bytes)?;
+            assert_eq!(&bytes, b"wasm\0\0\0\0");
+            store_fn.call(&mut store, 4, -1)?;
+            memory.grow(&mut store, 1)?;
+            store_fn.call(&mut store, 0x1_0000, -1)?;
+        }
+        Ok(())
+    }
+
+    #[universal_test]
+    fn pooled_memories_and_tables_are_bounded() -> Result<()> {
+        let pool = pool(1)?;
+        let mut store = pooled_store(&pool);
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let table = instance.exports.get_table("table")?;
+
+        // The module allows 4 pages, but the slots only hold 2.
+        assert_eq!(memory.grow(&mut store, 1)?, Pages(1));
+        assert!(memory.grow(&mut store, 1).is_err());
+        assert_eq!(memory.view(&store).size(), Pages(2));
+
+        assert_eq!(table.grow(&mut store, 3, Value::FuncRef(None))?, 1);
+        assert!(table.grow(&mut store, 1, Value::FuncRef(None)).is_err());
+
+        // Modules needing more than a slot can't be instantiated.
+        let large = Module::new(&store, "(module (memory 3))")?;
+        assert!(Instance::new(&mut store, &large, &imports! {}).is_err());
+        Ok(())
+    }
+
+    #[universal_test]
+    fn pooled_instances_can_be_reset() -> Result<()> {
+        let pool = pool(1)?;
+        let mut store = pooled_store(&pool);
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?.clone();
+        let store_fn: TypedFunction<(i32, i32), ()> =
+            instance.exports.get_typed_function(&store, "store")?;
+
+        memory.grow(&mut store, 1)?;
+        store_fn.call(&mut store, 0, -1)?;
+        instance.reset(&mut store, false)?;
+
+        assert_eq!(memory.view(&store).size(), Pages(1));
+        let mut bytes = [0; 4];
+        memory.view(&store).read(0, &mut bytes)?;
+        assert_eq!(&bytes, b"wasm");
+        Ok(())
+    }
+
+    #[universal_test]
+    fn pooling_allocator_from_engine_builder() -> Result<()> {
+        let pool = pool(1)?;
+        #[cfg(feature = "cranelift")]
+        let compiler = Cranelift::default();
+        #[cfg(all(not(feature = "cranelift"), feature = "singlepass"))]
+        let compiler = Singlepass::default();
+        #[cfg(all(
+            not(feature = "cranelift"),
+            not(feature = "singlepass"),
+            feature = "llvm"
+        ))]
+        let compiler = LLVM::default();
+        let engine = EngineBuilder::new(compiler)
+            .set_pooling_allocator(Some(pool.clone()))
+            .engine();
+        let mut store = Store::new(engine);
+        let module = Module::new(&store, WAT)?;
+        let _instance = Instance::new(&mut store, &module, &imports! {})?;
+        assert_eq!(pool.instances_in_use(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+#[cfg(feature = "sys")]
+pub mod resource_limiter {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(memory (export "memory") 1)
+(table 1 funcref)
+(func (export "grow_memory") (param $delta i32) (result i32)
+      (memory.grow (local.get $delta)))
+(func (export "grow_table") (param $delta i32) (result i32)
+      (table.grow (ref.null func) (local.get $delta)))
+)"#;
+
+    /// Limits memories to `pages` pages and tables to `elements`
+    /// elements, denying or trapping beyond.
+    struct Quota {
+        pages: u32,
+        elements: u32,
+        instances: usize,
+        action: ResourceLimitAction,
+    }
+
+    impl Quota {
+        fn new(pages: u32, elements: u32, action: ResourceLimitAction) -> Self {
+            Self {
+                pages,
+                elements,
+                instances: usize::MAX,
+                action,
+            }
+        }
+
+        fn decide(&self, allowed: bool) -> ResourceLimitAction {
+            if allowed {
+                ResourceLimitAction::Allow
+            } else {
+                self.action
+            }
+        }
+    }
+
+    impl ResourceLimiter for Quota {
+        fn memory_growing(
+            &mut self,
+            _current: Pages,
+            desired: Pages,
+            _maximum: Option<Pages>,
+        ) -> ResourceLimitAction {
+            self.decide(desired.0 <= self.pages)
+        }
+
+        fn table_growing(
+            &mut self,
+            _current: u32,
+            desired: u32,
+            _maximum: Option<u32>,
+        ) -> ResourceLimitAction {
+            self.decide(desired <= self.elements)
+        }
+
+        fn instance_creating(&mut self, instances: usize) -> ResourceLimitAction {
+            self.decide(instances < self.instances)
+        }
+    }
+
+    fn instantiate(store: &mut Store) -> Result<Instance> {
+        let module = Module::new(store, WAT)?;
+        Ok(Instance::new(store, &module, &imports! {})?)
+    }
+
+    #[universal_test]
+    fn limiter_denies_growth() -> Result<()> {
+        let mut store = Store::default();
+        store.set_resource_limiter(Quota::new(3, 2, ResourceLimitAction::Deny));
+        let instance = instantiate(&mut store)?;
+        let grow_memory: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "grow_memory")?;
+        let grow_table: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "grow_table")?;
+
+        assert_eq!(grow_memory.call(&mut store, 2)?, 1);
+        assert_eq!(grow_memory.call(&mut store, 1)?, -1);
+        assert_eq!(grow_table.call(&mut store, 1)?, 1);
+        assert_eq!(grow_table.call(&mut store, 1)?, -1);
+
+        // Growing from the host is limited too.
+        let memory = instance.exports.get_memory("memory")?;
+        assert!(memory.grow(&mut store, 1).is_err());
+        assert!(Memory::new(&mut store, MemoryType::new(4, None, false)).is_err());
+        assert!(Memory::new(&mut store, MemoryType::new(3, None, false)).is_ok());
+        Ok(())
+    }
+
+    #[universal_test]
+    fn limiter_traps() -> Result<()> {
+        let mut store = Store::default();
+        store.set_resource_limiter(Quota::new(3, 2, ResourceLimitAction::Trap));
+        let instance = instantiate(&mut store)?;
+        let grow_memory: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "grow_memory")?;
+        let grow_table: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "grow_table")?;
+
+        let error = grow_memory.call(&mut store, 3).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::ResourceLimitExceeded));
+        let error = grow_table.call(&mut store, 2).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::ResourceLimitExceeded));
+        assert_eq!(grow_memory.call(&mut store, 2)?, 1);
+
+        // Growing from the host reports the trap.
+        let trap = MemoryError::Trap(TrapCode::ResourceLimitExceeded);
+        let memory = instance.exports.get_memory("memory")?;
+        assert_eq!(memory.grow(&mut store, 1), Err(trap.clone()));
+        assert_eq!(
+            Memory::new(&mut store, MemoryType::new(4, None, false)).unwrap_err(),
+            trap
+        );
+        Ok(())
+    }
+
+    #[universal_test]
+    fn limiter_denies_creation() -> Result<()> {
+        // The memory of the module needs 1 page.
+        let mut store = Store::default();
+        store.set_resource_limiter(Quota::new(0, 2, ResourceLimitAction::Deny));
+        assert!(matches!(
+            instantiate(&mut store)
+                .unwrap_err()
+                .downcast::<InstantiationError>()?,
+            InstantiationError::Link(LinkError::Resource(_))
+        ));
+
+        let mut store = Store::default();
+        let mut quota = Quota::new(1, 1, ResourceLimitAction::Deny);
+        quota.instances = 1;
+        store.set_resource_limiter(quota);
+        instantiate(&mut store)?;
+        assert!(instantiate(&mut store).is_err());
+        Ok(())
+    }
+
+    #[universal_test]
+    fn limiters_are_per_store() -> Result<()> {
+        let mut small = Store::default();
+        small.set_resource_limiter(Quota::new(1, 1, ResourceLimitAction::Deny));
+        let mut large = Store::default();
+        large.set_resource_limiter(Quota::new(10, 1, ResourceLimitAction::Deny));
+
+        let instance = instantiate(&mut small)?;
+        let grow: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&small, "grow_memory")?;
+        assert_eq!(grow.call(&mut small, 1)?, -1);
+
+        let instance = instantiate(&mut large)?;
+        let grow: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&large, "grow_memory")?;
+        assert_eq!(grow.call(&mut large, 1)?, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+#[cfg(feature = "sys")]
+pub mod snapshot {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+(type $ret_i32_ty (func (result i32)))
+(memory (export "memory") 1)
+(table $table (export "table") 2 funcref)
+(global $counter (export "counter") (mut i32) (i32.const 0))
+(global $wide (export "wide") (mut i64) (i64.const 0))
+(elem (i32.const 0) $one)
+(elem declare func $two)
+
+(func $one (result i32) (i32.const 1))
+(func $two (result i32) (i32.const 2))
+
+(func (export "mutate")
+      (i32.store (i32.const 16) (i32.const 0xdeadbeef))
+      (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+      (global.set $wide (i64.const -7))
+      (drop (memory.grow (i32.const 1)))
+      (table.set $table (i32.const 0) (ref.func $two)))
+(func (export "call") (param i32) (result i32)
+      (call_indirect $table (type $ret_i32_ty) (local.get 0)))
+)"#;
+
+    #[universal_test]
+    fn instance_snapshot_roundtrip() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        instance
+            .exports
+            .get_function("mutate")?
+            .call(&mut store, &[])?;
+        let snapshot = instance.snapshot(&store)?;
+        let snapshot = StoreSnapshot::deserialize(&snapshot.serialize()?)?;
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        instance.restore_snapshot(&mut store, &snapshot)?;
+
+        let memory = instance.exports.get_memory("memory")?;
+        let view = memory.view(&store);
+        assert_eq!(view.size(), Pages(2));
+        let mut bytes = [0u8; 4];
+        view.read(16, &mut bytes)?;
+        assert_eq!(u32::from_le_bytes(bytes), 0xdeadbeef);
+
+        let counter = instance.exports.get_global("counter")?;
+        assert_eq!(counter.get(&mut store), Value::I32(1));
+        let wide = instance.exports.get_global("wide")?;
+        assert_eq!(wide.get(&mut store), Value::I64(-7));
+
+        let call = instance.exports.get_function("call")?;
+        assert_eq!(
+            call.call(&mut store, &[Value::I32(0)])?[..],
+            [Value::I32(2)]
+        );
+        assert!(call.call(&mut store, &[Value::I32(1)]).is_err());
+        Ok(())
+    }
+
+    #[universal_test]
+    fn store_snapshot_rollback() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let snapshot = store.snapshot()?;
+
+        instance
+            .exports
+            .get_function("mutate")?
+            .call(&mut store, &[])?;
+
+        // A snapshot cannot shrink a memory that has grown since.
+        assert!(matches!(
+            store.restore_snapshot(&snapshot),
+            Err(SnapshotError::Mismatch(_))
+        ));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn failed_restore_leaves_store_unchanged() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let first = Instance::new(&mut store, &module, &imports! {})?;
+        let second = Instance::new(&mut store, &module, &imports! {})?;
+        let snapshot = store.snapshot()?;
+
+        let memory = first.exports.get_memory("memory")?;
+        memory
+            .view(&store)
+            .write(16, &0xdeadbeefu32.to_le_bytes())?;
+        let counter = first.exports.get_global("counter")?;
+        counter.set(&mut store, Value::I32(5))?;
+        second.exports.get_memory("memory")?.grow(&mut store, 1)?;
+
+        // The second memory is now larger than its snapshot.
+        assert!(matches!(
+            store.restore_snapshot(&snapshot),
+            Err(SnapshotError::Mismatch(_))
+        ));
+
+        let mut bytes = [0u8; 4];
+        memory.view(&store).read(16, &mut bytes)?;
+        assert_eq!(u32::from_le_bytes(bytes), 0xdeadbeef);
+        assert_eq!(counter.get(&mut store), Value::I32(5));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn store_snapshot_restores_values() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?;
+        let table = instance.exports.get_table("table")?;
+        let counter = instance.exports.get_global("counter")?;
+        let wide = instance.exports.get_global("wide")?;
+        let call = instance.exports.get_function("call")?;
+        let snapshot = store.snapshot()?;
+
+        memory
+            .view(&store)
+            .write(16, &0xdeadbeefu32.to_le_bytes())?;
+        counter.set(&mut store, Value::I32(5))?;
+        wide.set(&mut store, Value::I64(-7))?;
+        let one = table.get(&mut store, 0).unwrap();
+        table.set(&mut store, 0, Value::FuncRef(None))?;
+        table.set(&mut store, 1, one)?;
+        assert!(call.call(&mut store, &[Value::I32(0)]).is_err());
+        assert_eq!(
+            call.call(&mut store, &[Value::I32(1)])?[..],
+            [Value::I32(1)]
+        );
+
+        store.restore_snapshot(&snapshot)?;
+
+        let mut bytes = [0u8; 4];
+        memory.view(&store).read(16, &mut bytes)?;
+        assert_eq!(bytes, [0; 4]);
+        assert_eq!(counter.get(&mut store), Value::I32(0));
+        assert_eq!(wide.get(&mut store), Value::I64(0));
+        assert_eq!(
+            call.call(&mut store, &[Value::I32(0)])?[..],
+            [Value::I32(1)]
+        );
+        assert!(call.call(&mut store, &[Value::I32(1)]).is_err());
+        Ok(())
+    }
+
+    #[universal_test]
+    fn snapshot_rejects_other_module() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let snapshot = instance.snapshot(&store)?;
+
+        let other = Module::new(&store, "(module (memory 1))")?;
+        let other = Instance::new(&mut store, &other, &imports! {})?;
+        assert!(other.restore_snapshot(&mut store, &snapshot).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(feature = "sys")]
+pub mod store_remove {
+
+    use anyhow::Result;
+    use macro_wasmer_universal_test::universal_test;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    #[cfg(feature = "js")]
+    use wasm_bindgen_test::*;
+    use wasmer::*;
+
+    const EXPORTER: &str = r#"(module
+(memory (export "memory") 1)
+(global (export "global") (mut i32) (i32.const 0))
+(func (export "answer") (result i32) (i32.const 42))
+)"#;
+
+    const IMPORTER: &str = r#"(module
+(import "env" "memory" (memory 1))
+(func (export "load") (result i32) (i32.load (i32.const 0)))
+)"#;
+
+    #[universal_test]
+    fn removed_instances_are_reclaimed() -> Result<()> {
+        let pool = PoolingAllocator::new(PoolingConfig {
+            instances: 1,
+            memories: 1,
+            tables: 1,
+            ..Default::default()
+        })
+        .map_err(anyhow::Error::msg)?;
+        let mut engine = Engine::default();
+        let base = BaseTunables::for_target(engine.target());
+        engine.set_tunables(PoolingTunables::new(base, pool.clone()));
+        let mut store = Store::new(engine);
+        let module = Module::new(&store, EXPORTER)?;
+
+        // The pool only has room for one instance at a time.
+        for _ in 0..10 {
+            let instance = Instance::new(&mut store, &module, &imports! {})?;
+            let answer: TypedFunction<(), i32> =
+                instance.exports.get_typed_function(&store, "answer")?;
+            assert_eq!(answer.call(&mut store)?, 42);
+            store.remove_instance(&instance)?;
+            assert_eq!(pool.instances_in_use(), 0);
+            assert_eq!(pool.memories_in_use(), 0);
+        }
+        Ok(())
+    }
+
+    #[universal_test]
+    fn removed_objects_are_detected() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, EXPORTER)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let memory = instance.exports.get_memory("memory")?.clone();
+        let global = instance.exports.get_global("global")?.clone();
+        let answer = instance.exports.get_function("answer")?.clone();
+        store.remove_instance(&instance)?;
+
+        assert!(!memory.is_from_store(&store));
+        assert!(!global.is_from_store(&store));
+        assert!(!answer.is_from_store(&store));
+        assert_eq!(store.remove_instance(&instance), Err(RemoveError::NotFound));
+
+        // The slots of the removed objects are reused, but the old handles
+        // don't point to the new objects.
+        let other = Instance::new(&mut store, &module, &imports! {})?;
+        let other_memory = other.exports.get_memory("memory")?;
+        assert!(other_memory.is_from_store(&store));
+        assert!(!memory.is_from_store(&store));
+        assert!(catch_unwind(AssertUnwindSafe(|| memory.ty(&store))).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| answer.call(&mut store, &[]))).is_err());
+
+        let mut other_store = Store::default();
+        assert_eq!(
+            other_store.remove_instance(&other),
+            Err(RemoveError::NotFound)
+        );
+        Ok(())
+    }
+
+    #[universal_test]
+    fn imported_objects_are_not_removed() -> Result<()> {
+        let mut store = Store::default();
+        let exporter = Module::new(&store, EXPORTER)?;
+        let importer = Module::new(&store, IMPORTER)?;
+        let exporter = Instance::new(&mut store, &exporter, &imports! {})?;
+        let memory = exporter.exports.get_memory("memory")?.clone();
+        let importer = Instance::new(
+            &mut store,
+            &importer,
+            &imports! { "env" => { "memory" => memory.clone() } },
+        )?;
+
+        assert_eq!(
+            store.remove_instance(&exporter),
+            Err(RemoveError::InUse("an instance".to_string()))
+        );
+        assert!(memory.is_from_store(&store));
+        store.remove_instance(&importer)?;
+        store.remove_instance(&exporter)?;
+        assert!(!memory.is_from_store(&store));
+        Ok(())
+    }
+
+    #[universal_test]
+    fn referenced_functions_are_not_removed() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, EXPORTER)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let answer = instance.exports.get_function("answer")?.clone();
+        let table = Table::new(
+            &mut store,
+            TableType::new(Type::FuncRef, 1, None),
+            Value::FuncRef(Some(answer)),
+        )?;
+
+        assert_eq!(
+            store.remove_instance(&instance),
+            Err(RemoveError::InUse("a table".to_string()))
+        );
+        table.set(&mut store, 0, Value::FuncRef(None))?;
+        store.remove_instance(&instance)?;
+        Ok(())
+    }
+
+    #[universal_test]
+    fn host_objects_can_be_removed() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, IMPORTER)?;
+        let memory = Memory::new(&mut store, MemoryType::new(1, None, false))?;
+        let instance = Instance::new(
+            &mut store,
+            &module,
+            &imports! { "env" => { "memory" => memory.clone() } },
+        )?;
+        let load = instance.exports.get_function("load")?.clone();
+
+        let memory = Extern::from(memory);
+        assert_eq!(
+            store.remove_extern(&memory),
+            Err(RemoveError::InUse("an instance".to_string()))
+        );
+        // The functions of an instance are removed with the instance.
+        assert_eq!(
+            store.remove_extern(&Extern::from(load)),
+            Err(RemoveError::InUse("an instance".to_string()))
+        );
+
+        store.remove_instance(&instance)?;
+        store.remove_extern(&memory)?;
+        assert!(!memory.is_from_store(&store));
+        assert_eq!(store.remove_extern(&memory), Err(RemoveError::NotFound));
+
+        let function = Function::new_typed(&mut store, || 1);
+        store.remove_extern(&Extern::from(function))?;
+        Ok(())
+    }
+
+    #[universal_test]
+    fn objects_can_be_removed_after_a_host_function_panics() -> Result<()> {
+        const CALLER: &str = r#"(module
+(import "env" "panic" (func $panic))
+(func (export "call") (call $panic))
+)"#;
+        const STARTER: &str = r#"(module
+(import "env" "panic" (func $panic))
+(start $panic)
+)"#;
+        let mut store = Store::default();
+        let panic = Function::new(&mut store, FunctionType::new([], []), |_| {
+            panic!("host function panic")
+        });
+        let imports = imports! { "env" => { "panic" => panic } };
+
+        let caller = Module::new(&store, CALLER)?;
+        let instance = Instance::new(&mut store, &caller, &imports)?;
+        let call = instance.exports.get_function("call")?.clone();
+        assert!(catch_unwind(AssertUnwindSafe(|| call.call(&mut store, &[]))).is_err());
+        store.remove_instance(&instance)?;
+
+        let starter = Module::new(&store, STARTER)?;
+        let started = catch_unwind(AssertUnwindSafe(|| {
+            Instance::new(&mut store, &starter, &imports)
+        }));
+        assert!(started.is_err());
+        let exporter = Module::new(&store, EXPORTER)?;
+        let instance = Instance::new(&mut store, &exporter, &imports! {})?;
+        store.remove_instance(&instance)?;
+        Ok(())
+    }
+
+    #[universal_test]
+    fn rollback_skips_removed_objects() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, EXPORTER)?;
+        let kept = Instance::new(&mut store, &module, &imports! {})?;
+        let removed = Instance::new(&mut store, &module, &imports! {})?;
+        let global = kept.exports.get_global("global")?.clone();
+
+        let checkpoint = store.checkpoint()?;
+        global.set(&mut store, Value::I32(1))?;
+        store.remove_instance(&removed)?;
+        let _new = Instance::new(&mut store, &module, &imports! {})?;
+        store.rollback(checkpoint)?;
+        assert_eq!(global.get(&mut store), Value::I32(0));
+        Ok(())
+    }
+}
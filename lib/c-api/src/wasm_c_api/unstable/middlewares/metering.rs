@@ -32,8 +32,11 @@
 //! }
 //!
 //! int main() {
-//!     // Create a new metering middleware, with our cost function.
-//!     wasmer_metering_t* metering = wasmer_metering_new(10, cost_function);
+//!     // Create a new metering middleware, with our cost function and
+//!     // its id, which lets the compiled modules be cached.
+//!     wasmer_metering_t* metering =
+//!         wasmer_metering_new_with_cost_function_id(10, cost_function, "example-v1");
+//!     assert(metering);
 //!
 //!     // Consume `metering` to produce a generic `wasmer_middleware_t` value.
 //!     wasmer_middleware_t* middleware = wasmer_metering_as_middleware(metering);
@@ -134,6 +137,9 @@
 use super::super::super::instance::wasm_instance_t;
 use super::super::parser::operator::wasmer_parser_operator_t;
 use super::wasmer_middleware_t;
+use crate::error::update_last_error;
+use std::ffi::CStr;
+use std::os::raw::c_char;
 use std::sync::Arc;
 use wasmer_api::wasmparser::Operator;
 use wasmer_middlewares::{
@@ -169,6 +175,9 @@ pub type wasmer_metering_cost_function_t =
 /// cost), in addition to a cost function. The cost function defines
 /// the cost of an operation, that will decrease the initial limit.
 ///
+/// The middleware has no fingerprint, so the modules compiled with
+/// it can't be cached, see [`wasmer_metering_new_with_cost_function_id`].
+///
 /// # Example
 ///
 /// See module's documentation.
@@ -184,6 +193,51 @@ pub extern "C" fn wasmer_metering_new(
     })
 }
 
+/// Creates a new metering middleware like [`wasmer_metering_new`],
+/// with an id for the cost function.
+///
+/// `cost_function_id` is a null-terminated UTF-8 string identifying
+/// the cost function, such as the name and version of a gas
+/// schedule. It must be changed whenever the cost function is. It
+/// stands for the cost function in the fingerprint of the middleware,
+/// so that the modules compiled with it can be cached.
+///
+/// Returns `NULL` if `cost_function_id` is `NULL` or isn't valid
+/// UTF-8, and the error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_metering_new_with_cost_function_id(
+    initial_limit: u64,
+    cost_function: wasmer_metering_cost_function_t,
+    cost_function_id: *const c_char,
+) -> Option<Box<wasmer_metering_t>> {
+    if cost_function_id.is_null() {
+        update_last_error("the cost function id is null");
+        return None;
+    }
+    let cost_function_id = match CStr::from_ptr(cost_function_id).to_str() {
+        Ok(cost_function_id) => cost_function_id,
+        Err(error) => {
+            update_last_error(format!(
+                "the cost function id is not valid UTF-8: {}",
+                error
+            ));
+            return None;
+        }
+    };
+    let cost_function = move |operator: &Operator| -> u64 { cost_function(operator.into()) };
+
+    Some(Box::new(wasmer_metering_t {
+        inner: Arc::new(
+            Metering::new(initial_limit, Box::new(cost_function))
+                .with_cost_function_id(cost_function_id),
+        ),
+    }))
+}
+
 /// Deletes a [`wasmer_metering_t`].
 ///
 /// # Example
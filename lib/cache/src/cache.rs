@@ -4,7 +4,7 @@
 
 use crate::hash::Hash;
 use std::error::Error;
-use wasmer::{AsEngineRef, Module};
+use wasmer::{AsEngineRef, DeserializeError, Module, SerializeError};
 
 /// A generic cache for storing and loading compiled wasm modules.
 pub trait Cache {
@@ -15,6 +15,12 @@ pub trait Cache {
 
     /// Loads a module using the provided [`Store`] and [`Hash`].
     ///
+    /// The cache may only use `engine` to select the modules compiled with
+    /// its middlewares: the [`MemoryCache`](crate::MemoryCache) returns the
+    /// modules as they were stored, without checking their target or CPU
+    /// features. Modules must be loaded with an engine compatible with the
+    /// one which compiled them.
+    ///
     /// # Safety
     /// This function is unsafe as the cache store could be tampered with.
     unsafe fn load(
@@ -26,3 +32,37 @@ pub trait Cache {
     /// Store a [`Module`] into the cache with the given [`Hash`].
     fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError>;
 }
+
+/// Returns the fingerprint of the middlewares of the modules loaded for
+/// `engine`.
+///
+/// A headless engine can't tell which middlewares it expects, it loads the
+/// modules compiled without middlewares. An engine with a middleware without
+/// a fingerprint loads no module.
+pub(crate) fn engine_middleware_fingerprint(
+    engine: &impl AsEngineRef,
+) -> Result<[u8; 32], DeserializeError> {
+    let engine = engine.as_engine_ref();
+    let engine = engine.engine();
+    match engine.middleware_fingerprint() {
+        Some(middleware_fingerprint) => Ok(middleware_fingerprint),
+        None if engine.is_headless() => Ok([0; 32]),
+        None => Err(DeserializeError::Incompatible(
+            "The engine has middlewares without a fingerprint, its modules are not cached"
+                .to_string(),
+        )),
+    }
+}
+
+/// Returns the fingerprint of the middlewares `module` was compiled with.
+///
+/// A module compiled with a middleware without a fingerprint can't be
+/// cached, since no engine could tell whether it may load it.
+pub(crate) fn module_middleware_fingerprint(module: &Module) -> Result<[u8; 32], SerializeError> {
+    module.middleware_fingerprint().ok_or_else(|| {
+        SerializeError::Generic(
+            "The module was compiled with middlewares without a fingerprint, it can't be cached"
+                .to_string(),
+        )
+    })
+}
@@ -1,16 +1,45 @@
 #![cfg_attr(not(feature = "filesystem"), allow(unused))]
-use crate::cache::Cache;
+use crate::cache::{engine_middleware_fingerprint, module_middleware_fingerprint, Cache};
 use crate::hash::Hash;
-use std::fs::{create_dir_all, File};
+use filetime::FileTime;
+use fs2::FileExt;
+use std::ffi::OsStr;
+use std::fs::{create_dir_all, File, OpenOptions};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
 use wasmer::{AsEngineRef, DeserializeError, Module, SerializeError};
 
+/// The file locked by the processes sharing a cache directory.
+const LOCK_FILE: &str = ".lock";
+
+/// The extension of the files modules are written to before being moved
+/// into the cache.
+const TEMP_EXTENSION: &str = "tmp";
+
+/// The age after which [`FileSystemCache::prune`] considers a temporary file
+/// abandoned by a writer that died.
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Makes the names of the temporary files of a process unique.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 /// Representation of a directory that contains compiled wasm artifacts.
 ///
 /// The `FileSystemCache` type implements the [`Cache`] trait, which allows it to be used
 /// generically when some sort of cache is required.
 ///
+/// Modules compiled with middlewares are cached apart from the modules compiled
+/// from the same bytes with other middlewares, and only loaded by engines with
+/// the same middlewares.
+///
+/// The directory can be shared by several processes: modules are written to
+/// a temporary file which is then renamed, so they are never read partially
+/// written, and the processes lock the directory while they read or remove
+/// modules. With [`FileSystemCache::set_max_size`], the least recently used
+/// modules are evicted when a store makes the cache exceed its size.
+///
 /// # Usage
 ///
 /// ```
@@ -22,6 +51,9 @@ use wasmer::{AsEngineRef, DeserializeError, Module, SerializeError};
 ///     // Create a new file system cache.
 ///     let mut fs_cache = FileSystemCache::new("some/directory/goes/here")?;
 ///
+///     // Keep at most 1 GiB of modules.
+///     fs_cache.set_max_size(Some(1 << 30));
+///
 ///     // Compute a key for a given WebAssembly binary
 ///     let key = Hash::generate(bytes);
 ///
@@ -34,6 +66,23 @@ use wasmer::{AsEngineRef, DeserializeError, Module, SerializeError};
 pub struct FileSystemCache {
     path: PathBuf,
     ext: Option<String>,
+    max_size: Option<u64>,
+}
+
+/// Statistics about the modules of a [`FileSystemCache`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileSystemCacheStats {
+    /// The number of modules.
+    pub entries: usize,
+    /// The total size of the modules, in bytes.
+    pub size: u64,
+}
+
+/// A module found in the cache directory.
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
 }
 
 #[cfg(feature = "filesystem")]
@@ -45,7 +94,11 @@ impl FileSystemCache {
             let metadata = path.metadata()?;
             if metadata.is_dir() {
                 if !metadata.permissions().readonly() {
-                    Ok(Self { path, ext: None })
+                    Ok(Self {
+                        path,
+                        ext: None,
+                        max_size: None,
+                    })
                 } else {
                     // This directory is readonly.
                     Err(io::Error::new(
@@ -72,11 +125,32 @@ impl FileSystemCache {
                     format!("failed to create cache directory: {}", path.display()),
                 ))
             } else {
-                Ok(Self { path, ext: None })
+                Ok(Self {
+                    path,
+                    ext: None,
+                    max_size: None,
+                })
             }
         }
     }
 
+    /// Returns the path of the module cached for `key`, compiled with the
+    /// middlewares of the given fingerprint.
+    ///
+    /// Modules compiled with middlewares, such as metering with a given
+    /// cost table, are cached apart from the modules compiled from the same
+    /// bytes with other middlewares.
+    fn module_path(&self, key: Hash, middleware_fingerprint: [u8; 32]) -> PathBuf {
+        let mut filename = key.to_string();
+        if middleware_fingerprint != [0; 32] {
+            filename = format!("{}-{}", filename, hex::encode(middleware_fingerprint));
+        }
+        if let Some(ref ext) = self.ext {
+            filename = format!("{}.{}", filename, ext);
+        }
+        self.path.join(filename)
+    }
+
     /// Set the extension for this cached file.
     ///
     /// This is needed for loading native files from Windows, as otherwise
@@ -84,6 +158,203 @@ impl FileSystemCache {
     pub fn set_cache_extension(&mut self, ext: Option<impl ToString>) {
         self.ext = ext.map(|ext| ext.to_string());
     }
+
+    /// Set the maximum size of the modules in the cache, in bytes.
+    ///
+    /// When storing a module makes the cache exceed this size, the least
+    /// recently stored or loaded modules are evicted, except the module just
+    /// stored. Without a maximum size, which is the default, the cache grows
+    /// without bound.
+    pub fn set_max_size(&mut self, max_size: Option<u64>) {
+        self.max_size = max_size;
+    }
+
+    /// Returns the number and the total size of the modules in the cache.
+    pub fn stats(&self) -> io::Result<FileSystemCacheStats> {
+        let _lock = self.lock(false)?;
+        Ok(self
+            .entries()?
+            .iter()
+            .fold(FileSystemCacheStats::default(), |stats, entry| {
+                FileSystemCacheStats {
+                    entries: stats.entries + 1,
+                    size: stats.size + entry.size,
+                }
+            }))
+    }
+
+    /// Evicts the least recently used modules until the cache fits in its
+    /// maximum size, and removes the temporary files left by writers that
+    /// died.
+    ///
+    /// Returns the number and the total size of the evicted modules.
+    pub fn prune(&self) -> io::Result<FileSystemCacheStats> {
+        let _lock = self.lock(true)?;
+        for entry in self.path.read_dir()? {
+            let entry = entry?;
+            let path = entry.path();
+            if !self.is_temp(&path) {
+                continue;
+            }
+            let age = entry.metadata()?.modified()?.elapsed().unwrap_or_default();
+            if age >= STALE_TEMP_AGE {
+                remove_file(&path)?;
+            }
+        }
+        self.evict(None)
+    }
+
+    /// Locks the cache directory, shared with the other readers, or
+    /// exclusively.
+    ///
+    /// The lock is released when the returned file is dropped.
+    fn lock(&self, exclusive: bool) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.path.join(LOCK_FILE))?;
+        if exclusive {
+            FileExt::lock_exclusive(&file)?;
+        } else {
+            FileExt::lock_shared(&file)?;
+        }
+        Ok(file)
+    }
+
+    /// Returns the modules in the cache directory.
+    ///
+    /// The other files, such as the lock and the modules being written, are
+    /// skipped.
+    fn entries(&self) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for entry in self.path.read_dir()? {
+            let entry = entry?;
+            let path = entry.path();
+            if !self.is_module(&path) {
+                continue;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                // The module was removed by a process which doesn't lock the
+                // directory.
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+            if metadata.is_file() {
+                entries.push(Entry {
+                    path,
+                    size: metadata.len(),
+                    last_used: metadata.modified()?,
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Whether `path` is a module of this cache, that is named after its key
+    /// and the fingerprint of its middlewares.
+    fn is_module(&self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => return false,
+        };
+        let name = match self.ext {
+            Some(ref ext) => match name.strip_suffix(ext.as_str()) {
+                Some(name) => match name.strip_suffix('.') {
+                    Some(name) => name,
+                    None => return false,
+                },
+                None => return false,
+            },
+            None => name,
+        };
+        let is_hash = |hash: &str| hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit());
+        match name.split_once('-') {
+            Some((key, middleware_fingerprint)) => is_hash(key) && is_hash(middleware_fingerprint),
+            None => is_hash(name),
+        }
+    }
+
+    /// Whether `path` is a temporary file a module is written to.
+    fn is_temp(&self, path: &Path) -> bool {
+        matches!(path.file_name().and_then(|name| name.to_str()), Some(name) if name.starts_with('.'))
+            && path.extension() == Some(OsStr::new(TEMP_EXTENSION))
+    }
+
+    /// Evicts the least recently used modules, except `keep`, until the
+    /// cache fits in its maximum size.
+    ///
+    /// The directory must be locked exclusively.
+    fn evict(&self, keep: Option<&Path>) -> io::Result<FileSystemCacheStats> {
+        let mut evicted = FileSystemCacheStats::default();
+        let max_size = match self.max_size {
+            Some(max_size) => max_size,
+            None => return Ok(evicted),
+        };
+        let mut entries = self.entries()?;
+        let mut size: u64 = entries.iter().map(|entry| entry.size).sum();
+        entries.sort_by(|a, b| (a.last_used, &a.path).cmp(&(b.last_used, &b.path)));
+        for entry in entries {
+            if size <= max_size {
+                break;
+            }
+            if Some(entry.path.as_path()) == keep {
+                continue;
+            }
+            remove_file(&entry.path)?;
+            size -= entry.size;
+            evicted.entries += 1;
+            evicted.size += entry.size;
+        }
+        Ok(evicted)
+    }
+
+    /// Writes `bytes` to a new temporary file of the cache directory, named
+    /// after `path`.
+    fn write_temp(&self, path: &Path, bytes: &[u8]) -> io::Result<PathBuf> {
+        let temp_path = self.path.join(format!(
+            ".{}.{}.{}.{}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+            TEMP_EXTENSION
+        ));
+        let written = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(bytes)?;
+                // The module must be on disk before it is renamed, otherwise
+                // a crash could leave an empty module in the cache.
+                file.sync_all()
+            });
+        match written {
+            Ok(()) => Ok(temp_path),
+            Err(error) => {
+                let _ = std::fs::remove_file(&temp_path);
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Removes a file, which may have already been removed by a process which
+/// doesn't lock the directory.
+fn remove_file(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the size and the modification time of a file, which tell whether
+/// it was replaced.
+fn file_version(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
 }
 
 #[cfg(feature = "filesystem")]
@@ -96,33 +367,259 @@ impl Cache for FileSystemCache {
         engine: &impl AsEngineRef,
         key: Hash,
     ) -> Result<Module, Self::DeserializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        let ret = Module::deserialize_from_file(engine, path.clone());
-        if ret.is_err() {
-            // If an error occurs while deserializing then we can not trust it anymore
-            // so delete the cache file
-            let _ = std::fs::remove_file(path);
+        let middleware_fingerprint = engine_middleware_fingerprint(engine)?;
+        let path = self.module_path(key, middleware_fingerprint);
+        let lock = self.lock(false)?;
+        let version = file_version(&path);
+        let ret = Module::deserialize_from_file(engine, path.clone()).and_then(|module| {
+            if module.middleware_fingerprint() != Some(middleware_fingerprint) {
+                return Err(DeserializeError::Incompatible(
+                    "The cached module was compiled with other middlewares".to_string(),
+                ));
+            }
+            Ok(module)
+        });
+        match ret {
+            // The modification time of the module tells when it was last
+            // used, for the eviction.
+            Ok(_) => {
+                let _ = filetime::set_file_mtime(&path, FileTime::now());
+            }
+            Err(DeserializeError::Io(ref error)) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(_) => {
+                // If an error occurs while deserializing then we can not trust it anymore
+                // so delete the cache file
+                drop(lock);
+                if let Ok(_lock) = self.lock(true) {
+                    // Unless another process stored the module again while
+                    // the directory was unlocked.
+                    if version.is_some() && file_version(&path) == version {
+                        let _ = std::fs::remove_file(path);
+                    }
+                }
+            }
         }
         ret
     }
 
     fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
-        let filename = if let Some(ref ext) = self.ext {
-            format!("{}.{}", key.to_string(), ext)
-        } else {
-            key.to_string()
-        };
-        let path = self.path.join(filename);
-        let mut file = File::create(path)?;
-
+        let path = self.module_path(key, module_middleware_fingerprint(module)?);
         let buffer = module.serialize()?;
-        file.write_all(&buffer)?;
+        let temp_path = self.write_temp(&path, &buffer)?;
+
+        let _lock = self.lock(true)?;
+        if let Err(error) = std::fs::rename(&temp_path, &path) {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(error.into());
+        }
+        // The module is stored even if the eviction fails, the cache then
+        // exceeds its size until the next store or prune.
+        let _ = self.evict(Some(&path));
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "filesystem")]
+mod tests {
+    use super::*;
+    use crate::test_utils::{keys, WASM};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use wasmer::{
+        CompilerConfig, EngineBuilder, FunctionMiddleware, LocalFunctionIndex, ModuleMiddleware,
+        Store,
+    };
+    use wasmer_compiler_singlepass::Singlepass;
+
+    /// A middleware changing nothing, with the given fingerprint.
+    #[derive(Debug)]
+    struct Passthrough(Option<u32>);
+
+    #[derive(Debug)]
+    struct FunctionPassthrough;
+
+    impl FunctionMiddleware for FunctionPassthrough {}
+
+    impl ModuleMiddleware for Passthrough {
+        fn generate_function_middleware(
+            &self,
+            _: LocalFunctionIndex,
+        ) -> Box<dyn FunctionMiddleware> {
+            Box::new(FunctionPassthrough)
+        }
+
+        fn fingerprint(&self) -> Option<String> {
+            self.0.map(|id| format!("passthrough {}", id))
+        }
+    }
+
+    fn store(middleware: Option<Passthrough>) -> Store {
+        let mut compiler_config = Singlepass::default();
+        if let Some(middleware) = middleware {
+            compiler_config.push_middleware(Arc::new(middleware));
+        }
+        Store::new(EngineBuilder::new(compiler_config))
+    }
+
+    #[test]
+    fn modules_are_cached_per_middlewares() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let key = Hash::generate(WASM);
+
+        let plain = store(None);
+        let metered = store(Some(Passthrough(Some(1))));
+        cache
+            .store(key, &Module::new(&plain, WASM).unwrap())
+            .unwrap();
+        cache
+            .store(key, &Module::new(&metered, WASM).unwrap())
+            .unwrap();
+        assert_eq!(cache.stats().unwrap().entries, 2);
+
+        for store in [&plain, &metered] {
+            let module = unsafe { cache.load(store, key) }.unwrap();
+            assert_eq!(
+                module.middleware_fingerprint(),
+                store.engine().middleware_fingerprint()
+            );
+        }
+        assert!(unsafe { cache.load(&store(Some(Passthrough(Some(2)))), key) }.is_err());
+
+        // Modules compiled with a middleware without a fingerprint are not
+        // cached, and such an engine doesn't load, nor remove, other modules.
+        let unnamed = store(Some(Passthrough(None)));
+        assert!(cache
+            .store(key, &Module::new(&unnamed, WASM).unwrap())
+            .is_err());
+        assert!(matches!(
+            unsafe { cache.load(&unnamed, key) },
+            Err(DeserializeError::Incompatible(_))
+        ));
+        assert_eq!(cache.stats().unwrap().entries, 2);
+
+        // A module which doesn't match its key is rejected, and removed.
+        let path = cache.module_path(key, metered.engine().middleware_fingerprint().unwrap());
+        std::fs::copy(cache.module_path(key, [0; 32]), &path).unwrap();
+        let headless = Store::new(EngineBuilder::headless());
+        unsafe { cache.load(&headless, key) }.unwrap();
+        assert!(unsafe { cache.load(&metered, key) }.is_err());
+        assert!(!path.exists());
+    }
+
+    fn set_last_used(cache: &FileSystemCache, key: Hash, secs: i64) {
+        let path = cache.module_path(key, [0; 32]);
+        filetime::set_file_mtime(path, FileTime::from_unix_time(secs, 0)).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_modules_are_evicted() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let store = store(None);
+        let module = Module::new(&store, WASM).unwrap();
+        let (first, second, third) = keys();
+
+        cache.store(first, &module).unwrap();
+        let size = cache.stats().unwrap().size;
+        cache.set_max_size(Some(2 * size));
+        cache.store(second, &module).unwrap();
+        set_last_used(&cache, first, 1_000);
+        set_last_used(&cache, second, 2_000);
+        // Loading the first module makes it the most recently used.
+        unsafe { cache.load(&store, first) }.unwrap();
+
+        cache.store(third, &module).unwrap();
+        assert_eq!(
+            cache.stats().unwrap(),
+            FileSystemCacheStats {
+                entries: 2,
+                size: 2 * size
+            }
+        );
+        assert!(unsafe { cache.load(&store, second) }.is_err());
+        unsafe { cache.load(&store, first) }.unwrap();
+        unsafe { cache.load(&store, third) }.unwrap();
+
+        // The module just stored is kept even if it doesn't fit.
+        cache.set_max_size(Some(0));
+        cache.store(second, &module).unwrap();
+        assert_eq!(
+            cache.stats().unwrap(),
+            FileSystemCacheStats { entries: 1, size }
+        );
+        unsafe { cache.load(&store, second) }.unwrap();
+    }
+
+    #[test]
+    fn prune_evicts_modules_and_stale_temporary_files() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = FileSystemCache::new(dir.path()).unwrap();
+        let store = store(None);
+        let module = Module::new(&store, WASM).unwrap();
+        for i in 0..3 {
+            cache.store(Hash::new([i; 32]), &module).unwrap();
+            set_last_used(&cache, Hash::new([i; 32]), 1_000 * i as i64);
+        }
+        let size = cache.stats().unwrap().size / 3;
+        let stale = dir.path().join(".module.1.0.tmp");
+        let fresh = dir.path().join(".module.1.1.tmp");
+        let other = dir.path().join("README");
+        for path in [&stale, &fresh, &other] {
+            std::fs::write(path, b"").unwrap();
+        }
+        filetime::set_file_mtime(&stale, FileTime::from_unix_time(0, 0)).unwrap();
+
+        // Without a maximum size, only the stale temporary files are removed.
+        assert_eq!(cache.prune().unwrap(), FileSystemCacheStats::default());
+        assert!(!stale.exists());
+        assert!(fresh.exists() && other.exists());
+        assert_eq!(cache.stats().unwrap().entries, 3);
+
+        cache.set_max_size(Some(size));
+        assert_eq!(
+            cache.prune().unwrap(),
+            FileSystemCacheStats {
+                entries: 2,
+                size: 2 * size
+            }
+        );
+        assert_eq!(
+            cache.stats().unwrap(),
+            FileSystemCacheStats { entries: 1, size }
+        );
+        unsafe { cache.load(&store, Hash::new([2; 32])) }.unwrap();
+    }
+
+    #[test]
+    fn concurrent_stores_never_expose_partial_modules() {
+        let dir = TempDir::new().unwrap();
+        let engine = store(None).engine().clone();
+        let module = Module::new(&engine, WASM).unwrap();
+        let key = Hash::new([0; 32]);
+        FileSystemCache::new(dir.path())
+            .unwrap()
+            .store(key, &module)
+            .unwrap();
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let (dir, engine, module) = (dir.path(), engine.clone(), module.clone());
+                scope.spawn(move || {
+                    let mut cache = FileSystemCache::new(dir).unwrap();
+                    for _ in 0..20 {
+                        if i % 2 == 0 {
+                            cache.store(key, &module).unwrap();
+                        } else {
+                            unsafe { cache.load(&engine, key) }.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+        // Only the module and the lock remain.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::cache::Cache;
+use crate::hash::Hash;
+use crate::memory::MemoryCache;
+use wasmer::{AsEngineRef, CompileError, Module};
+
+/// A cache keeping the recently used modules in memory in front of another
+/// cache, usually a [`FileSystemCache`](crate::FileSystemCache).
+///
+/// The `LayeredCache` type implements the [`Cache`] trait. Loading a module
+/// looks for it in memory first, then in the inner cache, and keeps the
+/// modules loaded from the inner cache in memory. Storing a module stores it
+/// in both.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{Module, Store};
+/// use wasmer_cache::{FileSystemCache, Hash, LayeredCache, MemoryCache};
+///
+/// fn get_module(store: &Store, bytes: &[u8]) -> Result<Module, Box<dyn std::error::Error>> {
+///     // Keep 64 modules in memory in front of a file system cache.
+///     let fs_cache = FileSystemCache::new("some/directory/goes/here")?;
+///     let mut cache = LayeredCache::new(MemoryCache::new(64), fs_cache);
+///
+///     // Load the module, or compile and store it if it isn't cached yet.
+///     Ok(unsafe { cache.load_or_compile(store, Hash::generate(bytes), bytes)? })
+/// }
+/// ```
+pub struct LayeredCache<C> {
+    memory: MemoryCache,
+    inner: C,
+}
+
+impl<C: Cache> LayeredCache<C> {
+    /// Construct a new `LayeredCache` keeping modules of `inner` in `memory`.
+    pub fn new(memory: MemoryCache, inner: C) -> Self {
+        Self { memory, inner }
+    }
+
+    /// Returns the cache of the modules in memory.
+    pub fn memory(&self) -> &MemoryCache {
+        &self.memory
+    }
+
+    /// Returns the inner cache.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the inner cache, mutably.
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Loads the module of `key`, or compiles `wasm` and stores the module
+    /// if it isn't cached.
+    ///
+    /// Failing to store the compiled module in the inner cache isn't an
+    /// error, the module is still kept in memory.
+    ///
+    /// # Safety
+    /// This function is unsafe as the cache store could be tampered with.
+    pub unsafe fn load_or_compile(
+        &mut self,
+        engine: &impl AsEngineRef,
+        key: Hash,
+        wasm: &[u8],
+    ) -> Result<Module, CompileError> {
+        if let Ok(module) = self.load(engine, key) {
+            return Ok(module);
+        }
+        let module = Module::new(engine, wasm)?;
+        let _ = self.store(key, &module);
+        Ok(module)
+    }
+}
+
+impl<C: Cache> Cache for LayeredCache<C> {
+    type DeserializeError = C::DeserializeError;
+    type SerializeError = C::SerializeError;
+
+    unsafe fn load(
+        &self,
+        engine: &impl AsEngineRef,
+        key: Hash,
+    ) -> Result<Module, Self::DeserializeError> {
+        if let Ok(module) = self.memory.load(engine, key) {
+            return Ok(module);
+        }
+        let module = self.inner.load(engine, key)?;
+        self.memory.insert(key, &module);
+        Ok(module)
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        self.memory.insert(key, module);
+        self.inner.store(key, module)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "filesystem")]
+mod tests {
+    use super::*;
+    use crate::test_utils::WASM;
+    use crate::FileSystemCache;
+    use tempfile::TempDir;
+    use wasmer::Store;
+    use wasmer_compiler_singlepass::Singlepass;
+
+    #[test]
+    fn modules_are_loaded_from_memory_then_from_the_inner_cache() {
+        let dir = TempDir::new().unwrap();
+        let store = Store::new(Singlepass::default());
+        let key = Hash::generate(WASM);
+        let layered = || {
+            LayeredCache::new(
+                MemoryCache::new(1),
+                FileSystemCache::new(dir.path()).unwrap(),
+            )
+        };
+
+        // The module is compiled and stored in both caches.
+        let mut cache = layered();
+        unsafe { cache.load_or_compile(&store, key, WASM) }.unwrap();
+        assert_eq!(cache.memory().len(), 1);
+        assert_eq!(cache.inner().stats().unwrap().entries, 1);
+
+        // The module is loaded from the inner cache, and kept in memory.
+        let mut cache = layered();
+        unsafe { cache.load(&store, key) }.unwrap();
+        assert_eq!(cache.memory().len(), 1);
+        std::fs::remove_dir_all(dir.path()).unwrap();
+        unsafe { cache.load(&store, key) }.unwrap();
+
+        // Invalid bytes aren't compiled when the module is cached.
+        unsafe { cache.load_or_compile(&store, key, b"not wasm") }.unwrap();
+        assert!(unsafe { cache.load_or_compile(&store, Hash::new([0; 32]), b"not wasm") }.is_err());
+    }
+}
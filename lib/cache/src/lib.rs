@@ -21,11 +21,17 @@
 mod cache;
 mod filesystem;
 mod hash;
+mod layered;
+mod memory;
+#[cfg(test)]
+mod test_utils;
 
 pub use crate::cache::Cache;
 #[cfg(feature = "filesystem")]
-pub use crate::filesystem::FileSystemCache;
+pub use crate::filesystem::{FileSystemCache, FileSystemCacheStats};
 pub use crate::hash::Hash;
+pub use crate::layered::LayeredCache;
+pub use crate::memory::MemoryCache;
 
 // We re-export those for convinience of users
 pub use wasmer::{DeserializeError, SerializeError};
@@ -0,0 +1,196 @@
+use crate::cache::{engine_middleware_fingerprint, module_middleware_fingerprint, Cache};
+use crate::hash::Hash;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use wasmer::{AsEngineRef, DeserializeError, Module, SerializeError};
+
+/// The key of a module, and the fingerprint of the middlewares it was
+/// compiled with.
+type Key = (Hash, [u8; 32]);
+
+/// A cache keeping the deserialized modules in memory.
+///
+/// The `MemoryCache` type implements the [`Cache`] trait. Loading a module
+/// from it is a clone of the module, which skips the deserialization a
+/// [`FileSystemCache`](crate::FileSystemCache) does on every load. When
+/// the cache is full, storing a module evicts the least recently stored or
+/// loaded one.
+///
+/// The modules are returned as they were stored, the engine passed to
+/// [`Cache::load`] is only used for the fingerprint of its middlewares. They
+/// should only be used with engines compatible with the one which compiled
+/// them. Like the `FileSystemCache`, modules
+/// compiled with middlewares are only loaded by engines with the same
+/// middlewares.
+///
+/// # Usage
+///
+/// ```
+/// use wasmer::{DeserializeError, Module, Store};
+/// use wasmer_cache::{Cache, Hash, MemoryCache};
+///
+/// fn load_module(
+///     cache: &MemoryCache,
+///     store: &Store,
+///     bytes: &[u8],
+/// ) -> Result<Module, DeserializeError> {
+///     // Compute a key for a given WebAssembly binary
+///     let key = Hash::generate(bytes);
+///
+///     // Load the module stored for this key
+///     unsafe { cache.load(store, key) }
+/// }
+/// ```
+pub struct MemoryCache {
+    capacity: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// The modules, and the time they were last used.
+    modules: HashMap<Key, (Module, u64)>,
+    /// The keys of the modules, by the time they were last used.
+    lru: BTreeMap<u64, Key>,
+    /// Incremented every time a module is used.
+    clock: u64,
+}
+
+impl State {
+    /// Marks the module of `key` as the most recently used one.
+    fn touch(&mut self, key: Key) -> Option<Module> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (module, last_used) = self.modules.get_mut(&key)?;
+        self.lru.remove(last_used);
+        self.lru.insert(clock, key);
+        *last_used = clock;
+        Some(module.clone())
+    }
+}
+
+impl MemoryCache {
+    /// Construct a new `MemoryCache` keeping at most `capacity` modules.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Returns the maximum number of modules kept by the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of modules in the cache.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().modules.len()
+    }
+
+    /// Returns whether the cache has no modules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all the modules from the cache.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.modules.clear();
+        state.lru.clear();
+    }
+
+    /// Inserts a module in the cache, evicting the least recently used
+    /// modules if the cache is full.
+    ///
+    /// Unlike [`Cache::store`], this only needs a shared reference to the
+    /// cache, so it can be called while loading a module. A module compiled
+    /// with a middleware without a fingerprint is not inserted.
+    pub fn insert(&self, key: Hash, module: &Module) {
+        let middleware_fingerprint = match module.middleware_fingerprint() {
+            Some(middleware_fingerprint) if self.capacity > 0 => middleware_fingerprint,
+            _ => return,
+        };
+        let key = (key, middleware_fingerprint);
+        let mut state = self.state.lock().unwrap();
+        if let Some((_, last_used)) = state.modules.remove(&key) {
+            state.lru.remove(&last_used);
+        }
+        while state.modules.len() >= self.capacity {
+            let oldest = match state.lru.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(evicted) = state.lru.remove(&oldest) {
+                state.modules.remove(&evicted);
+            }
+        }
+        state.modules.insert(key, (module.clone(), 0));
+        state.touch(key);
+    }
+}
+
+impl Cache for MemoryCache {
+    type DeserializeError = DeserializeError;
+    type SerializeError = SerializeError;
+
+    unsafe fn load(
+        &self,
+        engine: &impl AsEngineRef,
+        key: Hash,
+    ) -> Result<Module, Self::DeserializeError> {
+        let middleware_fingerprint = engine_middleware_fingerprint(engine)?;
+        self.state
+            .lock()
+            .unwrap()
+            .touch((key, middleware_fingerprint))
+            .ok_or_else(|| {
+                DeserializeError::Generic(format!(
+                    "the module {} is not in the cache",
+                    key.to_string()
+                ))
+            })
+    }
+
+    fn store(&mut self, key: Hash, module: &Module) -> Result<(), Self::SerializeError> {
+        module_middleware_fingerprint(module)?;
+        self.insert(key, module);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{keys, WASM};
+    use wasmer::Store;
+    use wasmer_compiler_singlepass::Singlepass;
+
+    #[test]
+    fn loads_count_as_uses_when_evicting() {
+        let store = Store::new(Singlepass::default());
+        let module = Module::new(&store, WASM).unwrap();
+        let mut cache = MemoryCache::new(2);
+        let (first, second, third) = keys();
+
+        cache.store(first, &module).unwrap();
+        cache.store(second, &module).unwrap();
+        unsafe { cache.load(&store, first) }.unwrap();
+        cache.store(third, &module).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        // The second module was stored after the first, but the first was
+        // loaded since.
+        assert!(unsafe { cache.load(&store, second) }.is_err());
+        unsafe { cache.load(&store, first) }.unwrap();
+        unsafe { cache.load(&store, third) }.unwrap();
+
+        // Storing a module again doesn't evict another one.
+        cache.store(first, &module).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(unsafe { cache.load(&store, first) }.is_err());
+    }
+}
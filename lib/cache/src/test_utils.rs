@@ -0,0 +1,12 @@
+//! Fixtures shared by the tests of the caches.
+
+use crate::hash::Hash;
+
+/// The binary of `(module (func))`.
+pub(crate) const WASM: &[u8] =
+    b"\0asm\x01\0\0\0\x01\x04\x01\x60\0\0\x03\x02\x01\0\x0a\x04\x01\x02\0\x0b";
+
+/// Returns three distinct keys.
+pub(crate) fn keys() -> (Hash, Hash, Hash) {
+    (Hash::new([1; 32]), Hash::new([2; 32]), Hash::new([3; 32]))
+}
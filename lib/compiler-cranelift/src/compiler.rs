@@ -147,7 +147,7 @@ impl Compiler for CraneliftCompiler {
                 reader.set_middleware_chain(
                     self.config
                         .middlewares
-                        .generate_function_middleware_chain(i),
+                        .generate_function_middleware_chain_for_module(module, i),
                 );
 
                 func_translator.translate(
@@ -176,7 +176,7 @@ impl Compiler for CraneliftCompiler {
                     .traps()
                     .into_iter()
                     .map(mach_trap_to_trap)
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>, _>>()?;
 
                 let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
                     #[cfg(feature = "unwind")]
@@ -259,7 +259,7 @@ impl Compiler for CraneliftCompiler {
                 reader.set_middleware_chain(
                     self.config
                         .middlewares
-                        .generate_function_middleware_chain(*i),
+                        .generate_function_middleware_chain_for_module(module, *i),
                 );
 
                 func_translator.translate(
@@ -288,7 +288,7 @@ impl Compiler for CraneliftCompiler {
                     .traps()
                     .iter()
                     .map(mach_trap_to_trap)
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>, _>>()?;
 
                 let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
                     #[cfg(feature = "unwind")]
@@ -442,17 +442,17 @@ fn mach_reloc_to_reloc(module: &ModuleInfo, reloc: &MachReloc) -> Relocation {
     }
 }
 
-fn mach_trap_to_trap(trap: &MachTrap) -> TrapInformation {
+fn mach_trap_to_trap(trap: &MachTrap) -> Result<TrapInformation, CompileError> {
     let &MachTrap { offset, code } = trap;
-    TrapInformation {
+    Ok(TrapInformation {
         code_offset: offset,
-        trap_code: translate_ir_trapcode(code),
-    }
+        trap_code: translate_ir_trapcode(code)?,
+    })
 }
 
 /// Translates the Cranelift IR TrapCode into generic Trap Code
-fn translate_ir_trapcode(trap: ir::TrapCode) -> TrapCode {
-    match trap {
+fn translate_ir_trapcode(trap: ir::TrapCode) -> Result<TrapCode, CompileError> {
+    Ok(match trap {
         ir::TrapCode::StackOverflow => TrapCode::StackOverflow,
         ir::TrapCode::HeapOutOfBounds => TrapCode::HeapAccessOutOfBounds,
         ir::TrapCode::HeapMisaligned => TrapCode::UnalignedAtomic,
@@ -463,9 +463,10 @@ fn translate_ir_trapcode(trap: ir::TrapCode) -> TrapCode {
         ir::TrapCode::IntegerDivisionByZero => TrapCode::IntegerDivisionByZero,
         ir::TrapCode::BadConversionToInteger => TrapCode::BadConversionToInteger,
         ir::TrapCode::UnreachableCodeReached => TrapCode::UnreachableCodeReached,
-        ir::TrapCode::Interrupt => unimplemented!("Interrupts not supported"),
-        ir::TrapCode::User(_user_code) => unimplemented!("User trap code not supported"),
-        // ir::TrapCode::Interrupt => TrapCode::Interrupt,
-        // ir::TrapCode::User(user_code) => TrapCode::User(user_code),
-    }
+        ir::TrapCode::Interrupt => TrapCode::Interrupted,
+        // Raised by middlewares, see `MiddlewareReaderState::push_trap`.
+        ir::TrapCode::User(user_code) => TrapCode::from_u32(user_code as u32).ok_or_else(|| {
+            CompileError::Codegen(format!("unknown user trap code {}", user_code))
+        })?,
+    })
 }
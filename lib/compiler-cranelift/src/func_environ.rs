@@ -113,6 +113,10 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `memory32.atomic.notify`.
     memory32_atomic_notify_sig: Option<ir::SigRef>,
 
+    /// The external function signature for the function called when the
+    /// epoch deadline of the store is reached.
+    epoch_deadline_reached_sig: Option<ir::SigRef>,
+
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -155,6 +159,7 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             memory32_atomic_wait32_sig: None,
             memory32_atomic_wait64_sig: None,
             memory32_atomic_notify_sig: None,
+            epoch_deadline_reached_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
@@ -696,6 +701,21 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_data_drop_index())
     }
 
+    fn get_epoch_deadline_reached_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.epoch_deadline_reached_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![AbiParam::special(
+                    self.pointer_type(),
+                    ArgumentPurpose::VMContext,
+                )],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.epoch_deadline_reached_sig = Some(sig);
+        sig
+    }
+
     fn get_memory32_atomic_wait32_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.memory32_atomic_wait32_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -851,6 +871,90 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
 
         (base, func_addr)
     }
+
+    /// Traps if the interrupt flag of the store is set, when the module
+    /// checks it.
+    fn translate_interrupt_check(&mut self, pos: &mut FuncCursor<'_>) {
+        let flag_offset = match self.offsets.vmctx_interrupt_flag_pointer() {
+            Some(offset) => i32::try_from(offset).unwrap(),
+            None => return,
+        };
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(pos.func);
+        let base = pos.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        // Load the pointer to the flag, then the flag itself. The flag is
+        // set from other threads, so it is loaded atomically to keep the
+        // load from being merged with the previous check.
+        let flag_ptr = pos.ins().load(pointer_type, mem_flags, base, flag_offset);
+        let flag = pos
+            .ins()
+            .atomic_load(I32, ir::MemFlags::trusted(), flag_ptr);
+        pos.ins().trapnz(flag, ir::TrapCode::Interrupt);
+    }
+
+    /// Lets the store decide whether to trap or to extend its epoch deadline
+    /// once it is reached, when the module checks it.
+    fn translate_epoch_check(&mut self, builder: &mut FunctionBuilder) {
+        let (epoch_offset, deadline_offset, reached_offset) = match (
+            self.offsets.vmctx_epoch_pointer(),
+            self.offsets.vmctx_epoch_deadline_pointer(),
+            self.offsets.vmctx_epoch_deadline_reached(),
+        ) {
+            (Some(epoch), Some(deadline), Some(reached)) => (
+                i32::try_from(epoch).unwrap(),
+                i32::try_from(deadline).unwrap(),
+                i32::try_from(reached).unwrap(),
+            ),
+            _ => return,
+        };
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        // The epoch is incremented from other threads, so like the interrupt
+        // flag it is loaded atomically. The deadline only changes in calls.
+        let epoch_ptr = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, epoch_offset);
+        let epoch = builder
+            .ins()
+            .atomic_load(I64, ir::MemFlags::trusted(), epoch_ptr);
+        let deadline_ptr = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, deadline_offset);
+        let deadline = builder
+            .ins()
+            .load(I64, ir::MemFlags::trusted(), deadline_ptr, 0);
+
+        let reached_block = builder.create_block();
+        let continuation_block = builder.create_block();
+        builder.set_cold_block(reached_block);
+        let reached = builder
+            .ins()
+            .icmp(IntCC::UnsignedGreaterThanOrEqual, epoch, deadline);
+        builder.ins().brnz(reached, reached_block, &[]);
+        builder.ins().jump(continuation_block, &[]);
+        builder.seal_block(reached_block);
+
+        // Let the store decide whether to trap or to extend the deadline.
+        builder.switch_to_block(reached_block);
+        let func_sig = self.get_epoch_deadline_reached_sig(builder.func);
+        let func_addr = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, reached_offset);
+        builder.ins().call_indirect(func_sig, func_addr, &[base]);
+        builder.ins().jump(continuation_block, &[]);
+        builder.seal_block(continuation_block);
+
+        builder.switch_to_block(continuation_block);
+    }
 }
 
 impl<'module_environment> TargetEnvironment for FuncEnvironment<'module_environment> {
@@ -1573,6 +1677,18 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
+    fn translate_function_header(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        self.translate_interrupt_check(&mut builder.cursor());
+        self.translate_epoch_check(builder);
+        Ok(())
+    }
+
+    fn translate_loop_header(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        self.translate_interrupt_check(&mut builder.cursor());
+        self.translate_epoch_check(builder);
+        Ok(())
+    }
+
     fn get_global_type(&self, global_index: GlobalIndex) -> Option<WasmerType> {
         Some(self.module.globals.get(global_index)?.ty)
     }
@@ -109,6 +109,7 @@ impl FuncTranslator {
         self.state.initialize(&builder.func.signature, exit_block);
 
         parse_local_decls(reader, &mut builder, num_params, environ)?;
+        environ.translate_function_header(&mut builder)?;
         parse_function_body(
             module_translation_state,
             reader,
@@ -233,7 +234,14 @@ fn parse_function_body<FE: FuncEnvironment + ?Sized>(
         builder.set_srcloc(cur_srcloc(reader));
         let op = reader.read_operator()?;
         environ.before_translate_operator(&op, builder, state)?;
-        translate_operator(module_translation_state, &op, builder, state, environ)?;
+        match reader.trap_code() {
+            // An `unreachable` raising a specific trap code.
+            Some(trap_code) if state.reachable => {
+                builder.ins().trap(ir::TrapCode::User(trap_code as u16));
+                state.reachable = false;
+            }
+            _ => translate_operator(module_translation_state, &op, builder, state, environ)?,
+        }
         environ.after_translate_operator(&op, builder, state)?;
     }
 
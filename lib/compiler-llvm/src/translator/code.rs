@@ -33,7 +33,8 @@ use wasmer_compiler::{
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
     CompileError, FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex,
-    ModuleInfo, RelocationTarget, SignatureIndex, Symbol, SymbolRegistry, TableIndex, Type,
+    ModuleInfo, RelocationTarget, SignatureIndex, Symbol, SymbolRegistry, TableIndex, TrapCode,
+    Type,
 };
 use wasmer_vm::{MemoryStyle, TableStyle, VMOffsets};
 
@@ -140,7 +141,7 @@ impl FuncTranslator {
         reader.set_middleware_chain(
             config
                 .middlewares
-                .generate_function_middleware_chain(*local_func_index),
+                .generate_function_middleware_chain_for_module(wasm_module, *local_func_index),
         );
 
         let mut params = vec![];
@@ -212,11 +213,13 @@ impl FuncTranslator {
             fcg.ctx.basic(),
             &func_attrs,
         );
+        fcg.trap_if_interrupted();
+        fcg.check_epoch_deadline();
 
         while fcg.state.has_control_frames() {
             let pos = reader.current_position() as u32;
             let op = reader.read_operator()?;
-            fcg.translate_operator(op, pos)?;
+            fcg.translate_operator(op, reader.trap_code(), pos)?;
         }
 
         fcg.finalize(wasm_fn_type)?;
@@ -1224,6 +1227,115 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         self.builder.position_at_end(continue_block);
     }
 
+    fn trap_if_interrupted(&mut self) {
+        let flag_ptr = match self.ctx.interrupt_flag(self.intrinsics) {
+            Some(flag_ptr) => flag_ptr,
+            None => return,
+        };
+        let flag = self.builder.build_load(flag_ptr, "interrupt_flag");
+        // The flag is set from other threads, so it must be loaded again
+        // every time.
+        flag.as_instruction_value()
+            .unwrap()
+            .set_volatile(true)
+            .unwrap();
+        let interrupted = self.builder.build_int_compare(
+            IntPredicate::NE,
+            flag.into_int_value(),
+            self.intrinsics.i32_zero,
+            "",
+        );
+        let interrupted = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    interrupted.into(),
+                    self.intrinsics.i1_ty.const_int(0, false).into(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "not_interrupted_continue_block");
+        let interrupted_block = self
+            .context
+            .append_basic_block(self.function, "interrupted_trap_block");
+        self.builder
+            .build_conditional_branch(interrupted, interrupted_block, continue_block);
+
+        self.builder.position_at_end(interrupted_block);
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_interrupted.into()],
+            "throw",
+        );
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(continue_block);
+    }
+
+    fn check_epoch_deadline(&mut self) {
+        let (epoch_ptr, deadline_ptr) = match self.ctx.epoch(self.intrinsics) {
+            Some(pointers) => pointers,
+            None => return,
+        };
+        let epoch = self.builder.build_load(epoch_ptr, "epoch");
+        // The epoch is incremented from other threads, so it must be loaded
+        // again every time.
+        epoch
+            .as_instruction_value()
+            .unwrap()
+            .set_volatile(true)
+            .unwrap();
+        let deadline = self.builder.build_load(deadline_ptr, "epoch_deadline");
+        let reached = self.builder.build_int_compare(
+            IntPredicate::UGE,
+            epoch.into_int_value(),
+            deadline.into_int_value(),
+            "",
+        );
+        let reached = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    reached.into(),
+                    self.intrinsics.i1_ty.const_int(0, false).into(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "epoch_deadline_continue_block");
+        let reached_block = self
+            .context
+            .append_basic_block(self.function, "epoch_deadline_reached_block");
+        self.builder
+            .build_conditional_branch(reached, reached_block, continue_block);
+
+        // Let the store decide whether to trap or to extend the deadline.
+        self.builder.position_at_end(reached_block);
+        self.builder.build_call(
+            self.intrinsics.epoch_deadline_reached,
+            &[self.ctx.basic().into()],
+            "",
+        );
+        self.builder.build_unconditional_branch(continue_block);
+
+        self.builder.position_at_end(continue_block);
+    }
+
     fn finalize(&mut self, wasm_fn_type: &FunctionType) -> Result<(), CompileError> {
         let func_type = self.function.get_type();
 
@@ -1385,7 +1497,12 @@ pub struct LLVMFunctionCodeGenerator<'ctx, 'a> {
 }
 
 impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
-    fn translate_operator(&mut self, op: Operator, _source_loc: u32) -> Result<(), CompileError> {
+    fn translate_operator(
+        &mut self,
+        op: Operator,
+        trap_code: Option<TrapCode>,
+        _source_loc: u32,
+    ) -> Result<(), CompileError> {
         // TODO: remove this vmctx by moving everything into CtxType. Values
         // computed off vmctx usually benefit from caching.
         let vmctx = &self.ctx.basic().into_pointer_value();
@@ -1522,6 +1639,8 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
+                self.trap_if_interrupted();
+                self.check_epoch_deadline();
                 self.state.push_loop(loop_body, loop_next, loop_phis, phis);
             }
             Operator::Br { relative_depth } => {
@@ -1874,11 +1993,16 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
-                self.builder.build_call(
-                    self.intrinsics.throw_trap,
-                    &[self.intrinsics.trap_unreachable.into()],
-                    "throw",
-                );
+                let trap_code = match trap_code {
+                    Some(trap_code) => self
+                        .intrinsics
+                        .i32_ty
+                        .const_int(trap_code as _, false)
+                        .as_basic_value_enum(),
+                    None => self.intrinsics.trap_unreachable,
+                };
+                self.builder
+                    .build_call(self.intrinsics.throw_trap, &[trap_code.into()], "throw");
                 self.builder.build_unreachable();
 
                 self.state.reachable = false;
@@ -217,6 +217,7 @@ pub struct Intrinsics<'ctx> {
     pub trap_bad_conversion_to_integer: BasicValueEnum<'ctx>,
     pub trap_unaligned_atomic: BasicValueEnum<'ctx>,
     pub trap_table_access_oob: BasicValueEnum<'ctx>,
+    pub trap_interrupted: BasicValueEnum<'ctx>,
 
     pub experimental_stackmap: FunctionValue<'ctx>,
 
@@ -234,6 +235,7 @@ pub struct Intrinsics<'ctx> {
     pub imported_table_grow: FunctionValue<'ctx>,
     pub memory_init: FunctionValue<'ctx>,
     pub data_drop: FunctionValue<'ctx>,
+    pub epoch_deadline_reached: FunctionValue<'ctx>,
     pub func_ref: FunctionValue<'ctx>,
     pub elem_drop: FunctionValue<'ctx>,
     pub memory_copy: FunctionValue<'ctx>,
@@ -792,6 +794,9 @@ impl<'ctx> Intrinsics<'ctx> {
             trap_table_access_oob: i32_ty
                 .const_int(TrapCode::TableAccessOutOfBounds as _, false)
                 .as_basic_value_enum(),
+            trap_interrupted: i32_ty
+                .const_int(TrapCode::Interrupted as _, false)
+                .as_basic_value_enum(),
 
             experimental_stackmap: module.add_function(
                 "llvm.experimental.stackmap",
@@ -1004,6 +1009,11 @@ impl<'ctx> Intrinsics<'ctx> {
                 void_ty.fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false),
                 None,
             ),
+            epoch_deadline_reached: module.add_function(
+                "wasmer_vm_epoch_deadline_reached",
+                void_ty.fn_type(&[ctx_ptr_ty_basic_md], false),
+                None,
+            ),
             func_ref: module.add_function(
                 "wasmer_vm_func_ref",
                 funcref_ty.fn_type(&[ctx_ptr_ty_basic_md, i32_ty_basic_md], false),
@@ -1252,6 +1262,8 @@ pub struct CtxType<'ctx, 'a> {
     cached_functions: HashMap<FunctionIndex, FunctionCache<'ctx>>,
     cached_memory_grow: HashMap<MemoryIndex, PointerValue<'ctx>>,
     cached_memory_size: HashMap<MemoryIndex, PointerValue<'ctx>>,
+    cached_interrupt_flag: Option<PointerValue<'ctx>>,
+    cached_epoch: Option<(PointerValue<'ctx>, PointerValue<'ctx>)>,
 
     offsets: VMOffsets,
 }
@@ -1277,6 +1289,8 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             cached_functions: HashMap::new(),
             cached_memory_grow: HashMap::new(),
             cached_memory_size: HashMap::new(),
+            cached_interrupt_flag: None,
+            cached_epoch: None,
 
             // TODO: pointer width
             offsets: VMOffsets::new(8, wasm_module),
@@ -1733,6 +1747,64 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
         })
     }
 
+    /// Returns a pointer to the `i32` interrupt flag of the store, if the
+    /// module checks it.
+    pub fn interrupt_flag(&mut self, intrinsics: &Intrinsics<'ctx>) -> Option<PointerValue<'ctx>> {
+        let offset = self.offsets.vmctx_interrupt_flag_pointer()?;
+        let (cached_interrupt_flag, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_interrupt_flag,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        Some(*cached_interrupt_flag.get_or_insert_with(|| {
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let flag_ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let flag_ptr_ptr = cache_builder
+                .build_bitcast(
+                    flag_ptr_ptr,
+                    intrinsics.i32_ptr_ty.ptr_type(AddressSpace::default()),
+                    "",
+                )
+                .into_pointer_value();
+            cache_builder
+                .build_load(flag_ptr_ptr, "interrupt_flag_ptr")
+                .into_pointer_value()
+        }))
+    }
+
+    /// Returns pointers to the `i64` epoch of the engine and to the `i64`
+    /// epoch deadline of the store, if the module checks it.
+    pub fn epoch(
+        &mut self,
+        intrinsics: &Intrinsics<'ctx>,
+    ) -> Option<(PointerValue<'ctx>, PointerValue<'ctx>)> {
+        let epoch_offset = self.offsets.vmctx_epoch_pointer()?;
+        let deadline_offset = self.offsets.vmctx_epoch_deadline_pointer()?;
+        let (cached_epoch, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_epoch,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        Some(*cached_epoch.get_or_insert_with(|| {
+            let load_ptr = |offset: u32, name| {
+                let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+                let ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+                let ptr_ptr = cache_builder
+                    .build_bitcast(
+                        ptr_ptr,
+                        intrinsics.i64_ptr_ty.ptr_type(AddressSpace::default()),
+                        "",
+                    )
+                    .into_pointer_value();
+                cache_builder.build_load(ptr_ptr, name).into_pointer_value()
+            };
+            (
+                load_ptr(epoch_offset, "epoch_ptr"),
+                load_ptr(deadline_offset, "epoch_deadline_ptr"),
+            )
+        }))
+    }
+
     pub fn memory_grow(
         &mut self,
         memory_index: MemoryIndex,
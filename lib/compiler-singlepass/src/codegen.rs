@@ -95,6 +95,7 @@ struct SpecialLabelSet {
     indirect_call_null: Label,
     bad_signature: Label,
     unaligned_atomic: Label,
+    interrupted: Label,
 }
 
 /// Metadata about a floating-point value.
@@ -1055,6 +1056,85 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         id
     }
 
+    /// Traps if the interrupt flag of the store is set, when the module
+    /// checks it.
+    fn emit_interrupt_check(&mut self) -> Result<(), CompileError> {
+        let offset = match self.vmoffsets.vmctx_interrupt_flag_pointer() {
+            Some(offset) => offset,
+            None => return Ok(()),
+        };
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(self.machine.get_vmctx_reg(), offset as i32),
+            Location::GPR(tmp),
+        )?;
+        self.machine
+            .move_location(Size::S32, Location::Memory(tmp, 0), Location::GPR(tmp))?;
+        self.machine
+            .emit_relaxed_cmp(Size::S32, Location::Imm32(0), Location::GPR(tmp))?;
+        self.machine
+            .jmp_on_different(self.special_labels.interrupted)?;
+        self.machine.release_gpr(tmp);
+        Ok(())
+    }
+
+    /// Lets the store decide whether to trap or to extend its epoch
+    /// deadline once it is reached, when the module checks it.
+    fn emit_epoch_check(&mut self) -> Result<(), CompileError> {
+        let (epoch_offset, deadline_offset, reached_offset) = match (
+            self.vmoffsets.vmctx_epoch_pointer(),
+            self.vmoffsets.vmctx_epoch_deadline_pointer(),
+            self.vmoffsets.vmctx_epoch_deadline_reached(),
+        ) {
+            (Some(epoch), Some(deadline), Some(reached)) => (epoch, deadline, reached),
+            _ => return Ok(()),
+        };
+        let epoch = self.machine.acquire_temp_gpr().unwrap();
+        let deadline = self.machine.acquire_temp_gpr().unwrap();
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(self.machine.get_vmctx_reg(), epoch_offset as i32),
+            Location::GPR(epoch),
+        )?;
+        self.machine
+            .move_location(Size::S64, Location::Memory(epoch, 0), Location::GPR(epoch))?;
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(self.machine.get_vmctx_reg(), deadline_offset as i32),
+            Location::GPR(deadline),
+        )?;
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(deadline, 0),
+            Location::GPR(deadline),
+        )?;
+        let not_reached = self.machine.get_label();
+        self.machine
+            .emit_relaxed_cmp(Size::S64, Location::GPR(epoch), Location::GPR(deadline))?;
+        self.machine.release_gpr(deadline);
+        self.machine.release_gpr(epoch);
+        self.machine.jmp_on_above(not_reached)?;
+
+        // Let the store decide whether to trap or to extend the deadline.
+        self.machine.move_location(
+            Size::S64,
+            Location::Memory(self.machine.get_vmctx_reg(), reached_offset as i32),
+            Location::GPR(self.machine.get_grp_for_call()),
+        )?;
+        self.emit_call_native(
+            |this| {
+                this.machine
+                    .emit_call_register(this.machine.get_grp_for_call())
+            },
+            // [vmctx]
+            iter::empty(),
+            iter::empty(),
+        )?;
+        self.machine.emit_label(not_reached)?;
+        Ok(())
+    }
+
     fn emit_head(&mut self) -> Result<(), CompileError> {
         self.machine.emit_function_prolog()?;
 
@@ -1093,7 +1173,8 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             state_diff_id,
         });
 
-        // TODO: Full preemption by explicit signal checking
+        self.emit_interrupt_check()?;
+        self.emit_epoch_check()?;
 
         // We insert set StackOverflow as the default trap that can happen
         // anywhere in the function prologue.
@@ -1139,6 +1220,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             indirect_call_null: machine.get_label(),
             bad_signature: machine.get_label(),
             unaligned_atomic: machine.get_label(),
+            interrupted: machine.get_label(),
         };
 
         let fsm = FunctionStateMap::new(
@@ -1181,7 +1263,13 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         !self.control_stack.is_empty()
     }
 
-    pub fn feed_operator(&mut self, op: Operator) -> Result<(), CompileError> {
+    /// Translates an operator. `trap_code` is the trap code raised by an
+    /// `unreachable` operator, when it is not `TrapCode::UnreachableCodeReached`.
+    pub fn feed_operator(
+        &mut self,
+        op: Operator,
+        trap_code: Option<TrapCode>,
+    ) -> Result<(), CompileError> {
         assert!(self.fp_stack.len() <= self.value_stack.len());
 
         self.state.wasm_inst_offset = self.state.wasm_inst_offset.wrapping_add(1);
@@ -3111,7 +3199,8 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 });
                 self.machine.emit_label(label)?;
 
-                // TODO: Re-enable interrupt signal check without branching
+                self.emit_interrupt_check()?;
+                self.emit_epoch_check()?;
             }
             Operator::Nop => {}
             Operator::MemorySize { mem, mem_byte: _ } => {
@@ -3966,7 +4055,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::Unreachable => {
                 self.mark_trappable();
                 self.machine
-                    .emit_illegal_op(TrapCode::UnreachableCodeReached)?;
+                    .emit_illegal_op(trap_code.unwrap_or(TrapCode::UnreachableCodeReached))?;
                 self.unreachable_depth = 1;
             }
             Operator::Return => {
@@ -6641,6 +6730,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             .emit_label(self.special_labels.unaligned_atomic)?;
         self.machine.emit_illegal_op(TrapCode::UnalignedAtomic)?;
 
+        if self.vmoffsets.vmctx_interrupt_flag_pointer().is_some() {
+            self.machine.emit_label(self.special_labels.interrupted)?;
+            self.machine.emit_illegal_op(TrapCode::Interrupted)?;
+        }
+
         // Notify the assembler backend to generate necessary code at end of function.
         self.machine.finalize_function()?;
 
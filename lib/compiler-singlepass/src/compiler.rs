@@ -142,7 +142,7 @@ impl Compiler for SinglepassCompiler {
                 let middleware_chain = self
                     .config
                     .middlewares
-                    .generate_function_middleware_chain(i);
+                    .generate_function_middleware_chain_for_module(module, i);
                 let mut reader =
                     MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
                 reader.set_middleware_chain(middleware_chain);
@@ -174,7 +174,7 @@ impl Compiler for SinglepassCompiler {
                         while generator.has_control_frames() {
                             generator.set_srcloc(reader.original_position() as u32);
                             let op = reader.read_operator()?;
-                            generator.feed_operator(op)?;
+                            generator.feed_operator(op, reader.trap_code())?;
                         }
 
                         generator.finalize(input)
@@ -195,7 +195,7 @@ impl Compiler for SinglepassCompiler {
                         while generator.has_control_frames() {
                             generator.set_srcloc(reader.original_position() as u32);
                             let op = reader.read_operator()?;
-                            generator.feed_operator(op)?;
+                            generator.feed_operator(op, reader.trap_code())?;
                         }
 
                         generator.finalize(input)
@@ -2624,8 +2624,9 @@ impl Machine for MachineX86_64 {
         .insert(offset, trap);
         self.assembler.emit_ud2();
         self.mark_instruction_address_end(offset);*/
-        let v = trap as u8;
         // payload needs to be between 0-15
+        const _: () = assert!(TrapCode::CallDepthExceeded as u32 <= 15);
+        let v = trap as u8;
         // this will emit an 40 0F B9 Cx opcode, with x the payload
         let offset = self.assembler.get_offset().0;
         self.assembler.emit_ud1_payload(v)?;
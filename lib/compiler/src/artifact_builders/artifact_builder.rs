@@ -6,25 +6,41 @@ use super::trampoline::{libcall_trampoline_len, make_libcall_trampolines};
 use crate::ArtifactCreate;
 use crate::EngineInner;
 use crate::Features;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    engine::{link_module_position_independent, CodeLayout},
+    FunctionExtent,
+};
 use crate::{ModuleEnvironment, ModuleMiddlewareChain};
 use enumset::EnumSet;
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
 use std::sync::Arc;
-use wasmer_types::entity::PrimaryMap;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
 #[cfg(feature = "compiler")]
 use wasmer_types::CompileModuleInfo;
+use wasmer_types::{
+    CodeImage, CompiledFunctionFrameInfo, FunctionBody, SerializableCompilation, SerializableModule,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use wasmer_types::{CodeRange, CustomSectionProtection};
 use wasmer_types::{
     CompileError, CpuFeature, CustomSection, Dwarf, FunctionIndex, LocalFunctionIndex, MemoryIndex,
     MemoryStyle, ModuleInfo, OwnedDataInitializer, Relocation, SectionIndex, SignatureIndex,
     TableIndex, TableStyle, Target,
 };
-use wasmer_types::{
-    CompiledFunctionFrameInfo, FunctionBody, SerializableCompilation, SerializableModule,
-};
 use wasmer_types::{MetadataHeader, SerializeError};
+#[cfg(not(target_arch = "wasm32"))]
+use wasmer_vm::{FunctionBodyPtr, SectionBodyPtr, VMFunctionBody};
 
 /// A compiled wasm module, ready to be instantiated.
 pub struct ArtifactBuild {
     serializable: SerializableModule,
+    /// The code image of an artifact whose code was mapped from a file.
+    /// The function bodies and custom sections of such an artifact are
+    /// empty: their bytes are in the image.
+    #[cfg(not(target_arch = "wasm32"))]
+    mapped_image: Option<Mmap>,
 }
 
 impl ArtifactBuild {
@@ -55,7 +71,12 @@ impl ArtifactBuild {
         // We try to apply the middleware first
         let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
+        middlewares
+            .check_module_info(&module)
+            .map_err(|error| CompileError::Wasm(error.into()))?;
         middlewares.apply_on_module_info(&mut module);
+        module.interrupt_checks = inner_engine.interrupt_checks();
+        module.epoch_interruption = inner_engine.epoch_interruption();
 
         let compile_info = CompileModuleInfo {
             module: Arc::new(module),
@@ -114,14 +135,16 @@ impl ArtifactBuild {
             debug: compilation.debug,
             libcall_trampolines,
             libcall_trampoline_len,
+            code_image: CodeImage::default(),
         };
         let serializable = SerializableModule {
             compilation: serializable_compilation,
             compile_info,
             data_initializers,
             cpu_features: cpu_features.as_u64(),
+            middleware_fingerprint: middlewares.fingerprint(),
         };
-        Ok(Self { serializable })
+        Ok(Self::from_serializable(serializable))
     }
 
     /// Compile a data buffer into a `ArtifactBuild`, which may then be instantiated.
@@ -141,7 +164,117 @@ impl ArtifactBuild {
 
     /// Create a new ArtifactBuild from a SerializableModule
     pub fn from_serializable(serializable: SerializableModule) -> Self {
-        Self { serializable }
+        Self {
+            serializable,
+            #[cfg(not(target_arch = "wasm32"))]
+            mapped_image: None,
+        }
+    }
+
+    /// Create a new ArtifactBuild from a SerializableModule without code,
+    /// whose code image is mapped in `image`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_mapped_image(serializable: SerializableModule, image: Mmap) -> Self {
+        Self {
+            serializable,
+            mapped_image: Some(image),
+        }
+    }
+
+    /// Get the layout of the code image
+    pub fn get_code_image_ref(&self) -> &CodeImage {
+        &self.serializable.compilation.code_image
+    }
+
+    /// Get the length of a custom section
+    pub fn get_custom_section_len(&self, index: SectionIndex) -> usize {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.mapped_image.is_some() {
+            return self.serializable.compilation.code_image.custom_sections[index.index()].len
+                as usize;
+        }
+        self.serializable.compilation.custom_sections[index]
+            .bytes
+            .len()
+    }
+
+    /// Lays out the functions and custom sections as they are in code
+    /// memory, and applies the relocations which don't depend on the
+    /// address they are loaded at, see [`CodeImage`].
+    #[cfg(not(target_arch = "wasm32"))]
+    fn code_image(&self) -> (CodeImage, Vec<u8>) {
+        let compilation = &self.serializable.compilation;
+        let functions = compilation
+            .function_bodies
+            .values()
+            .chain(compilation.function_call_trampolines.values())
+            .chain(compilation.dynamic_function_trampolines.values())
+            .collect::<Vec<_>>();
+        let (executable_sections, data_sections): (Vec<_>, _) = compilation
+            .custom_sections
+            .values()
+            .partition(|section| section.protection == CustomSectionProtection::ReadExecute);
+        let page_size = region::page::size();
+        let layout = CodeLayout::new(&functions, &executable_sections, &data_sections, page_size);
+        let mut image = vec![0; layout.len];
+        layout.copy(&mut image, &functions, &executable_sections, &data_sections);
+
+        let function_ranges = layout
+            .functions
+            .iter()
+            .zip(&functions)
+            .map(|(&offset, function)| CodeRange {
+                offset: offset as u64,
+                len: function.body.len() as u64,
+            })
+            .collect::<Vec<_>>();
+        let mut executable_offsets = layout.executable_sections.iter();
+        let mut data_offsets = layout.data_sections.iter();
+        let section_ranges = compilation
+            .custom_sections
+            .values()
+            .map(|section| CodeRange {
+                offset: *if section.protection == CustomSectionProtection::ReadExecute {
+                    executable_offsets.next()
+                } else {
+                    data_offsets.next()
+                }
+                .unwrap() as u64,
+                len: section.bytes.len() as u64,
+            })
+            .collect::<Vec<_>>();
+
+        let base = image.as_mut_ptr();
+        let allocated_functions = function_ranges[..compilation.function_bodies.len()]
+            .iter()
+            .map(|range| FunctionExtent {
+                ptr: FunctionBodyPtr(
+                    unsafe { base.add(range.offset as usize) } as *const VMFunctionBody
+                ),
+                length: range.len as usize,
+            })
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        let allocated_sections = section_ranges
+            .iter()
+            .map(|range| SectionBodyPtr(unsafe { base.add(range.offset as usize) }))
+            .collect::<PrimaryMap<SectionIndex, _>>();
+        link_module_position_independent(
+            &allocated_functions,
+            &compilation.function_relocations,
+            &allocated_sections,
+            &compilation.custom_section_relocations,
+            compilation.libcall_trampolines,
+            compilation.libcall_trampoline_len as usize,
+        );
+
+        let code_image = CodeImage {
+            page_size: page_size as u64,
+            len: layout.len as u64,
+            executable_len: layout.executable_len as u64,
+            functions: function_ranges,
+            custom_sections: section_ranges,
+        };
+        (code_image, image)
     }
 
     /// Get Functions Bodies ref
@@ -219,6 +352,10 @@ impl ArtifactCreate for ArtifactBuild {
         EnumSet::from_u64(self.serializable.cpu_features)
     }
 
+    fn middleware_fingerprint(&self) -> Option<[u8; 32]> {
+        self.serializable.middleware_fingerprint
+    }
+
     fn data_initializers(&self) -> &[OwnedDataInitializer] {
         &self.serializable.data_initializers
     }
@@ -231,6 +368,32 @@ impl ArtifactCreate for ArtifactBuild {
         &self.serializable.compile_info.table_styles
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        let built_image;
+        let (code_image, image) = match &self.mapped_image {
+            Some(image) => (self.get_code_image_ref().clone(), &image[..]),
+            None => {
+                built_image = self.code_image();
+                (built_image.0, &built_image.1[..])
+            }
+        };
+        let serialized_data = self
+            .serializable
+            .serialize_without_code(code_image.clone())?;
+        assert!(std::mem::align_of::<SerializableModule>() <= MetadataHeader::ALIGN);
+
+        let metadata_end = Self::MAGIC_HEADER.len() + MetadataHeader::LEN + serialized_data.len();
+        let mut metadata_binary = Vec::with_capacity(code_image.offset(metadata_end) + image.len());
+        metadata_binary.extend(Self::MAGIC_HEADER);
+        metadata_binary.extend(MetadataHeader::new(serialized_data.len()).into_bytes());
+        metadata_binary.extend(serialized_data);
+        metadata_binary.resize(code_image.offset(metadata_end), 0);
+        metadata_binary.extend(image);
+        Ok(metadata_binary)
+    }
+
+    #[cfg(target_arch = "wasm32")]
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
         let serialized_data = self.serializable.serialize()?;
         assert!(std::mem::align_of::<SerializableModule>() <= MetadataHeader::ALIGN);
@@ -8,14 +8,17 @@ use crate::Features;
 use crate::ModuleEnvironment;
 use crate::{
     register_frame_info, resolve_imports, FunctionExtent, GlobalFrameInfoRegistration,
-    InstantiationError, RuntimeError, Tunables,
+    InstantiationError, LinkError, RuntimeError, Tunables,
 };
 #[cfg(feature = "static-artifact-create")]
 use crate::{Compiler, FunctionBodyData, ModuleTranslationState};
 use crate::{Engine, EngineInner};
 use enumset::EnumSet;
+use memmap2::{Mmap, MmapOptions};
+use std::fs::File;
 #[cfg(any(feature = "static-artifact-create", feature = "static-artifact-load"))]
 use std::mem;
+use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -31,11 +34,14 @@ use wasmer_types::MetadataHeader;
 use wasmer_types::SerializableCompilation;
 use wasmer_types::{
     CompileError, CpuFeature, DataInitializer, DeserializeError, FunctionIndex, LocalFunctionIndex,
-    MemoryIndex, ModuleInfo, OwnedDataInitializer, SignatureIndex, TableIndex, Target,
+    MemoryIndex, ModuleInfo, OwnedDataInitializer, Pages, SectionIndex, SignatureIndex, TableIndex,
+    Target,
 };
 use wasmer_types::{SerializableModule, SerializeError};
-use wasmer_vm::{FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex, VMTrampoline};
-use wasmer_vm::{InstanceAllocator, StoreObjects, TrapHandlerFn, VMExtern, VMInstance};
+use wasmer_vm::{
+    FunctionBodyPtr, MemoryStyle, SectionBodyPtr, TableStyle, VMSharedSignatureIndex, VMTrampoline,
+};
+use wasmer_vm::{Imports, StoreObjects, Trap, TrapHandlerFn, VMExtern, VMInstance};
 
 pub struct AllocatedArtifact {
     finished_functions: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
@@ -164,19 +170,126 @@ impl Artifact {
             ));
         }
 
+        let serialized = bytes;
         let bytes = Self::get_byte_slice(bytes, ArtifactBuild::MAGIC_HEADER.len(), bytes.len())?;
 
         let metadata_len = MetadataHeader::parse(bytes)?;
         let metadata_slice = Self::get_byte_slice(bytes, MetadataHeader::LEN, bytes.len())?;
         let metadata_slice = Self::get_byte_slice(metadata_slice, 0, metadata_len)?;
 
-        let serializable = SerializableModule::deserialize(metadata_slice)?;
+        let mut serializable = SerializableModule::deserialize(metadata_slice)?;
+        engine.check_middleware_fingerprint(serializable.middleware_fingerprint())?;
+        engine.check_interruption(&serializable.compile_info.module)?;
+        let image = Self::code_image_slice(serialized, &serializable, metadata_len)?;
+        serializable.compilation.load_code(image)?;
         let artifact = ArtifactBuild::from_serializable(serializable);
         let mut inner_engine = engine.inner_mut();
         Self::from_parts(&mut inner_engine, artifact, engine.target())
             .map_err(DeserializeError::Compiler)
     }
 
+    /// Deserialize a `ArtifactBuild` from a file, mapping its code into
+    /// memory instead of copying it.
+    ///
+    /// The metadata of the artifact is validated in place before being
+    /// deserialized, and its code image, which holds the function bodies
+    /// and custom sections laid out as they are in code memory, is mapped
+    /// executable directly from the file. The pages of the image are
+    /// shared with the page cache, and only the ones which hold
+    /// relocations depending on the address the code is loaded at are
+    /// copied.
+    ///
+    /// If the code can't be mapped, e.g. because the artifact was
+    /// serialized on a host with a different page size, it is copied like
+    /// in [`Artifact::deserialize`].
+    ///
+    /// # Safety
+    /// The code of the artifact isn't validated, so the file must hold an
+    /// artifact serialized by Wasmer. The file must not be modified or
+    /// truncated while the engine is alive, since its code keeps being
+    /// read from the file.
+    pub unsafe fn deserialize_from_file_mapped(
+        engine: &Engine,
+        path: &Path,
+    ) -> Result<Self, DeserializeError> {
+        let file = File::open(path)?;
+        let serialized = Mmap::map(&file)?;
+        if !ArtifactBuild::is_deserializable(&serialized) {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes are not wasmer-universal".to_string(),
+            ));
+        }
+        let bytes = Self::get_byte_slice(
+            &serialized,
+            ArtifactBuild::MAGIC_HEADER.len(),
+            serialized.len(),
+        )?;
+
+        let metadata_len = MetadataHeader::parse(bytes)?;
+        let metadata_slice = Self::get_byte_slice(bytes, MetadataHeader::LEN, bytes.len())?;
+        let metadata_slice = Self::get_byte_slice(metadata_slice, 0, metadata_len)?;
+
+        let archived = SerializableModule::archive_from_slice_checked(metadata_slice)?;
+        let mut serializable = SerializableModule::deserialize_from_archive(archived)?;
+        engine.check_middleware_fingerprint(serializable.middleware_fingerprint())?;
+        engine.check_interruption(&serializable.compile_info.module)?;
+        let image = Self::code_image_slice(&serialized, &serializable, metadata_len)?;
+        let code_image = &serializable.compilation.code_image;
+        if !engine.target().is_native()
+            || !cfg!(unix)
+            || code_image.page_size as usize != region::page::size()
+        {
+            // The code can't be mapped on this host.
+            serializable.compilation.load_code(image)?;
+            let artifact = ArtifactBuild::from_serializable(serializable);
+            let mut inner_engine = engine.inner_mut();
+            return Self::from_parts(&mut inner_engine, artifact, engine.target())
+                .map_err(DeserializeError::Compiler);
+        }
+
+        let offset = code_image.offset(Self::metadata_end(metadata_len)) as u64;
+        let image = MmapOptions::new()
+            .offset(offset)
+            .len(image.len())
+            .map(&file)?;
+        let artifact = ArtifactBuild::from_mapped_image(serializable, image);
+        let mut inner_engine = engine.inner_mut();
+        let allocated = inner_engine.map(
+            &file,
+            offset,
+            artifact.get_code_image_ref(),
+            artifact.get_function_bodies_ref(),
+            artifact.get_function_call_trampolines_ref(),
+            artifact.get_dynamic_function_trampolines_ref(),
+            artifact.get_custom_sections_ref(),
+        )?;
+        Self::link_and_publish(&mut inner_engine, artifact, allocated)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    /// Returns the offset of the end of the metadata of a serialized
+    /// artifact.
+    fn metadata_end(metadata_len: usize) -> usize {
+        ArtifactBuild::MAGIC_HEADER.len() + MetadataHeader::LEN + metadata_len
+    }
+
+    /// Returns the code image of the serialized artifact `serialized`.
+    fn code_image_slice<'a>(
+        serialized: &'a [u8],
+        serializable: &SerializableModule,
+        metadata_len: usize,
+    ) -> Result<&'a [u8], DeserializeError> {
+        let code_image = &serializable.compilation.code_image;
+        let start = code_image.offset(Self::metadata_end(metadata_len));
+        let end = start.saturating_add(code_image.len as usize);
+        serialized
+            .get(start..end)
+            .ok_or(DeserializeError::InvalidByteLength {
+                expected: end,
+                got: serialized.len(),
+            })
+    }
+
     /// Construct a `ArtifactBuild` from component parts.
     pub fn from_parts(
         engine_inner: &mut EngineInner,
@@ -190,20 +303,35 @@ impl Artifact {
                 allocated: None,
             });
         }
-        let module_info = artifact.module_info();
-        let (
-            finished_functions,
-            finished_function_call_trampolines,
-            finished_dynamic_function_trampolines,
-            custom_sections,
-        ) = engine_inner.allocate(
-            module_info,
+        let allocated = engine_inner.allocate(
+            artifact.module_info(),
             artifact.get_function_bodies_ref(),
             artifact.get_function_call_trampolines_ref(),
             artifact.get_dynamic_function_trampolines_ref(),
             artifact.get_custom_sections_ref(),
         )?;
+        Self::link_and_publish(engine_inner, artifact, allocated)
+    }
 
+    /// Links the functions and custom sections of `artifact`, once they
+    /// are allocated in code memory, and makes them executable.
+    #[allow(clippy::type_complexity)]
+    fn link_and_publish(
+        engine_inner: &mut EngineInner,
+        artifact: ArtifactBuild,
+        (
+            finished_functions,
+            finished_function_call_trampolines,
+            finished_dynamic_function_trampolines,
+            custom_sections,
+        ): (
+            PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+            PrimaryMap<SignatureIndex, VMTrampoline>,
+            PrimaryMap<FunctionIndex, FunctionBodyPtr>,
+            PrimaryMap<SectionIndex, SectionBodyPtr>,
+        ),
+    ) -> Result<Self, CompileError> {
+        let module_info = artifact.module_info();
         link_module(
             module_info,
             &finished_functions,
@@ -226,9 +354,7 @@ impl Artifact {
 
         let eh_frame = match artifact.get_debug_ref() {
             Some(debug) => {
-                let eh_frame_section_size = artifact.get_custom_sections_ref()[debug.eh_frame]
-                    .bytes
-                    .len();
+                let eh_frame_section_size = artifact.get_custom_section_len(debug.eh_frame);
                 let eh_frame_section_pointer = custom_sections[debug.eh_frame];
                 Some(unsafe {
                     std::slice::from_raw_parts(*eh_frame_section_pointer, eh_frame_section_size)
@@ -315,6 +441,10 @@ impl ArtifactCreate for Artifact {
         self.artifact.cpu_features()
     }
 
+    fn middleware_fingerprint(&self) -> Option<[u8; 32]> {
+        self.artifact.middleware_fingerprint()
+    }
+
     fn data_initializers(&self) -> &[OwnedDataInitializer] {
         self.artifact.data_initializers()
     }
@@ -435,6 +565,21 @@ impl Artifact {
         imports: &[VMExtern],
         context: &mut StoreObjects,
     ) -> Result<VMInstance, InstantiationError> {
+        let imports = self.resolve_imports(imports, context)?;
+        self.instantiate_resolved(tunables, imports, context)
+    }
+
+    /// Checks that this `Artifact` can run on the host, and resolves the
+    /// given imports against the imports of its module, checking their
+    /// types.
+    ///
+    /// The resolved imports can be used for any number of instances
+    /// created with [`Artifact::instantiate_resolved`] in the same store.
+    pub fn resolve_imports(
+        &self,
+        imports: &[VMExtern],
+        context: &StoreObjects,
+    ) -> Result<Imports, InstantiationError> {
         // Validate the CPU features this module was compiled with against the
         // host CPU features.
         let host_cpu_features = CpuFeature::for_host();
@@ -447,22 +592,39 @@ impl Artifact {
 
         self.preinstantiate()?;
 
-        let module = self.create_module_info();
-        let imports = resolve_imports(
-            &module,
+        resolve_imports(
+            self.module_info(),
             imports,
             context,
             self.finished_dynamic_function_trampolines(),
             self.memory_styles(),
             self.table_styles(),
         )
-        .map_err(InstantiationError::Link)?;
+        .map_err(InstantiationError::Link)
+    }
+
+    /// Create an `Instance` from this `Artifact`, with imports resolved by
+    /// [`Artifact::resolve_imports`].
+    ///
+    /// # Safety
+    ///
+    /// See [`VMInstance::new`]. The imports must have been resolved for
+    /// this `Artifact` in the store given as `context`.
+    pub unsafe fn instantiate_resolved(
+        &self,
+        tunables: &dyn Tunables,
+        imports: Imports,
+        context: &mut StoreObjects,
+    ) -> Result<VMInstance, InstantiationError> {
+        let module = self.create_module_info();
+        check_resource_limits(&module, context).map_err(InstantiationError::Link)?;
 
         // Get pointers to where metadata about local memories should live in VM memory.
         // Get pointers to where metadata about local tables should live in VM memory.
 
-        let (allocator, memory_definition_locations, table_definition_locations) =
-            InstanceAllocator::new(&module);
+        let (allocator, memory_definition_locations, table_definition_locations) = tunables
+            .allocate_instance(&module)
+            .map_err(InstantiationError::Link)?;
         let finished_memories = tunables
             .create_memories(
                 context,
@@ -527,6 +689,31 @@ impl Artifact {
             .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))
     }
 
+    /// Brings a `VMInstance` of this artifact back to its state right after
+    /// instantiation.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must have been instantiated from this artifact.
+    pub unsafe fn reset_instance(
+        &self,
+        trap_handler: Option<*const TrapHandlerFn<'static>>,
+        handle: &mut VMInstance,
+        run_start: bool,
+    ) -> Result<(), RuntimeError> {
+        let data_initializers = self
+            .data_initializers()
+            .iter()
+            .map(|init| DataInitializer {
+                location: init.location.clone(),
+                data: &init.data,
+            })
+            .collect::<Vec<_>>();
+        handle
+            .reset(trap_handler, &data_initializers, run_start)
+            .map_err(RuntimeError::from_trap)
+    }
+
     #[allow(clippy::type_complexity)]
     #[cfg(feature = "static-artifact-create")]
     /// Generate a compilation
@@ -551,6 +738,9 @@ impl Artifact {
         use crate::translator::ModuleMiddlewareChain;
         let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
+        middlewares
+            .check_module_info(&module)
+            .map_err(|error| CompileError::Wasm(error.into()))?;
         middlewares.apply_on_module_info(&mut module);
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
@@ -614,12 +804,14 @@ impl Artifact {
             .map(|_function_body| 0u64)
             .collect::<PrimaryMap<LocalFunctionIndex, u64>>();
 
+        use crate::translator::ModuleMiddlewareChain;
         let metadata = ModuleMetadata {
             compile_info,
             prefix: metadata_prefix.map(|s| s.to_string()).unwrap_or_default(),
             data_initializers,
             function_body_lengths,
             cpu_features: target.cpu_features().as_u64(),
+            middleware_fingerprint: compiler.get_middlewares().fingerprint(),
         };
 
         Ok((metadata, module_translation, function_body_inputs))
@@ -751,6 +943,8 @@ impl Artifact {
         let metadata_slice = Self::get_byte_slice(bytes, MetadataHeader::LEN, bytes.len())?;
         let metadata_slice = Self::get_byte_slice(metadata_slice, 0, metadata_len)?;
         let metadata: ModuleMetadata = ModuleMetadata::deserialize(metadata_slice)?;
+        engine.check_middleware_fingerprint(metadata.middleware_fingerprint)?;
+        engine.check_interruption(&metadata.compile_info.module)?;
 
         const WORD_SIZE: usize = mem::size_of::<usize>();
         let mut byte_buffer = [0u8; WORD_SIZE];
@@ -832,6 +1026,7 @@ impl Artifact {
             compile_info: metadata.compile_info,
             data_initializers: metadata.data_initializers,
             cpu_features: metadata.cpu_features,
+            middleware_fingerprint: metadata.middleware_fingerprint,
         });
 
         let finished_function_lengths = finished_functions
@@ -856,3 +1051,31 @@ impl Artifact {
         })
     }
 }
+
+/// Asks the resource limiter of the store whether an instance of `module`
+/// may be created in it, with its memories and tables growing from nothing
+/// to their minimum size.
+fn check_resource_limits(module: &ModuleInfo, context: &mut StoreObjects) -> Result<(), LinkError> {
+    let check = |allowed: Result<bool, Trap>, resource: &str| match allowed {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(LinkError::Resource(format!(
+            "the resource limiter denied the creation of the {}",
+            resource
+        ))),
+        Err(trap) => Err(LinkError::Trap(RuntimeError::from_trap(trap))),
+    };
+    check(context.instance_creating(), "instance")?;
+    for memory in module.memories.values().skip(module.num_imported_memories) {
+        check(
+            context.memory_growing(Pages(0), memory.minimum, memory.maximum),
+            "memory",
+        )?;
+    }
+    for table in module.tables.values().skip(module.num_imported_tables) {
+        check(
+            context.table_growing(0, table.minimum, table.maximum),
+            "table",
+        )?;
+    }
+    Ok(())
+}
@@ -1,6 +1,10 @@
 use super::Engine;
 use crate::CompilerConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{BaseTunables, PoolingTunables};
 use wasmer_types::{Features, Target};
+#[cfg(not(target_arch = "wasm32"))]
+use wasmer_vm::PoolingAllocator;
 
 /// The Builder contents of `Engine`
 pub struct EngineBuilder {
@@ -10,6 +14,13 @@ pub struct EngineBuilder {
     target: Option<Target>,
     /// The features to compile the Wasm module with
     features: Option<Features>,
+    /// Whether the compiled code checks the interrupt flag of its store
+    interrupt_checks: bool,
+    /// Whether the compiled code checks the epoch deadline of its store
+    epoch_interruption: bool,
+    /// The pooling allocator of the instances
+    #[cfg(not(target_arch = "wasm32"))]
+    pooling_allocator: Option<PoolingAllocator>,
 }
 
 impl EngineBuilder {
@@ -22,6 +33,10 @@ impl EngineBuilder {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            interrupt_checks: false,
+            epoch_interruption: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            pooling_allocator: None,
         }
     }
 
@@ -31,6 +46,10 @@ impl EngineBuilder {
             compiler_config: None,
             target: None,
             features: None,
+            interrupt_checks: false,
+            epoch_interruption: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            pooling_allocator: None,
         }
     }
 
@@ -46,23 +65,61 @@ impl EngineBuilder {
         self
     }
 
+    /// Set whether the compiled code checks the interrupt flag of its
+    /// store, see [`Engine::set_interrupt_checks`]. It doesn't by default.
+    pub fn set_interrupt_checks(mut self, enable: bool) -> Self {
+        self.interrupt_checks = enable;
+        self
+    }
+
+    /// Set whether the compiled code checks the epoch deadline of its
+    /// store, see [`Engine::set_epoch_interruption`]. It doesn't by default.
+    pub fn set_epoch_interruption(mut self, enable: bool) -> Self {
+        self.epoch_interruption = enable;
+        self
+    }
+
+    /// Set the pooling allocator the instances are allocated from, instead
+    /// of allocating each of them, and the memories and tables they define,
+    /// on demand.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_pooling_allocator(mut self, allocator: Option<PoolingAllocator>) -> Self {
+        self.pooling_allocator = allocator;
+        self
+    }
+
     /// Build the `Engine` for this configuration
-    #[cfg(feature = "compiler")]
     pub fn engine(self) -> Engine {
+        #[cfg(not(target_arch = "wasm32"))]
+        let pooling_allocator = self.pooling_allocator.clone();
+        #[allow(unused_mut)]
+        let mut engine = self.build();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(allocator) = pooling_allocator {
+            let base = BaseTunables::for_target(engine.target());
+            engine.set_tunables(PoolingTunables::new(base, allocator));
+        }
+        engine
+    }
+
+    #[cfg(feature = "compiler")]
+    fn build(self) -> Engine {
         let target = self.target.unwrap_or_default();
         if let Some(compiler_config) = self.compiler_config {
             let features = self
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
-            Engine::new(compiler_config, target, features)
+            let mut engine = Engine::new(compiler_config, target, features);
+            engine.set_interrupt_checks(self.interrupt_checks);
+            engine.set_epoch_interruption(self.epoch_interruption);
+            engine
         } else {
             Engine::headless()
         }
     }
 
-    /// Build the `Engine` for this configuration
     #[cfg(not(feature = "compiler"))]
-    pub fn engine(self) -> Engine {
+    fn build(self) -> Engine {
         Engine::headless()
     }
 
@@ -75,4 +132,20 @@ impl EngineBuilder {
     pub fn target(&self) -> Option<&Target> {
         self.target.as_ref()
     }
+
+    /// Whether the compiled code checks the interrupt flag of its store
+    pub fn interrupt_checks(&self) -> bool {
+        self.interrupt_checks
+    }
+
+    /// Whether the compiled code checks the epoch deadline of its store
+    pub fn epoch_interruption(&self) -> bool {
+        self.epoch_interruption
+    }
+
+    /// The pooling allocator
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pooling_allocator(&self) -> Option<&PoolingAllocator> {
+        self.pooling_allocator.as_ref()
+    }
 }
@@ -0,0 +1,327 @@
+//! Checked artifacts: serialized artifacts wrapped in an envelope which
+//! authenticates them, and records the engine and target they were
+//! compiled for.
+//!
+//! Deserializing an artifact reads it directly as machine code and
+//! metadata, so a corrupted or tampered artifact leads to undefined
+//! behavior. A checked artifact is only deserialized once its checksum, or
+//! its signature, matches its content, and once the engine, the target and
+//! the CPU features it records match the engine loading it.
+//!
+//! The envelope has the following format, with integers in little endian:
+//!
+//! ```text
+//! magic (16 bytes) + version (u32) + header length (u32) + kind (u8)
+//! + padding (7 bytes) + digest (32 bytes) + header + padding + artifact
+//! ```
+//!
+//! The header holds the deterministic id of the engine and the target
+//! triple, both as a length (u32) followed by the UTF-8 bytes, then the CPU
+//! features (u64) and the length of the artifact (u64). The artifact starts
+//! at an offset aligned to [`MetadataHeader::ALIGN`].
+//!
+//! The digest is either the SHA-256 checksum, or the HMAC-SHA256 signature
+//! with a user-supplied key, of all the bytes of the envelope but the
+//! digest itself. A checksum only detects corruption: a signature is
+//! needed to detect tampering, so only signed artifacts are deserialized
+//! safely.
+
+use crate::{Artifact, ArtifactCreate, Engine};
+use enumset::EnumSet;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::path::Path;
+use std::sync::Arc;
+use wasmer_types::{CpuFeature, DeserializeError, IntegrityError, MetadataHeader, SerializeError};
+
+/// Magic number to identify checked artifacts.
+const MAGIC: &[u8; 16] = b"wasmer-checked\0\0";
+
+/// Current version of the envelope. Increment this any time breaking
+/// changes are made to its format.
+const CURRENT_VERSION: u32 = 1;
+
+/// Offset of the digest in the envelope.
+const DIGEST_OFFSET: usize = 32;
+
+/// Offset of the header in the envelope.
+const HEADER_OFFSET: usize = DIGEST_OFFSET + DIGEST_LEN;
+
+/// Length of a SHA-256 checksum or HMAC-SHA256 signature.
+const DIGEST_LEN: usize = 32;
+
+/// The kinds of digests of an envelope.
+const KIND_CHECKSUM: u8 = 0;
+const KIND_SIGNATURE: u8 = 1;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Computes the digest of `envelope`, skipping the digest itself.
+fn digest(envelope: &[u8], key: Option<&[u8]>) -> [u8; DIGEST_LEN] {
+    let (before, after) = (&envelope[..DIGEST_OFFSET], &envelope[HEADER_OFFSET..]);
+    match key {
+        Some(key) => {
+            let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+            mac.update(before);
+            mac.update(after);
+            mac.finalize().into_bytes().into()
+        }
+        None => {
+            let mut hasher = Sha256::new();
+            hasher.update(before);
+            hasher.update(after);
+            hasher.finalize().into()
+        }
+    }
+}
+
+/// Reads the fields of the header of an envelope.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DeserializeError> {
+        if self.bytes.len() < len {
+            return Err(DeserializeError::CorruptedBinary(
+                "truncated checked artifact".to_string(),
+            ));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, DeserializeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, DeserializeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, DeserializeError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| {
+            DeserializeError::CorruptedBinary("invalid string in checked artifact".to_string())
+        })
+    }
+}
+
+fn push_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend((string.len() as u32).to_le_bytes());
+    bytes.extend(string.as_bytes());
+}
+
+/// Rounds `offset` up to the alignment of the artifact.
+fn align(offset: usize) -> usize {
+    (offset + MetadataHeader::ALIGN - 1) & !(MetadataHeader::ALIGN - 1)
+}
+
+impl Engine {
+    /// Serializes `artifact` as a checked artifact, signed with `key` if
+    /// one is given, or with a checksum otherwise.
+    ///
+    /// The artifact must have been compiled by this engine.
+    pub fn serialize_checked(
+        &self,
+        artifact: &Artifact,
+        key: Option<&[u8]>,
+    ) -> Result<Vec<u8>, SerializeError> {
+        let serialized = artifact.serialize()?;
+
+        let mut header = Vec::new();
+        push_string(&mut header, self.deterministic_id());
+        push_string(&mut header, &self.target().triple().to_string());
+        header.extend(artifact.cpu_features().as_u64().to_le_bytes());
+        header.extend((serialized.len() as u64).to_le_bytes());
+
+        let artifact_offset = align(HEADER_OFFSET + header.len());
+        let mut envelope = Vec::with_capacity(artifact_offset + serialized.len());
+        envelope.extend(MAGIC);
+        envelope.extend(CURRENT_VERSION.to_le_bytes());
+        envelope.extend((header.len() as u32).to_le_bytes());
+        envelope.push(if key.is_some() {
+            KIND_SIGNATURE
+        } else {
+            KIND_CHECKSUM
+        });
+        envelope.resize(HEADER_OFFSET, 0);
+        envelope.extend(header);
+        envelope.resize(artifact_offset, 0);
+        envelope.extend(serialized);
+
+        let digest = digest(&envelope, key);
+        envelope[DIGEST_OFFSET..HEADER_OFFSET].copy_from_slice(&digest);
+        Ok(envelope)
+    }
+
+    /// Checks that `bytes` are a checked artifact with a valid signature
+    /// for `key`, produced for this engine and its target, and deserializes
+    /// it.
+    ///
+    /// Unlike [`Engine::deserialize`], this function is safe: bytes which
+    /// fail the checks are never deserialized, and a typed error is
+    /// returned instead.
+    pub fn deserialize_checked(
+        &self,
+        bytes: &[u8],
+        key: &[u8],
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        // The signature can't be forged without the key.
+        unsafe { self.deserialize_envelope(bytes, Some(key)) }
+    }
+
+    /// Checks that `bytes` are a checked artifact with a valid checksum,
+    /// produced for this engine and its target, and deserializes it.
+    ///
+    /// # Safety
+    ///
+    /// A checksum only detects corruption: anyone able to alter `bytes` can
+    /// compute the checksum of the altered bytes. As with
+    /// [`Engine::deserialize`], the bytes must come from a trusted source.
+    pub unsafe fn deserialize_checksummed(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        self.deserialize_envelope(bytes, None)
+    }
+
+    /// Deserializes the checked artifact in the file at `path`, see
+    /// [`Engine::deserialize_checked`].
+    ///
+    /// The file is read in memory before it is checked, so that it can't
+    /// change between the checks and the deserialization.
+    pub fn deserialize_checked_from_file(
+        &self,
+        path: &Path,
+        key: &[u8],
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        self.deserialize_checked(&std::fs::read(path)?, key)
+    }
+
+    /// Deserializes the checked artifact in the file at `path`, see
+    /// [`Engine::deserialize_checksummed`].
+    ///
+    /// # Safety
+    ///
+    /// See [`Engine::deserialize_checksummed`].
+    pub unsafe fn deserialize_checksummed_from_file(
+        &self,
+        path: &Path,
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        self.deserialize_checksummed(&std::fs::read(path)?)
+    }
+
+    /// Runs the checks of [`Engine::deserialize_checked`], with a checksum
+    /// instead of a signature if no `key` is given, and deserializes the
+    /// artifact.
+    ///
+    /// # Safety
+    ///
+    /// See [`Engine::deserialize_checksummed`] when no `key` is given.
+    unsafe fn deserialize_envelope(
+        &self,
+        bytes: &[u8],
+        key: Option<&[u8]>,
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        let artifact = self.check(bytes, key)?;
+        if artifact.as_ptr().align_offset(MetadataHeader::ALIGN) == 0 {
+            return self.deserialize(artifact);
+        }
+        // The artifact is read in place, so it must be aligned.
+        let mut aligned = vec![0u128; artifact.len() / 16 + 1];
+        let aligned =
+            std::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, artifact.len());
+        aligned.copy_from_slice(artifact);
+        self.deserialize(aligned)
+    }
+
+    /// Runs the checks of [`Engine::deserialize_checked`], and returns the
+    /// serialized artifact in `bytes`.
+    fn check<'a>(&self, bytes: &'a [u8], key: Option<&[u8]>) -> Result<&'a [u8], DeserializeError> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes are not a checked artifact".to_string(),
+            ));
+        }
+        if reader.u32()? != CURRENT_VERSION {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes were checked by an incompatible version of Wasmer".to_string(),
+            ));
+        }
+        let header_len = reader.u32()? as usize;
+        let kind = reader.take(1)?[0];
+        reader.take(HEADER_OFFSET - MAGIC.len() - 9)?;
+        let header = reader.take(header_len)?;
+
+        let expected = match (kind, key) {
+            (KIND_CHECKSUM, None) | (KIND_SIGNATURE, Some(_)) => digest(bytes, key),
+            (KIND_CHECKSUM, Some(_)) => return Err(IntegrityError::Unsigned.into()),
+            (KIND_SIGNATURE, None) => return Err(IntegrityError::KeyRequired.into()),
+            _ => {
+                return Err(DeserializeError::CorruptedBinary(
+                    "unknown kind of checked artifact".to_string(),
+                ))
+            }
+        };
+        let found = &bytes[DIGEST_OFFSET..HEADER_OFFSET];
+        if !constant_time_eq(&expected, found) {
+            return Err(match key {
+                Some(_) => IntegrityError::InvalidSignature,
+                None => IntegrityError::ChecksumMismatch,
+            }
+            .into());
+        }
+
+        // The content is authentic from now on.
+        let mut header = Reader { bytes: header };
+        let engine_id = header.string()?;
+        if engine_id != self.deterministic_id() {
+            return Err(IntegrityError::EngineMismatch {
+                expected: self.deterministic_id().to_string(),
+                found: engine_id,
+            }
+            .into());
+        }
+        let triple = header.string()?;
+        let target = self.target();
+        if triple != target.triple().to_string() {
+            return Err(IntegrityError::TargetMismatch {
+                expected: target.triple().to_string(),
+                found: triple,
+            }
+            .into());
+        }
+        let cpu_features = EnumSet::<CpuFeature>::try_from_u64(header.u64()?)
+            .ok_or_else(|| DeserializeError::CorruptedBinary("unknown CPU features".to_string()))?;
+        let missing = cpu_features - *target.cpu_features();
+        if !missing.is_empty() {
+            return Err(IntegrityError::MissingCpuFeatures(
+                missing
+                    .iter()
+                    .map(|feature| feature.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .into());
+        }
+        let artifact_len = header.u64()? as usize;
+
+        let artifact_offset = align(HEADER_OFFSET + header_len);
+        bytes
+            .get(artifact_offset..)
+            .filter(|artifact| artifact.len() == artifact_len)
+            .ok_or(DeserializeError::InvalidByteLength {
+                expected: artifact_offset + artifact_len,
+                got: bytes.len(),
+            })
+    }
+}
+
+/// Compares two digests in a time which doesn't depend on their content.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -3,7 +3,9 @@
 
 //! Memory management for executable code.
 use super::unwind::UnwindRegistry;
-use wasmer_types::{CompiledFunctionUnwindInfo, CustomSection, FunctionBody};
+use memmap2::{MmapMut, MmapOptions};
+use std::fs::File;
+use wasmer_types::{CodeImage, CodeRange, CompiledFunctionUnwindInfo, CustomSection, FunctionBody};
 use wasmer_vm::{Mmap, VMFunctionBody};
 
 /// The optimal alignment for functions.
@@ -17,10 +19,141 @@ const ARCH_FUNCTION_ALIGNMENT: usize = 16;
 ///
 const DATA_SECTION_ALIGNMENT: usize = 64;
 
+/// The memory holding the code.
+enum Memory {
+    /// Anonymous memory the code is copied to.
+    Anonymous(Mmap),
+    /// A private mapping of a code image in a file.
+    File(MmapMut),
+}
+
+impl Memory {
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        match self {
+            Self::Anonymous(mmap) => mmap.as_mut_ptr(),
+            Self::File(mmap) => mmap.as_mut_ptr(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Anonymous(mmap) => mmap.len(),
+            Self::File(mmap) => mmap.len(),
+        }
+    }
+}
+
+/// The placement of the functions and custom sections in code memory,
+/// as offsets from its start.
+pub struct CodeLayout {
+    /// The offsets of the functions.
+    pub functions: Vec<usize>,
+    /// The offsets of the executable sections.
+    pub executable_sections: Vec<usize>,
+    /// The offsets of the data sections.
+    pub data_sections: Vec<usize>,
+    /// The length of the executable code, at the start of the memory.
+    pub executable_len: usize,
+    /// The length of the memory.
+    pub len: usize,
+}
+
+impl CodeLayout {
+    /// Lays out the functions, followed by the executable sections, and
+    /// the data sections on separate pages of `page_size` bytes.
+    pub fn new(
+        functions: &[&FunctionBody],
+        executable_sections: &[&CustomSection],
+        data_sections: &[&CustomSection],
+        page_size: usize,
+    ) -> Self {
+        // The memory holds:
+        // - function body size, including all trampolines
+        // -- windows unwind info
+        // -- padding between functions
+        // - executable section body
+        // -- padding between executable sections
+        // - padding until a new page to change page permissions
+        // - data section body size
+        // -- padding between data sections
+        let mut len = 0;
+        let functions = functions
+            .iter()
+            .map(|func| {
+                let offset = len;
+                len += round_up(
+                    CodeMemory::function_allocation_size(func),
+                    ARCH_FUNCTION_ALIGNMENT,
+                );
+                offset
+            })
+            .collect();
+        let executable_sections = executable_sections
+            .iter()
+            .map(|section| {
+                let offset = len;
+                len += round_up(section.bytes.len(), ARCH_FUNCTION_ALIGNMENT);
+                offset
+            })
+            .collect();
+        let executable_len = len;
+
+        // Data sections have different page permissions from the executable
+        // code that came before it, so they need to be on different pages.
+        len = round_up(len, page_size);
+        let data_sections = data_sections
+            .iter()
+            .map(|section| {
+                let offset = len;
+                len += round_up(section.bytes.len(), DATA_SECTION_ALIGNMENT);
+                offset
+            })
+            .collect();
+
+        Self {
+            functions,
+            executable_sections,
+            data_sections,
+            executable_len,
+            len: round_up(len, page_size),
+        }
+    }
+
+    /// Copies the functions and custom sections to `buf`, at their
+    /// offsets.
+    pub fn copy(
+        &self,
+        buf: &mut [u8],
+        functions: &[&FunctionBody],
+        executable_sections: &[&CustomSection],
+        data_sections: &[&CustomSection],
+    ) {
+        for (func, &offset) in functions.iter().zip(&self.functions) {
+            let func_len = func.body.len();
+            buf[offset..offset + func_len].copy_from_slice(&func.body);
+
+            if let Some(CompiledFunctionUnwindInfo::WindowsX64(info)) = &func.unwind_info {
+                // Windows unwind information is written following the function body
+                // Keep unwind information 32-bit aligned (round up to the nearest 4 byte boundary)
+                let unwind_start = offset + ((func_len + 3) & !3);
+                buf[unwind_start..unwind_start + info.len()].copy_from_slice(info);
+            }
+        }
+        let sections = executable_sections
+            .iter()
+            .zip(&self.executable_sections)
+            .chain(data_sections.iter().zip(&self.data_sections));
+        for (section, &offset) in sections {
+            let section = section.bytes.as_slice();
+            buf[offset..offset + section.len()].copy_from_slice(section);
+        }
+    }
+}
+
 /// Memory manager for executable code.
 pub struct CodeMemory {
     unwind_registry: UnwindRegistry,
-    mmap: Mmap,
+    mmap: Memory,
     start_of_nonexecutable_pages: usize,
 }
 
@@ -29,7 +162,7 @@ impl CodeMemory {
     pub fn new() -> Self {
         Self {
             unwind_registry: UnwindRegistry::new(),
-            mmap: Mmap::new(),
+            mmap: Memory::Anonymous(Mmap::new()),
             start_of_nonexecutable_pages: 0,
         }
     }
@@ -47,98 +180,173 @@ impl CodeMemory {
         executable_sections: &[&CustomSection],
         data_sections: &[&CustomSection],
     ) -> Result<(Vec<&mut [VMFunctionBody]>, Vec<&mut [u8]>, Vec<&mut [u8]>), String> {
-        let mut function_result = vec![];
-        let mut data_section_result = vec![];
-        let mut executable_section_result = vec![];
+        let layout = CodeLayout::new(
+            functions,
+            executable_sections,
+            data_sections,
+            region::page::size(),
+        );
 
-        let page_size = region::page::size();
+        // Allocate the pages, all read-write, and copy the functions and
+        // sections in place.
+        let mut mmap = Mmap::with_at_least(layout.len)?;
+        layout.copy(
+            &mut mmap.as_mut_slice()[..layout.len],
+            functions,
+            executable_sections,
+            data_sections,
+        );
+        self.mmap = Memory::Anonymous(mmap);
+        self.start_of_nonexecutable_pages = layout.executable_len;
 
-        // 1. Calculate the total size, that is:
-        // - function body size, including all trampolines
-        // -- windows unwind info
-        // -- padding between functions
-        // - executable section body
-        // -- padding between executable sections
-        // - padding until a new page to change page permissions
-        // - data section body size
-        // -- padding between data sections
+        let sizes = functions.iter().map(|func| func.body.len());
+        let executable_ranges = Self::ranges(&layout.executable_sections, executable_sections);
+        let data_ranges = Self::ranges(&layout.data_sections, data_sections);
+        Ok(unsafe {
+            self.slices(
+                layout.functions.iter().copied().zip(sizes),
+                functions,
+                &executable_ranges,
+                &data_ranges,
+            )
+        })
+    }
 
-        let total_len = round_up(
-            functions.iter().fold(0, |acc, func| {
-                round_up(
-                    acc + Self::function_allocation_size(func),
-                    ARCH_FUNCTION_ALIGNMENT,
-                )
-            }) + executable_sections.iter().fold(0, |acc, exec| {
-                round_up(acc + exec.bytes.len(), ARCH_FUNCTION_ALIGNMENT)
-            }),
-            page_size,
-        ) + data_sections.iter().fold(0, |acc, data| {
-            round_up(acc + data.bytes.len(), DATA_SECTION_ALIGNMENT)
-        });
-
-        // 2. Allocate the pages. Mark them all read-write.
-
-        self.mmap = Mmap::with_at_least(total_len)?;
-
-        // 3. Determine where the pointers to each function, executable section
-        // or data section are. Copy the functions. Collect the addresses of each and return them.
-
-        let mut bytes = 0;
-        let mut buf = self.mmap.as_mut_slice();
-        for func in functions {
-            let len = round_up(
-                Self::function_allocation_size(func),
-                ARCH_FUNCTION_ALIGNMENT,
-            );
-            let (func_buf, next_buf) = buf.split_at_mut(len);
-            buf = next_buf;
-            bytes += len;
-
-            let vmfunc = Self::copy_function(&mut self.unwind_registry, func, func_buf);
-            assert_eq!(vmfunc.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
-            function_result.push(vmfunc);
+    /// Maps the code image at `offset` in `file`, described by `image`,
+    /// instead of allocating memory and copying the code in place.
+    ///
+    /// The mapping is private: the pages of the file are shared with the
+    /// page cache until they are written to, e.g. to apply relocations.
+    ///
+    /// `functions` are used for their unwind information only, and the
+    /// custom sections are given by their ranges in the image.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while the code memory is alive.
+    #[allow(clippy::type_complexity)]
+    pub unsafe fn map(
+        &mut self,
+        file: &File,
+        offset: u64,
+        image: &CodeImage,
+        functions: &[&FunctionBody],
+        executable_sections: &[CodeRange],
+        data_sections: &[CodeRange],
+    ) -> Result<(Vec<&mut [VMFunctionBody]>, Vec<&mut [u8]>, Vec<&mut [u8]>), String> {
+        let page_size = region::page::size();
+        let len = image.len as usize;
+        let executable_len = image.executable_len as usize;
+        if image.page_size as usize != page_size
+            || offset as usize & (page_size - 1) != 0
+            || len & (page_size - 1) != 0
+            || executable_len > len
+        {
+            return Err("the code image can't be mapped on this host".to_string());
         }
-        for section in executable_sections {
-            let section = &section.bytes;
-            assert_eq!(buf.as_mut_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
-            let len = round_up(section.len(), ARCH_FUNCTION_ALIGNMENT);
-            let (s, next_buf) = buf.split_at_mut(len);
-            buf = next_buf;
-            bytes += len;
-            s[..section.len()].copy_from_slice(section.as_slice());
-            executable_section_result.push(s);
+        let in_bounds = |range: &CodeRange, start: usize, end: usize, align: usize| {
+            let offset = range.offset as usize;
+            offset >= start
+                && offset & (align - 1) == 0
+                && matches!(offset.checked_add(range.len as usize), Some(range_end) if range_end <= end)
+        };
+        let data_start = round_up(executable_len, page_size);
+        if functions.len() != image.functions.len()
+            || !image
+                .functions
+                .iter()
+                .all(|range| in_bounds(range, 0, executable_len, ARCH_FUNCTION_ALIGNMENT))
+            || !executable_sections
+                .iter()
+                .all(|range| in_bounds(range, 0, executable_len, ARCH_FUNCTION_ALIGNMENT))
+            || !data_sections
+                .iter()
+                .all(|range| in_bounds(range, data_start, len, DATA_SECTION_ALIGNMENT))
+        {
+            return Err("code out of the bounds of the image".to_string());
         }
 
-        self.start_of_nonexecutable_pages = bytes;
-
-        if !data_sections.is_empty() {
-            // Data sections have different page permissions from the executable
-            // code that came before it, so they need to be on different pages.
-            let padding = round_up(bytes, page_size) - bytes;
-            buf = buf.split_at_mut(padding).1;
-
-            for section in data_sections {
-                let section = &section.bytes;
-                assert_eq!(buf.as_mut_ptr() as usize % DATA_SECTION_ALIGNMENT, 0);
-                let len = round_up(section.len(), DATA_SECTION_ALIGNMENT);
-                let (s, next_buf) = buf.split_at_mut(len);
-                buf = next_buf;
-                s[..section.len()].copy_from_slice(section.as_slice());
-                data_section_result.push(s);
-            }
-        }
+        let mmap = MmapOptions::new()
+            .offset(offset)
+            .len(len)
+            .map_copy(file)
+            .map_err(|error| format!("failed to map the code image: {}", error))?;
+        self.mmap = Memory::File(mmap);
+        self.start_of_nonexecutable_pages = executable_len;
+
+        let ranges = image
+            .functions
+            .iter()
+            .map(|range| (range.offset as usize, range.len as usize));
+        Ok(self.slices(ranges, functions, executable_sections, data_sections))
+    }
+
+    /// Returns the ranges of `sections` at `offsets`.
+    fn ranges(offsets: &[usize], sections: &[&CustomSection]) -> Vec<CodeRange> {
+        offsets
+            .iter()
+            .zip(sections)
+            .map(|(&offset, section)| CodeRange {
+                offset: offset as u64,
+                len: section.bytes.len() as u64,
+            })
+            .collect()
+    }
 
-        Ok((
+    /// Registers the unwind information of the functions at the given
+    /// offsets and lengths, and returns the slices of the memory holding
+    /// the functions and custom sections.
+    ///
+    /// # Safety
+    ///
+    /// The ranges must be in the bounds of the memory and must not overlap.
+    #[allow(clippy::type_complexity)]
+    unsafe fn slices<'a>(
+        &'a mut self,
+        function_ranges: impl Iterator<Item = (usize, usize)>,
+        functions: &[&FunctionBody],
+        executable_sections: &[CodeRange],
+        data_sections: &[CodeRange],
+    ) -> (
+        Vec<&'a mut [VMFunctionBody]>,
+        Vec<&'a mut [u8]>,
+        Vec<&'a mut [u8]>,
+    ) {
+        let base = self.mmap.as_mut_ptr();
+        let function_result = function_ranges
+            .zip(functions)
+            .map(|((offset, len), func)| {
+                let ptr = base.add(offset);
+                assert_eq!(ptr as usize % ARCH_FUNCTION_ALIGNMENT, 0);
+                if let Some(info) = &func.unwind_info {
+                    self.unwind_registry
+                        .register(ptr as usize, 0, len as u32, info)
+                        .expect("failed to register unwind information");
+                }
+                std::slice::from_raw_parts_mut(ptr as *mut VMFunctionBody, len)
+            })
+            .collect();
+        let section_slices = |ranges: &[CodeRange]| {
+            ranges
+                .iter()
+                .map(|range| {
+                    std::slice::from_raw_parts_mut(
+                        base.add(range.offset as usize),
+                        range.len as usize,
+                    )
+                })
+                .collect()
+        };
+        (
             function_result,
-            executable_section_result,
-            data_section_result,
-        ))
+            section_slices(executable_sections),
+            section_slices(data_sections),
+        )
     }
 
     /// Apply the page permissions.
     pub fn publish(&mut self) {
-        if self.mmap.is_empty() || self.start_of_nonexecutable_pages == 0 {
+        if self.mmap.len() == 0 || self.start_of_nonexecutable_pages == 0 {
             return;
         }
         assert!(self.mmap.len() >= self.start_of_nonexecutable_pages);
@@ -164,49 +372,6 @@ impl CodeMemory {
             _ => func.body.len(),
         }
     }
-
-    /// Copies the data of the compiled function to the given buffer.
-    ///
-    /// This will also add the function to the current function table.
-    fn copy_function<'a>(
-        registry: &mut UnwindRegistry,
-        func: &FunctionBody,
-        buf: &'a mut [u8],
-    ) -> &'a mut [VMFunctionBody] {
-        assert_eq!(buf.as_ptr() as usize % ARCH_FUNCTION_ALIGNMENT, 0);
-
-        let func_len = func.body.len();
-
-        let (body, remainder) = buf.split_at_mut(func_len);
-        body.copy_from_slice(&func.body);
-        let vmfunc = Self::view_as_mut_vmfunc_slice(body);
-
-        if let Some(CompiledFunctionUnwindInfo::WindowsX64(info)) = &func.unwind_info {
-            // Windows unwind information is written following the function body
-            // Keep unwind information 32-bit aligned (round up to the nearest 4 byte boundary)
-            let unwind_start = (func_len + 3) & !3;
-            let unwind_size = info.len();
-            let padding = unwind_start - func_len;
-            assert_eq!((func_len + padding) % 4, 0);
-            let slice = remainder.split_at_mut(padding + unwind_size).0;
-            slice[padding..].copy_from_slice(info);
-        }
-
-        if let Some(info) = &func.unwind_info {
-            registry
-                .register(vmfunc.as_ptr() as usize, 0, func_len as u32, info)
-                .expect("failed to register unwind information");
-        }
-
-        vmfunc
-    }
-
-    /// Convert mut a slice from u8 to VMFunctionBody.
-    fn view_as_mut_vmfunc_slice(slice: &mut [u8]) -> &mut [VMFunctionBody] {
-        let byte_ptr: *mut [u8] = slice;
-        let body_ptr = byte_ptr as *mut [VMFunctionBody];
-        unsafe { &mut *body_ptr }
-    }
 }
 
 fn round_up(size: usize, multiple: usize) -> usize {
@@ -2,6 +2,7 @@
 #[cfg(not(target_arch = "wasm32"))]
 use crate::engine::trap::RuntimeError;
 use thiserror::Error;
+use wasmer_types::ExternType;
 pub use wasmer_types::{DeserializeError, ImportError, SerializeError};
 
 /// The WebAssembly.LinkError object indicates an error during
@@ -25,6 +26,22 @@ pub enum LinkError {
     /// Insufficient resources available for linking.
     #[error("Insufficient resources: {0}")]
     Resource(String),
+
+    /// An import was defined twice while shadowing is not allowed.
+    #[error("Import {0:?}.{1:?} is already defined")]
+    Shadowed(String, String),
+
+    /// Imports of the module, with their expected type, were not found.
+    #[error("Unknown imports: {}", import_names(.0))]
+    UnknownImports(Vec<(String, String, ExternType)>),
+}
+
+fn import_names(imports: &[(String, String, ExternType)]) -> String {
+    imports
+        .iter()
+        .map(|(module, name, _)| format!("{:?}.{:?}", module, name))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 /// An error while instantiating a module.
@@ -14,6 +14,8 @@ use crate::{FunctionExtent, Tunables};
 #[cfg(not(target_arch = "wasm32"))]
 use memmap2::Mmap;
 #[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::{Arc, Mutex};
@@ -22,13 +24,13 @@ use wasmer_types::{
     entity::PrimaryMap, DeserializeError, FunctionBody, FunctionIndex, FunctionType,
     LocalFunctionIndex, SignatureIndex,
 };
-use wasmer_types::{CompileError, Features, ModuleInfo, Target};
 #[cfg(not(target_arch = "wasm32"))]
-use wasmer_types::{CustomSection, CustomSectionProtection, SectionIndex};
+use wasmer_types::{CodeImage, CustomSection, CustomSectionProtection, SectionIndex};
+use wasmer_types::{CompileError, Features, ModuleInfo, Target};
 #[cfg(not(target_arch = "wasm32"))]
 use wasmer_vm::{
-    FunctionBodyPtr, SectionBodyPtr, SignatureRegistry, VMFunctionBody, VMSharedSignatureIndex,
-    VMTrampoline,
+    EpochCounter, FunctionBodyPtr, SectionBodyPtr, SignatureRegistry, VMFunctionBody,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A WebAssembly `Universal` Engine.
@@ -40,6 +42,8 @@ pub struct Engine {
     engine_id: EngineId,
     #[cfg(not(target_arch = "wasm32"))]
     tunables: Arc<dyn Tunables + Send + Sync>,
+    #[cfg(not(target_arch = "wasm32"))]
+    epoch: EpochCounter,
     name: String,
 }
 
@@ -59,6 +63,8 @@ impl Engine {
             inner: Arc::new(Mutex::new(EngineInner {
                 compiler: Some(compiler),
                 features,
+                interrupt_checks: false,
+                epoch_interruption: false,
                 #[cfg(not(target_arch = "wasm32"))]
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
@@ -68,6 +74,8 @@ impl Engine {
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
+            #[cfg(not(target_arch = "wasm32"))]
+            epoch: EpochCounter::default(),
             name,
         }
     }
@@ -118,6 +126,8 @@ impl Engine {
                 compiler: None,
                 #[cfg(feature = "compiler")]
                 features: Features::default(),
+                interrupt_checks: false,
+                epoch_interruption: false,
                 #[cfg(not(target_arch = "wasm32"))]
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
@@ -127,6 +137,8 @@ impl Engine {
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
+            #[cfg(not(target_arch = "wasm32"))]
+            epoch: EpochCounter::default(),
             name: "engine-headless".to_string(),
         }
     }
@@ -215,6 +227,132 @@ impl Engine {
         self.deserialize(&mmap)
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Deserializes a WebAssembly module from a path, mapping its code into
+    /// memory instead of copying it, see
+    /// [`Artifact::deserialize_from_file_mapped`].
+    ///
+    /// # Safety
+    ///
+    /// The file's content must represent a serialized WebAssembly module,
+    /// and the file must not be modified while the engine is alive.
+    pub unsafe fn deserialize_from_file_mapped(
+        &self,
+        file_ref: &Path,
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        Ok(Arc::new(Artifact::deserialize_from_file_mapped(
+            self, file_ref,
+        )?))
+    }
+
+    /// Returns the fingerprint of the middleware chain of the compiler of
+    /// this engine, or `None` for a headless engine or if one of the
+    /// middlewares has no fingerprint.
+    ///
+    /// Artifacts record the fingerprint of the chain they were compiled
+    /// with, and an engine with a compiler only deserializes the artifacts
+    /// compiled with a chain of the same fingerprint.
+    pub fn middleware_fingerprint(&self) -> Option<[u8; 32]> {
+        #[cfg(feature = "compiler")]
+        {
+            use crate::ModuleMiddlewareChain;
+            let inner = self.inner();
+            inner
+                .compiler
+                .as_ref()
+                .and_then(|compiler| compiler.get_middlewares().fingerprint())
+        }
+        #[cfg(not(feature = "compiler"))]
+        None
+    }
+
+    /// Returns whether this engine has no compiler.
+    pub fn is_headless(&self) -> bool {
+        #[cfg(feature = "compiler")]
+        {
+            self.inner().compiler.is_none()
+        }
+        #[cfg(not(feature = "compiler"))]
+        true
+    }
+
+    /// Checks that an artifact compiled with the middleware chain of the
+    /// given fingerprint can be deserialized by this engine.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn check_middleware_fingerprint(
+        &self,
+        fingerprint: Option<[u8; 32]>,
+    ) -> Result<(), DeserializeError> {
+        // A headless engine can't tell which middlewares it expects.
+        if self.is_headless() {
+            return Ok(());
+        }
+        match (self.middleware_fingerprint(), fingerprint) {
+            (Some(expected), Some(fingerprint)) if expected == fingerprint => Ok(()),
+            (Some(_), Some(_)) => Err(DeserializeError::Incompatible(
+                "The artifact was compiled with different middlewares than the engine".to_string(),
+            )),
+            _ => Err(DeserializeError::Incompatible(
+                "The artifact or the engine has middlewares without a fingerprint".to_string(),
+            )),
+        }
+    }
+
+    /// Makes the modules compiled by this engine from now on check the
+    /// interrupt flag of their store, on entry to every function and at the
+    /// start of every loop iteration. Interrupt checks are off by default.
+    ///
+    /// An engine with a compiler only deserializes the artifacts compiled
+    /// with the same setting.
+    pub fn set_interrupt_checks(&mut self, enable: bool) {
+        self.inner_mut().interrupt_checks = enable;
+    }
+
+    /// Returns whether the modules compiled by this engine check the
+    /// interrupt flag of their store.
+    pub fn interrupt_checks(&self) -> bool {
+        self.inner().interrupt_checks
+    }
+
+    /// Makes the modules compiled by this engine from now on check the
+    /// epoch deadline of their store, on entry to every function and at
+    /// the start of every loop iteration. Epoch interruption is off by
+    /// default.
+    ///
+    /// An engine with a compiler only deserializes the artifacts compiled
+    /// with the same setting.
+    pub fn set_epoch_interruption(&mut self, enable: bool) {
+        self.inner_mut().epoch_interruption = enable;
+    }
+
+    /// Returns whether the modules compiled by this engine check the epoch
+    /// deadline of their store.
+    pub fn epoch_interruption(&self) -> bool {
+        self.inner().epoch_interruption
+    }
+
+    /// Checks that an artifact compiled for the given module checks the
+    /// interrupt flag and the epoch deadline of its store like this engine
+    /// wants.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn check_interruption(&self, module: &ModuleInfo) -> Result<(), DeserializeError> {
+        // A headless engine can't tell which checks it expects.
+        let headless = self.is_headless();
+        if !headless && self.interrupt_checks() != module.interrupt_checks {
+            return Err(DeserializeError::Incompatible(
+                "The artifact was compiled with different interrupt checks than the engine"
+                    .to_string(),
+            ));
+        }
+        if !headless && self.epoch_interruption() != module.epoch_interruption {
+            return Err(DeserializeError::Incompatible(
+                "The artifact was compiled with different epoch interruption than the engine"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// A unique identifier for this object.
     ///
     /// This exists to allow us to compare two Engines for equality. Otherwise,
@@ -224,6 +362,22 @@ impl Engine {
         &self.engine_id
     }
 
+    /// Increments the epoch of this engine, which is shared by all its
+    /// clones and the stores using them.
+    ///
+    /// This is typically called periodically by a timer thread, to make
+    /// the stores reach their epoch deadlines.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn increment_epoch(&self) {
+        self.epoch.increment();
+    }
+
+    /// Returns the epoch counter of this engine.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn epoch_counter(&self) -> EpochCounter {
+        self.epoch.clone()
+    }
+
     /// Clone the engine
     pub fn cloned(&self) -> Self {
         self.clone()
@@ -260,6 +414,10 @@ pub struct EngineInner {
     #[cfg(feature = "compiler")]
     /// The compiler and cpu features
     features: Features,
+    /// Whether the compiled code checks the interrupt flag of its store
+    interrupt_checks: bool,
+    /// Whether the compiled code checks the epoch deadline of its store
+    epoch_interruption: bool,
     /// The code memory is responsible of publishing the compiled
     /// functions to memory.
     #[cfg(not(target_arch = "wasm32"))]
@@ -295,6 +453,16 @@ impl EngineInner {
         &self.features
     }
 
+    /// Whether the compiled code checks the interrupt flag of its store
+    pub fn interrupt_checks(&self) -> bool {
+        self.interrupt_checks
+    }
+
+    /// Whether the compiled code checks the epoch deadline of its store
+    pub fn epoch_interruption(&self) -> bool {
+        self.epoch_interruption
+    }
+
     /// Allocate compiled functions into memory
     #[cfg(not(target_arch = "wasm32"))]
     #[allow(clippy::type_complexity)]
@@ -324,24 +492,127 @@ impl EngineInner {
             .partition(|section| section.protection == CustomSectionProtection::ReadExecute);
         self.code_memory.push(CodeMemory::new());
 
-        let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
-            self.code_memory
-                .last_mut()
-                .unwrap()
-                .allocate(
-                    function_bodies.as_slice(),
-                    executable_sections.as_slice(),
-                    data_sections.as_slice(),
-                )
-                .map_err(|message| {
-                    CompileError::Resource(format!(
-                        "failed to allocate memory for functions: {}",
-                        message
-                    ))
-                })?;
+        let allocated = self
+            .code_memory
+            .last_mut()
+            .unwrap()
+            .allocate(
+                function_bodies.as_slice(),
+                executable_sections.as_slice(),
+                data_sections.as_slice(),
+            )
+            .map_err(|message| {
+                CompileError::Resource(format!(
+                    "failed to allocate memory for functions: {}",
+                    message
+                ))
+            })?;
+
+        Ok(Self::allocated_pointers(
+            allocated,
+            functions.len(),
+            function_call_trampolines.len(),
+            custom_sections,
+        ))
+    }
+
+    /// Maps the code image of an artifact at `offset` in `file` into
+    /// memory, instead of allocating memory and copying the code in place.
+    ///
+    /// The bodies of the functions and custom sections are empty: their
+    /// bytes are in the image.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while the engine is alive.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(clippy::type_complexity)]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn map(
+        &mut self,
+        file: &File,
+        offset: u64,
+        image: &CodeImage,
+        functions: &PrimaryMap<LocalFunctionIndex, FunctionBody>,
+        function_call_trampolines: &PrimaryMap<SignatureIndex, FunctionBody>,
+        dynamic_function_trampolines: &PrimaryMap<FunctionIndex, FunctionBody>,
+        custom_sections: &PrimaryMap<SectionIndex, CustomSection>,
+    ) -> Result<
+        (
+            PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+            PrimaryMap<SignatureIndex, VMTrampoline>,
+            PrimaryMap<FunctionIndex, FunctionBodyPtr>,
+            PrimaryMap<SectionIndex, SectionBodyPtr>,
+        ),
+        DeserializeError,
+    > {
+        let function_bodies = functions
+            .values()
+            .chain(function_call_trampolines.values())
+            .chain(dynamic_function_trampolines.values())
+            .collect::<Vec<_>>();
+        if image.custom_sections.len() != custom_sections.len() {
+            return Err(DeserializeError::CorruptedBinary(
+                "the code image doesn't match the module".to_string(),
+            ));
+        }
+        let (executable_sections, data_sections): (Vec<_>, Vec<_>) = custom_sections
+            .values()
+            .zip(&image.custom_sections)
+            .partition(|(section, _)| section.protection == CustomSectionProtection::ReadExecute);
+        let executable_sections = executable_sections
+            .into_iter()
+            .map(|(_, range)| *range)
+            .collect::<Vec<_>>();
+        let data_sections = data_sections
+            .into_iter()
+            .map(|(_, range)| *range)
+            .collect::<Vec<_>>();
+        self.code_memory.push(CodeMemory::new());
+
+        let allocated = self
+            .code_memory
+            .last_mut()
+            .unwrap()
+            .map(
+                file,
+                offset,
+                image,
+                function_bodies.as_slice(),
+                &executable_sections,
+                &data_sections,
+            )
+            .map_err(DeserializeError::CorruptedBinary)?;
+
+        Ok(Self::allocated_pointers(
+            allocated,
+            functions.len(),
+            function_call_trampolines.len(),
+            custom_sections,
+        ))
+    }
 
+    /// Splits the memory allocated for the functions and custom sections
+    /// of an artifact into pointers to each of them.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(clippy::type_complexity)]
+    fn allocated_pointers(
+        (mut allocated_functions, allocated_executable_sections, allocated_data_sections): (
+            Vec<&mut [VMFunctionBody]>,
+            Vec<&mut [u8]>,
+            Vec<&mut [u8]>,
+        ),
+        function_count: usize,
+        function_call_trampoline_count: usize,
+        custom_sections: &PrimaryMap<SectionIndex, CustomSection>,
+    ) -> (
+        PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+        PrimaryMap<SignatureIndex, VMTrampoline>,
+        PrimaryMap<FunctionIndex, FunctionBodyPtr>,
+        PrimaryMap<SectionIndex, SectionBodyPtr>,
+    ) {
         let allocated_functions_result = allocated_functions
-            .drain(0..functions.len())
+            .drain(0..function_count)
             .map(|slice| FunctionExtent {
                 ptr: FunctionBodyPtr(slice.as_ptr()),
                 length: slice.len(),
@@ -351,7 +622,7 @@ impl EngineInner {
         let mut allocated_function_call_trampolines: PrimaryMap<SignatureIndex, VMTrampoline> =
             PrimaryMap::new();
         for ptr in allocated_functions
-            .drain(0..function_call_trampolines.len())
+            .drain(0..function_call_trampoline_count)
             .map(|slice| slice.as_ptr())
         {
             let trampoline =
@@ -381,12 +652,12 @@ impl EngineInner {
             })
             .collect::<PrimaryMap<SectionIndex, _>>();
 
-        Ok((
+        (
             allocated_functions_result,
             allocated_function_call_trampolines,
             allocated_dynamic_function_trampolines,
             allocated_custom_sections,
-        ))
+        )
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -9,6 +9,33 @@ use wasmer_types::{Relocation, RelocationKind, RelocationTarget, Relocations, Se
 use wasmer_vm::libcalls::function_pointer;
 use wasmer_vm::SectionBodyPtr;
 
+/// Writes `value` at `address`, unless it already holds it.
+///
+/// Code mapped from a file is only copied once it's written to, so the
+/// relocations which were applied when the code was serialized are not
+/// applied again.
+unsafe fn write_if_changed<T: Copy + PartialEq>(address: *mut T, value: T) {
+    if read_unaligned(address) != value {
+        write_unaligned(address, value);
+    }
+}
+
+/// Whether the relocation only depends on the placement of the functions
+/// and custom sections relative to each other, and not on the address
+/// they are loaded at.
+fn is_position_independent(r: &Relocation) -> bool {
+    let direct_libcall = matches!(r.reloc_target, RelocationTarget::LibCall(_))
+        && matches!(r.kind, RelocationKind::Abs8 | RelocationKind::X86PCRel8);
+    !direct_libcall
+        && matches!(
+            r.kind,
+            RelocationKind::X86PCRel4
+                | RelocationKind::X86PCRel8
+                | RelocationKind::X86CallPCRel4
+                | RelocationKind::Arm64Call
+        )
+}
+
 fn apply_relocation(
     body: usize,
     r: &Relocation,
@@ -40,19 +67,19 @@ fn apply_relocation(
     match r.kind {
         RelocationKind::Abs8 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
-            write_unaligned(reloc_address as *mut u64, reloc_delta);
+            write_if_changed(reloc_address as *mut u64, reloc_delta);
         },
         RelocationKind::X86PCRel4 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
-            write_unaligned(reloc_address as *mut u32, reloc_delta as _);
+            write_if_changed(reloc_address as *mut u32, reloc_delta as _);
         },
         RelocationKind::X86PCRel8 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
-            write_unaligned(reloc_address as *mut u64, reloc_delta);
+            write_if_changed(reloc_address as *mut u64, reloc_delta);
         },
         RelocationKind::X86CallPCRel4 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
-            write_unaligned(reloc_address as *mut u32, reloc_delta as _);
+            write_if_changed(reloc_address as *mut u32, reloc_delta as _);
         },
         RelocationKind::Arm64Call => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
@@ -67,31 +94,31 @@ fn apply_relocation(
             }
             let reloc_delta = (((reloc_delta / 4) as u32) & 0x3ff_ffff)
                 | (read_unaligned(reloc_address as *mut u32) & 0xfc00_0000);
-            write_unaligned(reloc_address as *mut u32, reloc_delta);
+            write_if_changed(reloc_address as *mut u32, reloc_delta);
         },
         RelocationKind::Arm64Movw0 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
-            let reloc_delta =
-                (((reloc_delta & 0xffff) as u32) << 5) | read_unaligned(reloc_address as *mut u32);
-            write_unaligned(reloc_address as *mut u32, reloc_delta);
+            let reloc_delta = (((reloc_delta & 0xffff) as u32) << 5)
+                | (read_unaligned(reloc_address as *mut u32) & !(0xffff << 5));
+            write_if_changed(reloc_address as *mut u32, reloc_delta);
         },
         RelocationKind::Arm64Movw1 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
             let reloc_delta = ((((reloc_delta >> 16) & 0xffff) as u32) << 5)
-                | read_unaligned(reloc_address as *mut u32);
-            write_unaligned(reloc_address as *mut u32, reloc_delta);
+                | (read_unaligned(reloc_address as *mut u32) & !(0xffff << 5));
+            write_if_changed(reloc_address as *mut u32, reloc_delta);
         },
         RelocationKind::Arm64Movw2 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
             let reloc_delta = ((((reloc_delta >> 32) & 0xffff) as u32) << 5)
-                | read_unaligned(reloc_address as *mut u32);
-            write_unaligned(reloc_address as *mut u32, reloc_delta);
+                | (read_unaligned(reloc_address as *mut u32) & !(0xffff << 5));
+            write_if_changed(reloc_address as *mut u32, reloc_delta);
         },
         RelocationKind::Arm64Movw3 => unsafe {
             let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
             let reloc_delta = ((((reloc_delta >> 48) & 0xffff) as u32) << 5)
-                | read_unaligned(reloc_address as *mut u32);
-            write_unaligned(reloc_address as *mut u32, reloc_delta);
+                | (read_unaligned(reloc_address as *mut u32) & !(0xffff << 5));
+            write_if_changed(reloc_address as *mut u32, reloc_delta);
         },
         kind => panic!(
             "Relocation kind unsupported in the current architecture {}",
@@ -110,10 +137,54 @@ pub fn link_module(
     section_relocations: &PrimaryMap<SectionIndex, Vec<Relocation>>,
     libcall_trampolines: SectionIndex,
     trampoline_len: usize,
+) {
+    apply_relocations(
+        allocated_functions,
+        &function_relocations,
+        allocated_sections,
+        section_relocations,
+        libcall_trampolines,
+        trampoline_len,
+        |_| true,
+    )
+}
+
+/// Applies the relocations of a module which don't depend on the address
+/// it is loaded at, see [`CodeImage`](wasmer_types::CodeImage).
+///
+/// The other relocations are left untouched, so that the result only
+/// depends on the layout of the functions and custom sections.
+pub(crate) fn link_module_position_independent(
+    allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    function_relocations: &Relocations,
+    allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
+    section_relocations: &PrimaryMap<SectionIndex, Vec<Relocation>>,
+    libcall_trampolines: SectionIndex,
+    trampoline_len: usize,
+) {
+    apply_relocations(
+        allocated_functions,
+        function_relocations,
+        allocated_sections,
+        section_relocations,
+        libcall_trampolines,
+        trampoline_len,
+        is_position_independent,
+    )
+}
+
+fn apply_relocations(
+    allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    function_relocations: &Relocations,
+    allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
+    section_relocations: &PrimaryMap<SectionIndex, Vec<Relocation>>,
+    libcall_trampolines: SectionIndex,
+    trampoline_len: usize,
+    filter: impl Fn(&Relocation) -> bool,
 ) {
     for (i, section_relocs) in section_relocations.iter() {
         let body = *allocated_sections[i] as usize;
-        for r in section_relocs {
+        for r in section_relocs.iter().filter(|r| filter(r)) {
             apply_relocation(
                 body,
                 r,
@@ -126,7 +197,7 @@ pub fn link_module(
     }
     for (i, function_relocs) in function_relocations.iter() {
         let body = *allocated_functions[i].ptr as usize;
-        for r in function_relocs {
+        for r in function_relocs.iter().filter(|r| filter(r)) {
             apply_relocation(
                 body,
                 r,
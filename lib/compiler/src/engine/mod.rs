@@ -15,6 +15,9 @@ mod artifact;
 mod builder;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
+mod checked;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
 mod code_memory;
 #[cfg(feature = "translator")]
 mod inner;
@@ -31,7 +34,7 @@ pub use self::resolver::resolve_imports;
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::trap::*;
 #[cfg(not(target_arch = "wasm32"))]
-pub use self::tunables::{BaseTunables, Tunables};
+pub use self::tunables::{BaseTunables, PoolingTunables, Tunables};
 
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
@@ -40,9 +43,15 @@ pub use self::artifact::Artifact;
 pub use self::builder::EngineBuilder;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
+pub(crate) use self::code_memory::CodeLayout;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
 pub use self::code_memory::CodeMemory;
 #[cfg(feature = "translator")]
 pub use self::inner::{Engine, EngineInner};
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::link::link_module;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use self::link::link_module_position_independent;
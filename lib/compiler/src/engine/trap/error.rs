@@ -3,6 +3,7 @@ use backtrace::Backtrace;
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
+use wasmer_types::MemoryError;
 use wasmer_vm::{Trap, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
@@ -308,3 +309,9 @@ impl From<Trap> for RuntimeError {
         Self::from_trap(trap)
     }
 }
+
+impl From<MemoryError> for RuntimeError {
+    fn from(error: MemoryError) -> Self {
+        Self::user(Box::new(error))
+    }
+}
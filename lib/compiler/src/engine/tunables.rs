@@ -5,8 +5,8 @@ use wasmer_types::{
     GlobalType, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType,
     ModuleInfo, Pages, PointerWidth, TableIndex, TableType, Target,
 };
-use wasmer_vm::{InternalStoreHandle, MemoryError, StoreObjects};
-use wasmer_vm::{MemoryStyle, TableStyle};
+use wasmer_vm::{InstanceAllocator, InternalStoreHandle, MemoryError, StoreObjects};
+use wasmer_vm::{MemoryStyle, PoolingAllocator, TableStyle};
 use wasmer_vm::{VMGlobal, VMMemory, VMTable};
 use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
@@ -56,6 +56,23 @@ pub trait Tunables {
         Ok(VMGlobal::new(ty))
     }
 
+    /// Allocate the memory of an instance of `module`, and get the
+    /// locations of the definitions of its memories and tables.
+    #[allow(clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        Ok(InstanceAllocator::new(module))
+    }
+
     /// Allocate memory for just the memories of the current module.
     ///
     /// # Safety
@@ -268,6 +285,97 @@ impl Tunables for BaseTunables {
     }
 }
 
+/// Tunables allocating the instances, and the memories and tables they
+/// define, from the slots of a [`PoolingAllocator`].
+///
+/// Memories are given a dynamic style with the guard region of the memory
+/// slots, so that they can be placed in any slot. Host memories and tables,
+/// shared memories, and everything else are handled by the base tunables.
+#[derive(Clone)]
+pub struct PoolingTunables {
+    base: BaseTunables,
+    allocator: PoolingAllocator,
+}
+
+impl PoolingTunables {
+    /// Creates tunables allocating from `allocator`, and deferring to `base`
+    /// for what isn't allocated from it.
+    pub fn new(base: BaseTunables, allocator: PoolingAllocator) -> Self {
+        Self { base, allocator }
+    }
+
+    /// Returns the allocator of these tunables.
+    pub fn allocator(&self) -> &PoolingAllocator {
+        &self.allocator
+    }
+}
+
+impl Tunables for PoolingTunables {
+    fn memory_style(&self, _memory: &MemoryType) -> MemoryStyle {
+        MemoryStyle::Dynamic {
+            offset_guard_size: self.allocator.config().memory_guard_size as u64,
+        }
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        self.base.create_host_memory(ty, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        if ty.shared {
+            self.base
+                .create_vm_memory(ty, style, vm_definition_location)
+        } else {
+            self.allocator
+                .create_memory(ty, style, vm_definition_location)
+        }
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        self.allocator
+            .create_table(ty, style, vm_definition_location)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.allocator
+            .allocate_instance(module)
+            .map_err(LinkError::Resource)
+    }
+}
+
 impl Tunables for Box<dyn Tunables + Send + Sync> {
     fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
         self.as_ref().memory_style(memory)
@@ -308,6 +416,21 @@ impl Tunables for Box<dyn Tunables + Send + Sync> {
         self.as_ref()
             .create_vm_table(ty, style, vm_definition_location)
     }
+
+    #[allow(clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.as_ref().allocate_instance(module)
+    }
 }
 
 impl Tunables for std::sync::Arc<dyn Tunables + Send + Sync> {
@@ -350,4 +473,19 @@ impl Tunables for std::sync::Arc<dyn Tunables + Send + Sync> {
         self.as_ref()
             .create_vm_table(ty, style, vm_definition_location)
     }
+
+    #[allow(clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.as_ref().allocate_instance(module)
+    }
 }
@@ -32,6 +32,13 @@ pub trait ArtifactCreate: Send + Sync + Upcastable {
     /// Returns the CPU features for this Artifact
     fn cpu_features(&self) -> EnumSet<CpuFeature>;
 
+    /// Returns the fingerprint of the middleware chain the compiler applied
+    /// to this Artifact, if it has one, see
+    /// [`ModuleMiddlewareChain::fingerprint`].
+    ///
+    /// [`ModuleMiddlewareChain::fingerprint`]: crate::ModuleMiddlewareChain::fingerprint
+    fn middleware_fingerprint(&self) -> Option<[u8; 32]>;
+
     /// Returns the memory styles associated with this `Artifact`.
     fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle>;
 
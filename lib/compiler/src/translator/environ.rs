@@ -8,6 +8,7 @@ use crate::wasmparser::{Operator, Range, Type};
 use std::convert::{TryFrom, TryInto};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::FunctionType;
+use wasmer_types::TrapCode;
 use wasmer_types::WasmResult;
 use wasmer_types::{
     CustomSectionIndex, DataIndex, DataInitializer, DataInitializerLocation, ElemIndex,
@@ -37,6 +38,13 @@ pub trait FunctionBinaryReader<'a> {
     /// Reads the next available `Operator`.
     fn read_operator(&mut self) -> WasmResult<Operator<'a>>;
 
+    /// Returns the trap code raised by the last operator read, when it is
+    /// an `unreachable` pushed by a middleware with
+    /// `MiddlewareReaderState::push_trap`.
+    fn trap_code(&self) -> Option<TrapCode> {
+        None
+    }
+
     /// Returns the current position.
     fn current_position(&self) -> usize;
 
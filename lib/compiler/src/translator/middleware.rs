@@ -1,11 +1,12 @@
 //! The middleware parses the function binary bytecodes and transform them
 //! with the chosen functions.
 
+use sha2::{Digest, Sha256};
 use smallvec::SmallVec;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::ops::Deref;
-use wasmer_types::{LocalFunctionIndex, MiddlewareError, ModuleInfo, WasmResult};
+use wasmer_types::{LocalFunctionIndex, MiddlewareError, ModuleInfo, TrapCode, WasmResult};
 use wasmparser::{BinaryReader, Operator, Range, Type};
 
 use super::error::from_binaryreadererror_wasmerror;
@@ -23,12 +24,51 @@ pub trait ModuleMiddleware: Debug + Send + Sync {
         local_function_index: LocalFunctionIndex,
     ) -> Box<dyn FunctionMiddleware>;
 
+    /// Generates a `FunctionMiddleware` for a given function of `module_info`, the module being compiled as
+    /// transformed by `transform_module_info`. This is the method called by the compilers, and it defaults to
+    /// `generate_function_middleware`.
+    ///
+    /// Since the same middleware can be used to compile several modules, per-module state should be looked up in
+    /// `module_info` rather than kept in the middleware.
+    fn generate_function_middleware_for_module(
+        &self,
+        _module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        self.generate_function_middleware(local_function_index)
+    }
+
+    /// Checks a `ModuleInfo` struct before it is transformed, failing the compilation of the module with an error.
+    fn check_module_info(&self, _: &ModuleInfo) -> Result<(), MiddlewareError> {
+        Ok(())
+    }
+
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
     fn transform_module_info(&self, _: &mut ModuleInfo) {}
+
+    /// Returns the fingerprint of the middleware, identifying the transformation it applies.
+    ///
+    /// The fingerprints of a middleware chain are recorded in the artifacts it compiles, so that
+    /// an artifact compiled with a differently configured chain, such as metering with another
+    /// cost table, is not loaded by mistake. Two middlewares must only have the same fingerprint
+    /// if they transform modules the same way.
+    ///
+    /// The default fingerprint is the `Debug` representation of the middleware, which must then
+    /// include all of its configuration. A middleware whose configuration can't be identified, such
+    /// as a closure, returns `None`: the artifacts it compiles then have no fingerprint, and can't
+    /// be cached or deserialized by an engine with a compiler.
+    fn fingerprint(&self) -> Option<String> {
+        Some(format!("{:?}", self))
+    }
 }
 
 /// A function middleware specialized for a single function.
 pub trait FunctionMiddleware: Debug {
+    /// Processes a declaration of `count` locals of type `ty`.
+    fn feed_local(&mut self, _count: u32, _ty: Type) -> Result<(), MiddlewareError> {
+        Ok(())
+    }
+
     /// Processes the given operator.
     fn feed<'a>(
         &mut self,
@@ -48,6 +88,9 @@ pub struct MiddlewareBinaryReader<'a> {
 
     /// The backing middleware chain for this reader.
     chain: Vec<Box<dyn FunctionMiddleware>>,
+
+    /// The trap code of the last operator read, see `FunctionBinaryReader::trap_code`.
+    trap_code: Option<TrapCode>,
 }
 
 /// The state of the binary reader. Exposed to middlewares to push their outputs.
@@ -56,8 +99,12 @@ pub struct MiddlewareReaderState<'a> {
     /// Raw binary reader.
     inner: BinaryReader<'a>,
 
-    /// The pending operations added by the middleware.
-    pending_operations: VecDeque<Operator<'a>>,
+    /// The pending operations added by the middleware, with the trap code
+    /// of the `unreachable` operators pushed with `push_trap`.
+    pending_operations: VecDeque<(Operator<'a>, Option<TrapCode>)>,
+
+    /// The trap code of the operator being fed to a middleware.
+    trap_code: Option<TrapCode>,
 }
 
 /// Trait for generating middleware chains from "prototype" (generator) chains.
@@ -68,8 +115,25 @@ pub trait ModuleMiddlewareChain {
         local_function_index: LocalFunctionIndex,
     ) -> Vec<Box<dyn FunctionMiddleware>>;
 
+    /// Generates a function middleware chain for a function of `module_info`, the module being compiled as
+    /// transformed by `apply_on_module_info`.
+    fn generate_function_middleware_chain_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Vec<Box<dyn FunctionMiddleware>>;
+
+    /// Checks a `ModuleInfo` struct with each middleware of the chain, before it is transformed.
+    fn check_module_info(&self, module_info: &ModuleInfo) -> Result<(), MiddlewareError>;
+
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
+
+    /// Combines the fingerprints of the middlewares of the chain, in order.
+    ///
+    /// The fingerprint of an empty chain is all zeros, and a chain has no fingerprint if one of its
+    /// middlewares has none.
+    fn fingerprint(&self) -> Option<[u8; 32]>;
 }
 
 impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
@@ -83,30 +147,77 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
             .collect()
     }
 
+    /// Generates a function middleware chain for a function of `module_info`.
+    fn generate_function_middleware_chain_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Vec<Box<dyn FunctionMiddleware>> {
+        self.iter()
+            .map(|x| x.generate_function_middleware_for_module(module_info, local_function_index))
+            .collect()
+    }
+
+    /// Checks a `ModuleInfo` struct with each middleware of the chain, before it is transformed.
+    fn check_module_info(&self, module_info: &ModuleInfo) -> Result<(), MiddlewareError> {
+        self.iter()
+            .try_for_each(|item| item.check_module_info(module_info))
+    }
+
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo) {
         for item in self {
             item.transform_module_info(module_info);
         }
     }
+
+    /// Combines the fingerprints of the middlewares of the chain, in order.
+    fn fingerprint(&self) -> Option<[u8; 32]> {
+        if self.is_empty() {
+            return Some([0; 32]);
+        }
+        let mut hasher = Sha256::new();
+        for item in self {
+            let fingerprint = item.fingerprint()?;
+            hasher.update((fingerprint.len() as u64).to_le_bytes());
+            hasher.update(fingerprint.as_bytes());
+        }
+        Some(hasher.finalize().into())
+    }
 }
 
 impl<'a> MiddlewareReaderState<'a> {
     /// Push an operator.
+    ///
+    /// When the operator being fed is a trap pushed with `push_trap`,
+    /// pushing it back keeps its trap code.
     pub fn push_operator(&mut self, operator: Operator<'a>) {
-        self.pending_operations.push_back(operator);
+        let trap_code = match operator {
+            Operator::Unreachable => self.trap_code,
+            _ => None,
+        };
+        self.pending_operations.push_back((operator, trap_code));
+    }
+
+    /// Push an `unreachable` operator raising `trap_code` instead of
+    /// `TrapCode::UnreachableCodeReached`.
+    pub fn push_trap(&mut self, trap_code: TrapCode) {
+        self.pending_operations
+            .push_back((Operator::Unreachable, Some(trap_code)));
     }
 }
 
 impl<'a> Extend<Operator<'a>> for MiddlewareReaderState<'a> {
     fn extend<I: IntoIterator<Item = Operator<'a>>>(&mut self, iter: I) {
-        self.pending_operations.extend(iter);
+        for operator in iter {
+            self.push_operator(operator);
+        }
     }
 }
 
 impl<'a: 'b, 'b> Extend<&'b Operator<'a>> for MiddlewareReaderState<'a> {
     fn extend<I: IntoIterator<Item = &'b Operator<'a>>>(&mut self, iter: I) {
-        self.pending_operations.extend(iter.into_iter().cloned());
+        self.extend(iter.into_iter().cloned());
     }
 }
 
@@ -118,8 +229,10 @@ impl<'a> MiddlewareBinaryReader<'a> {
             state: MiddlewareReaderState {
                 inner,
                 pending_operations: VecDeque::new(),
+                trap_code: None,
             },
             chain: vec![],
+            trap_code: None,
         }
     }
 
@@ -148,12 +261,16 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
             .inner
             .read_type()
             .map_err(from_binaryreadererror_wasmerror)?;
+        for stage in &mut self.chain {
+            stage.feed_local(count, ty)?;
+        }
         Ok((count, ty))
     }
 
     fn read_operator(&mut self) -> WasmResult<Operator<'a>> {
         if self.chain.is_empty() {
             // We short-circuit in case no chain is used
+            self.trap_code = None;
             return self
                 .state
                 .inner
@@ -170,22 +287,31 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
                 .map_err(from_binaryreadererror_wasmerror)?;
 
             // Fill the initial raw operator into pending buffer.
-            self.state.pending_operations.push_back(raw_op);
+            self.state.pending_operations.push_back((raw_op, None));
 
             // Run the operator through each stage.
             for stage in &mut self.chain {
                 // Take the outputs from the previous stage.
-                let pending: SmallVec<[Operator<'a>; 2]> =
+                let pending: SmallVec<[(Operator<'a>, Option<TrapCode>); 2]> =
                     self.state.pending_operations.drain(0..).collect();
 
                 // ...and feed them into the current stage.
-                for pending_op in pending {
-                    stage.feed(pending_op, &mut self.state)?;
+                for (pending_op, trap_code) in pending {
+                    self.state.trap_code = trap_code;
+                    let result = stage.feed(pending_op, &mut self.state);
+                    self.state.trap_code = None;
+                    result?;
                 }
             }
         }
 
-        Ok(self.state.pending_operations.pop_front().unwrap())
+        let (operator, trap_code) = self.state.pending_operations.pop_front().unwrap();
+        self.trap_code = trap_code;
+        Ok(operator)
+    }
+
+    fn trap_code(&self) -> Option<TrapCode> {
+        self.trap_code
     }
 
     fn current_position(&self) -> usize {
@@ -0,0 +1,579 @@
+//! `call_depth` is a middleware for putting a limit on the depth of
+//! nested calls made by WebAssembly code. Unlike the size of the native
+//! stack, the limit does not depend on the compiler or on the host, so
+//! the same code always fails at the same depth.
+//!
+//! Entering a function of an instance increments a counter, which is
+//! decremented when the function returns. The execution is stopped with a
+//! [`TrapCode::CallDepthExceeded`] trap when a call would take the counter
+//! past the limit. A tail call leaves the calling function before
+//! entering the callee, so chains of tail calls don't add to the depth.
+//!
+//! The limit counts calls, not the size of their frames: a function with
+//! many locals or a deep operand stack counts as much as any other, so
+//! the depth doesn't bound the stack height used by the code. Limiting
+//! the stack height is out of scope for this middleware.
+//!
+//! # Traps
+//!
+//! The functions stopped by a trap, of any kind, never return, so the
+//! counter keeps counting them: after a trap, the call depth of the
+//! instance must be reset with [`reset_call_depth`] before calling it
+//! again, otherwise the next calls start deeper and eventually all fail.
+
+use crate::FailingMiddleware;
+use std::convert::TryInto;
+use std::fmt;
+use wasmer::wasmparser::{BrTable, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    AsStoreMut, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance,
+    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::{GlobalIndex, ModuleInfo, TrapCode};
+
+#[derive(Clone)]
+struct CallDepthGlobalIndexes(GlobalIndex, GlobalIndex, GlobalIndex);
+
+impl CallDepthGlobalIndexes {
+    /// Finds the call depth globals of a module transformed by
+    /// [`CallDepthLimit::transform_module_info`], from their exports.
+    fn from_module_info(module_info: &ModuleInfo) -> Result<Self, MiddlewareError> {
+        let global = |name| match module_info.exports.get(name) {
+            Some(ExportIndex::Global(index)) => Ok(*index),
+            _ => Err(MiddlewareError::new(
+                "CallDepthLimit",
+                format!("the module has no `{}` global export", name),
+            )),
+        };
+        Ok(Self(
+            global("wasmer_call_depth")?,
+            global("wasmer_call_depth_exceeded")?,
+            global("wasmer_call_depth_condition")?,
+        ))
+    }
+
+    /// The global index in the current module for the current call depth.
+    fn call_depth(&self) -> GlobalIndex {
+        self.0
+    }
+
+    /// The global index in the current module for a boolean indicating whether the limit has
+    /// been exceeded or not.
+    /// This boolean is represented as a i32 global:
+    ///   * 0: the limit has not been exceeded
+    ///   * 1: the limit has been exceeded
+    fn call_depth_exceeded(&self) -> GlobalIndex {
+        self.1
+    }
+
+    /// The global index in the current module for an i32 global used to
+    /// hold the condition of a branch which may return from the function.
+    fn condition(&self) -> GlobalIndex {
+        self.2
+    }
+}
+
+impl fmt::Debug for CallDepthGlobalIndexes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallDepthGlobalIndexes")
+            .field("call_depth", &self.call_depth())
+            .field("call_depth_exceeded", &self.call_depth_exceeded())
+            .field("condition", &self.condition())
+            .finish()
+    }
+}
+
+/// The module-level call depth limiting middleware.
+///
+/// The depth counts the functions of the instance being run, whether they
+/// were called by WebAssembly code or by the host: a function called by
+/// the host while no other function of the instance runs is at depth one.
+/// Imported functions are not counted, and each instance counts its own
+/// functions.
+///
+/// The depth is not unwound by traps, see the [module
+/// documentation](crate::call_depth#traps).
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::CallDepthLimit;
+///
+/// fn create_call_depth_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Allow at most 1000 nested calls.
+///     compiler_config.push_middleware(Arc::new(CallDepthLimit::new(1000)));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CallDepthLimit {
+    /// Maximum number of nested calls.
+    max_depth: u32,
+}
+
+/// The function-level call depth limiting middleware.
+#[derive(Debug)]
+pub struct FunctionCallDepthLimit {
+    /// Maximum number of nested calls.
+    max_depth: u32,
+
+    /// The global indexes for the call depth.
+    global_indexes: CallDepthGlobalIndexes,
+
+    /// Whether the entry of the function has been instrumented.
+    entered: bool,
+
+    /// Number of blocks open at the current operator, not counting the
+    /// body of the function.
+    open_blocks: u32,
+}
+
+impl CallDepthLimit {
+    /// Creates a `CallDepthLimit` middleware allowing at most `max_depth`
+    /// functions of an instance to run at once.
+    pub fn new(max_depth: u32) -> Self {
+        Self { max_depth }
+    }
+}
+
+impl ModuleMiddleware for CallDepthLimit {
+    /// Fails: the call depth globals are looked up in the module.
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        FailingMiddleware::without_module_info("CallDepthLimit")
+    }
+
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        match CallDepthGlobalIndexes::from_module_info(module_info) {
+            Ok(global_indexes) => Box::new(FunctionCallDepthLimit {
+                max_depth: self.max_depth,
+                global_indexes,
+                entered: false,
+                open_blocks: 0,
+            }),
+            Err(error) => FailingMiddleware::new(error),
+        }
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        // Append a global for the call depth and initialize it.
+        let call_depth_global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            "wasmer_call_depth".to_string(),
+            ExportIndex::Global(call_depth_global_index),
+        );
+
+        // Append a global for the exceeded limit boolean and initialize it.
+        let call_depth_exceeded_global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            "wasmer_call_depth_exceeded".to_string(),
+            ExportIndex::Global(call_depth_exceeded_global_index),
+        );
+
+        // Append a global to hold the condition of branches.
+        let condition_global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            "wasmer_call_depth_condition".to_string(),
+            ExportIndex::Global(condition_global_index),
+        );
+    }
+}
+
+impl FunctionMiddleware for FunctionCallDepthLimit {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+            self.enter(state);
+        }
+
+        match operator {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Try { .. } => self.open_blocks += 1,
+            // `delegate` closes a `try` like `end` does.
+            Operator::End | Operator::Delegate { .. } if self.open_blocks > 0 => {
+                self.open_blocks -= 1
+            }
+            // The end of the function.
+            Operator::End | Operator::Return => self.leave(state),
+            // A tail call replaces the current function with the callee,
+            // which counts itself when it is entered.
+            Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => self.leave(state),
+            Operator::Br { relative_depth } if relative_depth == self.open_blocks => {
+                self.leave(state)
+            }
+            Operator::BrIf { relative_depth } if relative_depth == self.open_blocks => {
+                self.leave_if_taken(state)
+            }
+            Operator::BrTable { ref table } => self.leave_if_selected(table, state)?,
+            _ => {}
+        }
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+impl FunctionCallDepthLimit {
+    /// Emits the increment of the call depth on entry, failing if the
+    /// limit is exceeded.
+    #[rustfmt::skip]
+    fn enter(&self, state: &mut MiddlewareReaderState) {
+        state.extend(&[
+            // if unsigned(globals[call_depth_index]) >= unsigned(self.max_depth) { throw(); }
+            Operator::GlobalGet { global_index: self.global_indexes.call_depth().as_u32() },
+            Operator::I32Const { value: self.max_depth as i32 },
+            Operator::I32GeU,
+            Operator::If { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
+            Operator::I32Const { value: 1 },
+            Operator::GlobalSet { global_index: self.global_indexes.call_depth_exceeded().as_u32() },
+        ]);
+        state.push_trap(TrapCode::CallDepthExceeded);
+        state.extend(&[
+            Operator::End,
+
+            // globals[call_depth_index] += 1;
+            Operator::GlobalGet { global_index: self.global_indexes.call_depth().as_u32() },
+            Operator::I32Const { value: 1 },
+            Operator::I32Add,
+            Operator::GlobalSet { global_index: self.global_indexes.call_depth().as_u32() },
+        ]);
+    }
+
+    /// Emits the decrement of the call depth before the function returns.
+    #[rustfmt::skip]
+    fn leave(&self, state: &mut MiddlewareReaderState) {
+        state.extend(&[
+            // globals[call_depth_index] -= 1;
+            Operator::GlobalGet { global_index: self.global_indexes.call_depth().as_u32() },
+            Operator::I32Const { value: 1 },
+            Operator::I32Sub,
+            Operator::GlobalSet { global_index: self.global_indexes.call_depth().as_u32() },
+        ]);
+    }
+
+    /// Emits the decrement of the call depth before a `br_if` returning
+    /// from the function, if the branch is taken.
+    #[rustfmt::skip]
+    fn leave_if_taken(&self, state: &mut MiddlewareReaderState) {
+        state.extend(&[
+            // globals[condition_index] = pop();
+            Operator::GlobalSet { global_index: self.global_indexes.condition().as_u32() },
+
+            // globals[call_depth_index] -= globals[condition_index] != 0;
+            Operator::GlobalGet { global_index: self.global_indexes.call_depth().as_u32() },
+            Operator::GlobalGet { global_index: self.global_indexes.condition().as_u32() },
+            Operator::I32Const { value: 0 },
+            Operator::I32Ne,
+            Operator::I32Sub,
+            Operator::GlobalSet { global_index: self.global_indexes.call_depth().as_u32() },
+
+            // push(globals[condition_index]);
+            Operator::GlobalGet { global_index: self.global_indexes.condition().as_u32() },
+        ]);
+    }
+
+    /// Emits the decrement of the call depth before a `br_table`, if the
+    /// target it selects returns from the function.
+    #[rustfmt::skip]
+    fn leave_if_selected(
+        &self,
+        table: &BrTable,
+        state: &mut MiddlewareReaderState,
+    ) -> Result<(), MiddlewareError> {
+        let targets = table
+            .targets()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| MiddlewareError::new("CallDepthLimit", error.message()))?;
+        let returns = |target: u32| target == self.open_blocks;
+        if !returns(table.default()) && !targets.iter().copied().any(returns) {
+            return Ok(());
+        }
+
+        state.extend(&[
+            // globals[condition_index] = pop();
+            Operator::GlobalSet { global_index: self.global_indexes.condition().as_u32() },
+
+            // globals[call_depth_index] -= the selected target returns;
+            Operator::GlobalGet { global_index: self.global_indexes.call_depth().as_u32() },
+            Operator::I32Const { value: 0 },
+        ]);
+        for (index, _) in targets.into_iter().enumerate().filter(|&(_, target)| returns(target)) {
+            state.extend(&[
+                Operator::GlobalGet { global_index: self.global_indexes.condition().as_u32() },
+                Operator::I32Const { value: index as i32 },
+                Operator::I32Eq,
+                Operator::I32Or,
+            ]);
+        }
+        if returns(table.default()) {
+            state.extend(&[
+                Operator::GlobalGet { global_index: self.global_indexes.condition().as_u32() },
+                Operator::I32Const { value: table.len() as i32 },
+                Operator::I32GeU,
+                Operator::I32Or,
+            ]);
+        }
+        state.extend(&[
+            Operator::I32Sub,
+            Operator::GlobalSet { global_index: self.global_indexes.call_depth().as_u32() },
+
+            // push(globals[condition_index]);
+            Operator::GlobalGet { global_index: self.global_indexes.condition().as_u32() },
+        ]);
+
+        Ok(())
+    }
+}
+
+/// Returns whether the execution of an [`Instance`][wasmer::Instance]
+/// was stopped because the call depth limit was exceeded.
+///
+/// Calls are not unwound when an execution is stopped, so the call depth
+/// of the instance must then be reset with [`reset_call_depth`] before
+/// calling it again. The same is needed after any other trap, see the
+/// [module documentation](crate::call_depth#traps).
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`CallDepthLimit`] middleware at compile time, otherwise this will
+/// panic.
+pub fn call_depth_exceeded(ctx: &mut impl AsStoreMut, instance: &Instance) -> bool {
+    let exceeded: i32 = instance
+        .exports
+        .get_global("wasmer_call_depth_exceeded")
+        .expect("Can't get `wasmer_call_depth_exceeded` from Instance")
+        .get(ctx)
+        .try_into()
+        .expect("`wasmer_call_depth_exceeded` from Instance has wrong type");
+
+    exceeded > 0
+}
+
+/// Resets the call depth of an [`Instance`][wasmer::Instance] to 0, and
+/// clears the exceeded limit flag.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`CallDepthLimit`] middleware at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance, RuntimeError, Value};
+/// use wasmer_middlewares::call_depth::reset_call_depth;
+///
+/// fn call(
+///     store: &mut impl AsStoreMut,
+///     instance: &Instance,
+///     name: &str,
+/// ) -> Result<Box<[Value]>, RuntimeError> {
+///     let result = instance.exports.get_function(name).unwrap().call(store, &[]);
+///     if result.is_err() {
+///         // The calls of the failed execution were not unwound.
+///         reset_call_depth(store, instance);
+///     }
+///     result
+/// }
+/// ```
+pub fn reset_call_depth(ctx: &mut impl AsStoreMut, instance: &Instance) {
+    instance
+        .exports
+        .get_global("wasmer_call_depth")
+        .expect("Can't get `wasmer_call_depth` from Instance")
+        .set(ctx, 0i32.into())
+        .expect("Can't set `wasmer_call_depth` in Instance");
+
+    instance
+        .exports
+        .get_global("wasmer_call_depth_exceeded")
+        .expect("Can't get `wasmer_call_depth_exceeded` from Instance")
+        .set(ctx, 0i32.into())
+        .expect("Can't set `wasmer_call_depth_exceeded` in Instance");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, EngineBuilder, Module, Store, TypedFunction,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (type $t (func (param i32) (result i32)))
+            (table 1 funcref)
+            (elem (i32.const 0) $recurse)
+            (func $recurse (export "recurse") (type $t) (param $n i32) (result i32)
+                (if (result i32) (i32.eqz (local.get $n))
+                    (then (i32.const 0))
+                    (else
+                        (i32.add
+                            (i32.const 1)
+                            (call_indirect (type $t)
+                                (i32.sub (local.get $n) (i32.const 1))
+                                (i32.const 0)))))))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn call_depth(store: &mut Store, instance: &Instance) -> i32 {
+        instance
+            .exports
+            .get_global("wasmer_call_depth")
+            .unwrap()
+            .get(store)
+            .unwrap_i32()
+    }
+
+    #[test]
+    fn call_depth_limit_works() {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(CallDepthLimit::new(100)));
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, bytecode()).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let recurse: TypedFunction<i32, i32> = instance
+            .exports
+            .get_typed_function(&store, "recurse")
+            .unwrap();
+
+        // Calling `recurse` with `n` runs `n + 1` nested functions.
+        assert_eq!(recurse.call(&mut store, 99).unwrap(), 99);
+        assert_eq!(recurse.call(&mut store, 99).unwrap(), 99);
+        assert_eq!(call_depth(&mut store, &instance), 0);
+        assert!(!call_depth_exceeded(&mut store, &instance));
+
+        let error = recurse.call(&mut store, 100).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::CallDepthExceeded));
+        assert!(call_depth_exceeded(&mut store, &instance));
+        assert_eq!(call_depth(&mut store, &instance), 100);
+
+        reset_call_depth(&mut store, &instance);
+        assert!(!call_depth_exceeded(&mut store, &instance));
+        assert_eq!(recurse.call(&mut store, 99).unwrap(), 99);
+    }
+
+    #[test]
+    fn traps_must_be_followed_by_a_reset() {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(CallDepthLimit::new(3)));
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(
+            &store,
+            wat2wasm(
+                br#"
+                (module
+                (func $fail (export "fail") (call $unreachable))
+                (func $unreachable unreachable)
+                (func (export "two") (result i32) (call $one))
+                (func $one (result i32) (i32.const 1)))
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let fail: TypedFunction<(), ()> =
+            instance.exports.get_typed_function(&store, "fail").unwrap();
+        let two: TypedFunction<(), i32> =
+            instance.exports.get_typed_function(&store, "two").unwrap();
+
+        let error = fail.call(&mut store).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+        // The two functions stopped by the trap are still counted, so
+        // `two` can't run its second function.
+        assert_eq!(call_depth(&mut store, &instance), 2);
+        let error = two.call(&mut store).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::CallDepthExceeded));
+
+        reset_call_depth(&mut store, &instance);
+        assert_eq!(two.call(&mut store).unwrap(), 1);
+        assert_eq!(call_depth(&mut store, &instance), 0);
+    }
+
+    #[test]
+    fn branches_out_of_functions_are_counted() {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(CallDepthLimit::new(1)));
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(
+            &store,
+            wat2wasm(
+                br#"
+                (module
+                (func (export "return") (param $n i32) (result i32)
+                    (if (local.get $n) (then (return (i32.const 1))))
+                    (i32.const 0))
+                (func (export "br_if") (param $n i32) (result i32)
+                    (block
+                        (br_if 1 (i32.const 1) (local.get $n))
+                        (drop))
+                    (i32.const 0))
+                (func (export "br_table") (param $n i32) (result i32)
+                    (block (result i32)
+                        (block (result i32)
+                            (br_table 0 2 2 (i32.const 1) (local.get $n)))
+                        (drop)
+                        (i32.const 2))
+                    (drop)
+                    (i32.const 0)))
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+
+        for name in ["return", "br_if", "br_table"] {
+            let function: TypedFunction<i32, i32> =
+                instance.exports.get_typed_function(&store, name).unwrap();
+            // The functions return 1 from a branch if `n` is not 0.
+            for n in 0..4 {
+                assert_eq!(function.call(&mut store, n).unwrap(), (n != 0) as i32);
+                assert_eq!(call_depth(&mut store, &instance), 0);
+            }
+        }
+    }
+}
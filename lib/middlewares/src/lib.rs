@@ -1,6 +1,50 @@
+pub mod call_depth;
 pub mod metering;
+pub mod operator_policy;
 
 // The most commonly used symbol are exported at top level of the
 // module. Others are available via modules,
 // e.g. `wasmer_middlewares::metering::get_remaining_points`
-pub use metering::Metering;
+pub use call_depth::CallDepthLimit;
+pub use metering::{Metering, MeteringHandle};
+pub use operator_policy::OperatorPolicy;
+
+use wasmer::wasmparser::Operator;
+use wasmer::{FunctionMiddleware, MiddlewareError, MiddlewareReaderState};
+
+/// A function middleware failing the compilation of its function, for a
+/// module middleware that can't instrument it.
+#[derive(Debug)]
+struct FailingMiddleware {
+    name: String,
+    message: String,
+}
+
+impl FailingMiddleware {
+    fn new(error: MiddlewareError) -> Box<dyn FunctionMiddleware> {
+        Box::new(Self {
+            name: error.name,
+            message: error.message,
+        })
+    }
+
+    /// The function middleware of a module middleware which needs the
+    /// module being compiled, generated without it.
+    fn without_module_info(name: &str) -> Box<dyn FunctionMiddleware> {
+        Self::new(MiddlewareError::new(
+            name,
+            "the middleware needs the module being compiled, \
+             see `ModuleMiddleware::generate_function_middleware_for_module`",
+        ))
+    }
+}
+
+impl FunctionMiddleware for FailingMiddleware {
+    fn feed<'a>(
+        &mut self,
+        _: Operator<'a>,
+        _: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        Err(MiddlewareError::new(&self.name, &self.message))
+    }
+}
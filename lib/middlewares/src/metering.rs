@@ -1,27 +1,55 @@
 //! `metering` is a middleware for tracking how many operators are
 //! executed in total and putting a limit on the total number of
 //! operators executed. The WebAssembly instance execution is stopped
-//! when the limit is reached.
+//! with a [`TrapCode::GasExhausted`] trap when the limit is reached.
 //!
 //! # Example
 //!
 //! [See the `metering` detailed and complete
 //! example](https://github.com/wasmerio/wasmer/blob/master/examples/metering.rs).
 
+use crate::FailingMiddleware;
 use std::convert::TryInto;
 use std::fmt;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
 use wasmer::{
-    AsStoreMut, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance,
-    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+    AsStoreMut, ExportError, ExportIndex, FunctionMiddleware, Global, GlobalInit, GlobalType,
+    Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware,
+    Mutability, RuntimeError, Type,
 };
-use wasmer_types::{GlobalIndex, ModuleInfo};
+use wasmer_types::{GlobalIndex, ModuleInfo, TrapCode};
+use wasmer_vm::Trap;
 
 #[derive(Clone)]
-struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex);
+struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex, Option<GlobalIndex>);
 
 impl MeteringGlobalIndexes {
+    /// Finds the metering globals of a module transformed by
+    /// [`Metering::transform_module_info`], from their exports.
+    fn from_module_info(
+        module_info: &ModuleInfo,
+        dynamic_costs: &DynamicCosts,
+    ) -> Result<Self, MiddlewareError> {
+        let global = |name| match module_info.exports.get(name) {
+            Some(ExportIndex::Global(index)) => Ok(*index),
+            _ => Err(MiddlewareError::new(
+                "Metering",
+                format!("the module has no `{}` global export", name),
+            )),
+        };
+        let operand = if dynamic_costs.is_zero() {
+            None
+        } else {
+            Some(global("wasmer_metering_operand")?)
+        };
+        Ok(Self(
+            global("wasmer_metering_remaining_points")?,
+            global("wasmer_metering_points_exhausted")?,
+            operand,
+        ))
+    }
+
     /// The global index in the current module for remaining points.
     fn remaining_points(&self) -> GlobalIndex {
         self.0
@@ -35,6 +63,14 @@ impl MeteringGlobalIndexes {
     fn points_exhausted(&self) -> GlobalIndex {
         self.1
     }
+
+    /// The global index in the current module for an i64 global used to
+    /// hold the operand of operators with a dynamic cost while it is
+    /// being charged. It only exists when dynamic costs are configured.
+    fn operand(&self) -> GlobalIndex {
+        self.2
+            .expect("the operand global only exists with dynamic costs")
+    }
 }
 
 impl fmt::Debug for MeteringGlobalIndexes {
@@ -42,18 +78,17 @@ impl fmt::Debug for MeteringGlobalIndexes {
         f.debug_struct("MeteringGlobalIndexes")
             .field("remaining_points", &self.remaining_points())
             .field("points_exhausted", &self.points_exhausted())
+            .field("operand", &self.2)
             .finish()
     }
 }
 
 /// The module-level metering middleware.
 ///
-/// # Panic
-///
-/// An instance of `Metering` should _not_ be shared among different
-/// modules, since it tracks module-specific information like the
-/// global index to store metering state. Attempts to use a `Metering`
-/// instance from multiple modules will result in a panic.
+/// The metering state of each module lives in globals added to the module
+/// by the middleware, so a single `Metering` can be used to compile any
+/// number of modules, for instance by keeping it in a long-lived
+/// [`EngineBuilder`][wasmer::EngineBuilder].
 ///
 /// # Example
 ///
@@ -87,8 +122,11 @@ pub struct Metering<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// Function that maps each operator to a cost in "points".
     cost_function: Arc<F>,
 
-    /// The global indexes for metering points.
-    global_indexes: Mutex<Option<MeteringGlobalIndexes>>,
+    /// Costs depending on the operands of some operators.
+    dynamic_costs: DynamicCosts,
+
+    /// Identifies the cost function in the fingerprint of the middleware.
+    cost_function_id: Option<String>,
 }
 
 /// The function-level metering middleware.
@@ -96,6 +134,9 @@ pub struct FunctionMetering<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// Function that maps each operator to a cost in "points".
     cost_function: Arc<F>,
 
+    /// Costs depending on the operands of some operators.
+    dynamic_costs: DynamicCosts,
+
     /// The global indexes for metering points.
     global_indexes: MeteringGlobalIndexes,
 
@@ -103,6 +144,71 @@ pub struct FunctionMetering<F: Fn(&Operator) -> u64 + Send + Sync> {
     accumulated_cost: u64,
 }
 
+/// Costs in "points" charged at runtime from an operand of the operators
+/// whose work depends on it, in addition to the cost given by the cost
+/// function of [`Metering`].
+///
+/// Each cost is multiplied by the operand giving the amount of work: the
+/// number of pages or elements to add for `memory.grow` and `table.grow`,
+/// and the number of bytes or elements to write for the bulk memory
+/// operators. The points are charged before the operator runs, whether or
+/// not it then succeeds. All the costs default to 0, which charges
+/// nothing.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::wasmparser::Operator;
+/// use wasmer_middlewares::metering::{DynamicCosts, Metering};
+///
+/// let metering = Metering::new(1_000_000, |_: &Operator| 1).with_dynamic_costs(DynamicCosts {
+///     memory_grow_per_page: 1000,
+///     memory_fill_per_byte: 1,
+///     memory_copy_per_byte: 1,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DynamicCosts {
+    /// Cost per page added by `memory.grow`.
+    pub memory_grow_per_page: u64,
+    /// Cost per byte written by `memory.fill`.
+    pub memory_fill_per_byte: u64,
+    /// Cost per byte copied by `memory.copy`.
+    pub memory_copy_per_byte: u64,
+    /// Cost per byte copied by `memory.init`.
+    pub memory_init_per_byte: u64,
+    /// Cost per element added by `table.grow`.
+    pub table_grow_per_element: u64,
+    /// Cost per element written by `table.fill`.
+    pub table_fill_per_element: u64,
+    /// Cost per element copied by `table.copy`.
+    pub table_copy_per_element: u64,
+    /// Cost per element copied by `table.init`.
+    pub table_init_per_element: u64,
+}
+
+impl DynamicCosts {
+    /// Returns the cost per unit of the top operand of `operator`, or 0.
+    fn cost_per_unit(&self, operator: &Operator) -> u64 {
+        match operator {
+            Operator::MemoryGrow { .. } => self.memory_grow_per_page,
+            Operator::MemoryFill { .. } => self.memory_fill_per_byte,
+            Operator::MemoryCopy { .. } => self.memory_copy_per_byte,
+            Operator::MemoryInit { .. } => self.memory_init_per_byte,
+            Operator::TableGrow { .. } => self.table_grow_per_element,
+            Operator::TableFill { .. } => self.table_fill_per_element,
+            Operator::TableCopy { .. } => self.table_copy_per_element,
+            Operator::TableInit { .. } => self.table_init_per_element,
+            _ => 0,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Represents the type of the metering points, either `Remaining` or
 /// `Exhausted`.
 ///
@@ -124,13 +230,38 @@ pub enum MeteringPoints {
 
 impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
     /// Creates a `Metering` middleware.
+    ///
+    /// The middleware has no fingerprint until an id is given to the cost
+    /// function with [`Metering::with_cost_function_id`], so the modules
+    /// compiled with it can't be cached.
     pub fn new(initial_limit: u64, cost_function: F) -> Self {
         Self {
             initial_limit,
             cost_function: Arc::new(cost_function),
-            global_indexes: Mutex::new(None),
+            dynamic_costs: DynamicCosts::default(),
+            cost_function_id: None,
         }
     }
+
+    /// Sets the costs charged from the operands of some operators, see
+    /// [`DynamicCosts`].
+    pub fn with_dynamic_costs(mut self, dynamic_costs: DynamicCosts) -> Self {
+        self.dynamic_costs = dynamic_costs;
+        self
+    }
+
+    /// Sets an id for the cost function, such as the name and version of a
+    /// gas schedule, which stands for it in the fingerprint of the
+    /// middleware, see [`ModuleMiddleware::fingerprint`].
+    ///
+    /// A cost function can't be compared: the modules compiled with the
+    /// middleware are loaded from a cache, or deserialized, by any engine
+    /// whose metering has the same limit, costs and id, so the id must be
+    /// changed whenever the cost function is.
+    pub fn with_cost_function_id(mut self, id: impl Into<String>) -> Self {
+        self.cost_function_id = Some(id.into());
+        self
+    }
 }
 
 impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
@@ -138,29 +269,37 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
         f.debug_struct("Metering")
             .field("initial_limit", &self.initial_limit)
             .field("cost_function", &"<function>")
-            .field("global_indexes", &self.global_indexes)
+            .field("dynamic_costs", &self.dynamic_costs)
+            .field("cost_function_id", &self.cost_function_id)
             .finish()
     }
 }
 
 impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Metering<F> {
-    /// Generates a `FunctionMiddleware` for a given function.
+    /// Fails: the metering globals are looked up in the module.
     fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
-        Box::new(FunctionMetering {
-            cost_function: self.cost_function.clone(),
-            global_indexes: self.global_indexes.lock().unwrap().clone().unwrap(),
-            accumulated_cost: 0,
-        })
+        FailingMiddleware::without_module_info("Metering")
     }
 
-    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
-    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
-        let mut global_indexes = self.global_indexes.lock().unwrap();
-
-        if global_indexes.is_some() {
-            panic!("Metering::transform_module_info: Attempting to use a `Metering` middleware from multiple modules.");
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        match MeteringGlobalIndexes::from_module_info(module_info, &self.dynamic_costs) {
+            Ok(global_indexes) => Box::new(FunctionMetering {
+                cost_function: self.cost_function.clone(),
+                dynamic_costs: self.dynamic_costs,
+                global_indexes,
+                accumulated_cost: 0,
+            }),
+            Err(error) => FailingMiddleware::new(error),
         }
+    }
 
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
         // Append a global for remaining points and initialize it.
         let remaining_points_global_index = module_info
             .globals
@@ -189,10 +328,29 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
             ExportIndex::Global(points_exhausted_global_index),
         );
 
-        *global_indexes = Some(MeteringGlobalIndexes(
-            remaining_points_global_index,
-            points_exhausted_global_index,
-        ))
+        // Append a global to hold the operand of operators with a dynamic cost.
+        if !self.dynamic_costs.is_zero() {
+            let operand_global_index = module_info
+                .globals
+                .push(GlobalType::new(Type::I64, Mutability::Var));
+
+            module_info
+                .global_initializers
+                .push(GlobalInit::I64Const(0));
+
+            module_info.exports.insert(
+                "wasmer_metering_operand".to_string(),
+                ExportIndex::Global(operand_global_index),
+            );
+        }
+    }
+
+    /// Identifies the limit, the costs and the id of the cost function, if
+    /// it has one.
+    fn fingerprint(&self) -> Option<String> {
+        self.cost_function_id
+            .as_ref()
+            .map(|_| format!("{:?}", self))
     }
 }
 
@@ -200,6 +358,7 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for FunctionMetering<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FunctionMetering")
             .field("cost_function", &"<function>")
+            .field("dynamic_costs", &self.dynamic_costs)
             .field("global_indexes", &self.global_indexes)
             .finish()
     }
@@ -215,6 +374,7 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
         // This needs to be done before the metering logic, to prevent operators like `Call` from escaping metering in some
         // corner cases.
         self.accumulated_cost += (self.cost_function)(&operator);
+        let cost_per_unit = self.dynamic_costs.cost_per_unit(&operator);
 
         // Possible sources and targets of a branch. Finalize the cost of the previous basic block and perform necessary checks.
         match operator {
@@ -228,27 +388,13 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
             | Operator::CallIndirect { .. } // function call - branch source
             | Operator::Return // end of function - branch source
             => {
-                if self.accumulated_cost > 0 {
-                    state.extend(&[
-                        // if unsigned(globals[remaining_points_index]) < unsigned(self.accumulated_cost) { throw(); }
-                        Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
-                        Operator::I64Const { value: self.accumulated_cost as i64 },
-                        Operator::I64LtU,
-                        Operator::If { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
-                        Operator::I32Const { value: 1 },
-                        Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
-                        Operator::Unreachable,
-                        Operator::End,
-
-                        // globals[remaining_points_index] -= self.accumulated_cost;
-                        Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
-                        Operator::I64Const { value: self.accumulated_cost as i64 },
-                        Operator::I64Sub,
-                        Operator::GlobalSet { global_index: self.global_indexes.remaining_points().as_u32() },
-                    ]);
-
-                    self.accumulated_cost = 0;
-                }
+                self.charge_accumulated_cost(state);
+            }
+            _ if cost_per_unit > 0 => {
+                // Operators with a dynamic cost are charged before they run,
+                // together with the cost of the code preceding them.
+                self.charge_accumulated_cost(state);
+                self.charge_operand(cost_per_unit, state);
             }
             _ => {}
         }
@@ -258,6 +404,77 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
     }
 }
 
+impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMetering<F> {
+    /// Emits the charge of the accumulated cost of the current basic block.
+    #[rustfmt::skip]
+    fn charge_accumulated_cost(&mut self, state: &mut MiddlewareReaderState) {
+        if self.accumulated_cost > 0 {
+            state.extend(&[
+                // if unsigned(globals[remaining_points_index]) < unsigned(self.accumulated_cost) { throw(); }
+                Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
+                Operator::I64Const { value: self.accumulated_cost as i64 },
+                Operator::I64LtU,
+                Operator::If { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
+                Operator::I32Const { value: 1 },
+                Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
+            ]);
+            state.push_trap(TrapCode::GasExhausted);
+            state.extend(&[
+                Operator::End,
+
+                // globals[remaining_points_index] -= self.accumulated_cost;
+                Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
+                Operator::I64Const { value: self.accumulated_cost as i64 },
+                Operator::I64Sub,
+                Operator::GlobalSet { global_index: self.global_indexes.remaining_points().as_u32() },
+            ]);
+
+            self.accumulated_cost = 0;
+        }
+    }
+
+    /// Emits the charge of `cost_per_unit` times the i32 operand on top of
+    /// the stack, leaving the operand on the stack.
+    ///
+    /// 64-bit memories are rejected when translating a module, so the
+    /// operands of all the operators with a dynamic cost are i32.
+    #[rustfmt::skip]
+    fn charge_operand(&self, cost_per_unit: u64, state: &mut MiddlewareReaderState) {
+        state.extend(&[
+            // globals[operand_index] = unsigned(pop());
+            Operator::I64ExtendI32U,
+            Operator::GlobalSet { global_index: self.global_indexes.operand().as_u32() },
+
+            // if unsigned(globals[remaining_points_index]) / cost_per_unit < unsigned(globals[operand_index]) { throw(); }
+            // Dividing the points rather than multiplying the operand cannot overflow.
+            Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
+            Operator::I64Const { value: cost_per_unit as i64 },
+            Operator::I64DivU,
+            Operator::GlobalGet { global_index: self.global_indexes.operand().as_u32() },
+            Operator::I64LtU,
+            Operator::If { ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType) },
+            Operator::I32Const { value: 1 },
+            Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
+        ]);
+        state.push_trap(TrapCode::GasExhausted);
+        state.extend(&[
+            Operator::End,
+
+            // globals[remaining_points_index] -= unsigned(globals[operand_index]) * cost_per_unit;
+            Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
+            Operator::GlobalGet { global_index: self.global_indexes.operand().as_u32() },
+            Operator::I64Const { value: cost_per_unit as i64 },
+            Operator::I64Mul,
+            Operator::I64Sub,
+            Operator::GlobalSet { global_index: self.global_indexes.remaining_points().as_u32() },
+
+            // push(globals[operand_index]);
+            Operator::GlobalGet { global_index: self.global_indexes.operand().as_u32() },
+            Operator::I32WrapI64,
+        ]);
+    }
+}
+
 /// Get the remaining points in an [`Instance`][wasmer::Instance].
 ///
 /// Note: This can be used in a headless engine after an ahead-of-time
@@ -348,13 +565,117 @@ pub fn set_remaining_points(ctx: &mut impl AsStoreMut, instance: &Instance, poin
         .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
 }
 
+/// A handle to the metering state of an [`Instance`][wasmer::Instance].
+///
+/// Unlike [`get_remaining_points`] and [`set_remaining_points`], the
+/// handle does not need the instance once created, so it can be stored
+/// in a [`FunctionEnv`][wasmer::FunctionEnv] and used by host functions to
+/// charge for the work they do.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{FunctionEnvMut, RuntimeError};
+/// use wasmer_middlewares::metering::MeteringHandle;
+///
+/// struct Env {
+///     metering: Option<MeteringHandle>,
+/// }
+///
+/// /// A host function that charges 100 points per call.
+/// fn read_storage(mut env: FunctionEnvMut<Env>, key: u64) -> Result<u64, RuntimeError> {
+///     let (env, mut store) = env.data_and_store_mut();
+///     env.metering.as_ref().unwrap().consume(&mut store, 100)?;
+///     Ok(key)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeteringHandle {
+    remaining_points: Global,
+    points_exhausted: Global,
+}
+
+impl MeteringHandle {
+    /// Creates a handle to the metering state of `instance`.
+    ///
+    /// Fails if the instance has not been processed with the [`Metering`]
+    /// middleware at compile time.
+    pub fn new(instance: &Instance) -> Result<Self, ExportError> {
+        Ok(Self {
+            remaining_points: instance
+                .exports
+                .get_global("wasmer_metering_remaining_points")?
+                .clone(),
+            points_exhausted: instance
+                .exports
+                .get_global("wasmer_metering_points_exhausted")?
+                .clone(),
+        })
+    }
+
+    /// Get the remaining points, see [`get_remaining_points`].
+    pub fn get_remaining_points(&self, store: &mut impl AsStoreMut) -> MeteringPoints {
+        let exhausted: i32 = self
+            .points_exhausted
+            .get(store)
+            .try_into()
+            .expect("`wasmer_metering_points_exhausted` has wrong type");
+
+        if exhausted > 0 {
+            return MeteringPoints::Exhausted;
+        }
+
+        let points = self
+            .remaining_points
+            .get(store)
+            .try_into()
+            .expect("`wasmer_metering_remaining_points` has wrong type");
+
+        MeteringPoints::Remaining(points)
+    }
+
+    /// Set the remaining points, see [`set_remaining_points`].
+    pub fn set_remaining_points(&self, store: &mut impl AsStoreMut, points: u64) {
+        self.remaining_points
+            .set(store, points.into())
+            .expect("Can't set `wasmer_metering_remaining_points`");
+        self.points_exhausted
+            .set(store, 0i32.into())
+            .expect("Can't set `wasmer_metering_points_exhausted`");
+    }
+
+    /// Consumes `points` metering points.
+    ///
+    /// When fewer points are left, the points are marked as exhausted and
+    /// a [`TrapCode::GasExhausted`] trap is returned, exactly as when the
+    /// limit is reached by WebAssembly code. Returning this error from a
+    /// host function stops the execution of the instance.
+    pub fn consume(&self, store: &mut impl AsStoreMut, points: u64) -> Result<(), RuntimeError> {
+        match self.get_remaining_points(store) {
+            MeteringPoints::Remaining(remaining) if remaining >= points => {
+                self.remaining_points
+                    .set(store, (remaining - points).into())
+                    .expect("Can't set `wasmer_metering_remaining_points`");
+                Ok(())
+            }
+            _ => {
+                self.points_exhausted
+                    .set(store, 1i32.into())
+                    .expect("Can't set `wasmer_metering_points_exhausted`");
+                Err(RuntimeError::from_trap(Trap::lib(TrapCode::GasExhausted)))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::sync::Arc;
     use wasmer::{
-        imports, wat2wasm, CompilerConfig, Cranelift, EngineBuilder, Module, Store, TypedFunction,
+        imports, wat2wasm, CompilerConfig, Cranelift, EngineBuilder, Function, FunctionEnv,
+        FunctionEnvMut, Module, Store, TypedFunction,
     };
 
     fn cost_function(operator: &Operator) -> u64 {
@@ -422,7 +743,10 @@ mod tests {
         );
 
         // Third call fails due to limit
-        assert!(add_one.call(&mut store, 1).is_err());
+        assert_eq!(
+            add_one.call(&mut store, 1).unwrap_err().to_trap(),
+            Some(TrapCode::GasExhausted)
+        );
         assert_eq!(
             get_remaining_points(&mut store, &instance),
             MeteringPoints::Exhausted
@@ -485,4 +809,243 @@ mod tests {
             MeteringPoints::Remaining(4)
         );
     }
+
+    #[test]
+    fn metering_handle_consume_works() {
+        let metering = Arc::new(Metering::new(10, cost_function));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(
+            &store,
+            wat2wasm(
+                br#"
+                (module
+                (import "env" "charge" (func $charge (param i64)))
+                (func (export "call_charge") (param $points i64)
+                    local.get $points
+                    call $charge))
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let env = FunctionEnv::new(&mut store, None::<MeteringHandle>);
+        fn charge(
+            mut env: FunctionEnvMut<Option<MeteringHandle>>,
+            points: u64,
+        ) -> Result<(), RuntimeError> {
+            let (handle, mut store) = env.data_and_store_mut();
+            handle.as_ref().unwrap().consume(&mut store, points)
+        }
+        let imports = imports! {
+            "env" => {
+                "charge" => Function::new_typed_with_env(&mut store, &env, charge),
+            }
+        };
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        let handle = MeteringHandle::new(&instance).unwrap();
+        *env.as_mut(&mut store) = Some(handle.clone());
+
+        // Calling `call_charge` costs 1 point for `local.get $points`, plus
+        // the points charged by the host function.
+        let call_charge: TypedFunction<u64, ()> = instance
+            .exports
+            .get_typed_function(&store, "call_charge")
+            .unwrap();
+        call_charge.call(&mut store, 7).unwrap();
+        assert_eq!(
+            handle.get_remaining_points(&mut store),
+            MeteringPoints::Remaining(2)
+        );
+
+        let error = call_charge.call(&mut store, 4).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::GasExhausted));
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+
+        handle.set_remaining_points(&mut store, 5);
+        call_charge.call(&mut store, 4).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(0)
+        );
+    }
+
+    #[test]
+    fn dynamic_costs_work() {
+        let metering = Arc::new(Metering::new(100, cost_function).with_dynamic_costs(
+            DynamicCosts {
+                memory_grow_per_page: 10,
+                memory_fill_per_byte: 2,
+                ..Default::default()
+            },
+        ));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(
+            &store,
+            wat2wasm(
+                br#"
+                (module
+                (memory 1)
+                (func (export "fill") (param $len i32)
+                    (memory.fill (i32.const 0) (i32.const 0) (local.get $len)))
+                (func (export "grow") (param $pages i32) (result i32)
+                    (memory.grow (local.get $pages))))
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let fill: TypedFunction<i32, ()> =
+            instance.exports.get_typed_function(&store, "fill").unwrap();
+        let grow: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "grow").unwrap();
+
+        // `fill` costs 3 points for its operands, plus 2 points per byte.
+        fill.call(&mut store, 10).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(77)
+        );
+
+        // `grow` costs 1 point for its operand, plus 10 points per page.
+        assert_eq!(grow.call(&mut store, 2).unwrap(), 1);
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(56)
+        );
+
+        // Large operands do not overflow the cost.
+        assert_eq!(
+            fill.call(&mut store, -1).unwrap_err().to_trap(),
+            Some(TrapCode::GasExhausted)
+        );
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+    }
+
+    #[test]
+    fn metering_can_be_shared_by_modules() {
+        let metering = Arc::new(Metering::new(10, cost_function));
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(metering);
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+
+        // The metering globals of this module come after its own global.
+        let other = Module::new(
+            &store,
+            wat2wasm(
+                br#"
+                (module
+                (global $counter (mut i32) (i32.const 0))
+                (func (export "add_one") (param $value i32) (result i32)
+                    local.get $value
+                    i32.const 1
+                    i32.add))
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let module = Module::new(&store, bytecode()).unwrap();
+
+        for module in [&module, &other] {
+            let instance = Instance::new(&mut store, module, &imports! {}).unwrap();
+            let add_one: TypedFunction<i32, i32> = instance
+                .exports
+                .get_typed_function(&store, "add_one")
+                .unwrap();
+            add_one.call(&mut store, 1).unwrap();
+            assert_eq!(
+                get_remaining_points(&mut store, &instance),
+                MeteringPoints::Remaining(6)
+            );
+        }
+    }
+
+    #[test]
+    fn missing_globals_are_an_error() {
+        let error =
+            MeteringGlobalIndexes::from_module_info(&ModuleInfo::new(), &DynamicCosts::default())
+                .unwrap_err();
+        assert_eq!(
+            error.message,
+            "the module has no `wasmer_metering_remaining_points` global export"
+        );
+    }
+
+    #[test]
+    fn artifacts_record_the_metering_configuration() {
+        type CostFunction = fn(&Operator) -> u64;
+        let engine = |metering: Option<Metering<CostFunction>>| {
+            let mut compiler_config = Cranelift::default();
+            if let Some(metering) = metering {
+                compiler_config.push_middleware(Arc::new(metering));
+            }
+            Store::new(EngineBuilder::new(compiler_config))
+        };
+        let v1 = || Metering::new(10, cost_function as CostFunction).with_cost_function_id("v1");
+        let store = engine(Some(v1()));
+        let module = Module::new(&store, bytecode()).unwrap();
+        assert!(module.middleware_fingerprint().is_some());
+        assert_eq!(
+            module.middleware_fingerprint(),
+            store.engine().middleware_fingerprint()
+        );
+        let serialized = module.serialize().unwrap();
+
+        // Another middleware with the same configuration, as after a restart.
+        let same = engine(Some(v1()));
+        unsafe { Module::deserialize(&same, serialized.clone()) }.unwrap();
+        let headless = Store::new(EngineBuilder::headless());
+        unsafe { Module::deserialize(&headless, serialized.clone()) }.unwrap();
+
+        let others = [
+            engine(None),
+            engine(Some(v1().with_dynamic_costs(DynamicCosts {
+                memory_grow_per_page: 1,
+                ..Default::default()
+            }))),
+            engine(Some(
+                Metering::new(20, cost_function as CostFunction).with_cost_function_id("v1"),
+            )),
+            engine(Some(
+                Metering::new(10, cost_function as CostFunction).with_cost_function_id("v2"),
+            )),
+            engine(Some(Metering::new(10, cost_function as CostFunction))),
+        ];
+        for other in others {
+            assert_ne!(
+                other.engine().middleware_fingerprint(),
+                store.engine().middleware_fingerprint()
+            );
+            assert!(matches!(
+                unsafe { Module::deserialize(&other, serialized.clone()) },
+                Err(wasmer::DeserializeError::Incompatible(_))
+            ));
+        }
+
+        // Without an id, the artifacts have no fingerprint, and only a
+        // headless engine deserializes them.
+        let unnamed = || engine(Some(Metering::new(10, cost_function as CostFunction)));
+        let store = unnamed();
+        assert_eq!(store.engine().middleware_fingerprint(), None);
+        let module = Module::new(&store, bytecode()).unwrap();
+        assert_eq!(module.middleware_fingerprint(), None);
+        let serialized = module.serialize().unwrap();
+        assert!(matches!(
+            unsafe { Module::deserialize(&unnamed(), serialized.clone()) },
+            Err(wasmer::DeserializeError::Incompatible(_))
+        ));
+        unsafe { Module::deserialize(&headless, serialized) }.unwrap();
+    }
 }
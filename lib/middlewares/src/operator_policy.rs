@@ -0,0 +1,551 @@
+//! `operator_policy` is a middleware for rejecting, at compile time,
+//! the modules using operators denied by a policy, for instance the
+//! floating point operators whose results are not fully deterministic.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::CompilerConfig;
+//! use wasmer_middlewares::operator_policy::{OperatorClass, OperatorPolicy};
+//!
+//! fn create_operator_policy_middleware(compiler_config: &mut dyn CompilerConfig) {
+//!     let policy = OperatorPolicy::new()
+//!         .deny(OperatorClass::Float)
+//!         .deny(OperatorClass::Simd)
+//!         .deny(OperatorClass::Atomic)
+//!         .max_memory_pages(256);
+//!     compiler_config.push_middleware(Arc::new(policy));
+//! }
+//! ```
+
+use crate::FailingMiddleware;
+use std::fmt;
+use wasmer::wasmparser::{Operator, Type as WpType};
+use wasmer::{
+    FunctionMiddleware, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware, Pages, Type,
+};
+use wasmer_types::ModuleInfo;
+
+/// A class of operators, which can be denied by an [`OperatorPolicy`].
+///
+/// An operator can belong to several classes, for instance `f32x4.add`
+/// is both a [`Float`][OperatorClass::Float] and a
+/// [`Simd`][OperatorClass::Simd] operator.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum OperatorClass {
+    /// Operators taking or producing floating point values, either
+    /// scalars or vectors, including loads, stores and conversions.
+    ///
+    /// Denying them also rejects the `f32` and `f64` types in function
+    /// signatures, globals and locals.
+    Float,
+    /// 128-bit SIMD operators.
+    ///
+    /// Denying them also rejects the `v128` type in function signatures,
+    /// globals and locals.
+    Simd,
+    /// Atomic memory operators of the threads proposal.
+    Atomic,
+    /// Operators of the bulk memory proposal.
+    BulkMemory,
+    /// Operators of the reference types proposal.
+    ReferenceTypes,
+    /// Operators of the exception handling proposal.
+    Exceptions,
+    /// Operators of the tail call proposal.
+    TailCall,
+    /// The `memory.grow` operator.
+    MemoryGrow,
+}
+
+impl OperatorClass {
+    /// Returns whether `operator` belongs to this class.
+    #[rustfmt::skip]
+    pub fn contains(self, operator: &Operator) -> bool {
+        match self {
+            Self::Float => matches!(
+                operator,
+                Operator::F32Load { .. } | Operator::F64Load { .. } | Operator::F32Store { .. }
+                | Operator::F64Store { .. } | Operator::F32Const { .. } | Operator::F64Const { .. }
+                | Operator::F32Eq | Operator::F32Ne | Operator::F32Lt | Operator::F32Gt
+                | Operator::F32Le | Operator::F32Ge | Operator::F64Eq | Operator::F64Ne
+                | Operator::F64Lt | Operator::F64Gt | Operator::F64Le | Operator::F64Ge
+                | Operator::F32Abs | Operator::F32Neg | Operator::F32Ceil | Operator::F32Floor
+                | Operator::F32Trunc | Operator::F32Nearest | Operator::F32Sqrt | Operator::F32Add
+                | Operator::F32Sub | Operator::F32Mul | Operator::F32Div | Operator::F32Min
+                | Operator::F32Max | Operator::F32Copysign | Operator::F64Abs | Operator::F64Neg
+                | Operator::F64Ceil | Operator::F64Floor | Operator::F64Trunc | Operator::F64Nearest
+                | Operator::F64Sqrt | Operator::F64Add | Operator::F64Sub | Operator::F64Mul
+                | Operator::F64Div | Operator::F64Min | Operator::F64Max | Operator::F64Copysign
+                | Operator::I32TruncF32S | Operator::I32TruncF32U | Operator::I32TruncF64S
+                | Operator::I32TruncF64U | Operator::I64TruncF32S | Operator::I64TruncF32U
+                | Operator::I64TruncF64S | Operator::I64TruncF64U | Operator::F32ConvertI32S
+                | Operator::F32ConvertI32U | Operator::F32ConvertI64S | Operator::F32ConvertI64U
+                | Operator::F32DemoteF64 | Operator::F64ConvertI32S | Operator::F64ConvertI32U
+                | Operator::F64ConvertI64S | Operator::F64ConvertI64U | Operator::F64PromoteF32
+                | Operator::I32ReinterpretF32 | Operator::I64ReinterpretF64
+                | Operator::F32ReinterpretI32 | Operator::F64ReinterpretI64
+                | Operator::I32TruncSatF32S | Operator::I32TruncSatF32U | Operator::I32TruncSatF64S
+                | Operator::I32TruncSatF64U | Operator::I64TruncSatF32S | Operator::I64TruncSatF32U
+                | Operator::I64TruncSatF64S | Operator::I64TruncSatF64U
+                | Operator::F32x4ExtractLane { .. } | Operator::F32x4ReplaceLane { .. }
+                | Operator::F64x2ExtractLane { .. } | Operator::F64x2ReplaceLane { .. }
+                | Operator::F32x4Splat | Operator::F64x2Splat | Operator::F32x4Eq
+                | Operator::F32x4Ne | Operator::F32x4Lt | Operator::F32x4Gt | Operator::F32x4Le
+                | Operator::F32x4Ge | Operator::F64x2Eq | Operator::F64x2Ne | Operator::F64x2Lt
+                | Operator::F64x2Gt | Operator::F64x2Le | Operator::F64x2Ge | Operator::F32x4Ceil
+                | Operator::F32x4Floor | Operator::F32x4Trunc | Operator::F32x4Nearest
+                | Operator::F32x4Abs | Operator::F32x4Neg | Operator::F32x4Sqrt | Operator::F32x4Add
+                | Operator::F32x4Sub | Operator::F32x4Mul | Operator::F32x4Div | Operator::F32x4Min
+                | Operator::F32x4Max | Operator::F32x4PMin | Operator::F32x4PMax
+                | Operator::F64x2Ceil | Operator::F64x2Floor | Operator::F64x2Trunc
+                | Operator::F64x2Nearest | Operator::F64x2Abs | Operator::F64x2Neg
+                | Operator::F64x2Sqrt | Operator::F64x2Add | Operator::F64x2Sub | Operator::F64x2Mul
+                | Operator::F64x2Div | Operator::F64x2Min | Operator::F64x2Max | Operator::F64x2PMin
+                | Operator::F64x2PMax | Operator::I32x4TruncSatF32x4S
+                | Operator::I32x4TruncSatF32x4U | Operator::F32x4ConvertI32x4S
+                | Operator::F32x4ConvertI32x4U | Operator::I32x4TruncSatF64x2SZero
+                | Operator::I32x4TruncSatF64x2UZero | Operator::F64x2ConvertLowI32x4S
+                | Operator::F64x2ConvertLowI32x4U | Operator::F32x4DemoteF64x2Zero
+                | Operator::F64x2PromoteLowF32x4 | Operator::I32x4RelaxedTruncSatF32x4S
+                | Operator::I32x4RelaxedTruncSatF32x4U | Operator::I32x4RelaxedTruncSatF64x2SZero
+                | Operator::I32x4RelaxedTruncSatF64x2UZero | Operator::F32x4Fma | Operator::F32x4Fms
+                | Operator::F64x2Fma | Operator::F64x2Fms | Operator::F32x4RelaxedMin
+                | Operator::F32x4RelaxedMax | Operator::F64x2RelaxedMin | Operator::F64x2RelaxedMax
+            ),
+            Self::Simd => matches!(
+                operator,
+                Operator::V128Load { .. } | Operator::V128Load8x8S { .. }
+                | Operator::V128Load8x8U { .. } | Operator::V128Load16x4S { .. }
+                | Operator::V128Load16x4U { .. } | Operator::V128Load32x2S { .. }
+                | Operator::V128Load32x2U { .. } | Operator::V128Load8Splat { .. }
+                | Operator::V128Load16Splat { .. } | Operator::V128Load32Splat { .. }
+                | Operator::V128Load64Splat { .. } | Operator::V128Load32Zero { .. }
+                | Operator::V128Load64Zero { .. } | Operator::V128Store { .. }
+                | Operator::V128Load8Lane { .. } | Operator::V128Load16Lane { .. }
+                | Operator::V128Load32Lane { .. } | Operator::V128Load64Lane { .. }
+                | Operator::V128Store8Lane { .. } | Operator::V128Store16Lane { .. }
+                | Operator::V128Store32Lane { .. } | Operator::V128Store64Lane { .. }
+                | Operator::V128Const { .. } | Operator::I8x16Shuffle { .. }
+                | Operator::I8x16ExtractLaneS { .. } | Operator::I8x16ExtractLaneU { .. }
+                | Operator::I8x16ReplaceLane { .. } | Operator::I16x8ExtractLaneS { .. }
+                | Operator::I16x8ExtractLaneU { .. } | Operator::I16x8ReplaceLane { .. }
+                | Operator::I32x4ExtractLane { .. } | Operator::I32x4ReplaceLane { .. }
+                | Operator::I64x2ExtractLane { .. } | Operator::I64x2ReplaceLane { .. }
+                | Operator::F32x4ExtractLane { .. } | Operator::F32x4ReplaceLane { .. }
+                | Operator::F64x2ExtractLane { .. } | Operator::F64x2ReplaceLane { .. }
+                | Operator::I8x16Swizzle | Operator::I8x16Splat | Operator::I16x8Splat
+                | Operator::I32x4Splat | Operator::I64x2Splat | Operator::F32x4Splat
+                | Operator::F64x2Splat | Operator::I8x16Eq | Operator::I8x16Ne | Operator::I8x16LtS
+                | Operator::I8x16LtU | Operator::I8x16GtS | Operator::I8x16GtU | Operator::I8x16LeS
+                | Operator::I8x16LeU | Operator::I8x16GeS | Operator::I8x16GeU | Operator::I16x8Eq
+                | Operator::I16x8Ne | Operator::I16x8LtS | Operator::I16x8LtU | Operator::I16x8GtS
+                | Operator::I16x8GtU | Operator::I16x8LeS | Operator::I16x8LeU | Operator::I16x8GeS
+                | Operator::I16x8GeU | Operator::I32x4Eq | Operator::I32x4Ne | Operator::I32x4LtS
+                | Operator::I32x4LtU | Operator::I32x4GtS | Operator::I32x4GtU | Operator::I32x4LeS
+                | Operator::I32x4LeU | Operator::I32x4GeS | Operator::I32x4GeU | Operator::I64x2Eq
+                | Operator::I64x2Ne | Operator::I64x2LtS | Operator::I64x2GtS | Operator::I64x2LeS
+                | Operator::I64x2GeS | Operator::F32x4Eq | Operator::F32x4Ne | Operator::F32x4Lt
+                | Operator::F32x4Gt | Operator::F32x4Le | Operator::F32x4Ge | Operator::F64x2Eq
+                | Operator::F64x2Ne | Operator::F64x2Lt | Operator::F64x2Gt | Operator::F64x2Le
+                | Operator::F64x2Ge | Operator::V128Not | Operator::V128And | Operator::V128AndNot
+                | Operator::V128Or | Operator::V128Xor | Operator::V128Bitselect
+                | Operator::V128AnyTrue | Operator::I8x16Abs | Operator::I8x16Neg
+                | Operator::I8x16Popcnt | Operator::I8x16AllTrue | Operator::I8x16Bitmask
+                | Operator::I8x16NarrowI16x8S | Operator::I8x16NarrowI16x8U | Operator::I8x16Shl
+                | Operator::I8x16ShrS | Operator::I8x16ShrU | Operator::I8x16Add
+                | Operator::I8x16AddSatS | Operator::I8x16AddSatU | Operator::I8x16Sub
+                | Operator::I8x16SubSatS | Operator::I8x16SubSatU | Operator::I8x16MinS
+                | Operator::I8x16MinU | Operator::I8x16MaxS | Operator::I8x16MaxU
+                | Operator::I8x16RoundingAverageU | Operator::I16x8ExtAddPairwiseI8x16S
+                | Operator::I16x8ExtAddPairwiseI8x16U | Operator::I16x8Abs | Operator::I16x8Neg
+                | Operator::I16x8Q15MulrSatS | Operator::I16x8AllTrue | Operator::I16x8Bitmask
+                | Operator::I16x8NarrowI32x4S | Operator::I16x8NarrowI32x4U
+                | Operator::I16x8ExtendLowI8x16S | Operator::I16x8ExtendHighI8x16S
+                | Operator::I16x8ExtendLowI8x16U | Operator::I16x8ExtendHighI8x16U
+                | Operator::I16x8Shl | Operator::I16x8ShrS | Operator::I16x8ShrU
+                | Operator::I16x8Add | Operator::I16x8AddSatS | Operator::I16x8AddSatU
+                | Operator::I16x8Sub | Operator::I16x8SubSatS | Operator::I16x8SubSatU
+                | Operator::I16x8Mul | Operator::I16x8MinS | Operator::I16x8MinU
+                | Operator::I16x8MaxS | Operator::I16x8MaxU | Operator::I16x8RoundingAverageU
+                | Operator::I16x8ExtMulLowI8x16S | Operator::I16x8ExtMulHighI8x16S
+                | Operator::I16x8ExtMulLowI8x16U | Operator::I16x8ExtMulHighI8x16U
+                | Operator::I32x4ExtAddPairwiseI16x8S | Operator::I32x4ExtAddPairwiseI16x8U
+                | Operator::I32x4Abs | Operator::I32x4Neg | Operator::I32x4AllTrue
+                | Operator::I32x4Bitmask | Operator::I32x4ExtendLowI16x8S
+                | Operator::I32x4ExtendHighI16x8S | Operator::I32x4ExtendLowI16x8U
+                | Operator::I32x4ExtendHighI16x8U | Operator::I32x4Shl | Operator::I32x4ShrS
+                | Operator::I32x4ShrU | Operator::I32x4Add | Operator::I32x4Sub | Operator::I32x4Mul
+                | Operator::I32x4MinS | Operator::I32x4MinU | Operator::I32x4MaxS
+                | Operator::I32x4MaxU | Operator::I32x4DotI16x8S | Operator::I32x4ExtMulLowI16x8S
+                | Operator::I32x4ExtMulHighI16x8S | Operator::I32x4ExtMulLowI16x8U
+                | Operator::I32x4ExtMulHighI16x8U | Operator::I64x2Abs | Operator::I64x2Neg
+                | Operator::I64x2AllTrue | Operator::I64x2Bitmask | Operator::I64x2ExtendLowI32x4S
+                | Operator::I64x2ExtendHighI32x4S | Operator::I64x2ExtendLowI32x4U
+                | Operator::I64x2ExtendHighI32x4U | Operator::I64x2Shl | Operator::I64x2ShrS
+                | Operator::I64x2ShrU | Operator::I64x2Add | Operator::I64x2Sub | Operator::I64x2Mul
+                | Operator::I64x2ExtMulLowI32x4S | Operator::I64x2ExtMulHighI32x4S
+                | Operator::I64x2ExtMulLowI32x4U | Operator::I64x2ExtMulHighI32x4U
+                | Operator::F32x4Ceil | Operator::F32x4Floor | Operator::F32x4Trunc
+                | Operator::F32x4Nearest | Operator::F32x4Abs | Operator::F32x4Neg
+                | Operator::F32x4Sqrt | Operator::F32x4Add | Operator::F32x4Sub | Operator::F32x4Mul
+                | Operator::F32x4Div | Operator::F32x4Min | Operator::F32x4Max | Operator::F32x4PMin
+                | Operator::F32x4PMax | Operator::F64x2Ceil | Operator::F64x2Floor
+                | Operator::F64x2Trunc | Operator::F64x2Nearest | Operator::F64x2Abs
+                | Operator::F64x2Neg | Operator::F64x2Sqrt | Operator::F64x2Add | Operator::F64x2Sub
+                | Operator::F64x2Mul | Operator::F64x2Div | Operator::F64x2Min | Operator::F64x2Max
+                | Operator::F64x2PMin | Operator::F64x2PMax | Operator::I32x4TruncSatF32x4S
+                | Operator::I32x4TruncSatF32x4U | Operator::F32x4ConvertI32x4S
+                | Operator::F32x4ConvertI32x4U | Operator::I32x4TruncSatF64x2SZero
+                | Operator::I32x4TruncSatF64x2UZero | Operator::F64x2ConvertLowI32x4S
+                | Operator::F64x2ConvertLowI32x4U | Operator::F32x4DemoteF64x2Zero
+                | Operator::F64x2PromoteLowF32x4 | Operator::I8x16RelaxedSwizzle
+                | Operator::I32x4RelaxedTruncSatF32x4S | Operator::I32x4RelaxedTruncSatF32x4U
+                | Operator::I32x4RelaxedTruncSatF64x2SZero
+                | Operator::I32x4RelaxedTruncSatF64x2UZero | Operator::F32x4Fma | Operator::F32x4Fms
+                | Operator::F64x2Fma | Operator::F64x2Fms | Operator::I8x16LaneSelect
+                | Operator::I16x8LaneSelect | Operator::I32x4LaneSelect | Operator::I64x2LaneSelect
+                | Operator::F32x4RelaxedMin | Operator::F32x4RelaxedMax | Operator::F64x2RelaxedMin
+                | Operator::F64x2RelaxedMax
+            ),
+            Self::Atomic => matches!(
+                operator,
+                Operator::MemoryAtomicNotify { .. } | Operator::MemoryAtomicWait32 { .. }
+                | Operator::MemoryAtomicWait64 { .. } | Operator::AtomicFence { .. }
+                | Operator::I32AtomicLoad { .. } | Operator::I64AtomicLoad { .. }
+                | Operator::I32AtomicLoad8U { .. } | Operator::I32AtomicLoad16U { .. }
+                | Operator::I64AtomicLoad8U { .. } | Operator::I64AtomicLoad16U { .. }
+                | Operator::I64AtomicLoad32U { .. } | Operator::I32AtomicStore { .. }
+                | Operator::I64AtomicStore { .. } | Operator::I32AtomicStore8 { .. }
+                | Operator::I32AtomicStore16 { .. } | Operator::I64AtomicStore8 { .. }
+                | Operator::I64AtomicStore16 { .. } | Operator::I64AtomicStore32 { .. }
+                | Operator::I32AtomicRmwAdd { .. } | Operator::I64AtomicRmwAdd { .. }
+                | Operator::I32AtomicRmw8AddU { .. } | Operator::I32AtomicRmw16AddU { .. }
+                | Operator::I64AtomicRmw8AddU { .. } | Operator::I64AtomicRmw16AddU { .. }
+                | Operator::I64AtomicRmw32AddU { .. } | Operator::I32AtomicRmwSub { .. }
+                | Operator::I64AtomicRmwSub { .. } | Operator::I32AtomicRmw8SubU { .. }
+                | Operator::I32AtomicRmw16SubU { .. } | Operator::I64AtomicRmw8SubU { .. }
+                | Operator::I64AtomicRmw16SubU { .. } | Operator::I64AtomicRmw32SubU { .. }
+                | Operator::I32AtomicRmwAnd { .. } | Operator::I64AtomicRmwAnd { .. }
+                | Operator::I32AtomicRmw8AndU { .. } | Operator::I32AtomicRmw16AndU { .. }
+                | Operator::I64AtomicRmw8AndU { .. } | Operator::I64AtomicRmw16AndU { .. }
+                | Operator::I64AtomicRmw32AndU { .. } | Operator::I32AtomicRmwOr { .. }
+                | Operator::I64AtomicRmwOr { .. } | Operator::I32AtomicRmw8OrU { .. }
+                | Operator::I32AtomicRmw16OrU { .. } | Operator::I64AtomicRmw8OrU { .. }
+                | Operator::I64AtomicRmw16OrU { .. } | Operator::I64AtomicRmw32OrU { .. }
+                | Operator::I32AtomicRmwXor { .. } | Operator::I64AtomicRmwXor { .. }
+                | Operator::I32AtomicRmw8XorU { .. } | Operator::I32AtomicRmw16XorU { .. }
+                | Operator::I64AtomicRmw8XorU { .. } | Operator::I64AtomicRmw16XorU { .. }
+                | Operator::I64AtomicRmw32XorU { .. } | Operator::I32AtomicRmwXchg { .. }
+                | Operator::I64AtomicRmwXchg { .. } | Operator::I32AtomicRmw8XchgU { .. }
+                | Operator::I32AtomicRmw16XchgU { .. } | Operator::I64AtomicRmw8XchgU { .. }
+                | Operator::I64AtomicRmw16XchgU { .. } | Operator::I64AtomicRmw32XchgU { .. }
+                | Operator::I32AtomicRmwCmpxchg { .. } | Operator::I64AtomicRmwCmpxchg { .. }
+                | Operator::I32AtomicRmw8CmpxchgU { .. } | Operator::I32AtomicRmw16CmpxchgU { .. }
+                | Operator::I64AtomicRmw8CmpxchgU { .. } | Operator::I64AtomicRmw16CmpxchgU { .. }
+                | Operator::I64AtomicRmw32CmpxchgU { .. }
+            ),
+            Self::BulkMemory => matches!(
+                operator,
+                Operator::MemoryInit { .. } | Operator::DataDrop { .. }
+                | Operator::MemoryCopy { .. } | Operator::MemoryFill { .. }
+                | Operator::TableInit { .. } | Operator::ElemDrop { .. }
+                | Operator::TableCopy { .. }
+            ),
+            Self::ReferenceTypes => matches!(
+                operator,
+                Operator::RefNull { .. } | Operator::RefIsNull | Operator::RefFunc { .. }
+                | Operator::TypedSelect { .. } | Operator::TableFill { .. }
+                | Operator::TableGet { .. } | Operator::TableSet { .. } | Operator::TableGrow { .. }
+                | Operator::TableSize { .. }
+            ),
+            Self::Exceptions => matches!(
+                operator,
+                Operator::Try { .. } | Operator::Catch { .. } | Operator::Throw { .. }
+                | Operator::Rethrow { .. } | Operator::Delegate { .. } | Operator::CatchAll
+            ),
+            Self::TailCall => matches!(
+                operator,
+                Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. }
+            ),
+            Self::MemoryGrow => matches!(operator, Operator::MemoryGrow { .. }),
+        }
+    }
+
+    /// Returns whether values of type `ty` are denied with this class.
+    fn denies_type(self, ty: Type) -> bool {
+        match self {
+            Self::Float => matches!(ty, Type::F32 | Type::F64),
+            Self::Simd => ty == Type::V128,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for OperatorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Float => "floating point",
+            Self::Simd => "SIMD",
+            Self::Atomic => "atomic",
+            Self::BulkMemory => "bulk memory",
+            Self::ReferenceTypes => "reference types",
+            Self::Exceptions => "exception handling",
+            Self::TailCall => "tail call",
+            Self::MemoryGrow => "memory.grow",
+        })
+    }
+}
+
+/// The module-level operator policy middleware.
+///
+/// Compiling a module using a denied operator fails with a
+/// [`MiddlewareError`]. By default, every operator is allowed.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorPolicy {
+    /// Classes of operators rejected by the policy.
+    denied: Vec<OperatorClass>,
+
+    /// Maximum number of pages a memory can be grown to.
+    max_memory_pages: Option<Pages>,
+}
+
+/// The function-level operator policy middleware.
+#[derive(Debug)]
+pub struct FunctionOperatorPolicy {
+    /// Classes of operators rejected by the policy.
+    denied: Vec<OperatorClass>,
+
+    /// Maximum number of pages a memory can be grown to.
+    max_memory_pages: Option<Pages>,
+
+    /// Maximum number of pages of each memory of the module.
+    memory_maximums: Vec<Option<Pages>>,
+
+    /// Index of the function, for error messages.
+    local_function_index: LocalFunctionIndex,
+}
+
+impl OperatorPolicy {
+    /// Creates an `OperatorPolicy` allowing every operator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Denies the operators of `class`.
+    pub fn deny(mut self, class: OperatorClass) -> Self {
+        if !self.denied.contains(&class) {
+            self.denied.push(class);
+        }
+        self
+    }
+
+    /// Only allows `memory.grow` on memories whose declared maximum is at
+    /// most `pages`. Memories without a maximum cannot be grown.
+    ///
+    /// This does not limit the initial size of memories.
+    pub fn max_memory_pages(mut self, pages: impl Into<Pages>) -> Self {
+        self.max_memory_pages = Some(pages.into());
+        self
+    }
+}
+
+/// Fails if values of type `ty`, found in `place`, are denied.
+fn check_type(
+    denied: &[OperatorClass],
+    ty: Type,
+    place: impl FnOnce() -> String,
+) -> Result<(), MiddlewareError> {
+    match denied.iter().find(|class| class.denies_type(ty)) {
+        Some(class) => Err(MiddlewareError::new(
+            "OperatorPolicy",
+            format!(
+                "{} values are not allowed, found {} in {}",
+                class,
+                ty,
+                place()
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+impl ModuleMiddleware for OperatorPolicy {
+    /// Fails: the policy needs the memories of the module.
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        FailingMiddleware::without_module_info("OperatorPolicy")
+    }
+
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionOperatorPolicy {
+            denied: self.denied.clone(),
+            max_memory_pages: self.max_memory_pages,
+            memory_maximums: module_info
+                .memories
+                .values()
+                .map(|memory| memory.maximum)
+                .collect(),
+            local_function_index,
+        })
+    }
+
+    /// Rejects the denied types in the signatures and globals of the module.
+    fn check_module_info(&self, module_info: &ModuleInfo) -> Result<(), MiddlewareError> {
+        for (index, signature) in module_info.signatures.iter() {
+            for ty in signature.params().iter().chain(signature.results()) {
+                check_type(&self.denied, *ty, || {
+                    format!("signature {}", index.as_u32())
+                })?;
+            }
+        }
+        for (index, global) in module_info.globals.iter() {
+            check_type(&self.denied, global.ty, || {
+                format!("global {}", index.as_u32())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl FunctionMiddleware for FunctionOperatorPolicy {
+    fn feed_local(&mut self, _: u32, ty: WpType) -> Result<(), MiddlewareError> {
+        let ty = match ty {
+            WpType::F32 => Type::F32,
+            WpType::F64 => Type::F64,
+            WpType::V128 => Type::V128,
+            _ => return Ok(()),
+        };
+        check_type(&self.denied, ty, || {
+            format!("a local of function {}", self.local_function_index.as_u32())
+        })
+    }
+
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if let Some(class) = self.denied.iter().find(|class| class.contains(&operator)) {
+            return Err(MiddlewareError::new(
+                "OperatorPolicy",
+                format!(
+                    "{} operators are not allowed, found {:?} in function {}",
+                    class,
+                    operator,
+                    self.local_function_index.as_u32()
+                ),
+            ));
+        }
+
+        if let (Operator::MemoryGrow { mem, .. }, Some(max_memory_pages)) =
+            (&operator, self.max_memory_pages)
+        {
+            let maximum = self.memory_maximums.get(*mem as usize).copied().flatten();
+            if maximum.map_or(true, |maximum| maximum > max_memory_pages) {
+                return Err(MiddlewareError::new(
+                    "OperatorPolicy",
+                    format!(
+                        "memory {} can grow past {} pages, found memory.grow in function {}",
+                        mem,
+                        max_memory_pages.0,
+                        self.local_function_index.as_u32()
+                    ),
+                ));
+            }
+        }
+
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{
+        wat2wasm, CompileError, CompilerConfig, Cranelift, EngineBuilder, Module, Store, WasmError,
+    };
+
+    fn compile(policy: OperatorPolicy, wat: &str) -> Result<Module, CompileError> {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(policy));
+        let store = Store::new(EngineBuilder::new(compiler_config));
+        Module::new(&store, wat2wasm(wat.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn denied_operators_are_rejected() {
+        let wat = r#"
+            (module
+            (func (export "round") (param i32) (result i32)
+                local.get 0
+                f32.convert_i32_s
+                i32.trunc_f32_s))
+            "#;
+        assert!(compile(OperatorPolicy::new(), wat).is_ok());
+        assert!(compile(OperatorPolicy::new().deny(OperatorClass::Atomic), wat).is_ok());
+
+        match compile(OperatorPolicy::new().deny(OperatorClass::Float), wat) {
+            Err(CompileError::Wasm(WasmError::Middleware(error))) => {
+                assert_eq!(error.name, "OperatorPolicy");
+                assert!(error
+                    .message
+                    .starts_with("floating point operators are not allowed"));
+            }
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn denied_types_are_rejected() {
+        let policy = OperatorPolicy::new().deny(OperatorClass::Float);
+        let error_message = |wat| match compile(policy.clone(), wat) {
+            Err(CompileError::Wasm(WasmError::Middleware(error))) => error.message,
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        };
+
+        assert_eq!(
+            error_message(r#"(module (func (param f32)))"#),
+            "floating point values are not allowed, found F32 in signature 0"
+        );
+        assert_eq!(
+            error_message(r#"(module (global f64 (f64.const 0)))"#),
+            "floating point values are not allowed, found F64 in global 0"
+        );
+        assert_eq!(
+            error_message(r#"(module (func (local f32)))"#),
+            "floating point values are not allowed, found F32 in a local of function 0"
+        );
+        assert!(compile(policy, r#"(module (func (param i32) (local i64)))"#).is_ok());
+    }
+
+    #[test]
+    fn memory_grow_is_capped() {
+        let wat = |maximum: &str| {
+            format!(
+                r#"
+                (module
+                (memory 1 {})
+                (func (export "grow") (param i32) (result i32)
+                    local.get 0
+                    memory.grow))
+                "#,
+                maximum
+            )
+        };
+        let policy = OperatorPolicy::new().max_memory_pages(16);
+        assert!(compile(policy.clone(), &wat("16")).is_ok());
+        assert!(compile(policy.clone(), &wat("17")).is_err());
+        assert!(compile(policy.clone(), &wat("")).is_err());
+        assert!(compile(policy.deny(OperatorClass::MemoryGrow), &wat("16")).is_err());
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
 };
 use rkyv::{
     archived_value, de::deserializers::SharedDeserializeMap, ser::serializers::AllocSerializer,
-    ser::Serializer as RkyvSerializer, Archive, Deserialize as RkyvDeserialize,
+    ser::Serializer as RkyvSerializer, Archive, CheckBytes, Deserialize as RkyvDeserialize,
     Serialize as RkyvSerialize,
 };
 #[cfg(feature = "enable-serde")]
@@ -14,10 +14,21 @@ use serde::{Deserialize, Serialize};
 
 /// The kinds of wasmer_types objects that might be found in a native object file.
 #[derive(
-    RkyvSerialize, RkyvDeserialize, Archive, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug,
+    RkyvSerialize,
+    RkyvDeserialize,
+    Archive,
+    CheckBytes,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Debug,
 )]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 #[archive(as = "Self")]
+#[repr(u8)]
 pub enum Symbol {
     /// A metadata section, indexed by a unique prefix
     /// (usually the wasm file SHA256 hash)
@@ -50,6 +61,7 @@ pub trait SymbolRegistry: Send + Sync {
 /// Serializable struct that represents the compiled metadata.
 #[derive(Debug, RkyvSerialize, RkyvDeserialize, Archive)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(check_bytes)]
 pub struct ModuleMetadata {
     /// Compile info
     pub compile_info: CompileModuleInfo,
@@ -61,6 +73,9 @@ pub struct ModuleMetadata {
     pub function_body_lengths: PrimaryMap<LocalFunctionIndex, u64>,
     /// CPU features used (See [`CpuFeature`])
     pub cpu_features: u64,
+    /// Fingerprint of the middleware chain applied by the compiler, if it
+    /// has one
+    pub middleware_fingerprint: Option<[u8; 32]>,
 }
 
 /// A simple metadata registry
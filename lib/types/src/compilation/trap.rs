@@ -1,13 +1,13 @@
 //! Types for traps.
 use crate::CodeOffset;
 use crate::TrapCode;
-use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+use rkyv::{Archive, CheckBytes, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
 
 /// Information about trap.
 #[cfg_attr(feature = "enable-serde", derive(Deserialize, Serialize))]
-#[derive(RkyvSerialize, RkyvDeserialize, Archive, Clone, Debug, PartialEq, Eq)]
+#[derive(RkyvSerialize, RkyvDeserialize, Archive, CheckBytes, Clone, Debug, PartialEq, Eq)]
 #[archive(as = "Self")]
 pub struct TrapInformation {
     /// The offset of the trapping instruction in native code. It is relative to the beginning of the function.
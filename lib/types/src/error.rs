@@ -1,5 +1,5 @@
 //! The WebAssembly possible errors
-use crate::{ExternType, Pages};
+use crate::{ExternType, Pages, TrapCode};
 use std::io;
 use thiserror::Error;
 
@@ -43,6 +43,47 @@ pub enum DeserializeError {
         /// How many bytes the artifact contained
         got: usize,
     },
+    /// A checked artifact failed its integrity checks
+    #[error("integrity check failed: {0}")]
+    Integrity(#[from] IntegrityError),
+}
+
+/// The checks of a checked artifact which can fail before it is
+/// deserialized.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityError {
+    /// The checksum of the artifact doesn't match its content.
+    #[error("the checksum does not match the artifact")]
+    ChecksumMismatch,
+    /// The signature of the artifact doesn't match its content and the key.
+    #[error("the signature does not match the artifact")]
+    InvalidSignature,
+    /// A key was given, but the artifact isn't signed.
+    #[error("the artifact is not signed")]
+    Unsigned,
+    /// The artifact is signed, but no key was given to verify it.
+    #[error("the artifact is signed, but no key was given to verify it")]
+    KeyRequired,
+    /// The artifact was produced by another engine.
+    #[error("the artifact was produced by the engine {found:?}, expected {expected:?}")]
+    EngineMismatch {
+        /// The deterministic id of the engine.
+        expected: String,
+        /// The deterministic id recorded in the artifact.
+        found: String,
+    },
+    /// The artifact was compiled for another target.
+    #[error("the artifact was compiled for {found}, expected {expected}")]
+    TargetMismatch {
+        /// The target triple of the engine.
+        expected: String,
+        /// The target triple recorded in the artifact.
+        found: String,
+    },
+    /// The artifact needs CPU features the target of the engine doesn't
+    /// have.
+    #[error("the artifact needs the CPU features {0}, which the target does not have")]
+    MissingCpuFeatures(String),
 }
 
 /// Error type describing things that can go wrong when operating on Wasm Memories.
@@ -82,6 +123,14 @@ pub enum MemoryError {
         /// The number of pages requested as the maximum amount of memory.
         max_allowed: Pages,
     },
+    /// The resource limiter of the store refused the operation, and asked
+    /// for a trap with the given code.
+    #[error("The resource limiter of the store raised a trap: {0}")]
+    Trap(TrapCode),
+    /// The memory does not implement the requested operation, such as
+    /// checkpoints or dirty page tracking.
+    #[error("The memory does not support {0}")]
+    Unsupported(String),
     /// A user defined error value, used for error cases not listed above.
     #[error("A user-defined error occurred: {0}")]
     Generic(String),
@@ -118,6 +167,48 @@ pub enum PreInstantiationError {
     CpuFeature(String),
 }
 
+/// An error while capturing or restoring a
+/// [`StoreSnapshot`](crate::StoreSnapshot).
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    /// A non-null `externref` was found. Host objects are opaque and
+    /// can't be captured in a snapshot.
+    #[error("externref values can not be captured in a snapshot")]
+    UnsupportedExternRef,
+
+    /// A `funcref` points to a function that doesn't belong to the
+    /// captured objects.
+    #[error("funcref points to a function outside of the snapshot scope")]
+    UnknownFuncRef,
+
+    /// The snapshot doesn't match the objects it is restored into.
+    #[error("snapshot does not match the target: {0}")]
+    Mismatch(String),
+
+    /// A memory could not be resized to the size recorded in the snapshot.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+}
+
+/// An error while removing an object from a store.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RemoveError {
+    /// The object doesn't belong to the store, or was already removed
+    /// from it.
+    #[error("the object does not belong to the store or was already removed")]
+    NotFound,
+
+    /// WebAssembly code of the store is running, and may be using the
+    /// object.
+    #[error("objects can not be removed while WebAssembly code of the store is running")]
+    Running,
+
+    /// The object is still referenced by another object of the store,
+    /// described by the string.
+    #[error("the object is still referenced by {0}")]
+    InUse(String),
+}
+
 use crate::lib::std::string::String;
 
 // Compilation Errors
@@ -62,6 +62,7 @@ mod libcalls;
 mod memory;
 mod module;
 mod serialize;
+mod snapshot;
 mod store_id;
 mod table;
 mod trapcode;
@@ -75,10 +76,16 @@ pub use crate::compilation::target::{
     Aarch64Architecture, Architecture, BinaryFormat, CallingConvention, CpuFeature, Endianness,
     Environment, OperatingSystem, PointerWidth, Target, Triple, Vendor,
 };
-pub use crate::serialize::{MetadataHeader, SerializableCompilation, SerializableModule};
+pub use crate::serialize::{
+    CodeImage, CodeRange, MetadataHeader, SerializableCompilation, SerializableModule,
+};
+pub use crate::snapshot::{
+    GlobalSnapshot, MemorySnapshot, ReferenceSnapshot, StoreSnapshot, TableSnapshot,
+};
 pub use error::{
-    CompileError, DeserializeError, ImportError, MemoryError, MiddlewareError,
-    ParseCpuFeatureError, PreInstantiationError, SerializeError, WasmError, WasmResult,
+    CompileError, DeserializeError, ImportError, IntegrityError, MemoryError, MiddlewareError,
+    ParseCpuFeatureError, PreInstantiationError, RemoveError, SerializeError, SnapshotError,
+    WasmError, WasmResult,
 };
 
 /// The entity module, with common helpers for Rust structures
@@ -1,5 +1,5 @@
 use enum_iterator::IntoEnumIterator;
-use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+use rkyv::{Archive, CheckBytes, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -18,9 +18,11 @@ use std::fmt;
     RkyvSerialize,
     RkyvDeserialize,
     Archive,
+    CheckBytes,
 )]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 #[archive(as = "Self")]
+#[repr(u8)]
 pub enum LibCall {
     /// ceil.f32
     CeilF32,
@@ -133,6 +135,9 @@ pub enum LibCall {
 
     /// memory.atomic.botify for imported memories
     ImportedMemory32AtomicNotify,
+
+    /// The epoch deadline of the store was reached
+    EpochDeadlineReached,
 }
 
 impl LibCall {
@@ -181,6 +186,7 @@ impl LibCall {
             Self::ImportedMemory32AtomicWait64 => "wasmer_vm_imported_memory32_atomic_wait64",
             Self::Memory32AtomicNotify => "wasmer_vm_memory32_atomic_notify",
             Self::ImportedMemory32AtomicNotify => "wasmer_vm_imported_memory32_atomic_notify",
+            Self::EpochDeadlineReached => "wasmer_vm_epoch_deadline_reached",
         }
     }
 }
@@ -26,6 +26,7 @@ use std::iter::ExactSizeIterator;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive(check_bytes)]
 pub struct ModuleId {
     id: usize,
 }
@@ -48,6 +49,8 @@ impl Default for ModuleId {
 /// Hash key of an import
 #[derive(Debug, Hash, Eq, PartialEq, Clone, Default, RkyvSerialize, RkyvDeserialize, Archive)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(check_bytes)]
+#[archive_attr(derive(Hash, Eq, PartialEq))]
 pub struct ImportKey {
     /// Module name
     pub module: String,
@@ -173,10 +176,22 @@ pub struct ModuleInfo {
 
     /// Number of imported globals in the module.
     pub num_imported_globals: usize,
+
+    /// Whether the compiled code of the module checks the interrupt flag of
+    /// its store, as set by the engine compiling it. The `VMContext` of its
+    /// instances only holds a pointer to the flag when it does.
+    pub interrupt_checks: bool,
+
+    /// Whether the compiled code of the module checks the epoch deadline of
+    /// its store, as set by the engine compiling it. The `VMContext` of its
+    /// instances only holds the pointers to the epoch and to the deadline
+    /// when it does.
+    pub epoch_interruption: bool,
 }
 
 /// Mirror version of ModuleInfo that can derive rkyv traits
 #[derive(RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive(check_bytes)]
 pub struct ArchivableModuleInfo {
     name: Option<String>,
     imports: IndexMap<ImportKey, ImportIndex>,
@@ -198,6 +213,8 @@ pub struct ArchivableModuleInfo {
     num_imported_tables: usize,
     num_imported_memories: usize,
     num_imported_globals: usize,
+    interrupt_checks: bool,
+    epoch_interruption: bool,
 }
 
 impl From<ModuleInfo> for ArchivableModuleInfo {
@@ -223,6 +240,8 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            interrupt_checks: it.interrupt_checks,
+            epoch_interruption: it.epoch_interruption,
         }
     }
 }
@@ -251,6 +270,8 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            interrupt_checks: it.interrupt_checks,
+            epoch_interruption: it.epoch_interruption,
         }
     }
 }
@@ -311,6 +332,8 @@ impl PartialEq for ModuleInfo {
             && self.num_imported_tables == other.num_imported_tables
             && self.num_imported_memories == other.num_imported_memories
             && self.num_imported_globals == other.num_imported_globals
+            && self.interrupt_checks == other.interrupt_checks
+            && self.epoch_interruption == other.epoch_interruption
     }
 }
 
@@ -2,21 +2,85 @@ use crate::entity::PrimaryMap;
 use crate::{
     compilation::target::CpuFeature, CompileModuleInfo, CompiledFunctionFrameInfo, CustomSection,
     DeserializeError, Dwarf, Features, FunctionBody, FunctionIndex, LocalFunctionIndex,
-    MemoryIndex, MemoryStyle, ModuleInfo, OwnedDataInitializer, Relocation, SectionIndex,
-    SerializeError, SignatureIndex, TableIndex, TableStyle,
+    MemoryIndex, MemoryStyle, ModuleInfo, OwnedDataInitializer, Relocation, SectionBody,
+    SectionIndex, SerializeError, SignatureIndex, TableIndex, TableStyle,
 };
 use enumset::EnumSet;
 use rkyv::{
-    archived_value, de::deserializers::SharedDeserializeMap, ser::serializers::AllocSerializer,
-    ser::Serializer as RkyvSerializer, Archive, Deserialize as RkyvDeserialize,
-    Serialize as RkyvSerialize,
+    archived_value, check_archived_value, de::deserializers::SharedDeserializeMap,
+    ser::serializers::AllocSerializer, ser::Serializer as RkyvSerializer, Archive, CheckBytes,
+    Deserialize as RkyvDeserialize, Serialize as RkyvSerialize,
 };
 use std::convert::TryInto;
 use std::mem;
 
+/// A range of bytes in a [`CodeImage`].
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Archive, CheckBytes, RkyvDeserialize, RkyvSerialize,
+)]
+#[archive(as = "Self")]
+pub struct CodeRange {
+    /// Offset of the first byte of the range in the image.
+    pub offset: u64,
+    /// Length of the range.
+    pub len: u64,
+}
+
+/// Describes the code image which follows the metadata of a serialized
+/// module.
+///
+/// The bytes of the function bodies and custom sections of a serialized
+/// module are not part of its metadata, but of an image of the code
+/// memory: they are laid out as they are once loaded, starting with the
+/// executable code and followed by the data on separate pages. The image
+/// starts at the first offset after the metadata which is a multiple of
+/// `page_size`, so it can be mapped from a file without being copied.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Archive, RkyvDeserialize, RkyvSerialize)]
+#[archive(check_bytes)]
+pub struct CodeImage {
+    /// The size of the pages the image was laid out for, or `0` if there
+    /// is no image.
+    pub page_size: u64,
+    /// The length of the image, a multiple of `page_size`.
+    pub len: u64,
+    /// The length of the executable part of the image, at its start.
+    pub executable_len: u64,
+    /// The bodies of the local functions, followed by the bodies of the
+    /// function call trampolines and the dynamic function trampolines.
+    pub functions: Vec<CodeRange>,
+    /// The bytes of the custom sections.
+    pub custom_sections: Vec<CodeRange>,
+}
+
+impl CodeImage {
+    /// Returns the offset of the image in a serialized module whose image
+    /// follows `metadata_end` bytes.
+    pub fn offset(&self, metadata_end: usize) -> usize {
+        match self.page_size as usize {
+            0 => metadata_end,
+            page_size => match metadata_end % page_size {
+                0 => metadata_end,
+                rem => metadata_end.saturating_add(page_size - rem),
+            },
+        }
+    }
+
+    /// Returns the bytes of `range` in `image`.
+    fn get<'a>(image: &'a [u8], range: &CodeRange) -> Result<&'a [u8], DeserializeError> {
+        let start = range.offset as usize;
+        start
+            .checked_add(range.len as usize)
+            .and_then(|end| image.get(start..end))
+            .ok_or_else(|| {
+                DeserializeError::CorruptedBinary("code out of the bounds of the image".to_string())
+            })
+    }
+}
+
 /// The compilation related data for a serialized modules
 #[derive(Archive, Default, RkyvDeserialize, RkyvSerialize)]
 #[allow(missing_docs)]
+#[archive(check_bytes)]
 pub struct SerializableCompilation {
     pub function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBody>,
     pub function_relocations: PrimaryMap<LocalFunctionIndex, Vec<Relocation>>,
@@ -31,6 +95,9 @@ pub struct SerializableCompilation {
     pub libcall_trampolines: SectionIndex,
     // Length of each libcall trampoline.
     pub libcall_trampoline_len: u32,
+    // The layout of the code image, whose bytes are left out of the bodies
+    // of the functions and custom sections.
+    pub code_image: CodeImage,
 }
 
 impl SerializableCompilation {
@@ -46,11 +113,81 @@ impl SerializableCompilation {
         serialized_data.extend_from_slice(&pos.to_le_bytes());
         Ok(serialized_data.to_vec())
     }
+
+    /// Returns a copy of this compilation whose function bodies and custom
+    /// sections are left empty, to be serialized along with `code_image`.
+    pub fn without_code(&self, code_image: CodeImage) -> Self {
+        let strip = |function: &FunctionBody| FunctionBody {
+            body: Vec::new(),
+            unwind_info: function.unwind_info.clone(),
+        };
+        Self {
+            function_bodies: self.function_bodies.values().map(strip).collect(),
+            function_relocations: self.function_relocations.clone(),
+            function_frame_info: self.function_frame_info.clone(),
+            function_call_trampolines: self.function_call_trampolines.values().map(strip).collect(),
+            dynamic_function_trampolines: self
+                .dynamic_function_trampolines
+                .values()
+                .map(strip)
+                .collect(),
+            custom_sections: self
+                .custom_sections
+                .values()
+                .map(|section| CustomSection {
+                    protection: section.protection.clone(),
+                    bytes: SectionBody::default(),
+                    relocations: section.relocations.clone(),
+                })
+                .collect(),
+            custom_section_relocations: self.custom_section_relocations.clone(),
+            debug: self.debug.clone(),
+            libcall_trampolines: self.libcall_trampolines,
+            libcall_trampoline_len: self.libcall_trampoline_len,
+            code_image,
+        }
+    }
+
+    /// Copies the bytes of the function bodies and custom sections from
+    /// `image`, the code image described by `code_image`.
+    pub fn load_code(&mut self, image: &[u8]) -> Result<(), DeserializeError> {
+        let code_image = &self.code_image;
+        if code_image.page_size == 0 {
+            return Ok(());
+        }
+        let function_count = self.function_bodies.len()
+            + self.function_call_trampolines.len()
+            + self.dynamic_function_trampolines.len();
+        if code_image.functions.len() != function_count
+            || code_image.custom_sections.len() != self.custom_sections.len()
+        {
+            return Err(DeserializeError::CorruptedBinary(
+                "the code image doesn't match the module".to_string(),
+            ));
+        }
+        let functions = self
+            .function_bodies
+            .values_mut()
+            .chain(self.function_call_trampolines.values_mut())
+            .chain(self.dynamic_function_trampolines.values_mut());
+        for (function, range) in functions.zip(&code_image.functions) {
+            function.body = CodeImage::get(image, range)?.to_vec();
+        }
+        for (section, range) in self
+            .custom_sections
+            .values_mut()
+            .zip(&code_image.custom_sections)
+        {
+            section.bytes = SectionBody::new_with_vec(CodeImage::get(image, range)?.to_vec());
+        }
+        Ok(())
+    }
 }
 
 /// Serializable struct that is able to serialize from and to a `ArtifactInfo`.
 #[derive(Archive, RkyvDeserialize, RkyvSerialize)]
 #[allow(missing_docs)]
+#[archive(check_bytes)]
 pub struct SerializableModule {
     /// The main serializable compilation object
     pub compilation: SerializableCompilation,
@@ -60,6 +197,9 @@ pub struct SerializableModule {
     pub data_initializers: Box<[OwnedDataInitializer]>,
     /// CPU Feature flags for this compilation
     pub cpu_features: u64,
+    /// Fingerprint of the middleware chain applied by the compiler, if it
+    /// has one
+    pub middleware_fingerprint: Option<[u8; 32]>,
 }
 
 fn to_serialize_error(err: impl std::error::Error) -> SerializeError {
@@ -80,6 +220,20 @@ impl SerializableModule {
         Ok(serialized_data.to_vec())
     }
 
+    /// Serialize a Module into bytes, like [`SerializableModule::serialize`],
+    /// but leaving out the bytes of its function bodies and custom sections,
+    /// which are stored in the code image described by `code_image`.
+    pub fn serialize_without_code(&self, code_image: CodeImage) -> Result<Vec<u8>, SerializeError> {
+        Self {
+            compilation: self.compilation.without_code(code_image),
+            compile_info: self.compile_info.clone(),
+            data_initializers: self.data_initializers.clone(),
+            cpu_features: self.cpu_features,
+            middleware_fingerprint: self.middleware_fingerprint,
+        }
+        .serialize()
+    }
+
     /// Deserialize a Module from a slice.
     /// The slice must have the following format:
     /// RKYV serialization (any length) + POS (8 bytes)
@@ -96,6 +250,24 @@ impl SerializableModule {
         Self::deserialize_from_archive(archived)
     }
 
+    /// Validates the archived Module in a slice, and returns it.
+    /// The slice must have the format described in
+    /// [`SerializableModule::deserialize`], and be aligned to
+    /// [`MetadataHeader::ALIGN`].
+    pub fn archive_from_slice_checked(
+        metadata_slice: &[u8],
+    ) -> Result<&ArchivedSerializableModule, DeserializeError> {
+        if metadata_slice.len() < 8 {
+            return Err(DeserializeError::Incompatible(
+                "invalid serialized data".into(),
+            ));
+        }
+        let (data, pos) = metadata_slice.split_at(metadata_slice.len() - 8);
+        let pos = u64::from_le_bytes(pos.try_into().unwrap());
+        check_archived_value::<Self>(data, pos as usize)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{}", e)))
+    }
+
     /// # Safety
     ///
     /// This method is unsafe.
@@ -146,6 +318,12 @@ impl SerializableModule {
         EnumSet::from_u64(self.cpu_features)
     }
 
+    /// Returns the fingerprint of the middleware chain applied by the
+    /// compiler for this Artifact, if it has one
+    pub fn middleware_fingerprint(&self) -> Option<[u8; 32]> {
+        self.middleware_fingerprint
+    }
+
     /// Returns data initializers to pass to `VMInstance::initialize`
     pub fn data_initializers(&self) -> &[OwnedDataInitializer] {
         &self.data_initializers
@@ -175,7 +353,7 @@ pub struct MetadataHeader {
 impl MetadataHeader {
     /// Current ABI version. Increment this any time breaking changes are made
     /// to the format of the serialized data.
-    const CURRENT_VERSION: u32 = 2;
+    const CURRENT_VERSION: u32 = 4;
 
     /// Magic number to identify wasmer metadata.
     const MAGIC: [u8; 8] = *b"WASMER\0\0";
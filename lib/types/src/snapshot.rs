@@ -0,0 +1,203 @@
+//! Serializable snapshots of the runtime state of a store or an
+//! instance: the contents of its linear memories, tables and globals.
+
+use crate::lib::std::convert::TryInto;
+use crate::lib::std::vec::Vec;
+use crate::{DeserializeError, SerializeError};
+use rkyv::{
+    ser::serializers::AllocSerializer, ser::Serializer as RkyvSerializer, AlignedVec, Archive,
+    Deserialize as RkyvDeserialize, Infallible, Serialize as RkyvSerialize,
+};
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+
+/// A reference held by a table element or a reference-typed global.
+///
+/// References are recorded by index rather than by address so that a
+/// snapshot can be restored into a different process.
+#[derive(Clone, Debug, PartialEq, Eq, RkyvSerialize, RkyvDeserialize, Archive)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(check_bytes)]
+pub enum ReferenceSnapshot {
+    /// A null reference.
+    Null,
+    /// A function of a WebAssembly instance.
+    Function {
+        /// Position of the instance in the snapshot scope. When the
+        /// snapshot was taken from a single instance this is always `0`.
+        instance: u32,
+        /// The `FunctionIndex` of the function in that instance.
+        index: u32,
+    },
+    /// A host function, by its position in the store.
+    HostFunction {
+        /// Position of the function in the store.
+        index: u32,
+    },
+}
+
+/// The contents of a linear memory.
+#[derive(Clone, Debug, PartialEq, Eq, RkyvSerialize, RkyvDeserialize, Archive)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(check_bytes)]
+pub struct MemorySnapshot {
+    /// The bytes of the memory. Its length is always a multiple of the
+    /// WebAssembly page size.
+    pub data: Vec<u8>,
+}
+
+/// The elements of a table.
+#[derive(Clone, Debug, PartialEq, Eq, RkyvSerialize, RkyvDeserialize, Archive)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(check_bytes)]
+pub struct TableSnapshot {
+    /// The elements of the table, one per slot.
+    pub elements: Vec<ReferenceSnapshot>,
+}
+
+/// The value of a global.
+#[derive(Clone, Debug, PartialEq, Eq, RkyvSerialize, RkyvDeserialize, Archive)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(check_bytes)]
+pub enum GlobalSnapshot {
+    /// The raw bits of a numeric or vector global.
+    Value(u128),
+    /// The value of a reference-typed global.
+    Reference(ReferenceSnapshot),
+}
+
+/// A snapshot of the state of every memory, table and global in a
+/// scope (either a whole store or a single instance).
+///
+/// Objects are recorded in the order in which they appear in the
+/// scope, so a snapshot can only be restored into a scope that was set
+/// up the same way, e.g. a fresh instance of the same module.
+#[derive(Clone, Debug, Default, PartialEq, Eq, RkyvSerialize, RkyvDeserialize, Archive)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(check_bytes)]
+pub struct StoreSnapshot {
+    /// The linear memories.
+    pub memories: Vec<MemorySnapshot>,
+    /// The tables.
+    pub tables: Vec<TableSnapshot>,
+    /// The globals.
+    pub globals: Vec<GlobalSnapshot>,
+}
+
+impl StoreSnapshot {
+    /// Current version of the serialized format. Increment this any time
+    /// breaking changes are made to the format of the serialized data.
+    pub const CURRENT_VERSION: u32 = 1;
+
+    /// Magic number to identify serialized snapshots.
+    const MAGIC: [u8; 8] = *b"WASMSNAP";
+
+    /// Length of the header preceding the serialized data.
+    const HEADER_LEN: usize = 16;
+
+    /// Serialize the snapshot into bytes.
+    /// The bytes will have the following format:
+    /// MAGIC (8 bytes) + VERSION (4 bytes) + PADDING (4 bytes) +
+    /// RKYV serialization (any length) + POS (8 bytes)
+    pub fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        let mut serializer = AllocSerializer::<4096>::default();
+        let pos = serializer
+            .serialize_value(self)
+            .map_err(|e| SerializeError::Generic(format!("{}", e)))? as u64;
+        let data = serializer.into_serializer().into_inner();
+
+        let mut serialized = Vec::with_capacity(Self::HEADER_LEN + data.len() + 8);
+        serialized.extend_from_slice(&Self::MAGIC);
+        serialized.extend_from_slice(&Self::CURRENT_VERSION.to_le_bytes());
+        serialized.extend_from_slice(&[0; 4]);
+        serialized.extend_from_slice(&data);
+        serialized.extend_from_slice(&pos.to_le_bytes());
+        Ok(serialized)
+    }
+
+    /// Deserialize a snapshot produced by [`StoreSnapshot::serialize`].
+    ///
+    /// The serialized data is validated before being deserialized, so
+    /// corrupted input results in an error.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        if bytes.len() < Self::HEADER_LEN + 8 {
+            return Err(DeserializeError::InvalidByteLength {
+                expected: Self::HEADER_LEN + 8,
+                got: bytes.len(),
+            });
+        }
+        if bytes[..8] != Self::MAGIC {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes are not a Wasmer snapshot".to_string(),
+            ));
+        }
+        let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        if version != Self::CURRENT_VERSION {
+            return Err(DeserializeError::Incompatible(format!(
+                "The snapshot was serialized with version {} of the format, expected {}",
+                version,
+                Self::CURRENT_VERSION
+            )));
+        }
+
+        let (data, pos) = bytes[Self::HEADER_LEN..].split_at(bytes.len() - Self::HEADER_LEN - 8);
+        let pos = u64::from_le_bytes(pos.try_into().unwrap()) as usize;
+        // rkyv requires the archived data to be properly aligned.
+        let mut aligned = AlignedVec::with_capacity(data.len());
+        aligned.extend_from_slice(data);
+
+        let archived = rkyv::check_archived_value::<Self>(&aligned, pos)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{}", e)))?;
+        RkyvDeserialize::deserialize(archived, &mut Infallible)
+            .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> StoreSnapshot {
+        StoreSnapshot {
+            memories: vec![MemorySnapshot {
+                data: vec![0xab; crate::WASM_PAGE_SIZE],
+            }],
+            tables: vec![TableSnapshot {
+                elements: vec![
+                    ReferenceSnapshot::Null,
+                    ReferenceSnapshot::Function {
+                        instance: 0,
+                        index: 3,
+                    },
+                    ReferenceSnapshot::HostFunction { index: 1 },
+                ],
+            }],
+            globals: vec![
+                GlobalSnapshot::Value(42),
+                GlobalSnapshot::Reference(ReferenceSnapshot::Null),
+            ],
+        }
+    }
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let snapshot = sample();
+        let bytes = snapshot.serialize().unwrap();
+        assert_eq!(StoreSnapshot::deserialize(&bytes).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn snapshot_rejects_bad_input() {
+        let mut bytes = sample().serialize().unwrap();
+        assert!(matches!(
+            StoreSnapshot::deserialize(&bytes[..10]),
+            Err(DeserializeError::InvalidByteLength { .. })
+        ));
+
+        bytes[8] = 0xff;
+        assert!(matches!(
+            StoreSnapshot::deserialize(&bytes),
+            Err(DeserializeError::Incompatible(_))
+        ));
+    }
+}
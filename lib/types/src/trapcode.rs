@@ -5,7 +5,7 @@
 
 use core::fmt::{self, Display, Formatter};
 use core::str::FromStr;
-use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+use rkyv::{Archive, CheckBytes, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -14,7 +14,17 @@ use thiserror::Error;
 ///
 /// All trap instructions have an explicit trap code.
 #[derive(
-    Clone, Copy, PartialEq, Eq, Debug, Hash, Error, RkyvSerialize, RkyvDeserialize, Archive,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Hash,
+    Error,
+    RkyvSerialize,
+    RkyvDeserialize,
+    Archive,
+    CheckBytes,
 )]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 #[repr(u32)]
@@ -59,6 +69,28 @@ pub enum TrapCode {
 
     /// An atomic memory access was attempted with an unaligned pointer.
     UnalignedAtomic = 10,
+
+    /// The gas available to the execution was exhausted, as raised by
+    /// metering middlewares.
+    GasExhausted = 11,
+
+    /// The execution was interrupted through the interrupt handle of its
+    /// store.
+    Interrupted = 12,
+
+    /// The execution reached the epoch deadline of its store.
+    EpochDeadlineReached = 13,
+
+    /// The resource limiter of the store refused to let a memory or a
+    /// table grow.
+    ResourceLimitExceeded = 14,
+
+    /// The limit on the depth of nested calls was exceeded, as raised by
+    /// call depth limiting middlewares.
+    ///
+    /// This is the last code that fits in the 4-bit `ud1` payload used by
+    /// singlepass on x86_64: more codes need a wider encoding there.
+    CallDepthExceeded = 15,
 }
 
 impl TrapCode {
@@ -76,6 +108,34 @@ impl TrapCode {
             Self::BadConversionToInteger => "invalid conversion to integer",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
+            Self::GasExhausted => "gas exhausted",
+            Self::Interrupted => "interrupted",
+            Self::EpochDeadlineReached => "epoch deadline reached",
+            Self::ResourceLimitExceeded => "resource limit exceeded",
+            Self::CallDepthExceeded => "call depth exceeded",
+        }
+    }
+
+    /// Returns the trap code with the given numeric value, if any.
+    pub fn from_u32(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::StackOverflow),
+            1 => Some(Self::HeapAccessOutOfBounds),
+            2 => Some(Self::HeapMisaligned),
+            3 => Some(Self::TableAccessOutOfBounds),
+            4 => Some(Self::IndirectCallToNull),
+            5 => Some(Self::BadSignature),
+            6 => Some(Self::IntegerOverflow),
+            7 => Some(Self::IntegerDivisionByZero),
+            8 => Some(Self::BadConversionToInteger),
+            9 => Some(Self::UnreachableCodeReached),
+            10 => Some(Self::UnalignedAtomic),
+            11 => Some(Self::GasExhausted),
+            12 => Some(Self::Interrupted),
+            13 => Some(Self::EpochDeadlineReached),
+            14 => Some(Self::ResourceLimitExceeded),
+            15 => Some(Self::CallDepthExceeded),
+            _ => None,
         }
     }
 }
@@ -94,6 +154,11 @@ impl Display for TrapCode {
             Self::BadConversionToInteger => "bad_toint",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
+            Self::GasExhausted => "gas_exhausted",
+            Self::Interrupted => "interrupted",
+            Self::EpochDeadlineReached => "epoch_deadline_reached",
+            Self::ResourceLimitExceeded => "resource_limit_exceeded",
+            Self::CallDepthExceeded => "call_depth_exceeded",
         };
         f.write_str(identifier)
     }
@@ -115,6 +180,11 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(Self::BadConversionToInteger),
             "unreachable" => Ok(Self::UnreachableCodeReached),
             "unalign_atom" => Ok(Self::UnalignedAtomic),
+            "gas_exhausted" => Ok(Self::GasExhausted),
+            "interrupted" => Ok(Self::Interrupted),
+            "epoch_deadline_reached" => Ok(Self::EpochDeadlineReached),
+            "resource_limit_exceeded" => Ok(Self::ResourceLimitExceeded),
+            "call_depth_exceeded" => Ok(Self::CallDepthExceeded),
             _ => Err(()),
         }
     }
@@ -138,7 +208,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 11] = [
+    const CODES: [TrapCode; 16] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -150,6 +220,11 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::GasExhausted,
+        TrapCode::Interrupted,
+        TrapCode::EpochDeadlineReached,
+        TrapCode::ResourceLimitExceeded,
+        TrapCode::CallDepthExceeded,
     ];
 
     #[test]
@@ -160,6 +235,11 @@ mod tests {
         }
         assert_eq!("bogus".parse::<TrapCode>(), Err(()));
 
+        for r in &CODES {
+            assert_eq!(TrapCode::from_u32(*r as u32), Some(*r));
+        }
+        assert_eq!(TrapCode::from_u32(CODES.len() as u32), None);
+
         // assert_eq!(TrapCode::User(17).to_string(), "user17");
         // assert_eq!("user22".parse(), Ok(TrapCode::User(22)));
         assert_eq!("user".parse::<TrapCode>(), Err(()));
@@ -187,6 +187,10 @@ pub struct VMOffsets {
     num_local_memories: u32,
     /// The number of defined globals in the module.
     num_local_globals: u32,
+    /// Whether the module checks the interrupt flag of its store.
+    interrupt_checks: bool,
+    /// Whether the module checks the epoch deadline of its store.
+    epoch_interruption: bool,
 
     vmctx_signature_ids_begin: u32,
     vmctx_imported_functions_begin: u32,
@@ -201,6 +205,10 @@ pub struct VMOffsets {
     vmctx_gas_limiter_pointer: u32,
     vmctx_stack_limit_begin: u32,
     vmctx_stack_limit_initial_begin: u32,
+    vmctx_interrupt_flag_pointer: u32,
+    vmctx_epoch_pointer: u32,
+    vmctx_epoch_deadline_pointer: u32,
+    vmctx_epoch_deadline_reached: u32,
     size_of_vmctx: u32,
 }
 
@@ -217,6 +225,8 @@ impl VMOffsets {
             num_local_tables: cast_to_u32(module.tables.len()),
             num_local_memories: cast_to_u32(module.memories.len()),
             num_local_globals: cast_to_u32(module.globals.len()),
+            interrupt_checks: module.interrupt_checks,
+            epoch_interruption: module.epoch_interruption,
             vmctx_signature_ids_begin: 0,
             vmctx_imported_functions_begin: 0,
             vmctx_imported_tables_begin: 0,
@@ -230,6 +240,10 @@ impl VMOffsets {
             vmctx_gas_limiter_pointer: 0,
             vmctx_stack_limit_begin: 0,
             vmctx_stack_limit_initial_begin: 0,
+            vmctx_interrupt_flag_pointer: 0,
+            vmctx_epoch_pointer: 0,
+            vmctx_epoch_deadline_pointer: 0,
+            vmctx_epoch_deadline_reached: 0,
             size_of_vmctx: 0,
         };
         ret.precompute();
@@ -251,6 +265,8 @@ impl VMOffsets {
             num_local_tables: 0,
             num_local_memories: 0,
             num_local_globals: 0,
+            interrupt_checks: false,
+            epoch_interruption: false,
             vmctx_signature_ids_begin: 0,
             vmctx_imported_functions_begin: 0,
             vmctx_imported_tables_begin: 0,
@@ -264,6 +280,10 @@ impl VMOffsets {
             vmctx_gas_limiter_pointer: 0,
             vmctx_stack_limit_begin: 0,
             vmctx_stack_limit_initial_begin: 0,
+            vmctx_interrupt_flag_pointer: 0,
+            vmctx_epoch_pointer: 0,
+            vmctx_epoch_deadline_pointer: 0,
+            vmctx_epoch_deadline_reached: 0,
             size_of_vmctx: 0,
         }
     }
@@ -345,7 +365,30 @@ impl VMOffsets {
             u32::from(self.pointer_size),
         );
         self.vmctx_stack_limit_initial_begin = self.vmctx_stack_limit_begin.checked_add(4).unwrap();
-        self.size_of_vmctx = self.vmctx_stack_limit_begin.checked_add(4).unwrap();
+        let mut end = align(
+            self.vmctx_stack_limit_initial_begin.checked_add(4).unwrap(),
+            u32::from(self.pointer_size),
+        );
+        if self.interrupt_checks {
+            self.vmctx_interrupt_flag_pointer = end;
+            end = offset_by(end, 1, u32::from(self.pointer_size));
+        }
+        if self.epoch_interruption {
+            self.vmctx_epoch_pointer = end;
+            self.vmctx_epoch_deadline_pointer =
+                offset_by(self.vmctx_epoch_pointer, 1, u32::from(self.pointer_size));
+            self.vmctx_epoch_deadline_reached = offset_by(
+                self.vmctx_epoch_deadline_pointer,
+                1,
+                u32::from(self.pointer_size),
+            );
+            end = offset_by(
+                self.vmctx_epoch_deadline_reached,
+                1,
+                u32::from(self.pointer_size),
+            );
+        }
+        self.size_of_vmctx = end;
     }
 }
 
@@ -627,6 +670,34 @@ impl VMOffsets {
         self.vmctx_builtin_functions_begin
     }
 
+    /// The offset of the pointer to the interrupt flag of the store, which
+    /// is only part of the `VMContext` of the modules checking it.
+    pub fn vmctx_interrupt_flag_pointer(&self) -> Option<u32> {
+        self.interrupt_checks
+            .then(|| self.vmctx_interrupt_flag_pointer)
+    }
+
+    /// The offset of the pointer to the epoch counter of the engine, which
+    /// is only part of the `VMContext` of the modules checking the epoch
+    /// deadline of their store.
+    pub fn vmctx_epoch_pointer(&self) -> Option<u32> {
+        self.epoch_interruption.then(|| self.vmctx_epoch_pointer)
+    }
+
+    /// The offset of the pointer to the epoch deadline of the store, see
+    /// [`Self::vmctx_epoch_pointer`].
+    pub fn vmctx_epoch_deadline_pointer(&self) -> Option<u32> {
+        self.epoch_interruption
+            .then(|| self.vmctx_epoch_deadline_pointer)
+    }
+
+    /// The offset of the function called when the epoch deadline of the
+    /// store is reached, see [`Self::vmctx_epoch_pointer`].
+    pub fn vmctx_epoch_deadline_reached(&self) -> Option<u32> {
+        self.epoch_interruption
+            .then(|| self.vmctx_epoch_deadline_reached)
+    }
+
     /// Return the size of the `VMContext` allocation.
     pub fn size_of_vmctx(&self) -> u32 {
         self.size_of_vmctx
@@ -0,0 +1,96 @@
+//! Checkpoints of the memories, tables and globals of a store, which can
+//! be rolled back when a call fails.
+
+use crate::store::{InternalStoreHandle, StoreObject, StoreObjects};
+use crate::table::RawTableElement;
+use crate::{LinearMemory, VMGlobal, VMMemory, VMTable};
+use wasmer_types::{MemoryError, RawValue};
+
+/// The state of a store at a checkpoint, see [`StoreObjects::checkpoint`].
+#[derive(Debug)]
+#[must_use = "a checkpoint must be rolled back or committed"]
+pub struct StoreCheckpoint {
+    /// Memories that have a checkpoint.
+    memories: Vec<InternalStoreHandle<VMMemory>>,
+    /// Elements of the tables.
+    tables: Vec<(InternalStoreHandle<VMTable>, Vec<RawTableElement>)>,
+    /// Raw values of the globals.
+    globals: Vec<(InternalStoreHandle<VMGlobal>, u128)>,
+}
+
+impl StoreObjects {
+    /// Starts a checkpoint of every memory, table and global in the store.
+    ///
+    /// Memories only record the pages written after the checkpoint, so
+    /// rolling back costs time proportional to the amount of memory
+    /// written rather than to the size of the memories. Objects created
+    /// or removed after the checkpoint are not affected by
+    /// [`StoreObjects::rollback`].
+    pub fn checkpoint(&mut self) -> Result<StoreCheckpoint, MemoryError> {
+        let memories = VMMemory::list(self).handles();
+        for (i, memory) in memories.iter().enumerate() {
+            if let Err(e) = memory.get_mut(self).checkpoint() {
+                for memory in &memories[..i] {
+                    let _ = memory.get_mut(self).commit();
+                }
+                return Err(e);
+            }
+        }
+        Ok(StoreCheckpoint {
+            memories,
+            tables: VMTable::list(self)
+                .handles()
+                .into_iter()
+                .map(|table| (table, table.get(self).raw_elements()))
+                .collect(),
+            globals: VMGlobal::list(self)
+                .handles()
+                .into_iter()
+                .map(|global| {
+                    let value = unsafe { global.get(self).vmglobal().as_ref().val.u128 };
+                    (global, value)
+                })
+                .collect(),
+        })
+    }
+
+    /// Restores the memories, tables and globals of the store to their
+    /// state at `checkpoint`.
+    ///
+    /// Memories and tables grown since the checkpoint get back their
+    /// previous size.
+    pub fn rollback(&mut self, checkpoint: StoreCheckpoint) -> Result<(), MemoryError> {
+        let mut result = Ok(());
+        for memory in checkpoint.memories {
+            if let Some(memory) = VMMemory::list_mut(self).get_mut(memory) {
+                let rolled_back = memory.rollback();
+                result = result.and(rolled_back);
+            }
+        }
+        for (table, elements) in &checkpoint.tables {
+            if let Some(table) = VMTable::list_mut(self).get_mut(*table) {
+                table.set_raw_elements(elements);
+            }
+        }
+        for (global, value) in checkpoint.globals {
+            if let Some(global) = VMGlobal::list(self).get(global) {
+                unsafe {
+                    global.vmglobal().as_mut().val = RawValue { u128: value };
+                }
+            }
+        }
+        result
+    }
+
+    /// Ends `checkpoint`, keeping the changes made since.
+    pub fn commit(&mut self, checkpoint: StoreCheckpoint) -> Result<(), MemoryError> {
+        let mut result = Ok(());
+        for memory in checkpoint.memories {
+            if let Some(memory) = VMMemory::list_mut(self).get_mut(memory) {
+                let committed = memory.commit();
+                result = result.and(committed);
+            }
+        }
+        result
+    }
+}
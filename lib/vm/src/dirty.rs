@@ -0,0 +1,542 @@
+//! Tracking of the pages written to a linear memory.
+//!
+//! A tracked range is write-protected. The first write to each host
+//! page faults; the trap handler then hands the fault to
+//! [`handle_write_fault`], which records the page as written, saves its
+//! original contents into a journal if a checkpoint is active, and makes
+//! it writable again. Both reporting the written pages and rolling back
+//! a checkpoint cost time proportional to the number of pages written,
+//! not to the memory size.
+//!
+//! Writes made by the kernel on behalf of the process (for example a
+//! `read(2)` directly into linear memory) don't fault but fail with
+//! `EFAULT` while a page is protected.
+
+use crate::mmap::Mmap;
+use std::cell::UnsafeCell;
+use std::ops::Range;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use wasmer_types::WASM_PAGE_SIZE;
+
+/// The live `PageTracker`s.
+///
+/// The fault handler can't take a lock, as it may have interrupted the
+/// thread holding it. Instead, it only uses the trackers while no change
+/// is in progress, and a change waits for the handlers using the trackers
+/// to return. A handler finding a change in progress lets the faulting
+/// access be retried.
+struct Registry {
+    /// Serializes the changes.
+    lock: Mutex<()>,
+    /// Whether a change is in progress.
+    updating: AtomicBool,
+    /// The number of fault handlers using the trackers.
+    handlers: AtomicUsize,
+    /// Addresses of the live trackers.
+    trackers: UnsafeCell<Vec<usize>>,
+}
+
+// The trackers are only accessed as described on `Registry`.
+unsafe impl Sync for Registry {}
+
+static REGISTRY: Registry = Registry {
+    lock: Mutex::new(()),
+    updating: AtomicBool::new(false),
+    handlers: AtomicUsize::new(0),
+    trackers: UnsafeCell::new(Vec::new()),
+};
+
+/// Number of live `PageTracker`s, so that unrelated faults don't have to
+/// look at the registry.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// A change to the trackers, during which the fault handler doesn't use
+/// them.
+struct Update {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Update {
+    fn start() -> Self {
+        let lock = REGISTRY.lock.lock().unwrap();
+        REGISTRY.updating.store(true, Ordering::SeqCst);
+        while REGISTRY.handlers.load(Ordering::SeqCst) != 0 {
+            std::hint::spin_loop();
+        }
+        Self { _lock: lock }
+    }
+
+    fn trackers(&mut self) -> &mut Vec<usize> {
+        unsafe { &mut *REGISTRY.trackers.get() }
+    }
+}
+
+impl Drop for Update {
+    fn drop(&mut self) {
+        REGISTRY.updating.store(false, Ordering::SeqCst);
+    }
+}
+
+/// One bit per host page.
+#[derive(Debug)]
+struct Bitmap(Box<[AtomicU64]>);
+
+impl Bitmap {
+    fn new(len: usize) -> Self {
+        Self((0..(len + 63) / 64).map(|_| AtomicU64::new(0)).collect())
+    }
+
+    fn get(&self, index: usize) -> bool {
+        self.0[index / 64].load(Ordering::Acquire) & (1 << (index % 64)) != 0
+    }
+
+    /// Sets a bit, returning whether it was previously unset.
+    fn set(&self, index: usize) -> bool {
+        let bit = 1 << (index % 64);
+        self.0[index / 64].fetch_or(bit, Ordering::AcqRel) & bit == 0
+    }
+
+    /// Unsets a bit.
+    fn unset(&self, index: usize) {
+        self.0[index / 64].fetch_and(!(1 << (index % 64)), Ordering::AcqRel);
+    }
+
+    fn clear(&mut self, range: Range<usize>) {
+        for index in range {
+            *self.0[index / 64].get_mut() &= !(1 << (index % 64));
+        }
+    }
+
+    fn clear_all(&mut self) {
+        for word in self.0.iter_mut() {
+            *word.get_mut() = 0;
+        }
+    }
+
+    /// Sets every bit that is set in `other`.
+    fn merge(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *word.get_mut() |= other.load(Ordering::Acquire);
+        }
+    }
+}
+
+/// The original contents of the pages written since a checkpoint.
+#[derive(Debug)]
+struct Journal {
+    /// Length of the memory at the checkpoint.
+    len: usize,
+    /// The pages that have been journaled.
+    pages: Bitmap,
+    /// The saved pages, at the same offsets as in the memory.
+    data: Mmap,
+}
+
+/// Records the host pages written to a linear memory.
+#[derive(Debug)]
+pub(crate) struct PageTracker {
+    /// Start of the tracked memory.
+    base: usize,
+    /// Length of the tracked memory in bytes.
+    len: usize,
+    /// The host page size.
+    page_size: usize,
+    /// The pages made writable since they were last protected.
+    written: Bitmap,
+    /// The pages a fault handler is making writable, besides those in
+    /// `written`.
+    claimed: Bitmap,
+    /// The pages written since the last reset, besides those in `written`.
+    dirty: Bitmap,
+    /// Whether the written pages are being reported.
+    tracking_dirty: bool,
+    /// The active checkpoint.
+    journal: Option<Journal>,
+}
+
+impl PageTracker {
+    /// Starts tracking the writes to the `len` bytes at `base`, which can
+    /// later be extended up to `max_len` bytes.
+    ///
+    /// # Safety
+    /// - `base` and `len` must describe accessible memory aligned to the
+    ///   host page size, which must outlive the tracker.
+    pub(crate) unsafe fn new(
+        base: *mut u8,
+        len: usize,
+        max_len: usize,
+    ) -> Result<Box<Self>, String> {
+        let page_size = region::page::size();
+        assert_eq!(base as usize & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+
+        let pages = (max_len.max(len) + page_size - 1) / page_size;
+        let tracker = Box::new(Self {
+            base: base as usize,
+            len,
+            page_size,
+            written: Bitmap::new(pages),
+            claimed: Bitmap::new(pages),
+            dirty: Bitmap::new(pages),
+            tracking_dirty: false,
+            journal: None,
+        });
+        // The tracker has to be visible to the fault handler before any
+        // page gets protected.
+        {
+            let mut update = Update::start();
+            update.trackers().push(&*tracker as *const Self as usize);
+            ACTIVE.fetch_add(1, Ordering::AcqRel);
+        }
+        tracker.protect(0, len, region::Protection::READ)?;
+        Ok(tracker)
+    }
+
+    /// Returns whether the tracker has nothing left to record.
+    pub(crate) fn is_idle(&self) -> bool {
+        !self.tracking_dirty && self.journal.is_none()
+    }
+
+    /// Returns whether the written pages are being reported.
+    pub(crate) fn is_tracking_dirty(&self) -> bool {
+        self.tracking_dirty
+    }
+
+    /// Returns whether a checkpoint is active.
+    pub(crate) fn has_checkpoint(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// Changes the protection of part of the tracked memory.
+    fn protect(
+        &self,
+        start: usize,
+        len: usize,
+        protection: region::Protection,
+    ) -> Result<(), String> {
+        if len == 0 {
+            return Ok(());
+        }
+        unsafe { region::protect((self.base + start) as *const u8, len, protection) }
+            .map_err(|e| e.to_string())
+    }
+
+    /// Protects the pages in `pages` that haven't been written.
+    fn protect_unwritten(&self, pages: Range<usize>) -> Result<(), String> {
+        let mut index = pages.start;
+        while index < pages.end {
+            if self.written.get(index) {
+                index += 1;
+                continue;
+            }
+            let start = index;
+            while index < pages.end && !self.written.get(index) {
+                index += 1;
+            }
+            self.protect(
+                start * self.page_size,
+                (index - start) * self.page_size,
+                region::Protection::READ,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Protects every page again, remembering the written ones as dirty.
+    ///
+    /// Must be called during an `Update`.
+    fn rearm(&mut self) -> Result<(), String> {
+        self.dirty.merge(&self.written);
+        self.written.clear_all();
+        self.claimed.clear_all();
+        self.protect(0, self.len, region::Protection::READ)
+    }
+
+    /// Starts reporting the pages written from now on.
+    pub(crate) fn start_tracking_dirty(&mut self) -> Result<(), String> {
+        self.reset_dirty()?;
+        self.tracking_dirty = true;
+        Ok(())
+    }
+
+    /// Stops reporting the written pages.
+    pub(crate) fn stop_tracking_dirty(&mut self) {
+        self.tracking_dirty = false;
+    }
+
+    /// Forgets the pages written so far.
+    pub(crate) fn reset_dirty(&mut self) -> Result<(), String> {
+        let _update = Update::start();
+        self.rearm()?;
+        self.dirty.clear_all();
+        Ok(())
+    }
+
+    /// Returns the pages written since the last reset.
+    pub(crate) fn dirty_pages(&self) -> DirtyPages {
+        let pages = (0..self.len / self.page_size)
+            .filter(|&index| self.written.get(index) || self.dirty.get(index))
+            .collect();
+        DirtyPages {
+            page_size: self.page_size,
+            pages,
+        }
+    }
+
+    /// Starts journaling the pages written from now on.
+    pub(crate) fn start_checkpoint(&mut self) -> Result<(), String> {
+        let data = Mmap::with_at_least(self.len)?;
+        let _update = Update::start();
+        self.journal = Some(Journal {
+            len: self.len,
+            pages: Bitmap::new(self.len / self.page_size),
+            data,
+        });
+        self.rearm()
+    }
+
+    /// Ends the active checkpoint, copying the original contents of every
+    /// page written since back into the memory if `restore` is set.
+    ///
+    /// Returns the length of the memory at the checkpoint.
+    ///
+    /// # Safety
+    /// - No other thread may write to the tracked memory concurrently.
+    pub(crate) unsafe fn end_checkpoint(&mut self, restore: bool) -> Option<usize> {
+        let journal = {
+            let _update = Update::start();
+            self.journal.take()?
+        };
+        if restore {
+            let pages = journal.len / self.page_size;
+            for index in (0..pages).filter(|&index| journal.pages.get(index)) {
+                let offset = index * self.page_size;
+                ptr::copy_nonoverlapping(
+                    journal.data.as_ptr().add(offset),
+                    (self.base + offset) as *mut u8,
+                    self.page_size,
+                );
+            }
+        }
+        Some(journal.len)
+    }
+
+    /// Points the tracker at the new location of a memory that has been
+    /// copied to `base`, and protects the pages that haven't been written.
+    ///
+    /// # Safety
+    /// - `base` must satisfy the requirements of [`PageTracker::new`].
+    pub(crate) unsafe fn relocate(&mut self, base: *mut u8) -> Result<(), String> {
+        let _update = Update::start();
+        self.base = base as usize;
+        self.protect_unwritten(0..self.len / self.page_size)
+    }
+
+    /// Updates the length of the tracked memory after it has been grown,
+    /// or before it is shrunk. Pages added to it are protected.
+    pub(crate) fn resize(&mut self, len: usize) -> Result<(), String> {
+        let _update = Update::start();
+        let old_pages = self.len / self.page_size;
+        let new_pages = len / self.page_size;
+        self.len = len;
+        if new_pages > old_pages {
+            // Pages written before the memory was shrunk are still dirty,
+            // but have to be protected again.
+            for index in old_pages..new_pages {
+                if self.written.get(index) {
+                    self.dirty.set(index);
+                }
+            }
+            self.written.clear(old_pages..new_pages);
+            self.claimed.clear(old_pages..new_pages);
+            let start = old_pages * self.page_size;
+            self.protect(start, len - start, region::Protection::READ)?;
+        }
+        Ok(())
+    }
+
+    /// Records a write to the page containing `addr` and makes it
+    /// writable, if it is in the tracked memory.
+    ///
+    /// Returns whether the fault at `addr` was handled.
+    unsafe fn handle_fault(&self, addr: usize) -> bool {
+        if addr < self.base || addr >= self.base + self.len {
+            return false;
+        }
+        let index = (addr - self.base) / self.page_size;
+        if self.written.get(index) {
+            return true;
+        }
+        // Only the thread that claims the page handles it. Any other thread
+        // faulting on it in the meantime retries until the page is made
+        // writable.
+        if self.claimed.set(index) {
+            let offset = index * self.page_size;
+            if let Some(journal) = &self.journal {
+                if offset < journal.len && journal.pages.set(index) {
+                    ptr::copy_nonoverlapping(
+                        (self.base + offset) as *const u8,
+                        journal.data.as_ptr().add(offset) as *mut u8,
+                        self.page_size,
+                    );
+                }
+            }
+            let protected = self
+                .protect(offset, self.page_size, region::Protection::READ_WRITE)
+                .is_ok();
+            // The page is only recorded as written once it is writable.
+            if protected {
+                self.written.set(index);
+            }
+            self.claimed.unset(index);
+            return protected;
+        }
+        true
+    }
+}
+
+impl Drop for PageTracker {
+    fn drop(&mut self) {
+        // Make the whole memory writable again before the fault handler
+        // stops knowing about it.
+        let _ = self.protect(0, self.len, region::Protection::READ_WRITE);
+        let mut update = Update::start();
+        let addr = self as *const Self as usize;
+        update.trackers().retain(|&tracker| tracker != addr);
+        ACTIVE.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Handles a memory access fault at `addr` if it was caused by a write
+/// to a tracked page.
+///
+/// Returns whether the faulting access can be retried.
+pub(crate) unsafe fn handle_write_fault(addr: usize) -> bool {
+    if ACTIVE.load(Ordering::Acquire) == 0 {
+        return false;
+    }
+    REGISTRY.handlers.fetch_add(1, Ordering::SeqCst);
+    // A change to the trackers waits for this handler to return, or this
+    // handler lets the access fault again once the change is done.
+    let handled = REGISTRY.updating.load(Ordering::SeqCst)
+        || (*REGISTRY.trackers.get())
+            .iter()
+            .any(|&tracker| (*(tracker as *const PageTracker)).handle_fault(addr));
+    REGISTRY.handlers.fetch_sub(1, Ordering::SeqCst);
+    handled
+}
+
+/// The pages of a linear memory written since dirty page tracking was
+/// enabled or last reset.
+///
+/// Writes are tracked per host page, which is never larger than a
+/// WebAssembly page on the supported platforms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirtyPages {
+    page_size: usize,
+    pages: Vec<usize>,
+}
+
+impl DirtyPages {
+    /// Returns the size of the host pages in which writes are tracked.
+    pub fn host_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Returns the indexes of the written host pages, in increasing order.
+    pub fn host_pages(&self) -> &[usize] {
+        &self.pages
+    }
+
+    /// Returns whether no page was written.
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Returns the byte ranges of the memory covered by the written pages,
+    /// merging adjacent pages into a single range.
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for &index in &self.pages {
+            let start = index * self.page_size;
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end += self.page_size,
+                _ => ranges.push(start..start + self.page_size),
+            }
+        }
+        ranges
+    }
+
+    /// Returns the indexes of the WebAssembly pages containing a written
+    /// host page, in increasing order.
+    pub fn wasm_pages(&self) -> Vec<u32> {
+        let mut pages: Vec<u32> = Vec::new();
+        for range in self.ranges() {
+            let first = (range.start / WASM_PAGE_SIZE) as u32;
+            let last = ((range.end - 1) / WASM_PAGE_SIZE) as u32;
+            let first = match pages.last() {
+                Some(&previous) if previous >= first => previous + 1,
+                _ => first,
+            };
+            pages.extend(first..=last);
+        }
+        pages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_and_restores_written_pages() {
+        crate::init_traps();
+        let page_size = region::page::size();
+        let mut mmap = Mmap::with_at_least(4 * page_size).unwrap();
+        mmap.as_mut_slice()[page_size] = 1;
+
+        let mut tracker =
+            unsafe { PageTracker::new(mmap.as_mut_ptr(), mmap.len(), mmap.len()).unwrap() };
+        tracker.start_tracking_dirty().unwrap();
+        tracker.start_checkpoint().unwrap();
+        mmap.as_mut_slice()[page_size] = 2;
+        mmap.as_mut_slice()[3 * page_size + 7] = 3;
+        assert_eq!(tracker.dirty_pages().host_pages(), &[1, 3]);
+
+        tracker.reset_dirty().unwrap();
+        assert!(tracker.dirty_pages().is_empty());
+        mmap.as_mut_slice()[page_size] = 4;
+        mmap.as_mut_slice()[2 * page_size] = 5;
+        assert_eq!(
+            tracker.dirty_pages().ranges(),
+            vec![page_size..3 * page_size]
+        );
+
+        assert_eq!(unsafe { tracker.end_checkpoint(true) }, Some(mmap.len()));
+        drop(tracker);
+        assert_eq!(mmap.as_slice()[page_size], 1);
+        assert_eq!(mmap.as_slice()[2 * page_size], 0);
+        assert_eq!(mmap.as_slice()[3 * page_size + 7], 0);
+    }
+
+    #[test]
+    fn dirty_wasm_pages() {
+        let page_size = 4096;
+        let per_wasm_page = WASM_PAGE_SIZE / page_size;
+        let dirty = DirtyPages {
+            page_size,
+            pages: vec![0, 1, per_wasm_page - 1, 3 * per_wasm_page + 2],
+        };
+        assert_eq!(dirty.wasm_pages(), vec![0, 3]);
+        assert_eq!(
+            dirty.ranges(),
+            vec![
+                0..2 * page_size,
+                (per_wasm_page - 1) * page_size..per_wasm_page * page_size,
+                (3 * per_wasm_page + 2) * page_size..(3 * per_wasm_page + 3) * page_size,
+            ]
+        );
+    }
+}
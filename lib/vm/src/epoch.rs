@@ -0,0 +1,137 @@
+//! Epoch-based deadlines, to stop or yield the WebAssembly code running
+//! in a store once enough time has passed.
+//!
+//! An engine holds an epoch counter, which is typically incremented by a
+//! timer thread of the host. Each store sets a deadline, in epochs, that
+//! the code of the modules compiled with epoch interruption checks on entry
+//! to every function and at the start of every loop iteration.
+
+use crate::store::StoreObjects;
+use crate::trap::{Trap, TrapCode};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// An epoch counter, shared by an engine and the stores using it.
+#[derive(Debug, Clone, Default)]
+pub struct EpochCounter {
+    epoch: Arc<AtomicU64>,
+}
+
+impl EpochCounter {
+    /// Increments the epoch, and returns the new one.
+    pub fn increment(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Returns the current epoch.
+    pub fn current(&self) -> u64 {
+        self.epoch.load(Ordering::SeqCst)
+    }
+}
+
+/// What to do when a store reaches its epoch deadline, as decided by its
+/// epoch deadline callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpochDeadlineAction {
+    /// Stop the execution with a [`TrapCode::EpochDeadlineReached`] trap.
+    Trap,
+    /// Keep executing, with a new deadline the given number of epochs
+    /// after the current one.
+    Continue(u64),
+}
+
+/// The callback called with the current epoch when a store reaches its
+/// epoch deadline.
+pub type EpochDeadlineCallbackFn = dyn FnMut(u64) -> EpochDeadlineAction + Send;
+
+/// The epoch deadline of a store.
+pub(crate) struct EpochDeadline {
+    counter: EpochCounter,
+    /// Boxed so that compiled code can keep a pointer to it.
+    deadline: Box<AtomicU64>,
+    callback: Option<Box<EpochDeadlineCallbackFn>>,
+}
+
+impl Default for EpochDeadline {
+    fn default() -> Self {
+        Self {
+            counter: EpochCounter::default(),
+            deadline: Box::new(AtomicU64::new(u64::MAX)),
+            callback: None,
+        }
+    }
+}
+
+impl fmt::Debug for EpochDeadline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EpochDeadline")
+            .field("counter", &self.counter)
+            .field("deadline", &self.deadline)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+impl StoreObjects {
+    /// Makes this store follow the given epoch counter, usually the one of
+    /// its engine.
+    ///
+    /// This must be done before any instance is created in the store.
+    pub fn set_epoch_counter(&mut self, counter: EpochCounter) {
+        self.epoch.counter = counter;
+    }
+
+    /// Returns the epoch deadline of this store. There is no deadline
+    /// until one is set.
+    pub fn epoch_deadline(&self) -> u64 {
+        self.epoch.deadline.load(Ordering::SeqCst)
+    }
+
+    /// Sets the epoch deadline of this store to `ticks_beyond_current`
+    /// epochs after the current one.
+    pub fn set_epoch_deadline(&mut self, ticks_beyond_current: u64) {
+        let deadline = self
+            .epoch
+            .counter
+            .current()
+            .saturating_add(ticks_beyond_current);
+        self.epoch.deadline.store(deadline, Ordering::SeqCst);
+    }
+
+    /// Sets the callback deciding what to do when this store reaches its
+    /// epoch deadline. Without a callback, the execution traps.
+    pub fn set_epoch_deadline_callback(&mut self, callback: Option<Box<EpochDeadlineCallbackFn>>) {
+        self.epoch.callback = callback;
+    }
+
+    /// Called by compiled code when the epoch deadline of this store is
+    /// reached.
+    pub(crate) fn epoch_deadline_reached(&mut self) -> Result<(), Trap> {
+        let epoch = self.epoch.counter.current();
+        let action = match self.epoch.callback.as_mut() {
+            Some(callback) => callback(epoch),
+            None => EpochDeadlineAction::Trap,
+        };
+        match action {
+            EpochDeadlineAction::Trap => Err(Trap::lib(TrapCode::EpochDeadlineReached)),
+            EpochDeadlineAction::Continue(ticks) => {
+                let deadline = epoch.saturating_add(ticks);
+                self.epoch.deadline.store(deadline, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns a pointer to the epoch counter followed by this store. It
+    /// stays valid as long as the store.
+    pub(crate) fn epoch_pointer(&self) -> *const u64 {
+        Arc::as_ptr(&self.epoch.counter.epoch) as *const u64
+    }
+
+    /// Returns a pointer to the epoch deadline of this store. It stays
+    /// valid as long as the store.
+    pub(crate) fn epoch_deadline_pointer(&self) -> *const u64 {
+        &*self.epoch.deadline as *const AtomicU64 as *const u64
+    }
+}
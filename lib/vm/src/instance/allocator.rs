@@ -1,4 +1,5 @@
 use super::{Instance, VMInstance};
+use crate::pool::InstanceSlot;
 use crate::vmcontext::VMTableDefinition;
 use crate::VMMemoryDefinition;
 use std::alloc::{self, Layout};
@@ -42,11 +43,25 @@ pub struct InstanceAllocator {
     /// `instance_ptr` buffer. If it has not when being dropped,
     /// the buffer should be freed.
     consumed: bool,
+
+    /// The pool slot holding the `instance_ptr` buffer, if it was not
+    /// allocated on the heap.
+    slot: Option<InstanceSlot>,
+}
+
+/// Where the memory of an [`Instance`] comes from.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum InstanceMemory {
+    /// Allocated on the heap with this layout.
+    Heap(Layout),
+    /// Held by a slot of a pooling allocator, which is given back to the
+    /// pool when dropped.
+    Pool(InstanceSlot),
 }
 
 impl Drop for InstanceAllocator {
     fn drop(&mut self) {
-        if !self.consumed {
+        if !self.consumed && self.slot.is_none() {
             // If `consumed` has not been set, then we still have ownership
             // over the buffer and must free it.
             let instance_ptr = self.instance_ptr.as_ptr();
@@ -87,11 +102,63 @@ impl InstanceAllocator {
             alloc::handle_alloc_error(instance_layout);
         };
 
+        Self::with_buffer(instance_ptr, instance_layout, offsets, None)
+    }
+
+    /// Allocates instance data for use with [`VMInstance::new`] in a slot
+    /// of a pooling allocator, like [`InstanceAllocator::new`].
+    ///
+    /// Returns an error if the instance doesn't fit in the slot.
+    ///
+    /// [`VMInstance::new`]: super::VMInstance::new
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn new_in_slot(
+        module: &ModuleInfo,
+        slot: InstanceSlot,
+    ) -> Result<
+        (
+            Self,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        String,
+    > {
+        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
+        let instance_layout = Self::instance_layout(&offsets);
+        if instance_layout.size() > slot.size() {
+            return Err(format!(
+                "the instance needs {} bytes, more than the {} bytes of the instance slots of the pool",
+                instance_layout.size(),
+                slot.size()
+            ));
+        }
+
+        // Slots are page-aligned, which is enough for `Instance`.
+        let instance_ptr = NonNull::new(slot.ptr() as *mut Instance).unwrap();
+        Ok(Self::with_buffer(
+            instance_ptr,
+            instance_layout,
+            offsets,
+            Some(slot),
+        ))
+    }
+
+    fn with_buffer(
+        instance_ptr: NonNull<Instance>,
+        instance_layout: Layout,
+        offsets: VMOffsets,
+        slot: Option<InstanceSlot>,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
         let allocator = Self {
             instance_ptr,
             instance_layout,
             offsets,
             consumed: false,
+            slot,
         };
 
         // # Safety
@@ -201,14 +268,14 @@ impl InstanceAllocator {
             // Now `instance_ptr` is correctly initialized!
         }
         let instance = self.instance_ptr;
-        let instance_layout = self.instance_layout;
+        let memory = match self.slot.take() {
+            Some(slot) => InstanceMemory::Pool(slot),
+            None => InstanceMemory::Heap(self.instance_layout),
+        };
 
         // This is correct because of the invariants of `Self` and
         // because we write `Instance` to the pointer in this function.
-        VMInstance {
-            instance,
-            instance_layout,
-        }
+        VMInstance { instance, memory }
     }
 
     /// Get the [`VMOffsets`] for the allocated buffer.
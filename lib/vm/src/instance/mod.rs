@@ -10,6 +10,7 @@ mod allocator;
 
 use crate::export::VMExtern;
 use crate::imports::Imports;
+use crate::libcalls::wasmer_vm_epoch_deadline_reached;
 use crate::store::{InternalStoreHandle, StoreObjects};
 use crate::table::TableElement;
 use crate::trap::{catch_traps, Trap, TrapCode};
@@ -23,9 +24,9 @@ use crate::LinearMemory;
 use crate::{FunctionBodyPtr, MaybeInstanceOwned, TrapHandlerFn, VMFunctionBody};
 use crate::{VMFuncRef, VMFunction, VMGlobal, VMMemory, VMTable};
 pub use allocator::InstanceAllocator;
+use allocator::InstanceMemory;
 use memoffset::offset_of;
 use more_asserts::assert_lt;
-use std::alloc::Layout;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -56,6 +57,23 @@ struct NotifyMap {
     map: HashMap<NotifyLocation, Vec<NotifyWaiter>>,
 }
 
+/// Counts a call in the wasm calls of a store until it returns or unwinds,
+/// for instance when a host function panics.
+struct WasmCallGuard(*mut StoreObjects);
+
+impl WasmCallGuard {
+    unsafe fn begin(context: *mut StoreObjects) -> Self {
+        (*context).begin_wasm_call();
+        Self(context)
+    }
+}
+
+impl Drop for WasmCallGuard {
+    fn drop(&mut self) {
+        unsafe { (*self.0).end_wasm_call() }
+    }
+}
+
 /// A WebAssembly instance.
 ///
 /// The type is dynamically-sized. Indeed, the `vmctx` field can
@@ -139,11 +157,11 @@ impl Instance {
         &self.module
     }
 
-    fn context(&self) -> &StoreObjects {
+    pub(crate) fn context(&self) -> &StoreObjects {
         unsafe { &*self.context }
     }
 
-    fn context_mut(&mut self) -> &mut StoreObjects {
+    pub(crate) fn context_mut(&mut self) -> &mut StoreObjects {
         unsafe { &mut *self.context }
     }
 
@@ -306,6 +324,30 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_builtin_functions_begin()) }
     }
 
+    /// Return a pointer to the pointer to the interrupt flag of the store,
+    /// if the module checks it.
+    fn interrupt_flag_ptr(&self) -> Option<*mut *const u32> {
+        let offset = self.offsets.vmctx_interrupt_flag_pointer()?;
+        Some(unsafe { self.vmctx_plus_offset(offset) })
+    }
+
+    /// Return pointers to the pointers to the epoch counter of the engine
+    /// and to the epoch deadline of the store, and to the function called
+    /// when the deadline is reached, if the module checks it.
+    #[allow(clippy::type_complexity)]
+    fn epoch_ptrs(&self) -> Option<(*mut *const u64, *mut *const u64, *mut usize)> {
+        let epoch = self.offsets.vmctx_epoch_pointer()?;
+        let deadline = self.offsets.vmctx_epoch_deadline_pointer()?;
+        let reached = self.offsets.vmctx_epoch_deadline_reached()?;
+        unsafe {
+            Some((
+                self.vmctx_plus_offset(epoch),
+                self.vmctx_plus_offset(deadline),
+                self.vmctx_plus_offset(reached),
+            ))
+        }
+    }
+
     /// Return a reference to the vmctx used by compiled wasm code.
     fn vmctx(&self) -> &VMContext {
         &self.vmctx
@@ -347,8 +389,9 @@ impl Instance {
             }
         };
 
-        // Make the call.
+        // Make the call, during which no object can be removed from the store.
         unsafe {
+            let _guard = WasmCallGuard::begin(self.context);
             catch_traps(trap_handler, || {
                 mem::transmute::<*const VMFunctionBody, unsafe extern "C" fn(VMFunctionContext)>(
                     callee_address,
@@ -387,6 +430,24 @@ impl Instance {
         index
     }
 
+    /// Return the index of the function defined by this instance whose
+    /// `VMCallerCheckedAnyfunc` is `anyfunc`, if any.
+    pub(crate) fn function_index(
+        &self,
+        anyfunc: NonNull<VMCallerCheckedAnyfunc>,
+    ) -> Option<FunctionIndex> {
+        let begin = self.funcrefs.values().as_slice().as_ptr() as usize;
+        let address = anyfunc.as_ptr() as usize;
+        if address < begin {
+            return None;
+        }
+        let index = (address - begin) / mem::size_of::<VMCallerCheckedAnyfunc>();
+        if index >= self.funcrefs.len() {
+            return None;
+        }
+        Some(self.module.func_index(LocalFunctionIndex::new(index)))
+    }
+
     /// Grow memory by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
@@ -498,6 +559,34 @@ impl Instance {
         table.get_mut(self.context_mut()).grow(delta, init_value)
     }
 
+    /// Asks the resource limiter of the store whether the memory at
+    /// `memory_index` may grow by `delta` pages.
+    pub(crate) fn memory_growing(
+        &mut self,
+        memory_index: MemoryIndex,
+        delta: Pages,
+    ) -> Result<bool, Trap> {
+        let memory = self.get_vmmemory(memory_index);
+        let current = memory.size();
+        let maximum = memory.ty().maximum;
+        let desired = Pages(current.0.saturating_add(delta.0));
+        self.context_mut().memory_growing(current, desired, maximum)
+    }
+
+    /// Asks the resource limiter of the store whether the table at
+    /// `table_index` may grow by `delta` elements.
+    pub(crate) fn table_growing(
+        &mut self,
+        table_index: TableIndex,
+        delta: u32,
+    ) -> Result<bool, Trap> {
+        let table = self.get_table_handle(table_index).get(self.context());
+        let current = table.size();
+        let maximum = table.ty().maximum;
+        self.context_mut()
+            .table_growing(current, current.saturating_add(delta), maximum)
+    }
+
     /// Get table element by index.
     pub(crate) fn table_get(
         &self,
@@ -567,6 +656,30 @@ impl Instance {
         }
     }
 
+    /// Get the `FunctionIndex` of a `VMFuncRef` pointing to a function
+    /// defined by this instance.
+    pub(crate) fn local_func_index_of(&self, func_ref: VMFuncRef) -> Option<FunctionIndex> {
+        let ptr = func_ref.0.as_ptr() as *const VMCallerCheckedAnyfunc;
+        let base = self.funcrefs.values().as_slice().as_ptr();
+        let offset = (ptr as usize).checked_sub(base as usize)?;
+        let index = offset / mem::size_of::<VMCallerCheckedAnyfunc>();
+        if offset % mem::size_of::<VMCallerCheckedAnyfunc>() != 0 || index >= self.funcrefs.len() {
+            return None;
+        }
+        Some(self.module.func_index(LocalFunctionIndex::new(index)))
+    }
+
+    /// Get the `FunctionIndex` of a `VMFuncRef` pointing to a function
+    /// defined or imported by this instance.
+    pub(crate) fn func_index_of(&self, func_ref: VMFuncRef) -> Option<FunctionIndex> {
+        self.local_func_index_of(func_ref).or_else(|| {
+            self.imported_funcrefs
+                .iter()
+                .find(|(_, ptr)| **ptr == func_ref.0)
+                .map(|(index, _)| index)
+        })
+    }
+
     /// The `table.init` operation: initializes a portion of a table with a
     /// passive element.
     ///
@@ -786,10 +899,7 @@ impl Instance {
 
     /// Get a table handle by index regardless of whether it is locally-defined
     /// or an imported, foreign table.
-    pub(crate) fn get_table_handle(
-        &mut self,
-        table_index: TableIndex,
-    ) -> InternalStoreHandle<VMTable> {
+    pub(crate) fn get_table_handle(&self, table_index: TableIndex) -> InternalStoreHandle<VMTable> {
         if let Some(local_table_index) = self.module.local_table_index(table_index) {
             self.tables[local_table_index]
         } else {
@@ -797,6 +907,32 @@ impl Instance {
         }
     }
 
+    /// Get a memory handle by index regardless of whether it is locally-defined
+    /// or an imported, foreign memory.
+    pub(crate) fn get_memory_handle(
+        &self,
+        memory_index: MemoryIndex,
+    ) -> InternalStoreHandle<VMMemory> {
+        if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
+            self.memories[local_memory_index]
+        } else {
+            self.imported_memory(memory_index).handle
+        }
+    }
+
+    /// Get a global handle by index regardless of whether it is locally-defined
+    /// or an imported, foreign global.
+    pub(crate) fn get_global_handle(
+        &self,
+        global_index: GlobalIndex,
+    ) -> InternalStoreHandle<VMGlobal> {
+        if let Some(local_global_index) = self.module.local_global_index(global_index) {
+            self.globals[local_global_index]
+        } else {
+            self.imported_global(global_index).handle
+        }
+    }
+
     // To implement Wait / Notify, a HasMap, behind a mutex, will be used
     // to track the address of waiter. The key of the hashmap is based on the memory
     // and waiter threads are "park"'d (with or without timeout)
@@ -1025,8 +1161,9 @@ impl Instance {
 /// providing useful higher-level API.
 #[derive(Debug, Eq, PartialEq)]
 pub struct VMInstance {
-    /// The layout of `Instance` (which can vary).
-    instance_layout: Layout,
+    /// Where the memory of `Instance` comes from, with its layout (which
+    /// can vary) if it is on the heap.
+    memory: InstanceMemory,
 
     /// The `Instance` itself.
     ///
@@ -1050,8 +1187,12 @@ impl Drop for VMInstance {
         unsafe {
             // Need to drop all the actual Instance members
             instance_ptr.drop_in_place();
-            // And then free the memory allocated for the Instance itself
-            std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+            // And then free the memory allocated for the Instance itself,
+            // unless it belongs to a pool slot, which is released when
+            // `self.memory` is dropped.
+            if let InstanceMemory::Heap(layout) = self.memory {
+                std::alloc::dealloc(instance_ptr as *mut u8, layout);
+            }
         }
     }
 }
@@ -1188,6 +1329,14 @@ impl VMInstance {
             instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
             VMBuiltinFunctionsArray::initialized(),
         );
+        if let Some(interrupt_flag_ptr) = instance.interrupt_flag_ptr() {
+            ptr::write(interrupt_flag_ptr, context.interrupt_flag());
+        }
+        if let Some((epoch_ptr, deadline_ptr, reached_ptr)) = instance.epoch_ptrs() {
+            ptr::write(epoch_ptr, context.epoch_pointer());
+            ptr::write(deadline_ptr, context.epoch_deadline_pointer());
+            ptr::write(reached_ptr, wasmer_vm_epoch_deadline_reached as usize);
+        }
 
         // Perform infallible initialization in this constructor, while fallible
         // initialization is deferred to the `initialize` method.
@@ -1229,6 +1378,53 @@ impl VMInstance {
         Ok(())
     }
 
+    /// Brings the instance back to its state right after instantiation,
+    /// reusing its memories and tables instead of allocating new ones.
+    ///
+    /// The memories and tables defined by the instance are cleared and
+    /// shrunk back to their minimum size, its globals and its passive
+    /// segments are reset, and the initializers are applied again. The start
+    /// function is run again if `run_start` is set.
+    ///
+    /// # Safety
+    ///
+    /// `data_initializers` must be the data initializers of the module of the
+    /// instance.
+    pub unsafe fn reset(
+        &mut self,
+        trap_handler: Option<*const TrapHandlerFn<'static>>,
+        data_initializers: &[DataInitializer<'_>],
+        run_start: bool,
+    ) -> Result<(), Trap> {
+        let instance = self.instance_mut();
+
+        for memory in instance.memories.values() {
+            memory
+                .get_mut(&mut *instance.context)
+                .reset()
+                .map_err(|error| Trap::User(Box::new(error)))?;
+        }
+        for table in instance.tables.values() {
+            table.get_mut(&mut *instance.context).reset();
+        }
+        initialize_globals(instance);
+        instance.passive_elements.borrow_mut().clear();
+        initialize_passive_elements(instance);
+        *instance.passive_data.borrow_mut() = instance
+            .module
+            .passive_data
+            .iter()
+            .map(|(idx, bytes)| (*idx, Arc::from(&bytes[..])))
+            .collect();
+
+        initialize_tables(instance)?;
+        initialize_memories(instance, data_initializers)?;
+        if run_start {
+            instance.invoke_start_function(trap_handler)?;
+        }
+        Ok(())
+    }
+
     /// Return a reference to the vmctx used by compiled wasm code.
     pub fn vmctx(&self) -> &VMContext {
         self.instance().vmctx()
@@ -1338,6 +1534,15 @@ impl VMInstance {
         self.instance().memory_index(memory)
     }
 
+    /// Return the index of the function defined by this instance whose
+    /// `VMCallerCheckedAnyfunc` is `anyfunc`, if any.
+    pub fn function_index(
+        &self,
+        anyfunc: NonNull<VMCallerCheckedAnyfunc>,
+    ) -> Option<FunctionIndex> {
+        self.instance().function_index(anyfunc)
+    }
+
     /// Grow memory in this instance by the specified amount of pages.
     ///
     /// Returns `None` if memory can't be grown by the specified amount
@@ -0,0 +1,56 @@
+//! Interruption of the WebAssembly code running in a store, from another
+//! thread.
+
+use crate::store::StoreObjects;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// A handle to interrupt the WebAssembly code running in a store, see
+/// [`StoreObjects::interrupt_handle`].
+///
+/// The code of the modules compiled with interrupt checks checks the
+/// interrupt flag of its store on entry to every function and at the start
+/// of every loop iteration, and traps with
+/// [`TrapCode::Interrupted`](wasmer_types::TrapCode::Interrupted) when it
+/// is set. The code of other modules ignores it.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    flag: Arc<AtomicU32>,
+}
+
+impl InterruptHandle {
+    /// Makes the WebAssembly code running in the store trap at its next
+    /// function call or loop iteration.
+    ///
+    /// The interrupt stays pending until it has stopped a call: if no code
+    /// is running, the next call into the store traps as soon as it starts,
+    /// whichever function it calls.
+    pub fn interrupt(&self) {
+        self.flag.store(1, Ordering::SeqCst);
+    }
+
+    /// Returns whether an interrupt is pending.
+    pub fn is_interrupted(&self) -> bool {
+        self.flag.load(Ordering::SeqCst) != 0
+    }
+}
+
+impl StoreObjects {
+    /// Returns a handle to interrupt the WebAssembly code running in this
+    /// store, which can be sent to other threads.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Clears a pending interrupt of this store.
+    pub fn clear_interrupt(&self) {
+        self.interrupt.flag.store(0, Ordering::SeqCst);
+    }
+
+    /// Returns a pointer to the interrupt flag of this store, which is
+    /// nonzero while an interrupt is pending. It stays valid as long as
+    /// the store.
+    pub(crate) fn interrupt_flag(&self) -> *const u32 {
+        Arc::as_ptr(&self.interrupt.flag) as *const u32
+    }
+}
@@ -20,16 +20,24 @@
     )
 )]
 
+mod checkpoint;
+mod dirty;
+mod epoch;
 mod export;
 mod extern_ref;
 mod function_env;
 mod global;
 mod imports;
 mod instance;
+mod interrupt;
+mod limiter;
 mod memory;
 mod mmap;
+mod pool;
 mod probestack;
+mod remove;
 mod sig_registry;
+mod snapshot;
 mod store;
 mod table;
 mod trap;
@@ -39,6 +47,9 @@ pub mod libcalls;
 
 use std::ptr::NonNull;
 
+pub use crate::checkpoint::StoreCheckpoint;
+pub use crate::dirty::DirtyPages;
+pub use crate::epoch::{EpochCounter, EpochDeadlineAction, EpochDeadlineCallbackFn};
 pub use crate::export::*;
 pub use crate::extern_ref::{VMExternObj, VMExternRef};
 pub use crate::function_env::VMFunctionEnvironment;
@@ -46,10 +57,13 @@ pub use crate::global::*;
 pub use crate::imports::Imports;
 #[allow(deprecated)]
 pub use crate::instance::{InstanceAllocator, InstanceHandle, VMInstance};
+pub use crate::interrupt::InterruptHandle;
+pub use crate::limiter::{ResourceLimitAction, ResourceLimiter};
 pub use crate::memory::{
     initialize_memory_with_data, LinearMemory, VMMemory, VMOwnedMemory, VMSharedMemory,
 };
 pub use crate::mmap::Mmap;
+pub use crate::pool::{PoolingAllocator, PoolingConfig};
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::store::{InternalStoreHandle, MaybeInstanceOwned, StoreHandle, StoreObjects};
@@ -62,6 +76,7 @@ pub use crate::vmcontext::{
 };
 pub use wasmer_types::LibCall;
 pub use wasmer_types::MemoryError;
+pub use wasmer_types::RemoveError;
 pub use wasmer_types::MemoryStyle;
 use wasmer_types::RawValue;
 pub use wasmer_types::TableStyle;
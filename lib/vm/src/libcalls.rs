@@ -44,7 +44,7 @@ use crate::vmcontext::VMContext;
 use crate::{on_host_stack, VMFuncRef};
 pub use wasmer_types::LibCall;
 use wasmer_types::{
-    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
+    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, Pages,
     TableIndex, Type,
 };
 
@@ -149,15 +149,23 @@ pub unsafe extern "C" fn wasmer_vm_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
 
-        instance
+        let index = instance.module_ref().memory_index(memory_index);
+        if !instance.memory_growing(index, Pages(delta))? {
+            return Ok(u32::max_value());
+        }
+        Ok(instance
             .memory_grow(memory_index, delta)
             .map(|pages| pages.0)
-            .unwrap_or(u32::max_value())
-    })
+            .unwrap_or(u32::max_value()))
+    });
+    match result {
+        Ok(pages) => pages,
+        Err(trap) => raise_lib_trap(trap),
+    }
 }
 
 /// Implementation of memory.grow for imported 32-bit memories.
@@ -171,15 +179,22 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let memory_index = MemoryIndex::from_u32(memory_index);
 
-        instance
+        if !instance.memory_growing(memory_index, Pages(delta))? {
+            return Ok(u32::max_value());
+        }
+        Ok(instance
             .imported_memory_grow(memory_index, delta)
             .map(|pages| pages.0)
-            .unwrap_or(u32::max_value())
-    })
+            .unwrap_or(u32::max_value()))
+    });
+    match result {
+        Ok(pages) => pages,
+        Err(trap) => raise_lib_trap(trap),
+    }
 }
 
 /// Implementation of memory.size for locally-defined 32-bit memories.
@@ -442,7 +457,7 @@ pub unsafe extern "C" fn wasmer_vm_table_grow(
     delta: u32,
     table_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let table_index = LocalTableIndex::from_u32(table_index);
 
@@ -452,10 +467,18 @@ pub unsafe extern "C" fn wasmer_vm_table_grow(
             _ => panic!("Unrecognized table type: does not contain references"),
         };
 
-        instance
+        let index = instance.module_ref().table_index(table_index);
+        if !instance.table_growing(index, delta)? {
+            return Ok(u32::max_value());
+        }
+        Ok(instance
             .table_grow(table_index, delta, init_value)
-            .unwrap_or(u32::max_value())
-    })
+            .unwrap_or(u32::max_value()))
+    });
+    match result {
+        Ok(size) => size,
+        Err(trap) => raise_lib_trap(trap),
+    }
 }
 
 /// Implementation of `table.grow` for imported tables.
@@ -470,7 +493,7 @@ pub unsafe extern "C" fn wasmer_vm_imported_table_grow(
     delta: u32,
     table_index: u32,
 ) -> u32 {
-    on_host_stack(|| {
+    let result = on_host_stack(|| {
         let instance = (*vmctx).instance_mut();
         let table_index = TableIndex::from_u32(table_index);
         let init_value = match instance.get_table(table_index).ty().ty {
@@ -479,10 +502,17 @@ pub unsafe extern "C" fn wasmer_vm_imported_table_grow(
             _ => panic!("Unrecognized table type: does not contain references"),
         };
 
-        instance
+        if !instance.table_growing(table_index, delta)? {
+            return Ok(u32::max_value());
+        }
+        Ok(instance
             .imported_table_grow(table_index, delta, init_value)
-            .unwrap_or(u32::max_value())
-    })
+            .unwrap_or(u32::max_value()))
+    });
+    match result {
+        Ok(size) => size,
+        Err(trap) => raise_lib_trap(trap),
+    }
 }
 
 /// Implementation of `func.ref`.
@@ -815,6 +845,23 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_atomic_notify(
     result.unwrap()
 }
 
+/// Implementation of the epoch deadline check, called by compiled code
+/// once the epoch of the engine has reached the deadline of the store.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_epoch_deadline_reached(vmctx: *mut VMContext) {
+    let result = on_host_stack(|| {
+        let instance = (*vmctx).instance_mut();
+        instance.context_mut().epoch_deadline_reached()
+    });
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
 /// The function pointer to a libcall
 pub fn function_pointer(libcall: LibCall) -> usize {
     match libcall {
@@ -855,5 +902,6 @@ pub fn function_pointer(libcall: LibCall) -> usize {
         LibCall::ImportedMemory32AtomicWait64 => wasmer_vm_imported_memory32_atomic_wait64 as usize,
         LibCall::Memory32AtomicNotify => wasmer_vm_memory32_atomic_notify as usize,
         LibCall::ImportedMemory32AtomicNotify => wasmer_vm_imported_memory32_atomic_notify as usize,
+        LibCall::EpochDeadlineReached => wasmer_vm_epoch_deadline_reached as usize,
     }
 }
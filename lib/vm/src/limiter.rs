@@ -0,0 +1,134 @@
+//! Resource limiters, to give each store its own quotas of instances,
+//! memory pages and table elements.
+//!
+//! The limiter of a store is consulted before any instance, memory or
+//! table is created in it, and before any of its memories or tables
+//! grows, be it from WebAssembly code or from the host.
+
+use crate::store::{StoreObject, StoreObjects};
+use crate::trap::{Trap, TrapCode};
+use crate::VMInstance;
+use std::fmt;
+use wasmer_types::Pages;
+
+/// What to do with a request for resources, as decided by a
+/// [`ResourceLimiter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceLimitAction {
+    /// Grant the request.
+    Allow,
+    /// Refuse the request: `memory.grow` and `table.grow` return -1, and
+    /// the creation of an instance, a memory or a table fails.
+    Deny,
+    /// Refuse the request, and stop the WebAssembly code making it with a
+    /// [`TrapCode::ResourceLimitExceeded`] trap.
+    Trap,
+}
+
+/// Limits the resources used by a store.
+///
+/// The creation of a memory or a table is reported as a growth from zero to
+/// its minimum size.
+pub trait ResourceLimiter: Send {
+    /// Called before a memory grows from `current` to `desired` pages.
+    /// `maximum` is the maximum declared by the memory, if any.
+    fn memory_growing(
+        &mut self,
+        current: Pages,
+        desired: Pages,
+        maximum: Option<Pages>,
+    ) -> ResourceLimitAction;
+
+    /// Called before a table grows from `current` to `desired` elements.
+    /// `maximum` is the maximum declared by the table, if any.
+    fn table_growing(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> ResourceLimitAction;
+
+    /// Called before an instance is created in a store already holding
+    /// `instances` instances.
+    fn instance_creating(&mut self, instances: usize) -> ResourceLimitAction {
+        let _ = instances;
+        ResourceLimitAction::Allow
+    }
+}
+
+/// The resource limiter of a store.
+#[derive(Default)]
+pub(crate) struct StoreLimiter {
+    limiter: Option<Box<dyn ResourceLimiter>>,
+}
+
+impl fmt::Debug for StoreLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreLimiter")
+            .field("limiter", &self.limiter.is_some())
+            .finish()
+    }
+}
+
+impl StoreLimiter {
+    fn decide(
+        &mut self,
+        decide: impl FnOnce(&mut dyn ResourceLimiter) -> ResourceLimitAction,
+    ) -> Result<bool, Trap> {
+        let action = match self.limiter.as_mut() {
+            Some(limiter) => decide(limiter.as_mut()),
+            None => ResourceLimitAction::Allow,
+        };
+        match action {
+            ResourceLimitAction::Allow => Ok(true),
+            ResourceLimitAction::Deny => Ok(false),
+            ResourceLimitAction::Trap => Err(Trap::lib(TrapCode::ResourceLimitExceeded)),
+        }
+    }
+}
+
+impl StoreObjects {
+    /// Sets the resource limiter of this store. Without a limiter, only
+    /// the limits of the tunables of the engine apply.
+    pub fn set_resource_limiter(&mut self, limiter: Option<Box<dyn ResourceLimiter>>) {
+        self.limiter.limiter = limiter;
+    }
+
+    /// Asks the resource limiter of this store whether a memory may grow
+    /// from `current` to `desired` pages.
+    ///
+    /// Returns `Ok(false)` if the growth is denied, and the trap to raise if
+    /// the limiter asks for one.
+    pub fn memory_growing(
+        &mut self,
+        current: Pages,
+        desired: Pages,
+        maximum: Option<Pages>,
+    ) -> Result<bool, Trap> {
+        self.limiter
+            .decide(|limiter| limiter.memory_growing(current, desired, maximum))
+    }
+
+    /// Asks the resource limiter of this store whether a table may grow
+    /// from `current` to `desired` elements.
+    ///
+    /// Returns `Ok(false)` if the growth is denied, and the trap to raise if
+    /// the limiter asks for one.
+    pub fn table_growing(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> Result<bool, Trap> {
+        self.limiter
+            .decide(|limiter| limiter.table_growing(current, desired, maximum))
+    }
+
+    /// Asks the resource limiter of this store whether a new instance may
+    /// be created in it.
+    pub fn instance_creating(&mut self) -> Result<bool, Trap> {
+        let instances = VMInstance::list(self).len();
+        self.limiter
+            .decide(|limiter| limiter.instance_creating(instances))
+    }
+}
@@ -5,6 +5,7 @@
 //!
 //! `Memory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+use crate::dirty::{DirtyPages, PageTracker};
 use crate::trap::Trap;
 use crate::{mmap::Mmap, store::MaybeInstanceOwned, vmcontext::VMMemoryDefinition};
 use more_asserts::assert_ge;
@@ -13,11 +14,14 @@ use std::convert::TryInto;
 use std::ptr::NonNull;
 use std::slice;
 use std::sync::{Arc, RwLock};
-use wasmer_types::{Bytes, MemoryError, MemoryStyle, MemoryType, Pages};
+use wasmer_types::{Bytes, MemoryError, MemoryStyle, MemoryType, Pages, WASM_PAGE_SIZE};
 
 // The memory mapped area
 #[derive(Debug)]
 struct WasmMmap {
+    // The pages written since the last checkpoint. Declared before `alloc`
+    // so that the pages are unprotected before being unmapped.
+    tracker: Option<Box<PageTracker>>,
     // Our OS allocation of mmap'd memory.
     alloc: Mmap,
     // The current logical size in wasm pages of this linear memory.
@@ -96,6 +100,9 @@ impl WasmMmap {
             let copy_len = self.alloc.len() - conf.offset_guard_size;
             new_mmap.as_mut_slice()[..copy_len].copy_from_slice(&self.alloc.as_slice()[..copy_len]);
 
+            if let Some(tracker) = &mut self.tracker {
+                unsafe { tracker.relocate(new_mmap.as_mut_ptr()) }.map_err(MemoryError::Region)?;
+            }
             self.alloc = new_mmap;
         } else if delta_bytes > 0 {
             // Make the newly allocated pages accessible.
@@ -103,6 +110,9 @@ impl WasmMmap {
                 .make_accessible(prev_bytes, delta_bytes)
                 .map_err(MemoryError::Region)?;
         }
+        if let Some(tracker) = &mut self.tracker {
+            tracker.resize(new_bytes).map_err(MemoryError::Region)?;
+        }
 
         self.size = new_pages;
 
@@ -117,6 +127,143 @@ impl WasmMmap {
         Ok(prev_pages)
     }
 
+    /// Returns the tracker of the pages written to the memory, creating
+    /// it if needed.
+    fn tracker(&mut self, conf: &VMMemoryConfig) -> Result<&mut PageTracker, MemoryError> {
+        if self.tracker.is_none() {
+            let max_pages = conf.maximum.unwrap_or_else(Pages::max_value);
+            let max_bytes = (max_pages.0 as usize).saturating_mul(WASM_PAGE_SIZE);
+            let tracker = unsafe {
+                PageTracker::new(self.alloc.as_mut_ptr(), self.size.bytes().0, max_bytes)
+            }
+            .map_err(MemoryError::Region)?;
+            self.tracker = Some(tracker);
+        }
+        Ok(self.tracker.as_mut().unwrap())
+    }
+
+    /// Drops the tracker of the pages written to the memory once it has
+    /// nothing left to record.
+    fn release_idle_tracker(&mut self) {
+        if self
+            .tracker
+            .as_ref()
+            .map_or(false, |tracker| tracker.is_idle())
+        {
+            self.tracker = None;
+        }
+    }
+
+    fn checkpoint(&mut self, conf: &VMMemoryConfig) -> Result<(), MemoryError> {
+        if self
+            .tracker
+            .as_ref()
+            .map_or(false, |tracker| tracker.has_checkpoint())
+        {
+            return Err(MemoryError::Generic(
+                "the memory already has a checkpoint".to_string(),
+            ));
+        }
+        let result = self
+            .tracker(conf)?
+            .start_checkpoint()
+            .map_err(MemoryError::Region);
+        self.release_idle_tracker();
+        result
+    }
+
+    fn end_checkpoint(&mut self, restore: bool) -> Result<(), MemoryError> {
+        let checkpoint_len = self
+            .tracker
+            .as_mut()
+            .and_then(|tracker| unsafe { tracker.end_checkpoint(restore) })
+            .ok_or_else(|| MemoryError::Generic("the memory has no checkpoint".to_string()))?;
+
+        // Discard the pages grown since the checkpoint. They have to leave
+        // the tracked memory first, so that accessing them traps.
+        let current_len = self.size.bytes().0;
+        if restore && current_len > checkpoint_len {
+            if let Some(tracker) = &mut self.tracker {
+                tracker
+                    .resize(checkpoint_len)
+                    .map_err(MemoryError::Region)?;
+            }
+            self.alloc
+                .make_inaccessible(checkpoint_len, current_len - checkpoint_len)
+                .map_err(MemoryError::Region)?;
+            self.size = Bytes(checkpoint_len).try_into().unwrap();
+            unsafe {
+                let mut md_ptr = self.vm_memory_definition.as_ptr();
+                md_ptr.as_mut().current_length = checkpoint_len;
+            }
+        }
+        self.release_idle_tracker();
+        Ok(())
+    }
+
+    fn enable_dirty_tracking(&mut self, conf: &VMMemoryConfig) -> Result<(), MemoryError> {
+        let result = self
+            .tracker(conf)?
+            .start_tracking_dirty()
+            .map_err(MemoryError::Region);
+        self.release_idle_tracker();
+        result
+    }
+
+    fn disable_dirty_tracking(&mut self) {
+        if let Some(tracker) = &mut self.tracker {
+            tracker.stop_tracking_dirty();
+        }
+        self.release_idle_tracker();
+    }
+
+    fn dirty_tracker(&self) -> Result<&PageTracker, MemoryError> {
+        self.tracker
+            .as_deref()
+            .filter(|tracker| tracker.is_tracking_dirty())
+            .ok_or_else(|| MemoryError::Generic("dirty page tracking is not enabled".to_string()))
+    }
+
+    fn dirty_pages(&self) -> Result<DirtyPages, MemoryError> {
+        Ok(self.dirty_tracker()?.dirty_pages())
+    }
+
+    fn reset_dirty_pages(&mut self) -> Result<(), MemoryError> {
+        self.dirty_tracker()?;
+        self.tracker
+            .as_mut()
+            .unwrap()
+            .reset_dirty()
+            .map_err(MemoryError::Region)
+    }
+
+    /// Zeroes the memory and shrinks it back to `minimum` pages, keeping
+    /// its mapping.
+    fn reset(&mut self, minimum: Pages) -> Result<(), MemoryError> {
+        if self.tracker.is_some() {
+            return Err(MemoryError::Generic(
+                "the memory can't be reset while it has a checkpoint or tracks dirty pages"
+                    .to_string(),
+            ));
+        }
+        let current_len = self.size.bytes().0;
+        let minimum_len = minimum.bytes().0.min(current_len);
+        self.alloc
+            .reset(0, minimum_len)
+            .map_err(MemoryError::Region)?;
+        if current_len > minimum_len {
+            self.alloc
+                .make_inaccessible(minimum_len, current_len - minimum_len)
+                .map_err(MemoryError::Region)?;
+            self.size = Bytes(minimum_len).try_into().unwrap();
+            unsafe {
+                let mut md_ptr = self.vm_memory_definition.as_ptr();
+                md_ptr.as_mut().current_length = minimum_len;
+            }
+        }
+        Ok(())
+    }
+
     /// Copies the memory
     /// (in this case it performs a copy-on-write to save memory)
     pub fn duplicate(&mut self) -> Result<Self, MemoryError> {
@@ -127,6 +274,7 @@ impl WasmMmap {
             .map_err(MemoryError::Generic)?;
         let base_ptr = alloc.as_mut_ptr();
         Ok(Self {
+            tracker: None,
             vm_memory_definition: MaybeInstanceOwned::Host(Box::new(UnsafeCell::new(
                 VMMemoryDefinition {
                     base: base_ptr,
@@ -251,6 +399,7 @@ impl VMOwnedMemory {
         let base_ptr = alloc.as_mut_ptr();
         let mem_length = memory.minimum.bytes().0;
         let mmap = WasmMmap {
+            tracker: None,
             vm_memory_definition: if let Some(mem_loc) = vm_memory_location {
                 {
                     let mut ptr = mem_loc;
@@ -322,6 +471,46 @@ impl LinearMemory for VMOwnedMemory {
         self.mmap.grow(delta, self.config.clone())
     }
 
+    /// Starts recording the pages written to this memory.
+    fn checkpoint(&mut self) -> Result<(), MemoryError> {
+        self.mmap.checkpoint(&self.config)
+    }
+
+    /// Restores the memory to its state at the checkpoint.
+    fn rollback(&mut self) -> Result<(), MemoryError> {
+        self.mmap.end_checkpoint(true)
+    }
+
+    /// Keeps the changes made since the checkpoint.
+    fn commit(&mut self) -> Result<(), MemoryError> {
+        self.mmap.end_checkpoint(false)
+    }
+
+    /// Starts recording the pages written to this memory.
+    fn enable_dirty_tracking(&mut self) -> Result<(), MemoryError> {
+        self.mmap.enable_dirty_tracking(&self.config)
+    }
+
+    /// Stops recording the pages written to this memory.
+    fn disable_dirty_tracking(&mut self) {
+        self.mmap.disable_dirty_tracking()
+    }
+
+    /// Returns the pages written since the last reset.
+    fn dirty_pages(&self) -> Result<DirtyPages, MemoryError> {
+        self.mmap.dirty_pages()
+    }
+
+    /// Forgets the pages written so far.
+    fn reset_dirty_pages(&mut self) -> Result<(), MemoryError> {
+        self.mmap.reset_dirty_pages()
+    }
+
+    /// Zeroes the memory and shrinks it back to its minimum size.
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        self.mmap.reset(self.config.memory.minimum)
+    }
+
     /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
         self.mmap.vm_memory_definition.as_ptr()
@@ -415,6 +604,54 @@ impl LinearMemory for VMSharedMemory {
         guard.grow(delta, self.config.clone())
     }
 
+    /// Starts recording the pages written to this memory.
+    fn checkpoint(&mut self) -> Result<(), MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.checkpoint(&self.config)
+    }
+
+    /// Restores the memory to its state at the checkpoint.
+    fn rollback(&mut self) -> Result<(), MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.end_checkpoint(true)
+    }
+
+    /// Keeps the changes made since the checkpoint.
+    fn commit(&mut self) -> Result<(), MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.end_checkpoint(false)
+    }
+
+    /// Starts recording the pages written to this memory.
+    fn enable_dirty_tracking(&mut self) -> Result<(), MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.enable_dirty_tracking(&self.config)
+    }
+
+    /// Stops recording the pages written to this memory.
+    fn disable_dirty_tracking(&mut self) {
+        let mut guard = self.mmap.write().unwrap();
+        guard.disable_dirty_tracking()
+    }
+
+    /// Returns the pages written since the last reset.
+    fn dirty_pages(&self) -> Result<DirtyPages, MemoryError> {
+        let guard = self.mmap.read().unwrap();
+        guard.dirty_pages()
+    }
+
+    /// Forgets the pages written so far.
+    fn reset_dirty_pages(&mut self) -> Result<(), MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.reset_dirty_pages()
+    }
+
+    /// Zeroes the memory and shrinks it back to its minimum size.
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.reset(self.config.memory.minimum)
+    }
+
     /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
         let guard = self.mmap.read().unwrap();
@@ -474,6 +711,46 @@ impl LinearMemory for VMMemory {
         self.0.grow(delta)
     }
 
+    /// Starts recording the pages written to this memory.
+    fn checkpoint(&mut self) -> Result<(), MemoryError> {
+        self.0.checkpoint()
+    }
+
+    /// Restores the memory to its state at the checkpoint.
+    fn rollback(&mut self) -> Result<(), MemoryError> {
+        self.0.rollback()
+    }
+
+    /// Keeps the changes made since the checkpoint.
+    fn commit(&mut self) -> Result<(), MemoryError> {
+        self.0.commit()
+    }
+
+    /// Starts recording the pages written to this memory.
+    fn enable_dirty_tracking(&mut self) -> Result<(), MemoryError> {
+        self.0.enable_dirty_tracking()
+    }
+
+    /// Stops recording the pages written to this memory.
+    fn disable_dirty_tracking(&mut self) {
+        self.0.disable_dirty_tracking()
+    }
+
+    /// Returns the pages written since the last reset.
+    fn dirty_pages(&self) -> Result<DirtyPages, MemoryError> {
+        self.0.dirty_pages()
+    }
+
+    /// Forgets the pages written so far.
+    fn reset_dirty_pages(&mut self) -> Result<(), MemoryError> {
+        self.0.reset_dirty_pages()
+    }
+
+    /// Zeroes the memory and shrinks it back to its minimum size.
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        self.0.reset()
+    }
+
     /// Returns the memory style for this memory.
     fn style(&self) -> MemoryStyle {
         self.0.style()
@@ -598,6 +875,54 @@ where
     /// of wasm pages.
     fn grow(&mut self, delta: Pages) -> Result<Pages, MemoryError>;
 
+    /// Starts recording the pages written to this memory, so that its
+    /// contents and size can be restored with [`LinearMemory::rollback`].
+    ///
+    /// Only one checkpoint can be active at a time. Memories that don't
+    /// support checkpoints return [`MemoryError::Unsupported`].
+    fn checkpoint(&mut self) -> Result<(), MemoryError> {
+        Err(MemoryError::Unsupported("checkpoints".to_string()))
+    }
+
+    /// Restores the contents and size of the memory at the last
+    /// checkpoint, and ends it.
+    fn rollback(&mut self) -> Result<(), MemoryError> {
+        Err(MemoryError::Unsupported("checkpoints".to_string()))
+    }
+
+    /// Ends the last checkpoint, keeping the changes made since.
+    fn commit(&mut self) -> Result<(), MemoryError> {
+        Err(MemoryError::Unsupported("checkpoints".to_string()))
+    }
+
+    /// Starts recording the pages written to this memory, which are
+    /// reported by [`LinearMemory::dirty_pages`]. Memories that don't
+    /// support dirty page tracking return [`MemoryError::Unsupported`].
+    fn enable_dirty_tracking(&mut self) -> Result<(), MemoryError> {
+        Err(MemoryError::Unsupported("dirty page tracking".to_string()))
+    }
+
+    /// Stops recording the pages written to this memory.
+    fn disable_dirty_tracking(&mut self) {}
+
+    /// Returns the pages written since dirty page tracking was enabled or
+    /// last reset with [`LinearMemory::reset_dirty_pages`].
+    fn dirty_pages(&self) -> Result<DirtyPages, MemoryError> {
+        Err(MemoryError::Unsupported("dirty page tracking".to_string()))
+    }
+
+    /// Forgets the pages written so far.
+    fn reset_dirty_pages(&mut self) -> Result<(), MemoryError> {
+        Err(MemoryError::Unsupported("dirty page tracking".to_string()))
+    }
+
+    /// Zeroes the memory and shrinks it back to the minimum size of its
+    /// type, as if it had just been created. Memories that can't be reset
+    /// return [`MemoryError::Unsupported`].
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        Err(MemoryError::Unsupported("resetting".to_string()))
+    }
+
     /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
 
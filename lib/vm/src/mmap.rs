@@ -233,6 +233,89 @@ impl Mmap {
         Ok(())
     }
 
+    /// Make the memory starting at `start` and extending for `len` bytes inaccessible
+    /// again, discarding its contents. `start` and `len` must be native page-size
+    /// multiples and describe a range within `self`'s reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn make_inaccessible(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.total_size);
+        assert_le!(start, self.total_size - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        // Mapping fresh pages over the range releases the old ones.
+        let ptr = unsafe {
+            libc::mmap(
+                (self.ptr + start) as *mut libc::c_void,
+                len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+                -1,
+                0,
+            )
+        };
+        if ptr as isize == -1_isize {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        self.accessible_size = self.accessible_size.min(start);
+        Ok(())
+    }
+
+    /// Make the memory starting at `start` and extending for `len` bytes inaccessible
+    /// again, discarding its contents. `start` and `len` must be native page-size
+    /// multiples and describe a range within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn make_inaccessible(&mut self, start: usize, len: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::VirtualFree;
+        use winapi::um::winnt::MEM_DECOMMIT;
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len());
+        assert_le!(start, self.len() - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        if unsafe { VirtualFree((self.ptr + start) as *mut c_void, len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        self.accessible_size = self.accessible_size.min(start);
+        Ok(())
+    }
+
+    /// Zero the memory starting at `start` and extending for `len` bytes, which
+    /// must be accessible. `start` and `len` must be native page-size multiples.
+    ///
+    /// On Linux the pages are given back to the OS, and are zero-filled again
+    /// on their next access.
+    pub fn reset(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.total_size);
+        assert_le!(start, self.total_size - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        let ptr = (self.ptr + start) as *mut u8;
+        #[cfg(target_os = "linux")]
+        if unsafe { libc::madvise(ptr as *mut libc::c_void, len, libc::MADV_DONTNEED) } != 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        #[cfg(not(target_os = "linux"))]
+        unsafe {
+            ptr::write_bytes(ptr, 0, len);
+        }
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.total_size) }
@@ -0,0 +1,527 @@
+//! Pooling allocation of instances, and of the memories and tables they
+//! define.
+//!
+//! A [`PoolingAllocator`] reserves a fixed number of slots up front, and
+//! recycles them as the instances using them are dropped. This bounds the
+//! memory used by the instances, and saves the `mmap`/`munmap` calls of
+//! allocating them one by one.
+//!
+//! The instance and memory slots are carved out of two large mappings
+//! reserved when the allocator is created. Each memory slot is followed by
+//! a guard region which is never made accessible. Table slots are the
+//! backing storage of the tables, allocated once at their maximum size.
+
+use crate::mmap::Mmap;
+use crate::store::MaybeInstanceOwned;
+use crate::table::RawTableElement;
+use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
+use crate::{InstanceAllocator, LinearMemory, VMMemory, VMOwnedMemory, VMTable};
+use std::convert::TryInto;
+use std::fmt;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::{Arc, Mutex};
+use wasmer_types::{
+    Bytes, MemoryError, MemoryStyle, MemoryType, ModuleInfo, Pages, TableStyle, TableType,
+};
+
+/// The number and the size of the slots of a [`PoolingAllocator`].
+#[derive(Debug, Clone)]
+pub struct PoolingConfig {
+    /// The number of instance slots.
+    pub instances: usize,
+    /// The size in bytes of an instance slot, which holds the instance and
+    /// its `VMContext`.
+    pub instance_size: usize,
+    /// The number of memory slots.
+    pub memories: usize,
+    /// The size in wasm pages of a memory slot, which is the most a memory
+    /// allocated from the pool can grow to.
+    pub memory_pages: Pages,
+    /// The size in bytes of the guard region after each memory slot.
+    pub memory_guard_size: usize,
+    /// The number of table slots.
+    pub tables: usize,
+    /// The number of elements of a table slot, which is the most a table
+    /// allocated from the pool can grow to.
+    pub table_elements: u32,
+}
+
+impl Default for PoolingConfig {
+    fn default() -> Self {
+        Self {
+            instances: 1000,
+            instance_size: 0x10_0000,
+            memories: 1000,
+            memory_pages: Pages(160),
+            memory_guard_size: 0x1_0000,
+            tables: 1000,
+            table_elements: 10_000,
+        }
+    }
+}
+
+/// Fixed-size slots carved out of one mapping.
+struct SlotRegion {
+    mmap: Mutex<Mmap>,
+    slot_size: usize,
+    free: Mutex<Vec<usize>>,
+    count: usize,
+}
+
+impl SlotRegion {
+    fn new(count: usize, slot_size: usize, accessible: bool) -> Result<Self, String> {
+        let page_size = region::page::size();
+        let slot_size = (slot_size + (page_size - 1)) & !(page_size - 1);
+        let slots_size = count
+            .checked_mul(slot_size)
+            .ok_or_else(|| "the pool is too large".to_string())?;
+        let mmap = if accessible {
+            Mmap::accessible_reserved(slots_size, slots_size)?
+        } else {
+            // Keep a page after the last slot, so that a slot without a guard
+            // region can still be made accessible in full.
+            Mmap::accessible_reserved(0, slots_size + page_size)?
+        };
+        Ok(Self {
+            mmap: Mutex::new(mmap),
+            slot_size,
+            // Hand out the lowest slots first.
+            free: Mutex::new((0..count).rev().collect()),
+            count,
+        })
+    }
+
+    fn acquire(&self) -> Option<usize> {
+        self.free.lock().unwrap().pop()
+    }
+
+    fn release(&self, index: usize) {
+        self.free.lock().unwrap().push(index);
+    }
+
+    fn in_use(&self) -> usize {
+        self.count - self.free.lock().unwrap().len()
+    }
+
+    fn slot_ptr(&self, index: usize) -> *mut u8 {
+        let base = self.mmap.lock().unwrap().as_mut_ptr();
+        unsafe { base.add(index * self.slot_size) }
+    }
+
+    fn make_accessible(&self, index: usize, start: usize, len: usize) -> Result<(), String> {
+        if len == 0 {
+            return Ok(());
+        }
+        self.mmap
+            .lock()
+            .unwrap()
+            .make_accessible(index * self.slot_size + start, len)
+    }
+
+    fn make_inaccessible(&self, index: usize, start: usize, len: usize) -> Result<(), String> {
+        self.mmap
+            .lock()
+            .unwrap()
+            .make_inaccessible(index * self.slot_size + start, len)
+    }
+
+    fn reset(&self, index: usize, start: usize, len: usize) -> Result<(), String> {
+        self.mmap
+            .lock()
+            .unwrap()
+            .reset(index * self.slot_size + start, len)
+    }
+}
+
+/// The table slots in use, and the storage of the free ones, allocated up
+/// front.
+struct TableSlots {
+    free: Vec<Vec<RawTableElement>>,
+    in_use: usize,
+}
+
+// The released storage only holds null elements.
+unsafe impl Send for TableSlots {}
+
+struct PoolInner {
+    config: PoolingConfig,
+    instances: SlotRegion,
+    memories: SlotRegion,
+    tables: Mutex<TableSlots>,
+}
+
+impl fmt::Debug for PoolInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolInner")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Allocates instances, and the memories and tables they define, from
+/// slots reserved up front and recycled.
+///
+/// The allocator is used by an engine through its tunables.
+#[derive(Debug, Clone)]
+pub struct PoolingAllocator {
+    inner: Arc<PoolInner>,
+}
+
+impl PoolingAllocator {
+    /// Reserves the slots described by `config`.
+    pub fn new(config: PoolingConfig) -> Result<Self, String> {
+        let instances = SlotRegion::new(config.instances, config.instance_size, true)?;
+        let memory_bytes = config.memory_pages.bytes().0;
+        let memory_slot_size = memory_bytes
+            .checked_add(config.memory_guard_size)
+            .ok_or_else(|| "the memory slots are too large".to_string())?;
+        let memories = SlotRegion::new(config.memories, memory_slot_size, false)?;
+        let tables = TableSlots {
+            free: (0..config.tables)
+                .map(|_| Vec::with_capacity(config.table_elements as usize))
+                .collect(),
+            in_use: 0,
+        };
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                config,
+                instances,
+                memories,
+                tables: Mutex::new(tables),
+            }),
+        })
+    }
+
+    /// Returns the configuration of this allocator.
+    pub fn config(&self) -> &PoolingConfig {
+        &self.inner.config
+    }
+
+    /// Returns the number of instance slots in use.
+    pub fn instances_in_use(&self) -> usize {
+        self.inner.instances.in_use()
+    }
+
+    /// Returns the number of memory slots in use, including the ones which
+    /// could not be discarded when their memory was dropped, and were
+    /// leaked.
+    pub fn memories_in_use(&self) -> usize {
+        self.inner.memories.in_use()
+    }
+
+    /// Returns the number of table slots in use.
+    pub fn tables_in_use(&self) -> usize {
+        self.inner.tables.lock().unwrap().in_use
+    }
+
+    /// Allocates instance data for use with [`VMInstance::new`] in an
+    /// instance slot. See [`InstanceAllocator::new`].
+    ///
+    /// [`VMInstance::new`]: crate::VMInstance::new
+    #[allow(clippy::type_complexity)]
+    pub fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        String,
+    > {
+        let index = self
+            .inner
+            .instances
+            .acquire()
+            .ok_or_else(|| "all the instance slots of the pool are in use".to_string())?;
+        let slot = InstanceSlot {
+            pool: self.inner.clone(),
+            index,
+        };
+        InstanceAllocator::new_in_slot(module, slot)
+    }
+
+    /// Creates a memory defined by an instance in a memory slot.
+    ///
+    /// Only dynamic memories whose offset guard fits in the guard region of
+    /// the slots can be allocated from the pool.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid location in VM memory.
+    pub unsafe fn create_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        let config = &self.inner.config;
+        match style {
+            MemoryStyle::Dynamic { offset_guard_size }
+                if *offset_guard_size as usize <= config.memory_guard_size => {}
+            _ => {
+                return Err(MemoryError::Generic(format!(
+                    "the memory style {:?} is not supported by the pool",
+                    style
+                )))
+            }
+        }
+        if ty.shared {
+            return Err(MemoryError::Generic(
+                "shared memories can't be allocated from the pool".to_string(),
+            ));
+        }
+        if ty.minimum > config.memory_pages {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: ty.minimum,
+                max_allowed: config.memory_pages,
+            });
+        }
+
+        let memories = &self.inner.memories;
+        let index = memories.acquire().ok_or_else(|| {
+            MemoryError::Generic("all the memory slots of the pool are in use".to_string())
+        })?;
+        let mut memory = PooledMemory {
+            pool: self.inner.clone(),
+            index,
+            size: Pages(0),
+            maximum: ty.maximum.map_or(config.memory_pages, |maximum| {
+                maximum.min(config.memory_pages)
+            }),
+            ty: *ty,
+            style: *style,
+            vm_memory_definition: MaybeInstanceOwned::Instance(vm_definition_location),
+        };
+        {
+            let mut md_ptr = vm_definition_location;
+            let md = md_ptr.as_mut();
+            md.base = memories.slot_ptr(index);
+            md.current_length = 0;
+        }
+        memory.grow(ty.minimum)?;
+        Ok(VMMemory(Box::new(memory)))
+    }
+
+    /// Creates a table defined by an instance in a table slot.
+    ///
+    /// # Safety
+    /// - `vm_definition_location` must point to a valid location in VM memory.
+    pub unsafe fn create_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        let elements = self.inner.config.table_elements;
+        if ty.minimum > elements {
+            return Err(format!(
+                "the table minimum ({}) is larger than the table slots of the pool ({})",
+                ty.minimum, elements
+            ));
+        }
+        let storage = {
+            let mut tables = self.inner.tables.lock().unwrap();
+            if tables.in_use >= self.inner.config.tables {
+                return Err("all the table slots of the pool are in use".to_string());
+            }
+            tables.in_use += 1;
+            // The storage of a slot is lost when creating its table fails.
+            tables
+                .free
+                .pop()
+                .unwrap_or_else(|| Vec::with_capacity(elements as usize))
+        };
+        let slot = TableSlot {
+            pool: self.inner.clone(),
+        };
+        VMTable::from_definition_in_slot(ty, style, vm_definition_location, storage, slot)
+    }
+}
+
+/// An instance slot of a [`PoolingAllocator`], given back to the pool when
+/// dropped.
+pub(crate) struct InstanceSlot {
+    pool: Arc<PoolInner>,
+    index: usize,
+}
+
+impl InstanceSlot {
+    /// Returns the start of the slot.
+    pub(crate) fn ptr(&self) -> *mut u8 {
+        self.pool.instances.slot_ptr(self.index)
+    }
+
+    /// Returns the size in bytes of the slot.
+    pub(crate) fn size(&self) -> usize {
+        self.pool.instances.slot_size
+    }
+}
+
+impl Drop for InstanceSlot {
+    fn drop(&mut self) {
+        self.pool.instances.release(self.index);
+    }
+}
+
+impl fmt::Debug for InstanceSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstanceSlot")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl PartialEq for InstanceSlot {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pool, &other.pool) && self.index == other.index
+    }
+}
+
+impl Eq for InstanceSlot {}
+
+/// A table slot of a [`PoolingAllocator`], given back to the pool when
+/// dropped. Its storage is recycled with [`TableSlot::release`].
+pub(crate) struct TableSlot {
+    pool: Arc<PoolInner>,
+}
+
+impl Drop for TableSlot {
+    fn drop(&mut self) {
+        self.pool.tables.lock().unwrap().in_use -= 1;
+    }
+}
+
+impl TableSlot {
+    /// Returns the number of elements the storage of the slot can hold.
+    pub(crate) fn elements(&self) -> u32 {
+        self.pool.config.table_elements
+    }
+
+    /// Gives the storage of the table back to the pool.
+    pub(crate) fn release(&self, mut storage: Vec<RawTableElement>) {
+        storage.clear();
+        self.pool.tables.lock().unwrap().free.push(storage);
+    }
+}
+
+/// A linear memory in a memory slot of a [`PoolingAllocator`].
+///
+/// Growing the memory only changes the protection of the pages of the
+/// slot, so the memory never moves.
+#[derive(Debug)]
+struct PooledMemory {
+    pool: Arc<PoolInner>,
+    index: usize,
+    // The current size in wasm pages of the memory.
+    size: Pages,
+    // The size the memory can grow to, bounded by the size of the slot.
+    maximum: Pages,
+    ty: MemoryType,
+    style: MemoryStyle,
+    vm_memory_definition: MaybeInstanceOwned<VMMemoryDefinition>,
+}
+
+unsafe impl Send for PooledMemory {}
+unsafe impl Sync for PooledMemory {}
+
+impl PooledMemory {
+    fn set_size(&mut self, size: Pages) {
+        self.size = size;
+        unsafe {
+            let mut md_ptr = self.vm_memory_definition.as_ptr();
+            md_ptr.as_mut().current_length = size.bytes().0;
+        }
+    }
+}
+
+impl LinearMemory for PooledMemory {
+    fn ty(&self) -> MemoryType {
+        let mut ty = self.ty;
+        ty.minimum = self.size;
+        ty
+    }
+
+    fn size(&self) -> Pages {
+        self.size
+    }
+
+    fn style(&self) -> MemoryStyle {
+        self.style
+    }
+
+    fn grow(&mut self, delta: Pages) -> Result<Pages, MemoryError> {
+        let prev_pages = self.size;
+        let new_pages = match prev_pages.checked_add(delta) {
+            Some(new_pages) if new_pages <= self.maximum => new_pages,
+            _ => {
+                return Err(MemoryError::CouldNotGrow {
+                    current: prev_pages,
+                    attempted_delta: delta,
+                })
+            }
+        };
+        self.pool
+            .memories
+            .make_accessible(self.index, prev_pages.bytes().0, delta.bytes().0)
+            .map_err(MemoryError::Region)?;
+        self.set_size(new_pages);
+        Ok(prev_pages)
+    }
+
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        let current_len = self.size.bytes().0;
+        let minimum_len = self.ty.minimum.bytes().0.min(current_len);
+        let memories = &self.pool.memories;
+        memories
+            .reset(self.index, 0, minimum_len)
+            .map_err(MemoryError::Region)?;
+        memories
+            .make_inaccessible(self.index, minimum_len, current_len - minimum_len)
+            .map_err(MemoryError::Region)?;
+        self.set_size(Bytes(minimum_len).try_into().unwrap());
+        Ok(())
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.vm_memory_definition.as_ptr()
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn LinearMemory + 'static>> {
+        None
+    }
+
+    /// Copies this memory to a new memory, which isn't allocated from the
+    /// pool.
+    fn duplicate(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        let copy = VMOwnedMemory::new(&self.ty(), &self.style)?;
+        unsafe {
+            let from = self.vmmemory().as_ref();
+            let to = copy.vmmemory().as_ref();
+            slice::from_raw_parts_mut(to.base, to.current_length)
+                .copy_from_slice(slice::from_raw_parts(from.base, from.current_length));
+        }
+        Ok(Box::new(copy))
+    }
+}
+
+impl Drop for PooledMemory {
+    fn drop(&mut self) {
+        // Discard the contents of the slot before recycling it, so that the
+        // next memory starts zeroed. A slot which can't be discarded is
+        // leaked rather than recycled with the contents of this memory.
+        let memories = &self.pool.memories;
+        match memories.make_inaccessible(self.index, 0, self.size.bytes().0) {
+            Ok(()) => memories.release(self.index),
+            Err(_error) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "leaking the memory slot {} of the pool, which could not be discarded: {}",
+                    self.index,
+                    _error
+                );
+            }
+        }
+    }
+}
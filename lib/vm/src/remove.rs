@@ -0,0 +1,211 @@
+//! Removing instances and host objects from a store, to reclaim them
+//! before the whole store is dropped.
+//!
+//! An object is only removed when nothing left in the store refers to it:
+//! an instance importing it, or a table or global holding a reference to
+//! one of its functions, would otherwise point to freed memory. The handles
+//! still pointing to a removed object are detected as dangling when used.
+
+use crate::store::{InternalStoreHandle, StoreObject, StoreObjects};
+use crate::{
+    MaybeInstanceOwned, TableElement, VMExtern, VMFuncRef, VMFunction, VMGlobal, VMInstance,
+    VMMemory, VMTable,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, RemoveError, TableIndex, Type};
+
+/// The objects to remove from a store together.
+#[derive(Default)]
+struct Removal {
+    instance: Option<InternalStoreHandle<VMInstance>>,
+    functions: Vec<InternalStoreHandle<VMFunction>>,
+    memories: Vec<InternalStoreHandle<VMMemory>>,
+    tables: Vec<InternalStoreHandle<VMTable>>,
+    globals: Vec<InternalStoreHandle<VMGlobal>>,
+}
+
+impl Removal {
+    /// Returns `true` if `func_ref` points to one of the removed functions.
+    fn owns(&self, objects: &StoreObjects, func_ref: VMFuncRef) -> bool {
+        if let Some(instance) = self.instance {
+            let instance = instance.get(objects).instance();
+            if instance.local_func_index_of(func_ref).is_some() {
+                return true;
+            }
+        }
+        self.functions
+            .iter()
+            .any(|function| function.get(objects).anyfunc.as_ptr() == func_ref.0)
+    }
+
+    /// Checks that none of the objects kept in the store refers to the
+    /// removed ones.
+    fn check(&self, objects: &StoreObjects) -> Result<(), RemoveError> {
+        let in_use = |by: &str| Err(RemoveError::InUse(by.to_string()));
+
+        for handle in VMInstance::list(objects).handles() {
+            if Some(handle) == self.instance {
+                continue;
+            }
+            let instance = handle.get(objects).instance();
+            let module = instance.module_ref();
+            for index in 0..module.num_imported_functions {
+                let func_ref = instance.func_ref(FunctionIndex::new(index));
+                if matches!(func_ref, Some(func_ref) if self.owns(objects, func_ref)) {
+                    return in_use("an instance");
+                }
+            }
+            if (0..module.memories.len()).any(|index| {
+                let memory = instance.get_memory_handle(MemoryIndex::new(index));
+                self.memories.contains(&memory)
+            }) || (0..module.tables.len()).any(|index| {
+                let table = instance.get_table_handle(TableIndex::new(index));
+                self.tables.contains(&table)
+            }) || (0..module.globals.len()).any(|index| {
+                let global = instance.get_global_handle(GlobalIndex::new(index));
+                self.globals.contains(&global)
+            }) {
+                return in_use("an instance");
+            }
+        }
+
+        if self.instance.is_none() && self.functions.is_empty() {
+            return Ok(());
+        }
+        for handle in VMTable::list(objects).handles() {
+            if self.tables.contains(&handle) {
+                continue;
+            }
+            let table = handle.get(objects);
+            if (0..table.size()).any(|index| match table.get(index) {
+                Some(TableElement::FuncRef(Some(func_ref))) => self.owns(objects, func_ref),
+                _ => false,
+            }) {
+                return in_use("a table");
+            }
+        }
+        for handle in VMGlobal::list(objects).handles() {
+            let global = handle.get(objects);
+            if self.globals.contains(&handle) || global.ty().ty != Type::FuncRef {
+                continue;
+            }
+            let func_ref = unsafe { VMFuncRef::from_raw(global.vmglobal().as_ref().val) };
+            if matches!(func_ref, Some(func_ref) if self.owns(objects, func_ref)) {
+                return in_use("a global");
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(self, objects: &mut StoreObjects) {
+        for function in self.functions {
+            VMFunction::list_mut(objects).remove(function);
+        }
+        for memory in self.memories {
+            VMMemory::list_mut(objects).remove(memory);
+        }
+        for table in self.tables {
+            VMTable::list_mut(objects).remove(table);
+        }
+        for global in self.globals {
+            VMGlobal::list_mut(objects).remove(global);
+        }
+        // The instance goes last, as the objects above may point into it.
+        if let Some(instance) = self.instance {
+            VMInstance::list_mut(objects).remove(instance);
+        }
+    }
+}
+
+impl StoreObjects {
+    /// Removes an instance from the store, with the memories, tables,
+    /// globals and functions it defines.
+    ///
+    /// The instance can't be removed while another instance imports one of
+    /// these objects, or a table or global outside of the instance refers
+    /// to one of its functions.
+    pub fn remove_instance(
+        &mut self,
+        handle: InternalStoreHandle<VMInstance>,
+    ) -> Result<(), RemoveError> {
+        if self.wasm_calls > 0 {
+            return Err(RemoveError::Running);
+        }
+        let instance = VMInstance::list(self)
+            .get(handle)
+            .ok_or(RemoveError::NotFound)?
+            .instance();
+        let module = instance.module_ref();
+        let removal = Removal {
+            instance: Some(handle),
+            functions: VMFunction::list(self)
+                .handles()
+                .into_iter()
+                .filter(|function| match function.get(self).anyfunc {
+                    MaybeInstanceOwned::Instance(anyfunc) => {
+                        instance.local_func_index_of(VMFuncRef(anyfunc)).is_some()
+                    }
+                    MaybeInstanceOwned::Host(_) => false,
+                })
+                .collect(),
+            memories: (module.num_imported_memories..module.memories.len())
+                .map(|index| instance.get_memory_handle(MemoryIndex::new(index)))
+                .collect(),
+            tables: (module.num_imported_tables..module.tables.len())
+                .map(|index| instance.get_table_handle(TableIndex::new(index)))
+                .collect(),
+            globals: (module.num_imported_globals..module.globals.len())
+                .map(|index| instance.get_global_handle(GlobalIndex::new(index)))
+                .collect(),
+        };
+        removal.check(self)?;
+        removal.remove(self);
+        Ok(())
+    }
+
+    /// Removes a function, memory, table or global created by the host
+    /// from the store.
+    ///
+    /// The object can't be removed while an instance imports it, or while a
+    /// table or global refers to it if it is a function. The objects
+    /// defined by an instance are removed with
+    /// [`StoreObjects::remove_instance`].
+    pub fn remove_extern(&mut self, ext: VMExtern) -> Result<(), RemoveError> {
+        if self.wasm_calls > 0 {
+            return Err(RemoveError::Running);
+        }
+        let mut removal = Removal::default();
+        match ext {
+            VMExtern::Function(handle) => {
+                let function = VMFunction::list(self)
+                    .get(handle)
+                    .ok_or(RemoveError::NotFound)?;
+                if let MaybeInstanceOwned::Instance(_) = function.anyfunc {
+                    return Err(RemoveError::InUse("an instance".to_string()));
+                }
+                removal.functions.push(handle);
+            }
+            VMExtern::Memory(handle) => {
+                VMMemory::list(self)
+                    .get(handle)
+                    .ok_or(RemoveError::NotFound)?;
+                removal.memories.push(handle);
+            }
+            VMExtern::Table(handle) => {
+                VMTable::list(self)
+                    .get(handle)
+                    .ok_or(RemoveError::NotFound)?;
+                removal.tables.push(handle);
+            }
+            VMExtern::Global(handle) => {
+                VMGlobal::list(self)
+                    .get(handle)
+                    .ok_or(RemoveError::NotFound)?;
+                removal.globals.push(handle);
+            }
+        }
+        removal.check(self)?;
+        removal.remove(self);
+        Ok(())
+    }
+}
@@ -0,0 +1,475 @@
+//! Capturing and restoring the state of memories, tables and globals
+//! as a [`StoreSnapshot`].
+//!
+//! Function references can't be captured by address, so they are
+//! recorded relative to a scope: either every instance and host function
+//! of a store, or the functions visible to a single instance.
+
+use crate::instance::Instance;
+use crate::store::{InternalStoreHandle, StoreObject, StoreObjects};
+use crate::{
+    LinearMemory, MaybeInstanceOwned, TableElement, VMFuncRef, VMFunction, VMGlobal, VMInstance,
+    VMMemory, VMTable,
+};
+use std::convert::TryFrom;
+use std::slice;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{
+    FunctionIndex, GlobalIndex, GlobalSnapshot, MemoryIndex, MemorySnapshot, Pages, RawValue,
+    ReferenceSnapshot, SnapshotError, StoreSnapshot, TableIndex, TableSnapshot, Type,
+    WASM_PAGE_SIZE,
+};
+
+/// The set of functions that references in a snapshot are relative to.
+trait FuncRefScope {
+    /// Returns the snapshot representation of a function reference.
+    fn encode(&self, func_ref: VMFuncRef) -> Option<ReferenceSnapshot>;
+
+    /// Resolves the snapshot representation of a function reference.
+    fn decode(&self, reference: &ReferenceSnapshot) -> Option<VMFuncRef>;
+}
+
+/// Every instance and host function of a store.
+struct StoreScope<'a>(&'a StoreObjects);
+
+impl FuncRefScope for StoreScope<'_> {
+    fn encode(&self, func_ref: VMFuncRef) -> Option<ReferenceSnapshot> {
+        let instance = VMInstance::list(self.0)
+            .iter()
+            .enumerate()
+            .find_map(|(i, instance)| {
+                let index = instance.instance().local_func_index_of(func_ref)?;
+                Some(ReferenceSnapshot::Function {
+                    instance: i as u32,
+                    index: index.as_u32(),
+                })
+            });
+        instance.or_else(|| {
+            VMFunction::list(self.0)
+                .iter()
+                .position(|function| match &function.anyfunc {
+                    MaybeInstanceOwned::Host(_) => function.anyfunc.as_ptr() == func_ref.0,
+                    MaybeInstanceOwned::Instance(_) => false,
+                })
+                .map(|index| ReferenceSnapshot::HostFunction {
+                    index: index as u32,
+                })
+        })
+    }
+
+    fn decode(&self, reference: &ReferenceSnapshot) -> Option<VMFuncRef> {
+        match *reference {
+            ReferenceSnapshot::Null => None,
+            ReferenceSnapshot::Function { instance, index } => {
+                let instance = VMInstance::list(self.0)
+                    .iter()
+                    .nth(instance as usize)?
+                    .instance();
+                instance_func_ref(instance, index)
+            }
+            ReferenceSnapshot::HostFunction { index } => {
+                match &VMFunction::list(self.0).iter().nth(index as usize)?.anyfunc {
+                    anyfunc @ MaybeInstanceOwned::Host(_) => Some(VMFuncRef(anyfunc.as_ptr())),
+                    MaybeInstanceOwned::Instance(_) => None,
+                }
+            }
+        }
+    }
+}
+
+/// The functions defined or imported by a single instance.
+struct InstanceScope<'a>(&'a Instance);
+
+impl FuncRefScope for InstanceScope<'_> {
+    fn encode(&self, func_ref: VMFuncRef) -> Option<ReferenceSnapshot> {
+        let index = self.0.func_index_of(func_ref)?;
+        Some(ReferenceSnapshot::Function {
+            instance: 0,
+            index: index.as_u32(),
+        })
+    }
+
+    fn decode(&self, reference: &ReferenceSnapshot) -> Option<VMFuncRef> {
+        match *reference {
+            ReferenceSnapshot::Function { instance: 0, index } => instance_func_ref(self.0, index),
+            _ => None,
+        }
+    }
+}
+
+fn instance_func_ref(instance: &Instance, index: u32) -> Option<VMFuncRef> {
+    if index as usize >= instance.module_ref().functions.len() {
+        return None;
+    }
+    instance.func_ref(FunctionIndex::from_u32(index))
+}
+
+fn encode_reference(
+    element: TableElement,
+    scope: &impl FuncRefScope,
+) -> Result<ReferenceSnapshot, SnapshotError> {
+    match element {
+        TableElement::FuncRef(None) | TableElement::ExternRef(None) => Ok(ReferenceSnapshot::Null),
+        TableElement::FuncRef(Some(func_ref)) => {
+            scope.encode(func_ref).ok_or(SnapshotError::UnknownFuncRef)
+        }
+        TableElement::ExternRef(Some(_)) => Err(SnapshotError::UnsupportedExternRef),
+    }
+}
+
+fn decode_reference(
+    reference: &ReferenceSnapshot,
+    ty: Type,
+    scope: &impl FuncRefScope,
+) -> Result<TableElement, SnapshotError> {
+    match (ty, reference) {
+        (Type::FuncRef, ReferenceSnapshot::Null) => Ok(TableElement::FuncRef(None)),
+        (Type::FuncRef, reference) => scope
+            .decode(reference)
+            .map(|func_ref| TableElement::FuncRef(Some(func_ref)))
+            .ok_or(SnapshotError::UnknownFuncRef),
+        (Type::ExternRef, ReferenceSnapshot::Null) => Ok(TableElement::ExternRef(None)),
+        (ty, reference) => Err(SnapshotError::Mismatch(format!(
+            "{:?} can not be stored in a value of type {}",
+            reference, ty
+        ))),
+    }
+}
+
+fn capture_memory(memory: &VMMemory) -> MemorySnapshot {
+    let data = unsafe {
+        let definition = memory.vmmemory().as_ref();
+        slice::from_raw_parts(definition.base, definition.current_length).to_vec()
+    };
+    MemorySnapshot { data }
+}
+
+fn capture_table(
+    table: &VMTable,
+    scope: &impl FuncRefScope,
+) -> Result<TableSnapshot, SnapshotError> {
+    let elements = (0..table.size())
+        .map(|index| encode_reference(table.get(index).unwrap_or_default(), scope))
+        .collect::<Result<_, _>>()?;
+    Ok(TableSnapshot { elements })
+}
+
+fn capture_global(
+    global: &VMGlobal,
+    scope: &impl FuncRefScope,
+) -> Result<GlobalSnapshot, SnapshotError> {
+    let raw = unsafe { global.vmglobal().as_ref().val };
+    Ok(match global.ty().ty {
+        Type::FuncRef => GlobalSnapshot::Reference(encode_reference(
+            TableElement::FuncRef(unsafe { VMFuncRef::from_raw(raw) }),
+            scope,
+        )?),
+        Type::ExternRef => {
+            if unsafe { raw.externref } != 0 {
+                return Err(SnapshotError::UnsupportedExternRef);
+            }
+            GlobalSnapshot::Reference(ReferenceSnapshot::Null)
+        }
+        _ => GlobalSnapshot::Value(unsafe { raw.u128 }),
+    })
+}
+
+fn decode_table(
+    table: &VMTable,
+    snapshot: &TableSnapshot,
+    scope: &impl FuncRefScope,
+) -> Result<Vec<TableElement>, SnapshotError> {
+    snapshot
+        .elements
+        .iter()
+        .map(|reference| decode_reference(reference, table.ty().ty, scope))
+        .collect()
+}
+
+fn decode_global(
+    global: &VMGlobal,
+    snapshot: &GlobalSnapshot,
+    scope: &impl FuncRefScope,
+) -> Result<RawValue, SnapshotError> {
+    match (global.ty().ty, snapshot) {
+        (Type::FuncRef, GlobalSnapshot::Reference(reference))
+        | (Type::ExternRef, GlobalSnapshot::Reference(reference)) => {
+            match decode_reference(reference, global.ty().ty, scope)? {
+                TableElement::FuncRef(Some(func_ref)) => Ok(func_ref.into_raw()),
+                _ => Ok(RawValue { u128: 0 }),
+            }
+        }
+        (Type::FuncRef, _) | (Type::ExternRef, _) | (_, GlobalSnapshot::Reference(_)) => {
+            Err(SnapshotError::Mismatch(format!(
+                "{:?} can not be stored in a global of type {}",
+                snapshot,
+                global.ty().ty
+            )))
+        }
+        (_, GlobalSnapshot::Value(value)) => Ok(RawValue { u128: *value }),
+    }
+}
+
+/// Returns the number of pages of a memory snapshot, checking that the
+/// memory can be grown to it.
+fn memory_pages(memory: &VMMemory, snapshot: &MemorySnapshot) -> Result<Pages, SnapshotError> {
+    if snapshot.data.len() % WASM_PAGE_SIZE != 0 {
+        return Err(SnapshotError::Mismatch(
+            "memory length is not a multiple of the page size".to_string(),
+        ));
+    }
+    let pages = u32::try_from(snapshot.data.len() / WASM_PAGE_SIZE)
+        .map(Pages)
+        .map_err(|_| SnapshotError::Mismatch("memory is too large".to_string()))?;
+    let current = memory.size();
+    if current > pages {
+        return Err(SnapshotError::Mismatch(format!(
+            "memory has {} pages but the snapshot only {}",
+            current.0, pages.0
+        )));
+    }
+    if memory.ty().maximum.map_or(false, |maximum| pages > maximum) {
+        return Err(SnapshotError::Mismatch(format!(
+            "memory can not grow to {} pages",
+            pages.0
+        )));
+    }
+    Ok(pages)
+}
+
+/// Checks that a table can be grown to the length of its snapshot.
+fn check_table_len(table: &VMTable, elements: &[TableElement]) -> Result<(), SnapshotError> {
+    let len = u32::try_from(elements.len())
+        .map_err(|_| SnapshotError::Mismatch("table is too large".to_string()))?;
+    let current = table.size();
+    if current > len {
+        return Err(SnapshotError::Mismatch(format!(
+            "table has {} elements but the snapshot only {}",
+            current, len
+        )));
+    }
+    if table.ty().maximum.map_or(false, |maximum| len > maximum) {
+        return Err(SnapshotError::Mismatch(format!(
+            "table can not grow to {} elements",
+            len
+        )));
+    }
+    Ok(())
+}
+
+fn check_len(kind: &str, expected: usize, got: usize) -> Result<(), SnapshotError> {
+    if expected != got {
+        return Err(SnapshotError::Mismatch(format!(
+            "expected {} {} but the snapshot has {}",
+            expected, kind, got
+        )));
+    }
+    Ok(())
+}
+
+/// The handles of the objects covered by a snapshot.
+struct Targets {
+    memories: Vec<InternalStoreHandle<VMMemory>>,
+    tables: Vec<InternalStoreHandle<VMTable>>,
+    globals: Vec<InternalStoreHandle<VMGlobal>>,
+}
+
+/// A snapshot whose references have been resolved against a scope and
+/// whose sizes have been checked against the objects it is restored into.
+struct Resolved<'a> {
+    memories: Vec<(Pages, &'a [u8])>,
+    tables: Vec<Vec<TableElement>>,
+    globals: Vec<RawValue>,
+}
+
+impl Targets {
+    fn resolve<'a>(
+        &self,
+        objects: &StoreObjects,
+        snapshot: &'a StoreSnapshot,
+        scope: &impl FuncRefScope,
+    ) -> Result<Resolved<'a>, SnapshotError> {
+        check_len("memories", self.memories.len(), snapshot.memories.len())?;
+        check_len("tables", self.tables.len(), snapshot.tables.len())?;
+        check_len("globals", self.globals.len(), snapshot.globals.len())?;
+
+        let memories = self
+            .memories
+            .iter()
+            .zip(&snapshot.memories)
+            .map(|(memory, snapshot)| {
+                let pages = memory_pages(memory.get(objects), snapshot)?;
+                Ok((pages, &snapshot.data[..]))
+            })
+            .collect::<Result<_, SnapshotError>>()?;
+        let tables = self
+            .tables
+            .iter()
+            .zip(&snapshot.tables)
+            .map(|(table, snapshot)| {
+                let table = table.get(objects);
+                let elements = decode_table(table, snapshot, scope)?;
+                check_table_len(table, &elements)?;
+                Ok(elements)
+            })
+            .collect::<Result<_, SnapshotError>>()?;
+        let globals = self
+            .globals
+            .iter()
+            .zip(&snapshot.globals)
+            .map(|(global, snapshot)| decode_global(global.get(objects), snapshot, scope))
+            .collect::<Result<_, _>>()?;
+        Ok(Resolved {
+            memories,
+            tables,
+            globals,
+        })
+    }
+
+    /// Writes a resolved snapshot.
+    ///
+    /// Every memory and table is grown before anything is copied, so if
+    /// growing fails the contents of the store are left unchanged.
+    fn restore(
+        &self,
+        objects: &mut StoreObjects,
+        resolved: Resolved<'_>,
+    ) -> Result<(), SnapshotError> {
+        for (memory, (pages, _)) in self.memories.iter().zip(&resolved.memories) {
+            let memory = memory.get_mut(objects);
+            let current = memory.size();
+            memory.grow(Pages(pages.0 - current.0))?;
+        }
+        for (table, elements) in self.tables.iter().zip(&resolved.tables) {
+            let table = table.get_mut(objects);
+            let current = table.size();
+            if table
+                .grow(elements.len() as u32 - current, TableElement::default())
+                .is_none()
+            {
+                return Err(SnapshotError::Mismatch(format!(
+                    "table can not grow to {} elements",
+                    elements.len()
+                )));
+            }
+        }
+
+        for (memory, (_, data)) in self.memories.iter().zip(resolved.memories) {
+            unsafe {
+                let definition = memory.get(objects).vmmemory().as_ref();
+                slice::from_raw_parts_mut(definition.base, definition.current_length)
+                    .copy_from_slice(data);
+            }
+        }
+        for (table, elements) in self.tables.iter().zip(resolved.tables) {
+            let table = table.get_mut(objects);
+            for (index, element) in elements.into_iter().enumerate() {
+                table.set(index as u32, element).map_err(|_| {
+                    SnapshotError::Mismatch("table element out of bounds".to_string())
+                })?;
+            }
+        }
+        for (global, value) in self.globals.iter().zip(resolved.globals) {
+            unsafe {
+                global.get(objects).vmglobal().as_mut().val = value;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handles<T: StoreObject>(objects: &StoreObjects) -> Vec<InternalStoreHandle<T>> {
+    T::list(objects).handles()
+}
+
+impl StoreObjects {
+    /// Captures the state of every memory, table and global of this store.
+    ///
+    /// Function references are recorded relative to the instances and
+    /// host functions of the store, so the snapshot can only be restored
+    /// into a store where the same objects were created in the same order.
+    pub fn snapshot(&self) -> Result<StoreSnapshot, SnapshotError> {
+        let scope = StoreScope(self);
+        Ok(StoreSnapshot {
+            memories: VMMemory::list(self).iter().map(capture_memory).collect(),
+            tables: VMTable::list(self)
+                .iter()
+                .map(|table| capture_table(table, &scope))
+                .collect::<Result<_, _>>()?,
+            globals: VMGlobal::list(self)
+                .iter()
+                .map(|global| capture_global(global, &scope))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Restores a snapshot taken with [`StoreObjects::snapshot`].
+    ///
+    /// Memories and tables are grown as needed; an error is returned if
+    /// one of them is larger than the size recorded in the snapshot or
+    /// can't grow to it, in which case the store is left unchanged.
+    pub fn restore_snapshot(&mut self, snapshot: &StoreSnapshot) -> Result<(), SnapshotError> {
+        let targets = Targets {
+            memories: handles(self),
+            tables: handles(self),
+            globals: handles(self),
+        };
+        let resolved = targets.resolve(self, snapshot, &StoreScope(self))?;
+        targets.restore(self, resolved)
+    }
+}
+
+impl VMInstance {
+    /// Captures the state of the memories, tables and globals of this
+    /// instance, imported ones included, in the order of their index in
+    /// the module.
+    pub fn snapshot(&self) -> Result<StoreSnapshot, SnapshotError> {
+        let instance = self.instance();
+        let module = instance.module_ref();
+        let objects = instance.context();
+        let scope = InstanceScope(instance);
+        Ok(StoreSnapshot {
+            memories: (0..module.memories.len())
+                .map(|i| {
+                    capture_memory(instance.get_memory_handle(MemoryIndex::new(i)).get(objects))
+                })
+                .collect(),
+            tables: (0..module.tables.len())
+                .map(|i| {
+                    capture_table(
+                        instance.get_table_handle(TableIndex::new(i)).get(objects),
+                        &scope,
+                    )
+                })
+                .collect::<Result<_, _>>()?,
+            globals: (0..module.globals.len())
+                .map(|i| {
+                    capture_global(
+                        instance.get_global_handle(GlobalIndex::new(i)).get(objects),
+                        &scope,
+                    )
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Restores a snapshot taken with [`VMInstance::snapshot`], usually
+    /// from another instance of the same module.
+    pub fn restore_snapshot(&mut self, snapshot: &StoreSnapshot) -> Result<(), SnapshotError> {
+        let instance = self.instance();
+        let module = instance.module_ref();
+        let targets = Targets {
+            memories: (0..module.memories.len())
+                .map(|i| instance.get_memory_handle(MemoryIndex::new(i)))
+                .collect(),
+            tables: (0..module.tables.len())
+                .map(|i| instance.get_table_handle(TableIndex::new(i)))
+                .collect(),
+            globals: (0..module.globals.len())
+                .map(|i| instance.get_global_handle(GlobalIndex::new(i)))
+                .collect(),
+        };
+        let resolved = targets.resolve(instance.context(), snapshot, &InstanceScope(instance))?;
+        targets.restore(self.instance_mut().context_mut(), resolved)
+    }
+}
@@ -1,24 +1,26 @@
+use crate::epoch::EpochDeadline;
+use crate::limiter::StoreLimiter;
 use crate::{
-    VMExternObj, VMFunction, VMFunctionEnvironment, VMGlobal, VMInstance, VMMemory, VMTable,
+    InterruptHandle, VMCallerCheckedAnyfunc, VMExternObj, VMFunction, VMFunctionEnvironment,
+    VMGlobal, VMInstance, VMMemory, VMTable,
 };
-use core::slice::Iter;
 use std::{cell::UnsafeCell, fmt, marker::PhantomData, num::NonZeroUsize, ptr::NonNull};
-use wasmer_types::StoreId;
+use wasmer_types::{FunctionIndex, StoreId};
 
 /// Trait to represent an object managed by a context. This is implemented on
 /// the VM types managed by the context.
 pub trait StoreObject: Sized {
-    fn list(ctx: &StoreObjects) -> &Vec<Self>;
-    fn list_mut(ctx: &mut StoreObjects) -> &mut Vec<Self>;
+    fn list(ctx: &StoreObjects) -> &StoreList<Self>;
+    fn list_mut(ctx: &mut StoreObjects) -> &mut StoreList<Self>;
 }
 macro_rules! impl_context_object {
     ($($field:ident => $ty:ty,)*) => {
         $(
             impl StoreObject for $ty {
-                fn list(ctx: &StoreObjects) -> &Vec<Self> {
+                fn list(ctx: &StoreObjects) -> &StoreList<Self> {
                     &ctx.$field
                 }
-                fn list_mut(ctx: &mut StoreObjects) -> &mut Vec<Self> {
+                fn list_mut(ctx: &mut StoreObjects) -> &mut StoreList<Self> {
                     &mut ctx.$field
                 }
             }
@@ -39,13 +41,17 @@ impl_context_object! {
 #[derive(Debug, Default)]
 pub struct StoreObjects {
     id: StoreId,
-    memories: Vec<VMMemory>,
-    tables: Vec<VMTable>,
-    globals: Vec<VMGlobal>,
-    functions: Vec<VMFunction>,
-    instances: Vec<VMInstance>,
-    extern_objs: Vec<VMExternObj>,
-    function_environments: Vec<VMFunctionEnvironment>,
+    memories: StoreList<VMMemory>,
+    tables: StoreList<VMTable>,
+    globals: StoreList<VMGlobal>,
+    functions: StoreList<VMFunction>,
+    instances: StoreList<VMInstance>,
+    extern_objs: StoreList<VMExternObj>,
+    function_environments: StoreList<VMFunctionEnvironment>,
+    pub(crate) interrupt: InterruptHandle,
+    pub(crate) epoch: EpochDeadline,
+    pub(crate) limiter: StoreLimiter,
+    pub(crate) wasm_calls: usize,
 }
 
 impl StoreObjects {
@@ -67,19 +73,23 @@ impl StoreObjects {
         a: InternalStoreHandle<T>,
         b: InternalStoreHandle<T>,
     ) -> (&mut T, &mut T) {
-        assert_ne!(a.index(), b.index());
-        let list = T::list_mut(self);
-        if a.index() < b.index() {
-            let (low, high) = list.split_at_mut(b.index());
-            (&mut low[a.index()], &mut high[0])
-        } else {
-            let (low, high) = list.split_at_mut(a.index());
-            (&mut high[0], &mut low[a.index()])
-        }
+        T::list_mut(self).get_2_mut(a, b)
+    }
+
+    /// Returns the instance of this store defining the WebAssembly function
+    /// whose `VMCallerCheckedAnyfunc` is `anyfunc`, with the index of the
+    /// function in its module.
+    pub fn function_instance(
+        &self,
+        anyfunc: NonNull<VMCallerCheckedAnyfunc>,
+    ) -> Option<(&VMInstance, FunctionIndex)> {
+        self.instances
+            .iter()
+            .find_map(|instance| Some((instance, instance.function_index(anyfunc)?)))
     }
 
     /// Return an immutable iterator over all globals
-    pub fn iter_globals(&self) -> Iter<VMGlobal> {
+    pub fn iter_globals(&self) -> impl Iterator<Item = &VMGlobal> {
         self.globals.iter()
     }
 
@@ -96,8 +106,184 @@ impl StoreObjects {
     pub fn set_global_unchecked(&self, idx: usize, val: u128) {
         assert!(idx < self.globals.len());
         unsafe {
-            self.globals[idx].vmglobal().as_mut().val.u128 = val;
+            self.globals
+                .iter()
+                .nth(idx)
+                .unwrap()
+                .vmglobal()
+                .as_mut()
+                .val
+                .u128 = val;
+        }
+    }
+
+    /// Records the start of a call into WebAssembly code of this store.
+    ///
+    /// Objects can't be removed from the store until the call ends with
+    /// [`StoreObjects::end_wasm_call`].
+    pub fn begin_wasm_call(&mut self) {
+        self.wasm_calls += 1;
+    }
+
+    /// Records the end of a call started with
+    /// [`StoreObjects::begin_wasm_call`].
+    pub fn end_wasm_call(&mut self) {
+        self.wasm_calls -= 1;
+    }
+}
+
+/// Number of bits of a handle holding the index of its slot in a
+/// [`StoreList`]. On 64-bit targets the bits above hold the generation of
+/// the slot; elsewhere there is no room for it, and slots are never reused.
+#[cfg(target_pointer_width = "64")]
+const SLOT_BITS: u32 = 32;
+#[cfg(not(target_pointer_width = "64"))]
+const SLOT_BITS: u32 = usize::BITS;
+
+/// The last generation of a slot, after which it is retired.
+const MAX_GENERATION: usize = match usize::MAX.checked_shr(SLOT_BITS) {
+    Some(max) => max,
+    None => 0,
+};
+
+/// The objects of one type managed by a context.
+///
+/// The slot of a removed object is reused by a later object, with a new
+/// generation. Handles record the generation of their slot, so the handles
+/// of a removed object are detected as dangling instead of pointing to the
+/// object reusing its slot.
+#[derive(Debug)]
+pub struct StoreList<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    generation: usize,
+    value: Option<T>,
+}
+
+impl<T> Default for StoreList<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> StoreList<T> {
+    /// Returns the number of objects in the list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the list has no objects.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the objects, in the order of their slots.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    /// Returns an iterator over the objects, in the order of their slots.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+
+    /// Returns the handles of the objects, in the order of their slots.
+    pub fn handles(&self) -> Vec<InternalStoreHandle<T>> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.value.is_some())
+            .map(|(index, slot)| InternalStoreHandle::from_slot(index, slot.generation))
+            .collect()
+    }
+
+    /// Returns the object of `handle`, unless it was removed.
+    pub fn get(&self, handle: InternalStoreHandle<T>) -> Option<&T> {
+        let slot = self.slots.get(handle.slot())?;
+        if slot.generation != handle.generation() {
+            return None;
+        }
+        slot.value.as_ref()
+    }
+
+    /// Returns the object of `handle`, unless it was removed.
+    pub fn get_mut(&mut self, handle: InternalStoreHandle<T>) -> Option<&mut T> {
+        let slot = self.slots.get_mut(handle.slot())?;
+        if slot.generation != handle.generation() {
+            return None;
+        }
+        slot.value.as_mut()
+    }
+
+    fn push(&mut self, val: T) -> InternalStoreHandle<T> {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index];
+            slot.value = Some(val);
+            return InternalStoreHandle::from_slot(index, slot.generation);
         }
+        let index = self.slots.len();
+        assert!(
+            index < usize::MAX >> (usize::BITS - SLOT_BITS),
+            "too many objects in the store"
+        );
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(val),
+        });
+        InternalStoreHandle::from_slot(index, 0)
+    }
+
+    /// Removes the object of `handle` from the list, and returns it.
+    pub fn remove(&mut self, handle: InternalStoreHandle<T>) -> Option<T> {
+        let index = handle.slot();
+        let slot = self.slots.get_mut(index)?;
+        if slot.generation != handle.generation() {
+            return None;
+        }
+        let val = slot.value.take()?;
+        self.len -= 1;
+        if slot.generation < MAX_GENERATION {
+            slot.generation += 1;
+            self.free.push(index);
+        }
+        Some(val)
+    }
+
+    fn get_2_mut(
+        &mut self,
+        a: InternalStoreHandle<T>,
+        b: InternalStoreHandle<T>,
+    ) -> (&mut T, &mut T) {
+        let (i, j) = (a.slot(), b.slot());
+        assert_ne!(i, j);
+        let (first, second) = if i < j {
+            let (low, high) = self.slots.split_at_mut(j);
+            (&mut low[i], &mut high[0])
+        } else {
+            let (low, high) = self.slots.split_at_mut(i);
+            (&mut high[0], &mut low[j])
+        };
+        (
+            live_mut(first, a.generation()),
+            live_mut(second, b.generation()),
+        )
+    }
+}
+
+fn live_mut<T>(slot: &mut Slot<T>, generation: usize) -> &mut T {
+    match slot.value.as_mut() {
+        Some(val) if slot.generation == generation => val,
+        _ => panic!("object used after being removed from the store"),
     }
 }
 
@@ -164,6 +350,12 @@ impl<T: StoreObject> StoreHandle<T> {
         self.internal.get_mut(ctx)
     }
 
+    /// Returns `true` if the object that this handle points to is still in
+    /// the context, and hasn't been removed.
+    pub fn is_alive(&self, ctx: &StoreObjects) -> bool {
+        self.id == ctx.id && T::list(ctx).get(self.internal).is_some()
+    }
+
     /// Returns the internal handle contains within this handle.
     pub fn internal_handle(&self) -> InternalStoreHandle<T> {
         self.internal
@@ -223,23 +415,43 @@ impl<T> Eq for InternalStoreHandle<T> {}
 impl<T: StoreObject> InternalStoreHandle<T> {
     /// Moves the given object into a context and returns a handle to it.
     pub fn new(ctx: &mut StoreObjects, val: T) -> Self {
-        let list = T::list_mut(ctx);
-        let idx = NonZeroUsize::new(list.len() + 1).unwrap();
-        list.push(val);
-        Self {
-            idx,
-            marker: PhantomData,
-        }
+        T::list_mut(ctx).push(val)
     }
 
     /// Returns a reference to the object that this handle points to.
+    ///
+    /// Panics if the object was removed from the context.
     pub fn get<'a>(&self, ctx: &'a StoreObjects) -> &'a T {
-        &T::list(ctx)[self.idx.get() - 1]
+        T::list(ctx)
+            .get(*self)
+            .expect("object used after being removed from the store")
     }
 
     /// Returns a mutable reference to the object that this handle points to.
+    ///
+    /// Panics if the object was removed from the context.
     pub fn get_mut<'a>(&self, ctx: &'a mut StoreObjects) -> &'a mut T {
-        &mut T::list_mut(ctx)[self.idx.get() - 1]
+        T::list_mut(ctx)
+            .get_mut(*self)
+            .expect("object used after being removed from the store")
+    }
+}
+
+impl<T> InternalStoreHandle<T> {
+    fn from_slot(slot: usize, generation: usize) -> Self {
+        let idx = (slot + 1) | generation.checked_shl(SLOT_BITS).unwrap_or(0);
+        Self {
+            idx: NonZeroUsize::new(idx).unwrap(),
+            marker: PhantomData,
+        }
+    }
+
+    fn slot(&self) -> usize {
+        (self.idx.get() & (usize::MAX >> (usize::BITS - SLOT_BITS))) - 1
+    }
+
+    fn generation(&self) -> usize {
+        self.idx.get().checked_shr(SLOT_BITS).unwrap_or(0)
     }
 
     pub(crate) fn index(&self) -> usize {
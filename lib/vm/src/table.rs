@@ -5,6 +5,7 @@
 //!
 //! `Table` is to WebAssembly tables what `Memory` is to WebAssembly linear memories.
 
+use crate::pool::TableSlot;
 use crate::store::MaybeInstanceOwned;
 use crate::vmcontext::VMTableDefinition;
 use crate::Trap;
@@ -82,6 +83,17 @@ pub struct VMTable {
     style: TableStyle,
     #[derivative(Debug = "ignore")]
     vm_table_definition: MaybeInstanceOwned<VMTableDefinition>,
+    /// The pool slot lending `vec`, if any.
+    #[derivative(Debug = "ignore")]
+    slot: Option<TableSlot>,
+}
+
+impl Drop for VMTable {
+    fn drop(&mut self) {
+        if let Some(slot) = &self.slot {
+            slot.release(std::mem::take(&mut self.vec));
+        }
+    }
 }
 
 impl VMTable {
@@ -90,7 +102,7 @@ impl VMTable {
     /// This creates a `Table` with metadata owned by a VM, pointed to by
     /// `vm_table_location`: this can be used to create a local table.
     pub fn new(table: &TableType, style: &TableStyle) -> Result<Self, String> {
-        unsafe { Self::new_inner(table, style, None) }
+        unsafe { Self::new_inner(table, style, None, Vec::new(), None) }
     }
 
     /// Returns the size of the table
@@ -110,7 +122,23 @@ impl VMTable {
         style: &TableStyle,
         vm_table_location: NonNull<VMTableDefinition>,
     ) -> Result<Self, String> {
-        Self::new_inner(table, style, Some(vm_table_location))
+        Self::new_inner(table, style, Some(vm_table_location), Vec::new(), None)
+    }
+
+    /// Create a table with metadata owned by a VM, whose elements are
+    /// stored in `storage`, lent by a pool slot. The table can't grow
+    /// beyond the size of the slot.
+    ///
+    /// # Safety
+    /// - `vm_table_location` must point to a valid location in VM memory.
+    pub(crate) unsafe fn from_definition_in_slot(
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: NonNull<VMTableDefinition>,
+        storage: Vec<RawTableElement>,
+        slot: TableSlot,
+    ) -> Result<Self, String> {
+        Self::new_inner(table, style, Some(vm_table_location), storage, Some(slot))
     }
 
     /// Create a new `Table` with either self-owned or VM owned metadata.
@@ -118,6 +146,8 @@ impl VMTable {
         table: &TableType,
         style: &TableStyle,
         vm_table_location: Option<NonNull<VMTableDefinition>>,
+        mut vec: Vec<RawTableElement>,
+        slot: Option<TableSlot>,
     ) -> Result<Self, String> {
         match table.ty {
             ValType::FuncRef | ValType::ExternRef => (),
@@ -138,12 +168,20 @@ impl VMTable {
         }
         let table_minimum = usize::try_from(table.minimum)
             .map_err(|_| "Table minimum is bigger than usize".to_string())?;
-        let mut vec = vec![RawTableElement::default(); table_minimum];
+        vec.resize(table_minimum, RawTableElement::default());
         let base = vec.as_mut_ptr();
+        let maximum = match &slot {
+            Some(slot) => Some(
+                table
+                    .maximum
+                    .map_or(slot.elements(), |maximum| maximum.min(slot.elements())),
+            ),
+            None => table.maximum,
+        };
         match style {
             TableStyle::CallerChecksSignature => Ok(Self {
                 vec,
-                maximum: table.maximum,
+                maximum,
                 table: *table,
                 style: style.clone(),
                 vm_table_definition: if let Some(table_loc) = vm_table_location {
@@ -160,6 +198,7 @@ impl VMTable {
                         current_elements: table_minimum as _,
                     })))
                 },
+                slot,
             }),
         }
     }
@@ -217,6 +256,44 @@ impl VMTable {
         Some(size)
     }
 
+    /// Returns a copy of the elements of the table.
+    pub(crate) fn raw_elements(&self) -> Vec<RawTableElement> {
+        self.vec[..self.size() as usize].to_vec()
+    }
+
+    /// Replaces the elements of the table, which can't be more than its
+    /// current size.
+    pub(crate) fn set_raw_elements(&mut self, elements: &[RawTableElement]) {
+        let len = elements.len();
+        assert!(len <= self.vec.len());
+        self.vec.truncate(len);
+        self.vec.copy_from_slice(elements);
+
+        // update table definition
+        unsafe {
+            let mut td_ptr = self.get_vm_table_definition();
+            td_ptr.as_mut().current_elements = len as u32;
+        }
+    }
+
+    /// Shrinks the table back to its minimum size, with null elements.
+    pub(crate) fn reset(&mut self) {
+        let minimum = self.table.minimum;
+        self.vec.clear();
+        self.vec.resize(
+            usize::try_from(minimum).unwrap(),
+            RawTableElement::default(),
+        );
+
+        // update table definition
+        unsafe {
+            let mut td_ptr = self.get_vm_table_definition();
+            let td = td_ptr.as_mut();
+            td.current_elements = minimum;
+            td.base = self.vec.as_mut_ptr() as _;
+        }
+    }
+
     /// Get reference to the specified element.
     ///
     /// Returns `None` if the index is out of bounds.
@@ -10,18 +10,22 @@ use backtrace::Backtrace;
 use core::ptr::{read, read_unaligned};
 use corosensei::stack::DefaultStack;
 use corosensei::trap::{CoroutineTrapHandler, TrapHandlerRegs};
-use corosensei::{CoroutineResult, ScopedCoroutine, Yielder};
+use corosensei::{Coroutine, CoroutineResult, ScopedCoroutine, Yielder};
 use scopeguard::defer;
 use std::any::Any;
 use std::cell::Cell;
 use std::error::Error;
+use std::future::Future;
 use std::io;
 use std::mem;
 #[cfg(unix)]
 use std::mem::MaybeUninit;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
 use std::ptr::{self, NonNull};
 use std::sync::atomic::{compiler_fence, AtomicPtr, Ordering};
 use std::sync::{Mutex, Once};
+use std::task::{Context, Poll};
 use wasmer_types::TrapCode;
 
 // TrapInformation can be stored in the "Undefined Instruction" itself.
@@ -69,20 +73,7 @@ unsafe fn process_illegal_op(addr: usize) -> Option<TrapCode> {
         }
     }) {
         None => None,
-        Some(val) => match val {
-            0 => Some(TrapCode::StackOverflow),
-            1 => Some(TrapCode::HeapAccessOutOfBounds),
-            2 => Some(TrapCode::HeapMisaligned),
-            3 => Some(TrapCode::TableAccessOutOfBounds),
-            4 => Some(TrapCode::IndirectCallToNull),
-            5 => Some(TrapCode::BadSignature),
-            6 => Some(TrapCode::IntegerOverflow),
-            7 => Some(TrapCode::IntegerDivisionByZero),
-            8 => Some(TrapCode::BadConversionToInteger),
-            9 => Some(TrapCode::UnreachableCodeReached),
-            10 => Some(TrapCode::UnalignedAtomic),
-            _ => None,
-        },
+        Some(val) => TrapCode::from_u32(val as u32),
     }
 }
 
@@ -194,6 +185,14 @@ cfg_if::cfg_if! {
                 }
                 _ => None,
             };
+            // Writes to the protected pages of a tracked memory are resumed
+            // once the page has been recorded as written, and journaled if
+            // a checkpoint is active.
+            if let Some(addr) = maybe_fault_address {
+                if crate::dirty::handle_write_fault(addr) {
+                    return;
+                }
+            }
             let trap_code = match signum {
                 // check if it was cased by a UD and if the Trap info is a payload to it
                 libc::SIGILL => {
@@ -456,6 +455,15 @@ cfg_if::cfg_if! {
                 EXCEPTION_STACK_OVERFLOW => Some(sp),
                 _ => None,
             };
+            // Writes to the protected pages of a tracked memory are resumed
+            // once the page has been recorded as written, and journaled if
+            // a checkpoint is active.
+            if record.ExceptionCode == EXCEPTION_ACCESS_VIOLATION
+                && record.ExceptionInformation[0] == 1
+                && crate::dirty::handle_write_fault(record.ExceptionInformation[1])
+            {
+                return EXCEPTION_CONTINUE_EXECUTION;
+            }
             let trap_code = match record.ExceptionCode {
                 // check if it was cased by a UD and if the Trap info is a payload to it
                 EXCEPTION_ILLEGAL_INSTRUCTION => {
@@ -627,17 +635,45 @@ where
     on_wasm_stack(trap_handler, closure).map_err(UnwindReason::into_trap)
 }
 
-// We need two separate thread-local variables here:
+// We need three separate thread-local variables here:
 // - YIELDER is set within the new stack and is used to unwind back to the root
-//   of the stack from inside it.
+//   of the stack from inside it, or to suspend it.
 // - TRAP_HANDLER is set from outside the new stack and is solely used from
 //   signal handlers. It must be atomic since it is used by signal handlers.
+// - ASYNC_CX is set from outside the new stack while an asynchronous call is
+//   polled, and is used to poll the futures awaited by host functions.
 //
 // We also do per-thread signal stack initialization on the first time
 // TRAP_HANDLER is accessed.
 thread_local! {
-    static YIELDER: Cell<Option<NonNull<Yielder<(), UnwindReason>>>> = Cell::new(None);
+    static YIELDER: Cell<Option<NonNull<Yielder<Resumption, Suspension>>>> = Cell::new(None);
     static TRAP_HANDLER: AtomicPtr<TrapHandlerContext> = AtomicPtr::new(ptr::null_mut());
+    static ASYNC_CX: Cell<Option<NonNull<Context<'static>>>> = Cell::new(None);
+}
+
+// Allocating a new stack is pretty expensive since it involves several
+// system calls. We therefore keep a cache of pre-allocated stacks which
+// allows them to be reused multiple times.
+// FIXME(Amanieu): We should refactor this to avoid the lock.
+lazy_static::lazy_static! {
+    static ref STACK_POOL: Mutex<Vec<DefaultStack>> = Mutex::new(vec![]);
+}
+
+/// Why the Wasm stack was suspended.
+enum Suspension {
+    /// The stack is unwound to its root.
+    Unwind(UnwindReason),
+    /// A host function awaits a future which isn't ready.
+    Pending,
+}
+
+/// How a suspended Wasm stack is resumed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Resumption {
+    /// The stack runs on, and polls the future it awaits again.
+    Poll,
+    /// The asynchronous call is cancelled: the future it awaits is dropped.
+    Cancel,
 }
 
 /// Read-only information that is used by signal handlers to handle and recover
@@ -831,7 +867,7 @@ unsafe fn unwind_with(reason: UnwindReason) -> ! {
         .with(|cell| cell.replace(None))
         .expect("not running on Wasm stack");
 
-    yielder.as_ref().suspend(reason);
+    yielder.as_ref().suspend(Suspension::Unwind(reason));
 
     // on_wasm_stack will forcibly reset the coroutine stack after yielding.
     unreachable!();
@@ -844,34 +880,32 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
     trap_handler: Option<*const TrapHandlerFn<'static>>,
     f: F,
 ) -> Result<T, UnwindReason> {
-    // Allocating a new stack is pretty expensive since it involves several
-    // system calls. We therefore keep a cache of pre-allocated stacks which
-    // allows them to be reused multiple times.
-    // FIXME(Amanieu): We should refactor this to avoid the lock.
-    lazy_static::lazy_static! {
-        static ref STACK_POOL: Mutex<Vec<DefaultStack>> = Mutex::new(vec![]);
-    }
     let stack = STACK_POOL.lock().unwrap().pop().unwrap_or_default();
     let mut stack = scopeguard::guard(stack, |stack| STACK_POOL.lock().unwrap().push(stack));
 
     // Create a coroutine with a new stack to run the function on.
-    let mut coro = ScopedCoroutine::with_stack(&mut *stack, move |yielder, ()| {
+    let mut coro = ScopedCoroutine::with_stack(&mut *stack, move |yielder, _| {
         // Save the yielder to TLS so that it can be used later.
         YIELDER.with(|cell| cell.set(Some(yielder.into())));
 
         Ok(f())
     });
 
+    // The host functions called from this stack can't await futures, even
+    // when it is entered from an asynchronous call.
+    let async_cx = ASYNC_CX.with(|cell| cell.replace(None));
+
     // Ensure that YIELDER is reset on exit even if the coroutine panics,
     defer! {
         YIELDER.with(|cell| cell.set(None));
+        ASYNC_CX.with(|cell| cell.set(async_cx));
     }
 
     // Set up metadata for the trap handler for the duration of the coroutine
     // execution. This is restored to its previous value afterwards.
     TrapHandlerContext::install(trap_handler, coro.trap_handler(), || {
-        match coro.resume(()) {
-            CoroutineResult::Yield(trap) => {
+        match coro.resume(Resumption::Poll) {
+            CoroutineResult::Yield(Suspension::Unwind(trap)) => {
                 // This came from unwind_with which requires that there be only
                 // Wasm code on the stack.
                 unsafe {
@@ -879,11 +913,197 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
                 }
                 Err(trap)
             }
+            CoroutineResult::Yield(Suspension::Pending) => {
+                unreachable!("a future was awaited outside of an asynchronous call")
+            }
             CoroutineResult::Return(result) => result,
         }
     })
 }
 
+/// An asynchronous call of a WebAssembly function, which completes once
+/// the function returns or traps.
+///
+/// The function runs on a separate stack, like with
+/// [`wasmer_call_trampoline`]. When one of the host functions it calls
+/// awaits a future with [`await_on_wasm_stack`] and the future is pending,
+/// the stack is suspended and the call returns [`Poll::Pending`]: the stack
+/// is resumed the next time the call is polled, possibly from another
+/// thread.
+///
+/// Dropping a call which is suspended cancels it: the futures awaited by
+/// the host functions are dropped and the stack is unwound.
+pub struct AsyncWasmCall {
+    coro: Option<Coroutine<Resumption, Suspension, Result<(), UnwindReason>, DefaultStack>>,
+    trap_handler: Option<*const TrapHandlerFn<'static>>,
+}
+
+// While a call is suspended, its stack only holds WebAssembly frames and host
+// functions awaiting a future, which must be `Send`. The thread-local state
+// the stack depends on is set up again every time it is resumed.
+unsafe impl Send for AsyncWasmCall {}
+
+impl AsyncWasmCall {
+    /// Prepares the call of the wasm function pointed to by `callee`, see
+    /// [`wasmer_call_trampoline`] for the arguments.
+    ///
+    /// # Safety
+    ///
+    /// Wildly unsafe because it calls raw function pointers and reads/writes
+    /// raw function pointers. `values_vec` must stay valid until the call
+    /// completes or is dropped.
+    pub unsafe fn new(
+        trap_handler: Option<*const TrapHandlerFn<'static>>,
+        vmctx: VMFunctionContext,
+        trampoline: VMTrampoline,
+        callee: *const VMFunctionBody,
+        values_vec: *mut u8,
+    ) -> Self {
+        let stack = STACK_POOL.lock().unwrap().pop().unwrap_or_default();
+        let coro = Coroutine::with_stack(stack, move |yielder, _| {
+            YIELDER.with(|cell| cell.set(Some(yielder.into())));
+
+            mem::transmute::<_, extern "C" fn(VMFunctionContext, *const VMFunctionBody, *mut u8)>(
+                trampoline,
+            )(vmctx, callee, values_vec);
+            Ok(())
+        });
+        Self {
+            coro: Some(coro),
+            trap_handler,
+        }
+    }
+
+    /// Resumes the stack of the call until it completes or is suspended, and
+    /// returns the result of the call if it completed.
+    fn resume(
+        &mut self,
+        resumption: Resumption,
+        cx: Option<&mut Context<'_>>,
+    ) -> Option<Result<(), UnwindReason>> {
+        let coro = self
+            .coro
+            .as_mut()
+            .expect("asynchronous call polled after completion");
+        let cx = cx.map(|cx| NonNull::from(cx).cast::<Context<'static>>());
+        let async_cx = ASYNC_CX.with(|cell| cell.replace(cx));
+        defer! {
+            YIELDER.with(|cell| cell.set(None));
+            ASYNC_CX.with(|cell| cell.set(async_cx));
+        }
+
+        let result = TrapHandlerContext::install(self.trap_handler, coro.trap_handler(), || {
+            match coro.resume(resumption) {
+                CoroutineResult::Yield(Suspension::Unwind(trap)) => {
+                    // This came from unwind_with which requires that there be
+                    // only Wasm code on the stack.
+                    unsafe {
+                        coro.force_reset();
+                    }
+                    Some(Err(trap))
+                }
+                CoroutineResult::Yield(Suspension::Pending) => None,
+                CoroutineResult::Return(result) => Some(result),
+            }
+        });
+        if result.is_some() {
+            let coro = self.coro.take().unwrap();
+            STACK_POOL.lock().unwrap().push(coro.into_stack());
+        }
+        result
+    }
+}
+
+impl Future for AsyncWasmCall {
+    type Output = Result<(), Trap>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The call may be polled from a thread it hasn't run on yet.
+        lazy_per_thread_init()?;
+
+        match self.resume(Resumption::Poll, Some(cx)) {
+            Some(result) => Poll::Ready(result.map_err(UnwindReason::into_trap)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for AsyncWasmCall {
+    fn drop(&mut self) {
+        // The stack of a suspended call is resumed to cancel it, so that the
+        // host functions on it drop their futures and unwind it with a trap.
+        while matches!(&self.coro, Some(coro) if coro.started()) {
+            if let Some(Err(UnwindReason::Panic(panic))) = self.resume(Resumption::Cancel, None) {
+                if !std::thread::panicking() {
+                    panic::resume_unwind(panic);
+                }
+            }
+        }
+    }
+}
+
+/// Why a future awaited with [`await_on_wasm_stack`] didn't complete.
+pub enum AwaitError {
+    /// The future was awaited outside of an [`AsyncWasmCall`].
+    NotAsync,
+    /// The [`AsyncWasmCall`] was dropped while the future was pending.
+    Cancelled,
+    /// Polling the future panicked.
+    Panic(Box<dyn Any + Send>),
+}
+
+/// Drives `future` to completion from a host function called on the Wasm
+/// stack of an [`AsyncWasmCall`].
+///
+/// The future is polled on the host stack, with the context of the
+/// asynchronous call. While it is pending, the Wasm stack is suspended and
+/// the asynchronous call returns [`Poll::Pending`].
+///
+/// Since the asynchronous call may be cancelled, or the future may panic,
+/// the caller must be prepared to unwind the Wasm stack with a trap once the
+/// future is dropped.
+pub fn await_on_wasm_stack<F: Future + ?Sized>(mut future: Pin<&mut F>) -> Result<F::Output, AwaitError> {
+    loop {
+        let poll = on_host_stack(|| {
+            let mut cx = current_async_cx().ok_or(AwaitError::NotAsync)?;
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                future.as_mut().poll(unsafe { cx.as_mut() })
+            }))
+            .map_err(AwaitError::Panic)
+        })?;
+        if let Poll::Ready(output) = poll {
+            return Ok(output);
+        }
+        if suspend_wasm_stack() == Resumption::Cancel {
+            return Err(AwaitError::Cancelled);
+        }
+    }
+}
+
+// The functions below read the thread-local variables again after the stack
+// is suspended, since it may then be resumed from another thread. They are
+// never inlined so that the addresses of the variables aren't reused.
+
+#[inline(never)]
+fn current_async_cx() -> Option<NonNull<Context<'static>>> {
+    ASYNC_CX.with(|cell| cell.get())
+}
+
+#[inline(never)]
+fn suspend_wasm_stack() -> Resumption {
+    let yielder = YIELDER
+        .with(|cell| cell.replace(None))
+        .expect("not running on Wasm stack");
+    let resumption = unsafe { yielder.as_ref() }.suspend(Suspension::Pending);
+    restore_yielder(yielder);
+    resumption
+}
+
+#[inline(never)]
+fn restore_yielder(yielder: NonNull<Yielder<Resumption, Suspension>>) {
+    YIELDER.with(|cell| cell.set(Some(yielder)));
+}
+
 /// When executing on the Wasm stack, temporarily switch back to the host stack
 /// to perform an operation that should not be constrainted by the Wasm stack
 /// limits.
@@ -15,6 +15,8 @@ pub struct Config {
     pub features: Option<Features>,
     pub middlewares: Vec<Arc<dyn ModuleMiddleware>>,
     pub canonicalize_nans: bool,
+    pub interrupt_checks: bool,
+    pub epoch_interruption: bool,
 }
 
 impl Config {
@@ -23,6 +25,8 @@ impl Config {
             compiler,
             features: None,
             canonicalize_nans: false,
+            interrupt_checks: false,
+            epoch_interruption: false,
             middlewares: vec![],
         }
     }
@@ -39,6 +43,14 @@ impl Config {
         self.canonicalize_nans = canonicalize_nans;
     }
 
+    pub fn set_interrupt_checks(&mut self, interrupt_checks: bool) {
+        self.interrupt_checks = interrupt_checks;
+    }
+
+    pub fn set_epoch_interruption(&mut self, epoch_interruption: bool) {
+        self.epoch_interruption = epoch_interruption;
+    }
+
     pub fn store(&self) -> Store {
         let compiler_config = self.compiler_config(self.canonicalize_nans);
         let engine = self.engine(compiler_config);
@@ -51,7 +63,9 @@ impl Config {
     }
 
     pub fn engine(&self, compiler_config: Box<dyn CompilerConfig>) -> Engine {
-        let mut engine = wasmer_compiler::EngineBuilder::new(compiler_config);
+        let mut engine = wasmer_compiler::EngineBuilder::new(compiler_config)
+            .set_interrupt_checks(self.interrupt_checks)
+            .set_epoch_interruption(self.epoch_interruption);
         if let Some(ref features) = self.features {
             engine = engine.set_features(Some(features.clone()));
         }
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wasmer::*;
+
+const WAT: &str = r#"(module
+    (func $spin (export "spin")
+        (loop (br 0)))
+    (func $nested (export "nested") (param i32) (result i32)
+        (if (result i32) (i32.eqz (local.get 0))
+            (then (i32.const 0))
+            (else (call $nested (i32.sub (local.get 0) (i32.const 1))))))
+)"#;
+
+#[compiler_test(epoch)]
+fn epoch_deadline_loop(mut config: crate::Config) -> Result<()> {
+    config.set_epoch_interruption(true);
+    let mut store = config.store();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let spin: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "spin")?;
+
+    let engine = store.engine().clone();
+    let done = Arc::new(AtomicBool::new(false));
+    let timer_done = done.clone();
+    let timer = thread::spawn(move || {
+        while !timer_done.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+            engine.increment_epoch();
+        }
+    });
+
+    // The loop yields to the callback at every epoch, until it has used
+    // 5 slices.
+    let mut slices = 0;
+    store.set_epoch_deadline_callback(move |_| {
+        slices += 1;
+        if slices < 5 {
+            EpochDeadlineAction::Continue(1)
+        } else {
+            EpochDeadlineAction::Trap
+        }
+    });
+    store.set_epoch_deadline(1);
+    let error = spin.call(&mut store).unwrap_err();
+    done.store(true, Ordering::SeqCst);
+    timer.join().unwrap();
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineReached));
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn epoch_deadline_function_entry(mut config: crate::Config) -> Result<()> {
+    config.set_epoch_interruption(true);
+    let mut store = config.store();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let nested: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "nested")?;
+
+    // A deadline already reached stops the next call as soon as it starts.
+    store.set_epoch_deadline(0);
+    let error = nested.call(&mut store, 10).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::EpochDeadlineReached));
+
+    store.set_epoch_deadline(1);
+    assert_eq!(nested.call(&mut store, 10)?, 0);
+    Ok(())
+}
+
+#[compiler_test(epoch)]
+fn epoch_interruption_must_match_to_deserialize(mut config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let serialized = Module::new(&store, WAT)?.serialize()?;
+
+    config.set_epoch_interruption(true);
+    let store = config.store();
+    match unsafe { Module::deserialize(&store, serialized) } {
+        Err(DeserializeError::Incompatible(_)) => {}
+        _ => panic!("modules compiled without epoch interruption should be incompatible"),
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use std::thread;
+use std::time::Duration;
+use wasmer::*;
+
+const WAT: &str = r#"(module
+    (func $spin (export "spin")
+        (loop (br 0)))
+    (func $nested (export "nested") (param i32) (result i32)
+        (if (result i32) (i32.eqz (local.get 0))
+            (then (i32.const 0))
+            (else (call $nested (i32.sub (local.get 0) (i32.const 1))))))
+)"#;
+
+#[compiler_test(interrupt)]
+fn interrupt_loop(mut config: crate::Config) -> Result<()> {
+    config.set_interrupt_checks(true);
+    let mut store = config.store();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let spin: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "spin")?;
+
+    let handle = store.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+    let error = spin.call(&mut store).unwrap_err();
+    interrupter.join().unwrap();
+    assert_eq!(error.to_trap(), Some(TrapCode::Interrupted));
+    Ok(())
+}
+
+#[compiler_test(interrupt)]
+fn interrupt_function_entry(mut config: crate::Config) -> Result<()> {
+    config.set_interrupt_checks(true);
+    let mut store = config.store();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let nested: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "nested")?;
+
+    // An interrupt raised while no code is running stops the next call.
+    store.interrupt_handle().interrupt();
+    let error = nested.call(&mut store, 10).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::Interrupted));
+
+    // The interrupt is cleared once it has stopped a call.
+    assert_eq!(nested.call(&mut store, 10)?, 0);
+    Ok(())
+}
+
+#[compiler_test(interrupt)]
+fn interrupt_checks_must_match_to_deserialize(mut config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let serialized = Module::new(&store, WAT)?.serialize()?;
+
+    config.set_interrupt_checks(true);
+    let store = config.store();
+    match unsafe { Module::deserialize(&store, serialized.clone()) } {
+        Err(DeserializeError::Incompatible(_)) => {}
+        _ => panic!("modules compiled without interrupt checks should be incompatible"),
+    }
+
+    // A headless engine can't tell which checks it expects.
+    let store = config.headless_store();
+    unsafe { Module::deserialize(&store, serialized)? };
+    Ok(())
+}
@@ -7,7 +7,9 @@ extern crate compiler_test_derive;
 
 mod config;
 mod deterministic;
+mod epoch;
 mod imports;
+mod interrupt;
 mod issues;
 mod metering;
 mod middlewares;
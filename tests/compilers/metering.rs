@@ -75,6 +75,27 @@ fn metering_fail(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(metering)]
+fn metering_fail_trap_code(mut config: crate::Config) -> Result<()> {
+    config
+        .middlewares
+        .push(Arc::new(Metering::new(3, cost_always_one)));
+    let mut store = config.store();
+    let wat = r#"(module
+        (func (export "add") (param i32 i32) (result i32)
+           (i32.add (local.get 0)
+                    (local.get 1)))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let f: TypedFunction<(i32, i32), i32> =
+        instance.exports.get_typed_function(&mut store, "add")?;
+    let error = f.call(&mut store, 4, 6).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::GasExhausted));
+    Ok(())
+}
+
 #[compiler_test(metering)]
 fn loop_once(config: crate::Config) -> Result<()> {
     assert!(run_loop(config.clone(), 12, 1).is_ok());